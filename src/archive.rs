@@ -0,0 +1,200 @@
+//! Files inside archives (`.jar`, `.zip`, `.tar`, `.tar.gz`), indexed under
+//! virtual paths such as `vendor/lib.jar!/com/example/Util.java`
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Suffix marking an archive component in a virtual path
+const ARCHIVE_MARKER: char = '!';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+fn format_of(path: &Path) -> Option<Format> {
+    let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(Format::TarGz)
+    } else if name.ends_with(".tar") {
+        Some(Format::Tar)
+    } else if [".zip", ".jar", ".war", ".aar"].iter().any(|ext| name.ends_with(ext)) {
+        Some(Format::Zip)
+    } else {
+        None
+    }
+}
+
+/// Whether a file is an archive whose entries can be indexed
+pub fn is_archive(path: &Path) -> bool {
+    format_of(path).is_some()
+}
+
+/// Virtual path of an entry inside an archive
+pub fn virtual_path(archive: &Path, entry: &Path) -> PathBuf {
+    let mut marked = archive.as_os_str().to_owned();
+    marked.push(ARCHIVE_MARKER.to_string());
+    PathBuf::from(marked).join(entry)
+}
+
+/// Split a virtual path into (archive path, entry path), or `None` for a
+/// plain file
+pub fn split_virtual(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    let components: Vec<_> = path.components().collect();
+    let index = components.iter().position(|c| {
+        c.as_os_str()
+            .to_str()
+            .and_then(|s| s.strip_suffix(ARCHIVE_MARKER))
+            .is_some_and(|name| is_archive(Path::new(name)))
+    })?;
+
+    let mut archive: PathBuf = components[..=index].iter().collect();
+    let name = archive.file_name()?.to_str()?.trim_end_matches(ARCHIVE_MARKER).to_string();
+    archive.set_file_name(name);
+    let entry: PathBuf = components[index + 1..].iter().collect();
+    Some((archive, entry))
+}
+
+/// Text entries of an archive no larger than `max_size`, as (entry path,
+/// content); binary entries are left out
+pub async fn read_text_entries(archive: PathBuf, max_size: u64) -> Result<Vec<(PathBuf, String)>> {
+    tokio::task::spawn_blocking(move || {
+        let mut entries = Vec::new();
+        for_each_entry(&archive, max_size, |path, content| {
+            if let Ok(text) = String::from_utf8(content) {
+                entries.push((path, text));
+            }
+            true
+        })?;
+        Ok(entries)
+    })
+    .await?
+}
+
+/// Content of one entry, `None` if it is missing, too large or not text
+pub async fn read_entry(archive: PathBuf, entry: PathBuf, max_size: u64) -> Result<Option<String>> {
+    tokio::task::spawn_blocking(move || {
+        let mut found = None;
+        for_each_entry(&archive, max_size, |path, content| {
+            if path == entry {
+                found = String::from_utf8(content).ok();
+                return false;
+            }
+            true
+        })?;
+        Ok(found)
+    })
+    .await?
+}
+
+/// Read an indexed file by its project-relative path, looking inside the
+/// archive for virtual paths; `None` if it is missing, unreadable or larger
+/// than `max_size` bytes
+pub async fn read_source(project_root: &Path, relative_path: &Path, max_size: u64) -> Result<Option<String>> {
+    match split_virtual(relative_path) {
+        Some((archive, entry)) => read_entry(project_root.join(archive), entry, max_size).await,
+        None => {
+            let path = project_root.join(relative_path);
+            match tokio::fs::metadata(&path).await {
+                Ok(metadata) if metadata.len() <= max_size => Ok(tokio::fs::read_to_string(&path).await.ok()),
+                _ => Ok(None),
+            }
+        }
+    }
+}
+
+/// Call `visit` with each regular file entry (up to `max_size` bytes) until
+/// it returns false
+fn for_each_entry(archive: &Path, max_size: u64, mut visit: impl FnMut(PathBuf, Vec<u8>) -> bool) -> Result<()> {
+    let format = format_of(archive).with_context(|| format!("Not a supported archive: {}", archive.display()))?;
+    let file = File::open(archive).with_context(|| format!("Failed to open archive: {}", archive.display()))?;
+
+    match format {
+        Format::Zip => {
+            let mut zip = zip::ZipArchive::new(file)
+                .with_context(|| format!("Failed to read archive: {}", archive.display()))?;
+            for i in 0..zip.len() {
+                let mut entry = zip.by_index(i)?;
+                if !entry.is_file() || entry.size() > max_size {
+                    continue;
+                }
+                // Entries with unsafe names (absolute, `..`) are skipped
+                let Some(path) = entry.enclosed_name().map(Path::to_path_buf) else {
+                    continue;
+                };
+                let mut content = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut content)?;
+                if !visit(path, content) {
+                    break;
+                }
+            }
+        }
+        Format::Tar | Format::TarGz => {
+            let reader: Box<dyn Read> = if format == Format::TarGz {
+                Box::new(flate2::read::GzDecoder::new(file))
+            } else {
+                Box::new(file)
+            };
+            let mut tar = tar::Archive::new(reader);
+            for entry in tar.entries()? {
+                let mut entry = entry?;
+                if !entry.header().entry_type().is_file() || entry.size() > max_size {
+                    continue;
+                }
+                let path = entry.path()?.into_owned();
+                if path.components().any(|c| !matches!(c, std::path::Component::Normal(_))) {
+                    continue;
+                }
+                let mut content = Vec::with_capacity(entry.size() as usize);
+                entry.read_to_end(&mut content)?;
+                if !visit(path, content) {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_paths() {
+        let path = virtual_path(Path::new("vendor/lib.jar"), Path::new("com/example/Util.java"));
+        assert_eq!(crate::paths::to_slash(&path), "vendor/lib.jar!/com/example/Util.java");
+        assert_eq!(
+            split_virtual(&path),
+            Some((PathBuf::from("vendor/lib.jar"), PathBuf::from("com/example/Util.java")))
+        );
+        assert_eq!(split_virtual(Path::new("src/wow!/main.rs")), None);
+        assert!(is_archive(Path::new("deps/left-pad.tar.gz")));
+        assert!(!is_archive(Path::new("src/main.rs")));
+    }
+
+    #[tokio::test]
+    async fn test_read_zip_entries() {
+        let dir = std::env::temp_dir().join(format!("code-context-archive-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let archive = dir.join("sources.jar");
+
+        let mut writer = zip::ZipWriter::new(File::create(&archive).unwrap());
+        writer.start_file("com/example/Util.java", Default::default()).unwrap();
+        std::io::Write::write_all(&mut writer, b"class Util {}").unwrap();
+        writer.start_file("logo.png", Default::default()).unwrap();
+        std::io::Write::write_all(&mut writer, &[0x89, 0xff, 0xfe]).unwrap();
+        writer.finish().unwrap();
+
+        let entries = read_text_entries(archive.clone(), 1024).await.unwrap();
+        assert_eq!(entries, vec![(PathBuf::from("com/example/Util.java"), "class Util {}".to_string())]);
+        let entry = read_entry(archive, PathBuf::from("com/example/Util.java"), 1024).await.unwrap();
+        assert_eq!(entry.as_deref(), Some("class Util {}"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
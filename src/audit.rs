@@ -0,0 +1,119 @@
+//! Opt-in audit log of tool invocations (one JSON object per line)
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// One audited tool call
+#[derive(Debug, Serialize)]
+pub struct AuditEntry<'a> {
+    pub timestamp_ms: u64,
+    /// Identifies the server process, so entries group by session
+    pub session: &'a str,
+    pub tool: &'a str,
+    pub arguments: &'a Value,
+    pub duration_ms: u64,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Bytes of text returned to the client
+    pub result_bytes: usize,
+}
+
+/// Append-only JSONL audit log
+pub struct AuditLog {
+    file: Mutex<File>,
+    session: String,
+}
+
+impl AuditLog {
+    /// Open (or create) the log file for appending
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open audit log: {}", path.display()))?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+            session: format!("{}-{}", std::process::id(), now_ms()),
+        })
+    }
+
+    /// Append an entry for a finished tool call; write failures are logged,
+    /// never surfaced to the client
+    pub fn record(&self, tool: &str, arguments: &Value, elapsed: Duration, result: Result<usize, String>) {
+        let (success, error, result_bytes) = match result {
+            Ok(bytes) => (true, None, bytes),
+            Err(e) => (false, Some(e), 0),
+        };
+        let entry = AuditEntry {
+            timestamp_ms: now_ms(),
+            session: &self.session,
+            tool,
+            arguments,
+            duration_ms: elapsed.as_millis() as u64,
+            success,
+            error,
+            result_bytes,
+        };
+
+        let line = match serde_json::to_string(&entry) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::warn!("Failed to serialize audit entry: {}", e);
+                return;
+            }
+        };
+        let mut file = self.file.lock().unwrap();
+        if let Err(e) = writeln!(file, "{}", line) {
+            tracing::warn!("Failed to write audit log: {}", e);
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_records_one_json_line_per_call() {
+        let path = std::env::temp_dir().join(format!("audit-{}.jsonl", uuid::Uuid::new_v4()));
+        let log = AuditLog::open(&path).unwrap();
+        let arguments = json!({ "path": "/repo", "query": "parse config" });
+        log.record("search_code", &arguments, Duration::from_millis(42), Ok(128));
+        log.record("index_codebase", &json!({}), Duration::from_millis(7), Err("Path does not exist".to_string()));
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["tool"], "search_code");
+        assert_eq!(lines[0]["arguments"], arguments);
+        assert_eq!(lines[0]["duration_ms"], 42);
+        assert_eq!(lines[0]["success"], true);
+        assert_eq!(lines[0]["result_bytes"], 128);
+        assert!(lines[0].get("error").is_none());
+        assert_eq!(lines[1]["success"], false);
+        assert_eq!(lines[1]["error"], "Path does not exist");
+        assert_eq!(lines[0]["session"], lines[1]["session"]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,217 @@
+//! Last-commit provenance of indexed code: a first-parent `git blame` over
+//! the repository containing a project
+
+use anyhow::Result;
+use gix::bstr::ByteSlice;
+use gix::diff::blob::{diff, intern::InternedInput, Algorithm};
+use gix::ObjectId;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Commits walked back per file; older lines are left unattributed
+const MAX_BLAME_COMMITS: usize = 1000;
+
+/// Hex characters of a reported commit id
+const COMMIT_ID_LEN: usize = 12;
+
+/// The commit that last changed a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineCommit {
+    /// Abbreviated commit id
+    pub commit: String,
+    pub author: String,
+    pub email: String,
+    /// Commit time, seconds since the Unix epoch
+    pub time: i64,
+}
+
+/// Last commit of each line of a file; lines not committed yet are `None`
+pub type Blame = Vec<Option<Arc<LineCommit>>>;
+
+/// Git repository containing a project, for blaming its files
+#[derive(Clone)]
+pub struct GitBlame {
+    repo: gix::ThreadSafeRepository,
+    /// Project root relative to the repository's working tree
+    prefix: PathBuf,
+}
+
+impl GitBlame {
+    /// The repository containing `project_root`, if there is one with a
+    /// working tree
+    pub fn open(project_root: &Path) -> Option<Self> {
+        let repo = gix::discover(project_root).ok()?;
+        let workdir = repo.workdir()?.canonicalize().ok()?;
+        let prefix = project_root.canonicalize().ok()?.strip_prefix(&workdir).ok()?.to_path_buf();
+        Some(Self {
+            repo: repo.into_sync(),
+            prefix,
+        })
+    }
+
+    /// Blame `content`, the working tree version of a project file
+    pub async fn blame(&self, relative_path: &Path, content: String) -> Result<Blame> {
+        let repo = self.repo.clone();
+        let path = self.prefix.join(relative_path);
+        tokio::task::spawn_blocking(move || blame_file(&repo.to_thread_local(), &path, &content)).await?
+    }
+}
+
+/// Email (or name) of the git user configured for the repository containing
+/// `path`, used to resolve "me" in author filters
+pub fn current_user(path: &Path) -> Option<String> {
+    let repo = gix::discover(path).ok()?;
+    let author = repo.author()?.ok()?;
+    let email = author.email.to_str_lossy().trim().to_string();
+    if email.is_empty() {
+        Some(author.name.to_str_lossy().trim().to_string())
+    } else {
+        Some(email)
+    }
+}
+
+/// Abbreviated id of the commit checked out in the repository containing
+/// `path`, `None` outside a repository or on an unborn branch
+pub fn head_commit(path: &Path) -> Option<String> {
+    let repo = gix::discover(path).ok()?;
+    let id = repo.head_id().ok()?;
+    Some(id.to_hex_with_len(COMMIT_ID_LEN).to_string())
+}
+
+/// Walk first-parent history from HEAD, attributing each line to the first
+/// commit whose parent doesn't have it
+fn blame_file(repo: &gix::Repository, path: &Path, content: &str) -> Result<Blame> {
+    let mut blamed: Blame = vec![None; content.lines().count()];
+    // An unborn HEAD has nothing to attribute
+    let Ok(mut commit) = repo.head_commit() else {
+        return Ok(blamed);
+    };
+    let Some(mut blob) = blob_at(repo, &commit, path)? else {
+        return Ok(blamed);
+    };
+
+    // (working tree line, line in `blob`); uncommitted changes are left out
+    let mut tracked: Vec<(usize, u32)> = line_mapping(&blob.1.to_str_lossy(), content)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(line, at)| Some((line, at?)))
+        .collect();
+
+    for _ in 0..MAX_BLAME_COMMITS {
+        if tracked.is_empty() {
+            break;
+        }
+        let parent = match commit.parent_ids().next() {
+            Some(id) => Some(repo.find_commit(id.detach())?),
+            None => None,
+        };
+        let parent_blob = match &parent {
+            Some(parent) => blob_at(repo, parent, path)?,
+            None => None,
+        };
+
+        match (parent, parent_blob) {
+            (Some(parent), Some(parent_blob)) => {
+                if parent_blob.0 != blob.0 {
+                    let mapping = line_mapping(&parent_blob.1.to_str_lossy(), &blob.1.to_str_lossy());
+                    let info = Arc::new(line_commit(&commit)?);
+                    tracked.retain_mut(|(line, at)| match mapping.get(*at as usize).copied().flatten() {
+                        Some(previous) => {
+                            *at = previous;
+                            true
+                        }
+                        None => {
+                            blamed[*line] = Some(info.clone());
+                            false
+                        }
+                    });
+                    blob = parent_blob;
+                }
+                commit = parent;
+            }
+            // The file (or history) starts here
+            _ => {
+                let info = Arc::new(line_commit(&commit)?);
+                for (line, _) in tracked.drain(..) {
+                    blamed[line] = Some(info.clone());
+                }
+            }
+        }
+    }
+    Ok(blamed)
+}
+
+/// Id and content of the blob at `path` in a commit
+fn blob_at(repo: &gix::Repository, commit: &gix::Commit<'_>, path: &Path) -> Result<Option<(ObjectId, Vec<u8>)>> {
+    let Some(entry) = commit.tree()?.lookup_entry_by_path(path)? else {
+        return Ok(None);
+    };
+    if !entry.mode().is_blob() {
+        return Ok(None);
+    }
+    let id = entry.object_id();
+    let data = repo.find_blob(id)?.take_data();
+    Ok(Some((id, data)))
+}
+
+fn line_commit(commit: &gix::Commit<'_>) -> Result<LineCommit> {
+    let author = commit.author()?;
+    Ok(LineCommit {
+        commit: commit.id.to_hex_with_len(COMMIT_ID_LEN).to_string(),
+        author: author.name.to_str_lossy().trim().to_string(),
+        email: author.email.to_str_lossy().trim().to_string(),
+        time: commit.time()?.seconds,
+    })
+}
+
+/// For each line of `after`, the line of `before` it is unchanged from
+fn line_mapping(before: &str, after: &str) -> Vec<Option<u32>> {
+    let input = InternedInput::new(before, after);
+    let mut mapping = Vec::with_capacity(input.after.len());
+    let (mut b, mut a) = (0u32, 0u32);
+    diff(Algorithm::Histogram, &input, |removed: Range<u32>, added: Range<u32>| {
+        while a < added.start {
+            mapping.push(Some(b));
+            a += 1;
+            b += 1;
+        }
+        mapping.extend(added.clone().map(|_| None));
+        a = added.end;
+        b = removed.end;
+    });
+    while (a as usize) < input.after.len() {
+        mapping.push(Some(b));
+        a += 1;
+        b += 1;
+    }
+    mapping
+}
+
+/// The most recent commit among lines `start..=end`
+pub fn last_change(blame: &Blame, start: usize, end: usize) -> Option<&LineCommit> {
+    blame
+        .get(start..=end.min(blame.len().saturating_sub(1)))?
+        .iter()
+        .flatten()
+        .map(|c| c.as_ref())
+        .max_by_key(|c| c.time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_mapping() {
+        let before = "fn a() {}\nfn b() {}\nfn c() {}\n";
+        let after = "fn a() {}\nfn b2() {}\nfn new() {}\nfn c() {}\n";
+        assert_eq!(line_mapping(before, after), vec![Some(0), None, None, Some(2)]);
+
+        let commit = |time| Some(Arc::new(LineCommit { commit: String::new(), author: String::new(), email: String::new(), time }));
+        let blame: Blame = vec![commit(10), None, commit(30), commit(20)];
+        assert_eq!(last_change(&blame, 0, 1).map(|c| c.time), Some(10));
+        assert_eq!(last_change(&blame, 0, 9).map(|c| c.time), Some(30));
+        assert_eq!(last_change(&blame, 1, 1), None);
+    }
+}
@@ -0,0 +1,1395 @@
+//! Server configuration: `config.toml` files with environment overrides
+//!
+//! Settings are merged in increasing order of precedence:
+//! 1. Built-in defaults
+//! 2. Global file: `~/.config/code-context-mcp/config.toml` (`%APPDATA%` on Windows)
+//! 3. Project-local file: `.code-context.toml` in the working directory
+//! 4. Environment variables (`OLLAMA_HOST`, `MAX_INDEXED_PROJECTS`, ...)
+
+use crate::parser::{ChunkingOptions, DEFAULT_PARSE_TIMEOUT_SECS};
+use crate::paths;
+use crate::snapshot::{DEFAULT_MAX_PROJECTS, DEFAULT_SQLITE_THRESHOLD};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Name of the project-local config file
+pub const LOCAL_CONFIG_FILE: &str = ".code-context.toml";
+
+/// Effective server configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub embedding: EmbeddingConfig,
+    pub vector_db: VectorDbConfig,
+    pub snapshot: SnapshotConfig,
+    pub limits: LimitsConfig,
+    /// Default chunking for newly indexed projects
+    pub chunking: ChunkingOptions,
+    pub filters: FiltersConfig,
+    pub background_refresh: BackgroundRefreshSettings,
+    pub security: SecurityConfig,
+    pub redaction: RedactionConfig,
+    pub encryption: EncryptionConfig,
+    pub remote: RemoteConfig,
+    pub audit: AuditConfig,
+    pub query: QueryConfig,
+    pub output: OutputConfig,
+    pub lsp: LspConfig,
+    pub fulltext: FullTextConfig,
+    /// Config files that were loaded, lowest precedence first
+    #[serde(skip)]
+    pub sources: Vec<PathBuf>,
+    /// Environment variables that overrode file settings
+    #[serde(skip)]
+    pub env_overrides: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmbeddingConfig {
+    /// `ollama`, `openai`, `openai-compatible`, `voyage`, `cohere`, `mistral`,
+    /// `bedrock` or `local`
+    pub provider: String,
+    pub ollama_host: String,
+    /// Model of the `ollama` and `openai-compatible` providers
+    pub model: String,
+    /// Base URL of the `openai-compatible` provider, including the API
+    /// version (`http://localhost:8000/v1`)
+    pub base_url: Option<String>,
+    /// Dimension of the stored vectors: asked of the models that shorten
+    /// their vectors on request, other models' vectors are truncated to it.
+    /// Required by `openai-compatible`, whose model's size isn't known.
+    pub dimension: Option<usize>,
+    /// Send `dimensions` to the `openai-compatible` server, for models that
+    /// shorten their vectors on request, instead of truncating them
+    pub send_dimensions: bool,
+    /// Model of the `openai` provider
+    pub openai_model: String,
+    /// Model of the `voyage` provider
+    pub voyage_model: String,
+    /// Model of the `cohere` provider
+    pub cohere_model: String,
+    /// Model of the `mistral` provider
+    pub mistral_model: String,
+    /// Model ID of the `bedrock` provider
+    pub bedrock_model: String,
+    /// AWS region of the `bedrock` provider (`AWS_REGION`)
+    pub bedrock_region: Option<String>,
+    /// Model of the `local` provider, run in-process (`all-minilm-l6-v2`,
+    /// `bge-small-en-v1.5`, `bge-base-en-v1.5` or `nomic-embed-text-v1.5`)
+    pub local_model: String,
+    /// Directory the `local` provider downloads its models to
+    pub local_cache_dir: PathBuf,
+    /// Bounds of the embedding requests an index run sends at a time; the
+    /// limit adapts in between to the provider's latency and errors
+    pub min_concurrency: usize,
+    pub max_concurrency: usize,
+    /// Keep computed vectors on disk by model and chunk text, so text that
+    /// was embedded before is never sent to the provider again
+    pub cache: bool,
+    /// SQLite file of the embedding cache
+    pub cache_path: PathBuf,
+    /// Vectors the embedding cache keeps; the least recently used are
+    /// dropped beyond it
+    pub cache_max_entries: usize,
+    /// Request and token quotas by provider name (`[embedding.rate_limits.openai]`);
+    /// only the selected provider's applies
+    pub rate_limits: BTreeMap<String, RateLimitConfig>,
+    /// Query and document prefixes by model name
+    /// (`[embedding.task_prefixes."my-embed"]`), replacing the built-in ones
+    /// of nomic-embed-text, e5, mxbai-embed-large and bge models
+    pub task_prefixes: BTreeMap<String, TaskPrefixConfig>,
+    /// Timeouts and connection pool of the embedding provider's HTTP client
+    pub http: HttpClientConfig,
+    /// Key of the `openai` provider, only read from `OPENAI_API_KEY` so it
+    /// stays out of config files and `get_config`
+    #[serde(skip)]
+    pub openai_api_key: Option<String>,
+    /// Optional key of the `openai-compatible` provider, only read from
+    /// `EMBEDDING_API_KEY`
+    #[serde(skip)]
+    pub api_key: Option<String>,
+    /// Key of the `voyage` provider, only read from `VOYAGE_API_KEY`
+    #[serde(skip)]
+    pub voyage_api_key: Option<String>,
+    /// Key of the `cohere` provider, only read from `COHERE_API_KEY`
+    #[serde(skip)]
+    pub cohere_api_key: Option<String>,
+    /// Key of the `mistral` provider, only read from `MISTRAL_API_KEY`
+    #[serde(skip)]
+    pub mistral_api_key: Option<String>,
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            provider: "ollama".to_string(),
+            ollama_host: "http://127.0.0.1:11434".to_string(),
+            model: "nomic-embed-text".to_string(),
+            base_url: None,
+            dimension: None,
+            send_dimensions: false,
+            openai_model: "text-embedding-3-small".to_string(),
+            voyage_model: "voyage-code-3".to_string(),
+            cohere_model: "embed-english-v3.0".to_string(),
+            mistral_model: "mistral-embed".to_string(),
+            bedrock_model: "amazon.titan-embed-text-v2:0".to_string(),
+            bedrock_region: None,
+            local_model: "bge-small-en-v1.5".to_string(),
+            local_cache_dir: paths::data_dir().join("models"),
+            min_concurrency: 1,
+            max_concurrency: 16,
+            cache: true,
+            cache_path: paths::data_dir().join("embedding_cache.db"),
+            cache_max_entries: 200_000,
+            rate_limits: BTreeMap::new(),
+            task_prefixes: BTreeMap::new(),
+            http: HttpClientConfig::default(),
+            openai_api_key: None,
+            api_key: None,
+            voyage_api_key: None,
+            cohere_api_key: None,
+            mistral_api_key: None,
+        }
+    }
+}
+
+/// Most an embedding provider may be sent per minute; unset quotas aren't
+/// enforced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub requests_per_minute: Option<u32>,
+    /// Estimated at four characters per token
+    pub tokens_per_minute: Option<u32>,
+    /// Most estimated tokens in one request; larger batches are split
+    pub max_tokens_per_request: Option<u32>,
+}
+
+/// Default whole-request timeout of hosted backends
+pub const DEFAULT_REQUEST_TIMEOUT_SECS: f64 = 120.0;
+
+/// Default whole-request timeout of local embedding providers (Ollama, an
+/// OpenAI-compatible server), whose first request loads the model and whose
+/// batches run on local hardware
+pub const LOCAL_REQUEST_TIMEOUT_SECS: f64 = 600.0;
+
+/// HTTP client settings of a backend; a zero timeout waits forever.
+/// reqwest has no limit on the connections open to a host: requests in
+/// flight are bounded by the embedding and vector database concurrency
+/// limits instead, and only idle connections are capped here.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpClientConfig {
+    pub connect_timeout_secs: f64,
+    /// Whole request, response body included, so a hung call fails instead
+    /// of stalling an index run; the backend's default when unset
+    pub request_timeout_secs: Option<f64>,
+    /// Idle connections are kept alive this long for reuse
+    pub keep_alive_secs: f64,
+    /// Idle connections kept per host (unlimited when unset)
+    pub max_idle_connections: Option<usize>,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10.0,
+            request_timeout_secs: None,
+            keep_alive_secs: 90.0,
+            max_idle_connections: None,
+        }
+    }
+}
+
+impl HttpClientConfig {
+    /// Client with these timeouts and pool settings, timing requests out
+    /// after `default_timeout_secs` unless a timeout is configured
+    pub fn client(&self, default_timeout_secs: f64) -> Result<reqwest::Client> {
+        let duration = |secs: f64| Duration::try_from_secs_f64(secs).ok().filter(|d| !d.is_zero());
+        let mut builder = reqwest::Client::builder().pool_idle_timeout(duration(self.keep_alive_secs));
+        if let Some(timeout) = duration(self.connect_timeout_secs) {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = duration(self.request_timeout_secs.unwrap_or(default_timeout_secs)) {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(max) = self.max_idle_connections {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        builder.build().context("Failed to build HTTP client")
+    }
+
+    /// Override the settings from `<prefix>_CONNECT_TIMEOUT_SECS`,
+    /// `<prefix>_REQUEST_TIMEOUT_SECS`, `<prefix>_KEEP_ALIVE_SECS` and
+    /// `<prefix>_MAX_IDLE_CONNECTIONS`
+    fn apply_env(&mut self, names: [&'static str; 4], overrides: &mut Vec<&'static str>) {
+        if let Some(secs) = parse_env(names[0], overrides) {
+            self.connect_timeout_secs = secs;
+        }
+        if let Some(secs) = parse_env(names[1], overrides) {
+            self.request_timeout_secs = Some(secs);
+        }
+        if let Some(secs) = parse_env(names[2], overrides) {
+            self.keep_alive_secs = secs;
+        }
+        if let Some(max) = parse_env(names[3], overrides) {
+            self.max_idle_connections = Some(max);
+        }
+    }
+}
+
+/// Text prepended to what a model embeds, for models trained with
+/// role-specific prefixes; empty strings embed the text as it is
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaskPrefixConfig {
+    /// Prepended to search queries
+    pub query: String,
+    /// Prepended to chunks being indexed
+    pub document: String,
+}
+
+/// Embedding provider selected by `[embedding] provider`, with its settings
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmbeddingBackend {
+    Ollama { host: String, model: String },
+    OpenAI {
+        api_key: String,
+        model: String,
+        dimension: Option<usize>,
+    },
+    OpenAICompatible {
+        base_url: String,
+        api_key: Option<String>,
+        model: String,
+        dimension: usize,
+        /// Whether `dimension` is sent as `dimensions`
+        send_dimensions: bool,
+    },
+    Voyage {
+        api_key: String,
+        model: String,
+        dimension: Option<usize>,
+    },
+    Cohere { api_key: String, model: String },
+    Mistral {
+        api_key: String,
+        model: String,
+        dimension: Option<usize>,
+    },
+    /// Credentials come from the AWS credential chain when the provider is built
+    Bedrock {
+        region: String,
+        model: String,
+        dimension: Option<usize>,
+    },
+    /// Requires the `local-embedding` feature
+    Local { model: String, cache_dir: PathBuf },
+}
+
+impl EmbeddingConfig {
+    /// HTTP client of the selected provider, with a longer default request
+    /// timeout for local providers
+    pub fn http_client(&self) -> Result<reqwest::Client> {
+        let default_timeout = match self.provider.to_ascii_lowercase().as_str() {
+            "ollama" | "openai-compatible" => LOCAL_REQUEST_TIMEOUT_SECS,
+            _ => DEFAULT_REQUEST_TIMEOUT_SECS,
+        };
+        self.http.client(default_timeout)
+    }
+
+    /// Quotas of the selected provider
+    pub fn rate_limit(&self) -> RateLimitConfig {
+        self.rate_limits.get(&self.provider.to_ascii_lowercase()).copied().unwrap_or_default()
+    }
+
+    /// Dimension the selected provider's vectors are cut down to, for
+    /// models that can't be asked for it; vectors no longer than it are
+    /// kept as they are
+    pub fn truncated_dimension(&self) -> Option<usize> {
+        let dimension = self.dimension.filter(|d| *d > 0)?;
+        match self.provider.to_ascii_lowercase().as_str() {
+            "ollama" | "cohere" | "local" => Some(dimension),
+            "openai" if !crate::embedding::openai::supports_dimensions(&self.openai_model) => Some(dimension),
+            "mistral" if !crate::embedding::mistral::supports_output_dimension(&self.mistral_model) => Some(dimension),
+            "openai-compatible" if !self.send_dimensions => Some(dimension),
+            _ => None,
+        }
+    }
+
+    /// The selected provider, failing if it is unknown or its required
+    /// settings are missing
+    pub fn backend(&self) -> Result<EmbeddingBackend> {
+        match self.provider.to_ascii_lowercase().as_str() {
+            "ollama" => Ok(EmbeddingBackend::Ollama {
+                host: self.ollama_host.clone(),
+                model: self.model.clone(),
+            }),
+            "openai" => {
+                let api_key = self
+                    .openai_api_key
+                    .clone()
+                    .filter(|key| !key.trim().is_empty())
+                    .context("EMBEDDING_PROVIDER=openai requires OPENAI_API_KEY to be set")?;
+                anyhow::ensure!(!self.openai_model.is_empty(), "EMBEDDING_PROVIDER=openai requires OPENAI_EMBEDDING_MODEL");
+                Ok(EmbeddingBackend::OpenAI {
+                    api_key,
+                    model: self.openai_model.clone(),
+                    dimension: self.dimension.filter(|d| *d > 0),
+                })
+            }
+            "openai-compatible" => Ok(EmbeddingBackend::OpenAICompatible {
+                base_url: self
+                    .base_url
+                    .clone()
+                    .filter(|url| !url.trim().is_empty())
+                    .context("EMBEDDING_PROVIDER=openai-compatible requires EMBEDDING_BASE_URL (such as http://localhost:8000/v1)")?,
+                api_key: self.api_key.clone().filter(|key| !key.trim().is_empty()),
+                model: self.model.clone(),
+                dimension: self
+                    .dimension
+                    .filter(|d| *d > 0)
+                    .context("EMBEDDING_PROVIDER=openai-compatible requires EMBEDDING_DIMENSION, the vector size of the model")?,
+                send_dimensions: self.send_dimensions,
+            }),
+            "voyage" => {
+                let api_key = self
+                    .voyage_api_key
+                    .clone()
+                    .filter(|key| !key.trim().is_empty())
+                    .context("EMBEDDING_PROVIDER=voyage requires VOYAGE_API_KEY to be set")?;
+                anyhow::ensure!(!self.voyage_model.is_empty(), "EMBEDDING_PROVIDER=voyage requires VOYAGE_EMBEDDING_MODEL");
+                Ok(EmbeddingBackend::Voyage {
+                    api_key,
+                    model: self.voyage_model.clone(),
+                    dimension: self.dimension.filter(|d| *d > 0),
+                })
+            }
+            "cohere" => {
+                let api_key = self
+                    .cohere_api_key
+                    .clone()
+                    .filter(|key| !key.trim().is_empty())
+                    .context("EMBEDDING_PROVIDER=cohere requires COHERE_API_KEY to be set")?;
+                anyhow::ensure!(!self.cohere_model.is_empty(), "EMBEDDING_PROVIDER=cohere requires COHERE_EMBEDDING_MODEL");
+                Ok(EmbeddingBackend::Cohere {
+                    api_key,
+                    model: self.cohere_model.clone(),
+                })
+            }
+            "mistral" => {
+                let api_key = self
+                    .mistral_api_key
+                    .clone()
+                    .filter(|key| !key.trim().is_empty())
+                    .context("EMBEDDING_PROVIDER=mistral requires MISTRAL_API_KEY to be set")?;
+                anyhow::ensure!(!self.mistral_model.is_empty(), "EMBEDDING_PROVIDER=mistral requires MISTRAL_EMBEDDING_MODEL");
+                Ok(EmbeddingBackend::Mistral {
+                    api_key,
+                    model: self.mistral_model.clone(),
+                    dimension: self.dimension.filter(|d| *d > 0),
+                })
+            }
+            "bedrock" => {
+                let region = self
+                    .bedrock_region
+                    .clone()
+                    .filter(|region| !region.trim().is_empty())
+                    .context("EMBEDDING_PROVIDER=bedrock requires AWS_REGION (or [embedding] bedrock_region)")?;
+                anyhow::ensure!(!self.bedrock_model.is_empty(), "EMBEDDING_PROVIDER=bedrock requires BEDROCK_EMBEDDING_MODEL");
+                Ok(EmbeddingBackend::Bedrock {
+                    region,
+                    model: self.bedrock_model.clone(),
+                    dimension: self.dimension.filter(|d| *d > 0),
+                })
+            }
+            "local" => {
+                anyhow::ensure!(!self.local_model.is_empty(), "EMBEDDING_PROVIDER=local requires LOCAL_EMBEDDING_MODEL");
+                Ok(EmbeddingBackend::Local {
+                    model: self.local_model.clone(),
+                    cache_dir: self.local_cache_dir.clone(),
+                })
+            }
+            other => anyhow::bail!(
+                "Unknown embedding provider '{}' (EMBEDDING_PROVIDER): expected ollama, openai, openai-compatible, voyage, cohere, mistral, bedrock or local",
+                other
+            ),
+        }
+    }
+}
+
+/// Vector database selected by `[vector_db] backend`, with the address of
+/// its `default` instance
+#[derive(Debug, Clone, PartialEq)]
+pub enum VectorDbBackend {
+    Milvus { address: String },
+    Qdrant { address: String, api_key: Option<String> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VectorDbConfig {
+    /// `milvus` or `qdrant`
+    pub backend: String,
+    pub milvus_address: String,
+    /// REST address of Qdrant, used with `backend = "qdrant"`
+    pub qdrant_address: String,
+    /// More instances of the selected backend by name
+    /// (`shared = "http://milvus.internal:19530"`), which projects are
+    /// indexed into with `vector_db`; `milvus_address` (or
+    /// `qdrant_address`) is the `default` instance
+    pub instances: BTreeMap<String, String>,
+    /// Timeouts and connection pool of the vector database HTTP client
+    pub http: HttpClientConfig,
+    /// Requests sent to the vector database at a time across all tool
+    /// calls (unlimited when zero)
+    pub max_concurrent_requests: usize,
+    /// Requests one tool call (such as a cross-project search) sends at a
+    /// time (unlimited when zero)
+    pub max_concurrent_requests_per_call: usize,
+    /// Store chunk content zstd-compressed (content stored either way is
+    /// read back transparently)
+    pub compress_content: bool,
+    /// Key of a Qdrant Cloud or secured Qdrant instance, only read from
+    /// `QDRANT_API_KEY`
+    #[serde(skip)]
+    pub qdrant_api_key: Option<String>,
+}
+
+impl Default for VectorDbConfig {
+    fn default() -> Self {
+        Self {
+            backend: "milvus".to_string(),
+            milvus_address: "http://127.0.0.1:19530".to_string(),
+            qdrant_address: "http://127.0.0.1:6333".to_string(),
+            instances: BTreeMap::new(),
+            http: HttpClientConfig::default(),
+            max_concurrent_requests: 16,
+            max_concurrent_requests_per_call: 4,
+            compress_content: false,
+            qdrant_api_key: None,
+        }
+    }
+}
+
+impl VectorDbConfig {
+    /// The selected backend, failing if it is unknown
+    pub fn backend(&self) -> Result<VectorDbBackend> {
+        match self.backend.to_ascii_lowercase().as_str() {
+            "milvus" => Ok(VectorDbBackend::Milvus {
+                address: self.milvus_address.clone(),
+            }),
+            "qdrant" => Ok(VectorDbBackend::Qdrant {
+                address: self.qdrant_address.clone(),
+                api_key: self.qdrant_api_key.clone().filter(|key| !key.trim().is_empty()),
+            }),
+            other => anyhow::bail!("Unknown vector database '{}' (VECTOR_DB): expected milvus or qdrant", other),
+        }
+    }
+
+    /// Address of the `default` instance of the selected backend
+    pub fn address(&self) -> &str {
+        match self.backend.to_ascii_lowercase().as_str() {
+            "qdrant" => &self.qdrant_address,
+            _ => &self.milvus_address,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnapshotConfig {
+    pub path: PathBuf,
+    /// Indexed file count above which the snapshot migrates to SQLite
+    pub sqlite_threshold: usize,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            path: paths::data_dir().join("snapshot.json"),
+            sqlite_threshold: DEFAULT_SQLITE_THRESHOLD,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    pub max_indexed_projects: usize,
+    /// Total chunk budget for the `size` eviction policy
+    pub max_indexed_chunks: Option<usize>,
+    /// `lru`, `size`, `disabled` or `confirm`
+    pub eviction_policy: String,
+    /// Memory an index run may hold in chunk contents and vectors
+    pub index_memory_budget_mb: usize,
+    /// Time a single collection may take to answer a multi-project search
+    /// before it is left out of the results
+    pub search_timeout_secs: f64,
+    /// Time a grammar may take to parse one file before it is chunked into
+    /// line windows instead (unlimited when zero)
+    pub parse_timeout_secs: f64,
+    /// Files larger than this are not indexed
+    pub max_file_size_mb: f64,
+    /// Size limits overriding `max_file_size_mb` by file extension
+    pub max_file_size_by_extension: BTreeMap<String, f64>,
+    /// Unchanged files indexed longer ago than this are re-embedded if the
+    /// embedding model or chunking changed since (never when unset)
+    pub max_chunk_age_days: Option<u64>,
+    /// Approximate vector database storage one project may use
+    pub max_project_storage_mb: Option<f64>,
+    /// Approximate vector database storage all projects together may use
+    pub max_total_storage_mb: Option<f64>,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_indexed_projects: DEFAULT_MAX_PROJECTS,
+            max_indexed_chunks: None,
+            eviction_policy: "lru".to_string(),
+            index_memory_budget_mb: 256,
+            search_timeout_secs: 10.0,
+            parse_timeout_secs: DEFAULT_PARSE_TIMEOUT_SECS,
+            max_file_size_mb: DEFAULT_MAX_FILE_SIZE_MB,
+            max_file_size_by_extension: BTreeMap::new(),
+            max_chunk_age_days: None,
+            max_project_storage_mb: None,
+            max_total_storage_mb: None,
+        }
+    }
+}
+
+impl LimitsConfig {
+    pub fn index_memory_budget_bytes(&self) -> usize {
+        self.index_memory_budget_mb.saturating_mul(1024 * 1024)
+    }
+
+    pub fn max_chunk_age_secs(&self) -> Option<u64> {
+        self.max_chunk_age_days.map(|days| days.saturating_mul(24 * 60 * 60))
+    }
+
+    pub fn max_project_storage_bytes(&self) -> Option<u64> {
+        self.max_project_storage_mb.map(mb_to_bytes)
+    }
+
+    pub fn max_total_storage_bytes(&self) -> Option<u64> {
+        self.max_total_storage_mb.map(mb_to_bytes)
+    }
+
+    pub fn search_timeout(&self) -> Duration {
+        Duration::try_from_secs_f64(self.search_timeout_secs.max(0.0)).unwrap_or(Duration::MAX)
+    }
+
+    pub fn parse_timeout(&self) -> Duration {
+        Duration::try_from_secs_f64(self.parse_timeout_secs.max(0.0)).unwrap_or(Duration::ZERO)
+    }
+
+    /// File size limits, with a project's own limits taking precedence
+    pub fn file_size_limits(&self, project_max_mb: Option<f64>, project_by_extension: &BTreeMap<String, f64>) -> FileSizeLimits {
+        let mut limits = FileSizeLimits {
+            default: mb_to_bytes(project_max_mb.unwrap_or(self.max_file_size_mb)),
+            by_extension: HashMap::new(),
+        };
+        for (extension, mb) in self.max_file_size_by_extension.iter().chain(project_by_extension) {
+            limits.by_extension.insert(normalize_extension(extension), mb_to_bytes(*mb));
+        }
+        limits
+    }
+}
+
+/// Default maximum size of an indexed file
+pub const DEFAULT_MAX_FILE_SIZE_MB: f64 = 10.0;
+
+/// Effective file size limits of an index run
+#[derive(Debug, Clone)]
+pub struct FileSizeLimits {
+    default: u64,
+    /// By lowercase extension without the dot
+    by_extension: HashMap<String, u64>,
+}
+
+impl Default for FileSizeLimits {
+    fn default() -> Self {
+        Self {
+            default: mb_to_bytes(DEFAULT_MAX_FILE_SIZE_MB),
+            by_extension: HashMap::new(),
+        }
+    }
+}
+
+impl FileSizeLimits {
+    /// Largest indexed size of a file, in bytes
+    pub fn limit_for(&self, path: &Path) -> u64 {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(|e| self.by_extension.get(&e.to_ascii_lowercase()))
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// The largest of all limits
+    pub fn largest(&self) -> u64 {
+        self.by_extension.values().copied().fold(self.default, u64::max)
+    }
+}
+
+fn mb_to_bytes(mb: f64) -> u64 {
+    (mb.max(0.0) * 1024.0 * 1024.0) as u64
+}
+
+/// Extension as a size-limit key: lowercase, without a leading dot
+pub fn normalize_extension(extension: &str) -> String {
+    extension.trim().trim_start_matches('.').to_ascii_lowercase()
+}
+
+/// Parse `ext=MB` pairs separated by commas, e.g. `json=1,sql=100`
+fn parse_size_overrides(value: &str) -> Option<BTreeMap<String, f64>> {
+    value
+        .split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (extension, mb) = pair.split_once('=')?;
+            Some((normalize_extension(extension), mb.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FiltersConfig {
+    /// Glob patterns excluded from every project, in addition to per-project `ignore`
+    pub ignore: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackgroundRefreshSettings {
+    /// Interval between refresh rounds of pinned projects (disabled when unset)
+    pub interval_secs: Option<u64>,
+    /// Time budget per project for each round
+    pub budget_secs: u64,
+}
+
+impl Default for BackgroundRefreshSettings {
+    fn default() -> Self {
+        Self {
+            interval_secs: None,
+            budget_secs: crate::handlers::background::DEFAULT_BACKGROUND_REFRESH_BUDGET_SECS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecurityConfig {
+    /// Directories that index_codebase and search_code may touch (unrestricted when empty)
+    pub allowed_roots: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedactionConfig {
+    /// Redact chunk content before embedding and storage (projects can override)
+    pub enabled: bool,
+    /// Apply the built-in token and credential detectors
+    pub builtin: bool,
+    /// Extra regular expressions; a `secret` group limits redaction to that group
+    pub patterns: Vec<String>,
+    pub replacement: String,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            builtin: true,
+            patterns: Vec::new(),
+            replacement: "[REDACTED]".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EncryptionConfig {
+    /// Encrypt stored chunk content (projects can override)
+    pub enabled: bool,
+    /// Directory holding one key file per project collection
+    pub key_dir: PathBuf,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            key_dir: paths::data_dir().join("keys"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RemoteConfig {
+    /// Accept git URLs as index_codebase paths; off by default, as any
+    /// client could make the server clone with its git credentials
+    pub enabled: bool,
+    /// Hosts URLs may point at (`*` allows any)
+    pub allowed_hosts: Vec<String>,
+    /// Directory holding shallow clones of remote repositories
+    pub cache_dir: PathBuf,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allowed_hosts: ["github.com", "gitlab.com", "bitbucket.org", "codeberg.org"].map(String::from).to_vec(),
+            cache_dir: paths::data_dir().join("remotes"),
+        }
+    }
+}
+
+impl RemoteConfig {
+    /// Whether `url` points at an allowed host
+    pub fn allows(&self, url: &str) -> bool {
+        let Some(host) = crate::remote::url_host(url) else {
+            return false;
+        };
+        self.allowed_hosts.iter().any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(&host))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// JSONL file recording every tool call (disabled when unset)
+    pub log_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueryConfig {
+    /// Normalize search queries before embedding (`raw_query: true` skips it)
+    pub preprocess: bool,
+    /// Add expansions of common abbreviations (db, auth, ctx, ...)
+    pub expand_synonyms: bool,
+}
+
+impl Default for QueryConfig {
+    fn default() -> Self {
+        Self {
+            preprocess: true,
+            expand_synonyms: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// URI scheme of editor links added to search results (`vscode`,
+    /// `vscode-insiders`, `cursor`); only `file://` links when unset
+    pub editor_scheme: Option<String>,
+    /// JSON report of each index run (files indexed, skipped and failed,
+    /// time per stage); `{collection}` in the path is replaced by the
+    /// project's collection. Not written when unset
+    pub index_report_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FullTextConfig {
+    /// Keep a full-text index of chunk text next to the vectors, for the
+    /// `keyword`, `regex` and `hybrid` search modes and for searching while
+    /// the vector database is down
+    pub enabled: bool,
+    /// Directory holding one index per project collection
+    pub path: PathBuf,
+}
+
+impl Default for FullTextConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: paths::data_dir().join("fulltext"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LspConfig {
+    /// Language server command lines by language (`rust = "rust-analyzer"`),
+    /// asked to name chunks of projects indexed with `lsp`
+    pub servers: BTreeMap<String, String>,
+    /// Time a language server may take to start or answer a request
+    pub timeout_secs: f64,
+}
+
+impl Default for LspConfig {
+    fn default() -> Self {
+        Self {
+            servers: BTreeMap::new(),
+            timeout_secs: 10.0,
+        }
+    }
+}
+
+impl LspConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::try_from_secs_f64(self.timeout_secs.max(0.0)).unwrap_or(Duration::ZERO)
+    }
+}
+
+/// Parse `language=command` pairs separated by commas, e.g.
+/// `rust=rust-analyzer,python=pyright-langserver --stdio`
+fn parse_lsp_servers(value: &str) -> Option<BTreeMap<String, String>> {
+    value
+        .split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (language, command) = pair.split_once('=')?;
+            let (language, command) = (language.trim().to_lowercase(), command.trim().to_string());
+            (!language.is_empty() && !command.is_empty()).then_some((language, command))
+        })
+        .collect()
+}
+
+impl Config {
+    /// Load the global and project-local config files, then apply
+    /// environment overrides
+    pub fn load() -> Result<Self> {
+        let mut candidates = Vec::new();
+        if let Some(dir) = paths::config_dir() {
+            candidates.push(dir.join("code-context-mcp").join("config.toml"));
+        }
+        if let Ok(cwd) = std::env::current_dir() {
+            candidates.push(cwd.join(LOCAL_CONFIG_FILE));
+        }
+
+        let mut merged = toml::Value::Table(Default::default());
+        let mut sources = Vec::new();
+        for path in candidates.into_iter().filter(|p| p.is_file()) {
+            merge(&mut merged, read_toml(&path)?);
+            sources.push(path);
+        }
+
+        let mut config: Config = merged
+            .try_into()
+            .with_context(|| format!("Invalid configuration in {}", display_paths(&sources)))?;
+        config.sources = sources;
+        config.apply_env();
+        Ok(config)
+    }
+
+    /// Override file settings with the environment variables that are set
+    fn apply_env(&mut self) {
+        let mut overrides = Vec::new();
+        let mut string_var = |name: &'static str, target: &mut String| {
+            if let Ok(value) = std::env::var(name) {
+                *target = value;
+                overrides.push(name);
+            }
+        };
+        string_var("OLLAMA_HOST", &mut self.embedding.ollama_host);
+        string_var("EMBEDDING_PROVIDER", &mut self.embedding.provider);
+        string_var("EMBEDDING_MODEL", &mut self.embedding.model);
+        string_var("OPENAI_EMBEDDING_MODEL", &mut self.embedding.openai_model);
+        string_var("VOYAGE_EMBEDDING_MODEL", &mut self.embedding.voyage_model);
+        string_var("COHERE_EMBEDDING_MODEL", &mut self.embedding.cohere_model);
+        string_var("MISTRAL_EMBEDDING_MODEL", &mut self.embedding.mistral_model);
+        string_var("BEDROCK_EMBEDDING_MODEL", &mut self.embedding.bedrock_model);
+        string_var("LOCAL_EMBEDDING_MODEL", &mut self.embedding.local_model);
+        string_var("VECTOR_DB", &mut self.vector_db.backend);
+        string_var("MILVUS_ADDRESS", &mut self.vector_db.milvus_address);
+        string_var("QDRANT_ADDRESS", &mut self.vector_db.qdrant_address);
+        string_var("EVICTION_POLICY", &mut self.limits.eviction_policy);
+
+        // `name=address` pairs separated by commas
+        if let Ok(instances) = std::env::var("MILVUS_INSTANCES") {
+            self.vector_db.instances = instances
+                .split(',')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(name, address)| (name.trim().to_string(), address.trim().to_string()))
+                .filter(|(name, address)| !name.is_empty() && !address.is_empty())
+                .collect();
+            overrides.push("MILVUS_INSTANCES");
+        }
+
+        self.embedding.http.apply_env(
+            [
+                "EMBEDDING_CONNECT_TIMEOUT_SECS",
+                "EMBEDDING_REQUEST_TIMEOUT_SECS",
+                "EMBEDDING_KEEP_ALIVE_SECS",
+                "EMBEDDING_MAX_IDLE_CONNECTIONS",
+            ],
+            &mut overrides,
+        );
+        self.vector_db.http.apply_env(
+            [
+                "MILVUS_CONNECT_TIMEOUT_SECS",
+                "MILVUS_REQUEST_TIMEOUT_SECS",
+                "MILVUS_KEEP_ALIVE_SECS",
+                "MILVUS_MAX_IDLE_CONNECTIONS",
+            ],
+            &mut overrides,
+        );
+
+        if let Ok(key) = std::env::var("OPENAI_API_KEY") {
+            self.embedding.openai_api_key = Some(key);
+            overrides.push("OPENAI_API_KEY");
+        }
+        if let Ok(key) = std::env::var("EMBEDDING_API_KEY") {
+            self.embedding.api_key = Some(key);
+            overrides.push("EMBEDDING_API_KEY");
+        }
+        if let Ok(key) = std::env::var("VOYAGE_API_KEY") {
+            self.embedding.voyage_api_key = Some(key);
+            overrides.push("VOYAGE_API_KEY");
+        }
+        if let Ok(key) = std::env::var("COHERE_API_KEY") {
+            self.embedding.cohere_api_key = Some(key);
+            overrides.push("COHERE_API_KEY");
+        }
+        if let Ok(key) = std::env::var("MISTRAL_API_KEY") {
+            self.embedding.mistral_api_key = Some(key);
+            overrides.push("MISTRAL_API_KEY");
+        }
+        if let Ok(key) = std::env::var("QDRANT_API_KEY") {
+            self.vector_db.qdrant_api_key = Some(key);
+            overrides.push("QDRANT_API_KEY");
+        }
+        // The AWS CLI's region variables, the newer one winning
+        for name in ["AWS_DEFAULT_REGION", "AWS_REGION"] {
+            if let Ok(region) = std::env::var(name) {
+                self.embedding.bedrock_region = Some(region);
+                overrides.push(name);
+            }
+        }
+        if let Ok(url) = std::env::var("EMBEDDING_BASE_URL") {
+            self.embedding.base_url = Some(url);
+            overrides.push("EMBEDDING_BASE_URL");
+        }
+
+        if let Ok(path) = std::env::var("SNAPSHOT_PATH") {
+            self.snapshot.path = PathBuf::from(path);
+            overrides.push("SNAPSHOT_PATH");
+        }
+
+        if let Ok(path) = std::env::var("LOCAL_MODEL_CACHE_DIR") {
+            self.embedding.local_cache_dir = PathBuf::from(path);
+            overrides.push("LOCAL_MODEL_CACHE_DIR");
+        }
+
+        if let Ok(path) = std::env::var("EMBEDDING_CACHE_PATH") {
+            self.embedding.cache_path = PathBuf::from(path);
+            overrides.push("EMBEDDING_CACHE_PATH");
+        }
+
+        if let Ok(path) = std::env::var("FULLTEXT_INDEX_PATH") {
+            self.fulltext.path = PathBuf::from(path);
+            overrides.push("FULLTEXT_INDEX_PATH");
+        }
+
+        if let Ok(path) = std::env::var("REMOTE_CACHE_DIR") {
+            self.remote.cache_dir = PathBuf::from(path);
+            overrides.push("REMOTE_CACHE_DIR");
+        }
+
+        if let Ok(hosts) = std::env::var("REMOTE_ALLOWED_HOSTS") {
+            self.remote.allowed_hosts = hosts.split(',').map(str::trim).filter(|h| !h.is_empty()).map(String::from).collect();
+            overrides.push("REMOTE_ALLOWED_HOSTS");
+        }
+
+        if let Ok(scheme) = std::env::var("EDITOR_URI_SCHEME") {
+            self.output.editor_scheme = Some(scheme).filter(|s| !s.is_empty());
+            overrides.push("EDITOR_URI_SCHEME");
+        }
+
+        if let Ok(path) = std::env::var("INDEX_REPORT_PATH") {
+            self.output.index_report_path = Some(PathBuf::from(path)).filter(|p| !p.as_os_str().is_empty());
+            overrides.push("INDEX_REPORT_PATH");
+        }
+
+        if let Ok(path) = std::env::var("AUDIT_LOG") {
+            self.audit.log_path = Some(PathBuf::from(path));
+            overrides.push("AUDIT_LOG");
+        }
+
+        // Platform path list: `:`-separated on Unix, `;` on Windows
+        if let Some(roots) = std::env::var_os("ALLOWED_ROOTS") {
+            self.security.allowed_roots = std::env::split_paths(&roots).filter(|p| !p.as_os_str().is_empty()).collect();
+            overrides.push("ALLOWED_ROOTS");
+        }
+
+        if let Some(value) = parse_env("ENCRYPT_CONTENT", &mut overrides) {
+            self.encryption.enabled = value;
+        }
+        if let Some(value) = parse_env("COMPRESS_CONTENT", &mut overrides) {
+            self.vector_db.compress_content = value;
+        }
+        if let Some(value) = parse_env("EMBEDDING_CACHE", &mut overrides) {
+            self.embedding.cache = value;
+        }
+        if let Some(value) = parse_env("FULLTEXT_INDEX", &mut overrides) {
+            self.fulltext.enabled = value;
+        }
+        if let Some(value) = parse_env("REMOTE_REPOSITORIES", &mut overrides) {
+            self.remote.enabled = value;
+        }
+        if let Some(value) = parse_env("REDACTION_ENABLED", &mut overrides) {
+            self.redaction.enabled = value;
+        }
+        if let Some(value) = parse_env("SNAPSHOT_SQLITE_THRESHOLD", &mut overrides) {
+            self.snapshot.sqlite_threshold = value;
+        }
+        if let Some(value) = parse_env("MAX_INDEXED_PROJECTS", &mut overrides) {
+            self.limits.max_indexed_projects = value;
+        }
+        if let Some(value) = parse_env("MAX_INDEXED_CHUNKS", &mut overrides) {
+            self.limits.max_indexed_chunks = Some(value);
+        }
+        if let Some(value) = parse_env("INDEX_MEMORY_BUDGET_MB", &mut overrides) {
+            self.limits.index_memory_budget_mb = value;
+        }
+        if let Some(value) = parse_env("SEARCH_TIMEOUT_SECS", &mut overrides) {
+            self.limits.search_timeout_secs = value;
+        }
+        if let Some(value) = parse_env("EMBEDDING_DIMENSION", &mut overrides) {
+            self.embedding.dimension = Some(value);
+        }
+        if let Some(value) = parse_env("EMBEDDING_SEND_DIMENSIONS", &mut overrides) {
+            self.embedding.send_dimensions = value;
+        }
+        if let Some(value) = parse_env("EMBEDDING_MIN_CONCURRENCY", &mut overrides) {
+            self.embedding.min_concurrency = value;
+        }
+        if let Some(value) = parse_env("EMBEDDING_MAX_CONCURRENCY", &mut overrides) {
+            self.embedding.max_concurrency = value;
+        }
+        if let Some(value) = parse_env("EMBEDDING_CACHE_MAX_ENTRIES", &mut overrides) {
+            self.embedding.cache_max_entries = value;
+        }
+        // Quotas set in the environment are the selected provider's
+        let provider = self.embedding.provider.to_ascii_lowercase();
+        if let Some(value) = parse_env("EMBEDDING_REQUESTS_PER_MINUTE", &mut overrides) {
+            self.embedding.rate_limits.entry(provider.clone()).or_default().requests_per_minute = Some(value);
+        }
+        if let Some(value) = parse_env("EMBEDDING_TOKENS_PER_MINUTE", &mut overrides) {
+            self.embedding.rate_limits.entry(provider.clone()).or_default().tokens_per_minute = Some(value);
+        }
+        if let Some(value) = parse_env("EMBEDDING_MAX_TOKENS_PER_REQUEST", &mut overrides) {
+            self.embedding.rate_limits.entry(provider).or_default().max_tokens_per_request = Some(value);
+        }
+        if let Some(value) = parse_env("VECTOR_DB_MAX_CONCURRENCY", &mut overrides) {
+            self.vector_db.max_concurrent_requests = value;
+        }
+        if let Some(value) = parse_env("VECTOR_DB_MAX_CONCURRENCY_PER_CALL", &mut overrides) {
+            self.vector_db.max_concurrent_requests_per_call = value;
+        }
+        if let Some(value) = parse_env("PARSE_TIMEOUT_SECS", &mut overrides) {
+            self.limits.parse_timeout_secs = value;
+        }
+        if let Some(value) = parse_env("MAX_CHUNK_AGE_DAYS", &mut overrides) {
+            self.limits.max_chunk_age_days = Some(value);
+        }
+        if let Some(value) = parse_env("MAX_PROJECT_STORAGE_MB", &mut overrides) {
+            self.limits.max_project_storage_mb = Some(value);
+        }
+        if let Some(value) = parse_env("MAX_TOTAL_STORAGE_MB", &mut overrides) {
+            self.limits.max_total_storage_mb = Some(value);
+        }
+        if let Some(value) = parse_env("MAX_FILE_SIZE_MB", &mut overrides) {
+            self.limits.max_file_size_mb = value;
+        }
+        if let Ok(value) = std::env::var("MAX_FILE_SIZE_BY_EXTENSION") {
+            match parse_size_overrides(&value) {
+                Some(sizes) => {
+                    self.limits.max_file_size_by_extension = sizes;
+                    overrides.push("MAX_FILE_SIZE_BY_EXTENSION");
+                }
+                None => tracing::warn!("Ignoring invalid MAX_FILE_SIZE_BY_EXTENSION={}", value),
+            }
+        }
+        if let Ok(value) = std::env::var("LSP_SERVERS") {
+            match parse_lsp_servers(&value) {
+                Some(servers) => {
+                    self.lsp.servers = servers;
+                    overrides.push("LSP_SERVERS");
+                }
+                None => tracing::warn!("Ignoring invalid LSP_SERVERS={}", value),
+            }
+        }
+        if let Some(value) = parse_env("LSP_TIMEOUT_SECS", &mut overrides) {
+            self.lsp.timeout_secs = value;
+        }
+        if let Some(value) = parse_env("BACKGROUND_REFRESH_INTERVAL_SECS", &mut overrides) {
+            self.background_refresh.interval_secs = Some(value);
+        }
+        if let Some(value) = parse_env("BACKGROUND_REFRESH_BUDGET_SECS", &mut overrides) {
+            self.background_refresh.budget_secs = value;
+        }
+
+        self.env_overrides = overrides;
+    }
+
+    /// Human-readable dump of the effective configuration
+    pub fn describe(&self) -> Result<String> {
+        let mut text = String::from("Effective configuration:\n\n");
+        text.push_str(&toml::to_string_pretty(self)?);
+
+        text.push_str("\nConfig files: ");
+        if self.sources.is_empty() {
+            text.push_str("none (defaults)");
+        } else {
+            text.push_str(&display_paths(&self.sources));
+        }
+
+        text.push_str("\nEnvironment overrides: ");
+        if self.env_overrides.is_empty() {
+            text.push_str("none");
+        } else {
+            text.push_str(&self.env_overrides.join(", "));
+        }
+        Ok(text)
+    }
+}
+
+fn read_toml(path: &Path) -> Result<toml::Value> {
+    std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?
+        .parse()
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))
+}
+
+/// Recursively merge `overlay` into `base`; tables merge key by key, any
+/// other value replaces the base value
+fn merge(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Parse a numeric environment variable, warning about (and ignoring) bad values
+fn parse_env<T: std::str::FromStr>(name: &'static str, overrides: &mut Vec<&'static str>) -> Option<T> {
+    let value = std::env::var(name).ok()?;
+    match value.parse() {
+        Ok(parsed) => {
+            overrides.push(name);
+            Some(parsed)
+        }
+        Err(_) => {
+            tracing::warn!("Ignoring invalid {}={}", name, value);
+            None
+        }
+    }
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_file_overrides_global() {
+        let mut merged: toml::Value = "[limits]\nmax_indexed_projects = 5\neviction_policy = \"size\"\n"
+            .parse()
+            .unwrap();
+        merge(&mut merged, "[limits]\nmax_indexed_projects = 20\n".parse().unwrap());
+
+        let config: Config = merged.try_into().unwrap();
+        assert_eq!(config.limits.max_indexed_projects, 20);
+        assert_eq!(config.limits.eviction_policy, "size");
+        assert_eq!(config.vector_db.milvus_address, VectorDbConfig::default().milvus_address);
+        assert!(config.describe().unwrap().contains("max_indexed_projects = 20"));
+    }
+
+    #[test]
+    fn test_embedding_backend() {
+        let mut embedding = EmbeddingConfig::default();
+        assert!(matches!(embedding.backend().unwrap(), EmbeddingBackend::Ollama { .. }));
+
+        embedding.provider = "openai".to_string();
+        assert!(embedding.backend().unwrap_err().to_string().contains("OPENAI_API_KEY"));
+        embedding.openai_api_key = Some("sk-test".to_string());
+        assert_eq!(
+            embedding.backend().unwrap(),
+            EmbeddingBackend::OpenAI {
+                api_key: "sk-test".to_string(),
+                model: "text-embedding-3-small".to_string(),
+                dimension: None,
+            }
+        );
+
+        embedding.provider = "openai-compatible".to_string();
+        embedding.base_url = Some("http://localhost:8000/v1".to_string());
+        assert!(embedding.backend().unwrap_err().to_string().contains("EMBEDDING_DIMENSION"));
+        embedding.dimension = Some(1024);
+        assert!(matches!(
+            embedding.backend().unwrap(),
+            EmbeddingBackend::OpenAICompatible { api_key: None, dimension: 1024, send_dimensions: false, .. }
+        ));
+        // Longer vectors are truncated unless the server is asked for the size
+        assert_eq!(embedding.truncated_dimension(), Some(1024));
+        embedding.send_dimensions = true;
+        assert_eq!(embedding.truncated_dimension(), None);
+        embedding.send_dimensions = false;
+
+        embedding.provider = "voyage".to_string();
+        assert!(embedding.backend().unwrap_err().to_string().contains("VOYAGE_API_KEY"));
+        embedding.voyage_api_key = Some("pa-test".to_string());
+        assert_eq!(
+            embedding.backend().unwrap(),
+            EmbeddingBackend::Voyage {
+                api_key: "pa-test".to_string(),
+                model: "voyage-code-3".to_string(),
+                dimension: Some(1024),
+            }
+        );
+
+        embedding.provider = "cohere".to_string();
+        assert!(embedding.backend().unwrap_err().to_string().contains("COHERE_API_KEY"));
+        embedding.cohere_api_key = Some("co-test".to_string());
+        assert!(matches!(embedding.backend().unwrap(), EmbeddingBackend::Cohere { .. }));
+        assert_eq!(embedding.truncated_dimension(), Some(1024));
+
+        embedding.provider = "mistral".to_string();
+        assert!(embedding.backend().unwrap_err().to_string().contains("MISTRAL_API_KEY"));
+        embedding.mistral_api_key = Some("ms-test".to_string());
+        assert!(matches!(
+            embedding.backend().unwrap(),
+            EmbeddingBackend::Mistral { ref model, dimension: Some(1024), .. } if model == "mistral-embed"
+        ));
+        assert_eq!(embedding.truncated_dimension(), Some(1024));
+        embedding.mistral_model = "codestral-embed".to_string();
+        assert_eq!(embedding.truncated_dimension(), None);
+        embedding.mistral_model = "mistral-embed".to_string();
+
+        embedding.provider = "bedrock".to_string();
+        assert!(embedding.backend().unwrap_err().to_string().contains("AWS_REGION"));
+        embedding.bedrock_region = Some("eu-west-1".to_string());
+        assert_eq!(
+            embedding.backend().unwrap(),
+            EmbeddingBackend::Bedrock {
+                region: "eu-west-1".to_string(),
+                model: "amazon.titan-embed-text-v2:0".to_string(),
+                dimension: Some(1024),
+            }
+        );
+
+        embedding.provider = "local".to_string();
+        assert!(matches!(
+            embedding.backend().unwrap(),
+            EmbeddingBackend::Local { ref model, .. } if model == "bge-small-en-v1.5"
+        ));
+
+        embedding.provider = "gemini".to_string();
+        assert!(embedding.backend().is_err());
+    }
+
+    #[test]
+    fn test_vector_db_backend() {
+        let mut vector_db = VectorDbConfig::default();
+        assert_eq!(vector_db.backend().unwrap(), VectorDbBackend::Milvus { address: "http://127.0.0.1:19530".to_string() });
+        assert_eq!(vector_db.address(), "http://127.0.0.1:19530");
+
+        vector_db.backend = "Qdrant".to_string();
+        vector_db.qdrant_api_key = Some(" ".to_string());
+        assert_eq!(
+            vector_db.backend().unwrap(),
+            VectorDbBackend::Qdrant {
+                address: "http://127.0.0.1:6333".to_string(),
+                api_key: None,
+            }
+        );
+        assert_eq!(vector_db.address(), "http://127.0.0.1:6333");
+
+        vector_db.backend = "pgvector".to_string();
+        assert!(vector_db.backend().unwrap_err().to_string().contains("expected milvus or qdrant"));
+    }
+
+    #[test]
+    fn test_http_client_config() {
+        let config: Config = toml::from_str("[embedding.http]\nrequest_timeout_secs = 0\nmax_idle_connections = 4\n").unwrap();
+        assert_eq!(config.embedding.http.request_timeout_secs, Some(0.0));
+        assert_eq!(config.embedding.http.connect_timeout_secs, 10.0);
+        assert_eq!(config.vector_db.http, HttpClientConfig::default());
+        assert!(config.embedding.http_client().is_ok());
+        assert!(config.vector_db.http.client(DEFAULT_REQUEST_TIMEOUT_SECS).is_ok());
+    }
+
+    #[test]
+    fn test_rate_limits_by_provider() {
+        let config: Config = "[embedding]\nprovider = \"OpenAI\"\n\n[embedding.rate_limits.openai]\ntokens_per_minute = 1000000\n\n[embedding.rate_limits.cohere]\nrequests_per_minute = 100\n"
+            .parse::<toml::Value>()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            config.embedding.rate_limit(),
+            RateLimitConfig {
+                requests_per_minute: None,
+                tokens_per_minute: Some(1_000_000),
+                max_tokens_per_request: None,
+            }
+        );
+
+        let ollama = EmbeddingConfig::default();
+        assert_eq!(ollama.rate_limit(), RateLimitConfig::default());
+    }
+
+    #[test]
+    fn test_file_size_limits() {
+        let limits = LimitsConfig {
+            max_file_size_by_extension: parse_size_overrides("sql=100, .JSON=1").unwrap(),
+            ..Default::default()
+        };
+        let project = BTreeMap::from([("sql".to_string(), 50.0)]);
+        let sizes = limits.file_size_limits(Some(2.0), &project);
+
+        assert_eq!(sizes.limit_for(Path::new("dump.sql")), 50 * 1024 * 1024);
+        assert_eq!(sizes.limit_for(Path::new("data.Json")), 1024 * 1024);
+        assert_eq!(sizes.limit_for(Path::new("main.rs")), 2 * 1024 * 1024);
+        assert_eq!(sizes.largest(), 50 * 1024 * 1024);
+        assert!(parse_size_overrides("sql").is_none());
+    }
+
+    #[test]
+    fn test_lsp_servers() {
+        let servers = parse_lsp_servers("TypeScript=typescript-language-server --stdio, rust=rust-analyzer").unwrap();
+        assert_eq!(servers["typescript"], "typescript-language-server --stdio");
+        assert_eq!(servers["rust"], "rust-analyzer");
+        assert!(parse_lsp_servers("rust=").is_none());
+    }
+
+    #[test]
+    fn test_remote_hosts() {
+        let mut remote = RemoteConfig::default();
+        assert!(!remote.enabled);
+        assert!(remote.allows("https://github.com/rust-lang/log.git"));
+        assert!(remote.allows("git@GitHub.com:rust-lang/log.git"));
+        assert!(!remote.allows("https://169.254.169.254/latest"));
+        assert!(!remote.allows("https://github.com.evil.example/repo"));
+
+        remote.allowed_hosts = vec!["*".to_string()];
+        assert!(remote.allows("ssh://git.internal:2222/repo"));
+    }
+}
@@ -0,0 +1,183 @@
+//! Adaptive limit on concurrent embedding requests while indexing: raised
+//! additively while the provider keeps up and halved when it errors or slows
+//! down (AIMD), so fast machines get more throughput and struggling
+//! providers are backed off
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+/// Limit before any request has been measured (the former fixed value)
+const INITIAL_CONCURRENCY: usize = 5;
+
+/// A request slower than this multiple of the average latency counts as
+/// congestion
+const SLOW_LATENCY_FACTOR: f64 = 3.0;
+
+/// Weight of the newest sample in the latency average
+const LATENCY_EWMA_WEIGHT: f64 = 0.2;
+
+#[derive(Debug)]
+struct State {
+    /// Current limit; fractional so it grows by about one per round trip
+    limit: f64,
+    in_flight: usize,
+    /// Moving average of request latency, the reference for "slow"; it
+    /// follows the provider's load, so a single fast response early on
+    /// doesn't make every later one look slow
+    average: Option<Duration>,
+    /// Last decrease, so a burst of failures halves the limit only once
+    decreased_at: Option<Instant>,
+}
+
+/// AIMD concurrency limit shared by all index runs against one provider
+#[derive(Debug, Clone)]
+pub struct AdaptiveConcurrency {
+    min: usize,
+    max: usize,
+    state: Arc<Mutex<State>>,
+    released: Arc<Notify>,
+}
+
+impl AdaptiveConcurrency {
+    /// A limit between `min` and `max` (equal bounds fix it)
+    pub fn new(min: usize, max: usize) -> Self {
+        let min = min.max(1);
+        let max = max.max(min);
+        Self {
+            min,
+            max,
+            state: Arc::new(Mutex::new(State {
+                limit: INITIAL_CONCURRENCY.clamp(min, max) as f64,
+                in_flight: 0,
+                average: None,
+                decreased_at: None,
+            })),
+            released: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn min(&self) -> usize {
+        self.min
+    }
+
+    /// Upper bound of the limit, for sizing request buffers
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Current limit
+    pub fn limit(&self) -> usize {
+        self.state.lock().unwrap().limit as usize
+    }
+
+    /// Average request latency, `None` before the first request
+    pub fn average_latency(&self) -> Option<Duration> {
+        self.state.lock().unwrap().average
+    }
+
+    /// Wait for a free slot; report the request's outcome on the permit
+    pub async fn acquire(&self) -> ConcurrencyPermit {
+        loop {
+            let released = self.released.notified();
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.in_flight < state.limit as usize {
+                    state.in_flight += 1;
+                    return ConcurrencyPermit { limiter: self.clone() };
+                }
+            }
+            released.await;
+        }
+    }
+
+    fn record(&self, latency: Duration, success: bool) {
+        let mut state = self.state.lock().unwrap();
+        let baseline = state.average.unwrap_or(latency);
+        if success {
+            state.average = Some(baseline.mul_f64(1.0 - LATENCY_EWMA_WEIGHT) + latency.mul_f64(LATENCY_EWMA_WEIGHT));
+        }
+
+        let congested = !success || latency.as_secs_f64() > baseline.as_secs_f64() * SLOW_LATENCY_FACTOR;
+        if congested {
+            // Requests sent before the last decrease still report the old load
+            let recently = state.decreased_at.is_some_and(|at| at.elapsed() < latency);
+            if !recently {
+                state.limit = (state.limit / 2.0).max(self.min as f64);
+                state.decreased_at = Some(Instant::now());
+                tracing::debug!("Embedding concurrency lowered to {}", state.limit as usize);
+            }
+        } else {
+            state.limit = (state.limit + 1.0 / state.limit).min(self.max as f64);
+        }
+    }
+
+    fn release(&self) {
+        self.state.lock().unwrap().in_flight -= 1;
+        self.released.notify_waiters();
+    }
+}
+
+/// A slot of [`AdaptiveConcurrency`], freed on drop
+pub struct ConcurrencyPermit {
+    limiter: AdaptiveConcurrency,
+}
+
+impl ConcurrencyPermit {
+    /// Report how the request went, adjusting the limit
+    pub fn finish(self, latency: Duration, success: bool) {
+        self.limiter.record(latency, success);
+    }
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        self.limiter.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_limit_grows_and_backs_off() {
+        let limiter = AdaptiveConcurrency::new(1, 8);
+        assert_eq!(limiter.limit(), 5);
+
+        // Steady fast requests raise the limit up to the maximum
+        for _ in 0..100 {
+            limiter.acquire().await.finish(Duration::from_millis(10), true);
+        }
+        assert_eq!(limiter.limit(), 8);
+
+        // A failure halves it, once per burst
+        limiter.acquire().await.finish(Duration::from_millis(10), false);
+        limiter.acquire().await.finish(Duration::from_millis(10), false);
+        assert_eq!(limiter.limit(), 4);
+
+        // Requests much slower than the average count as congestion
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        limiter.acquire().await.finish(Duration::from_millis(40), true);
+        assert_eq!(limiter.limit(), 2);
+
+        let _first = limiter.acquire().await;
+        let _second = limiter.acquire().await;
+        assert!(tokio::time::timeout(Duration::from_millis(20), limiter.acquire()).await.is_err());
+
+        assert_eq!(AdaptiveConcurrency::new(3, 3).limit(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_one_fast_request_does_not_pin_the_limit() {
+        let limiter = AdaptiveConcurrency::new(1, 8);
+        limiter.acquire().await.finish(Duration::from_millis(1), true);
+
+        // Steady requests at the provider's usual latency still raise it
+        for _ in 0..100 {
+            limiter.acquire().await.finish(Duration::from_millis(20), true);
+        }
+        assert_eq!(limiter.limit(), 8);
+    }
+}
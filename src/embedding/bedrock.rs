@@ -0,0 +1,500 @@
+//! AWS Bedrock embedding provider (`EMBEDDING_PROVIDER=bedrock`) for the
+//! Titan Text Embeddings and Cohere embed-v3 models, with requests signed
+//! by AWS Signature Version 4.
+//!
+//! Only static credentials are read (environment and shared credentials
+//! file), not SSO, `credential_process`, web identity or instance metadata.
+//! Temporary credentials are re-read when AWS reports them expired, so a
+//! tool refreshing the environment's file (`aws configure export-credentials`,
+//! a credentials helper) keeps the server signing with current ones.
+
+use super::{record_usage, Embedding, EmbeddingProvider};
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+const SERVICE: &str = "bedrock";
+
+/// Texts per request to a Cohere model on Bedrock
+const COHERE_MAX_BATCH_SIZE: usize = 96;
+
+/// Titan embeds one text per request; this many are sent at a time
+const TITAN_CONCURRENCY: usize = 8;
+
+/// AWS credentials, from the environment or the shared credentials file
+#[derive(Clone)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Set for temporary (STS) credentials
+    pub session_token: Option<String>,
+}
+
+impl std::fmt::Debug for AwsCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AwsCredentials {
+    /// Credentials of the standard chain's static sources, in order:
+    /// `AWS_ACCESS_KEY_ID`/`AWS_SECRET_ACCESS_KEY` (and `AWS_SESSION_TOKEN`),
+    /// then the `AWS_PROFILE` (or `default`) profile of the shared
+    /// credentials file (`AWS_SHARED_CREDENTIALS_FILE`, `~/.aws/credentials`)
+    pub fn load() -> Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        if let (Some(access_key_id), Some(secret_access_key)) = (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY")) {
+            return Ok(Self {
+                access_key_id,
+                secret_access_key,
+                session_token: var("AWS_SESSION_TOKEN"),
+            });
+        }
+
+        let path = var("AWS_SHARED_CREDENTIALS_FILE")
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".aws").join("credentials")))
+            .context("No home directory for the AWS credentials file")?;
+        let profile = var("AWS_PROFILE").unwrap_or_else(|| "default".to_string());
+        let text = std::fs::read_to_string(&path).with_context(|| {
+            format!(
+                "No AWS credentials: set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, or add them to {}",
+                path.display()
+            )
+        })?;
+        Self::from_profile(&text, &profile)
+            .with_context(|| format!("No AWS credentials for profile '{}' in {}", profile, path.display()))
+    }
+
+    /// Credentials of `profile` in the INI text of a shared credentials file
+    fn from_profile(text: &str, profile: &str) -> Option<Self> {
+        let mut in_profile = false;
+        let (mut access_key_id, mut secret_access_key, mut session_token) = (None, None, None);
+        for line in text.lines().map(str::trim) {
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                in_profile = section.trim() == profile;
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=').filter(|_| in_profile) else {
+                continue;
+            };
+            let value = Some(value.trim().to_string());
+            match key.trim() {
+                "aws_access_key_id" => access_key_id = value,
+                "aws_secret_access_key" => secret_access_key = value,
+                "aws_session_token" => session_token = value,
+                _ => {}
+            }
+        }
+        Some(Self {
+            access_key_id: access_key_id?,
+            secret_access_key: secret_access_key?,
+            session_token,
+        })
+    }
+}
+
+/// Request and response format of a Bedrock model family
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ModelFamily {
+    /// `amazon.titan-embed-text-v1`: one text per request, 1536 dimensions
+    TitanV1,
+    /// `amazon.titan-embed-text-v2:0`: one text per request, 256/512/1024 dimensions
+    TitanV2,
+    /// `cohere.embed-*-v3`: batches of texts with an input type
+    Cohere,
+}
+
+impl ModelFamily {
+    fn of(model: &str) -> Self {
+        if model.starts_with("cohere.") {
+            ModelFamily::Cohere
+        } else if model.contains("titan-embed-text-v2") {
+            ModelFamily::TitanV2
+        } else {
+            ModelFamily::TitanV1
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TitanRequest<'a> {
+    input_text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normalize: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TitanResponse {
+    embedding: Vec<f32>,
+    #[serde(rename = "inputTextTokenCount", default)]
+    input_text_token_count: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct CohereRequest<'a> {
+    texts: &'a [&'a str],
+    input_type: &'a str,
+    truncate: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct CohereResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+/// AWS Bedrock embedding provider
+pub struct BedrockEmbedding {
+    client: Client,
+    /// Shared with the providers of other models, reloaded when they expire
+    credentials: Arc<RwLock<AwsCredentials>>,
+    region: String,
+    model: String,
+    family: ModelFamily,
+    dimension: usize,
+}
+
+impl BedrockEmbedding {
+    /// `dimension` picks the output size of Titan v2 (256, 512 or 1024);
+    /// `None` uses the model's default
+    pub fn new(credentials: AwsCredentials, region: &str, model: &str, dimension: Option<usize>) -> Self {
+        let family = ModelFamily::of(model);
+        let dimension = match family {
+            ModelFamily::TitanV1 => 1536,
+            ModelFamily::TitanV2 => dimension.unwrap_or(1024),
+            ModelFamily::Cohere => 1024,
+        };
+        Self {
+            client: Client::new(),
+            credentials: Arc::new(RwLock::new(credentials)),
+            region: region.to_string(),
+            model: model.to_string(),
+            family,
+            dimension,
+        }
+    }
+
+    /// Send requests through a client with configured timeouts and pooling
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// POST a signed InvokeModel request and parse its JSON response. A
+    /// request rejected for expired credentials is retried once with the
+    /// credentials loaded again.
+    async fn invoke<T: for<'de> Deserialize<'de>>(&self, body: Vec<u8>) -> Result<T> {
+        let credentials = self.credentials.read().unwrap().clone();
+        let mut response = self.send(&credentials, body.clone()).await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            if !is_expired_credentials(status, &text) {
+                anyhow::bail!("AWS Bedrock API error ({}): {}", status, text);
+            }
+            let reloaded = tokio::task::spawn_blocking(AwsCredentials::load)
+                .await?
+                .context("AWS credentials expired and could not be loaded again")?;
+            if reloaded.access_key_id == credentials.access_key_id && reloaded.session_token == credentials.session_token {
+                anyhow::bail!("AWS Bedrock API error ({}): {} (no newer credentials found)", status, text);
+            }
+            tracing::info!("AWS credentials expired, loaded new ones");
+            *self.credentials.write().unwrap() = reloaded.clone();
+            response = self.send(&reloaded, body).await?;
+        }
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("AWS Bedrock API error ({}): {}", status, body);
+        }
+        response.json().await.context("Failed to parse AWS Bedrock response")
+    }
+
+    /// POST an InvokeModel request signed with `credentials`
+    async fn send(&self, credentials: &AwsCredentials, body: Vec<u8>) -> Result<reqwest::Response> {
+        let host = format!("bedrock-runtime.{}.amazonaws.com", self.region);
+        // Model ids contain `:`, encoded in the path (and again when signing)
+        let path = format!("/model/{}/invoke", uri_encode(&self.model));
+        let amz_date = amz_date(std::time::SystemTime::now());
+
+        let mut headers = vec![
+            ("content-type", "application/json".to_string()),
+            ("host", host.clone()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let authorization = sign(credentials, &self.region, &amz_date, &path, &headers, &body);
+
+        let mut request = self
+            .client
+            .post(format!("https://{}{}", host, path))
+            .header("authorization", authorization)
+            .body(body);
+        for (name, value) in &headers {
+            if *name != "host" {
+                request = request.header(*name, value);
+            }
+        }
+        request.send().await.context("Failed to send request to AWS Bedrock")
+    }
+
+    async fn embed_titan(&self, text: &str) -> Result<Embedding> {
+        let request = TitanRequest {
+            input_text: text,
+            dimensions: (self.family == ModelFamily::TitanV2).then_some(self.dimension),
+            normalize: (self.family == ModelFamily::TitanV2).then_some(true),
+        };
+        let response: TitanResponse = self.invoke(serde_json::to_vec(&request)?).await?;
+        if let Some(tokens) = response.input_text_token_count {
+            record_usage(tokens);
+        }
+        Ok(Embedding {
+            values: response.embedding,
+        })
+    }
+
+    async fn embed_cohere(&self, texts: &[&str], input_type: &str) -> Result<Vec<Embedding>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(COHERE_MAX_BATCH_SIZE) {
+            let request = CohereRequest {
+                texts: batch,
+                input_type,
+                truncate: "END",
+            };
+            let response: CohereResponse = self.invoke(serde_json::to_vec(&request)?).await?;
+            anyhow::ensure!(
+                response.embeddings.len() == batch.len(),
+                "AWS Bedrock returned {} embeddings for {} texts",
+                response.embeddings.len(),
+                batch.len()
+            );
+            embeddings.extend(response.embeddings.into_iter().map(|values| Embedding { values }));
+        }
+        Ok(embeddings)
+    }
+
+    async fn embed_texts(&self, texts: &[&str], input_type: &str) -> Result<Vec<Embedding>> {
+        if self.family == ModelFamily::Cohere {
+            return self.embed_cohere(texts, input_type).await;
+        }
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(TITAN_CONCURRENCY) {
+            let requests = batch.iter().map(|text| self.embed_titan(text));
+            embeddings.extend(futures::future::try_join_all(requests).await?);
+        }
+        Ok(embeddings)
+    }
+
+    async fn embed_one(&self, text: &str, input_type: &str) -> Result<Embedding> {
+        self.embed_texts(&[text], input_type)
+            .await?
+            .into_iter()
+            .next()
+            .context("No embedding returned")
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for BedrockEmbedding {
+    async fn embed(&self, text: &str) -> Result<Embedding> {
+        self.embed_one(text, "search_document").await
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        self.embed_texts(texts, "search_document").await
+    }
+
+    /// Only Cohere models distinguish queries from documents
+    async fn embed_query(&self, text: &str) -> Result<Embedding> {
+        self.embed_one(text, "search_query").await
+    }
+
+    async fn embed_document(&self, text: &str) -> Result<Embedding> {
+        self.embed_one(text, "search_document").await
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn with_model(&self, model: &str) -> Option<Arc<dyn EmbeddingProvider>> {
+        let credentials = self.credentials.read().unwrap().clone();
+        let mut provider = BedrockEmbedding::new(credentials, &self.region, model, None).with_client(self.client.clone());
+        provider.credentials = self.credentials.clone();
+        Some(Arc::new(provider))
+    }
+
+    fn cost_per_million_tokens(&self) -> Option<f64> {
+        match self.family {
+            ModelFamily::TitanV2 => Some(0.02),
+            ModelFamily::TitanV1 | ModelFamily::Cohere => Some(0.10),
+        }
+    }
+}
+
+/// Whether AWS rejected a request because its temporary credentials expired
+fn is_expired_credentials(status: reqwest::StatusCode, body: &str) -> bool {
+    let body = body.to_ascii_lowercase();
+    matches!(status.as_u16(), 400 | 403) && (body.contains("expiredtoken") || body.contains("token included in the request is expired"))
+}
+
+/// `Authorization` header of a Signature Version 4 signed POST to `path`
+/// with no query string; `headers` are the signed headers, lowercase and
+/// sorted by name
+fn sign(
+    credentials: &AwsCredentials,
+    region: &str,
+    amz_date: &str,
+    path: &str,
+    headers: &[(&str, String)],
+    body: &[u8],
+) -> String {
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/{}/aws4_request", date, region, SERVICE);
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+
+    // Paths of services other than S3 are encoded once more for signing
+    let canonical_request = format!(
+        "POST\n{}\n\n{}\n{}\n{}",
+        uri_encode(path).replace("%2F", "/"),
+        canonical_headers,
+        signed_headers,
+        hex::encode(Sha256::digest(body))
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = signing_key(&credentials.secret_access_key, date, region, SERVICE);
+    let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> [u8; 32] {
+    let key = hmac_sha256(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    let key = hmac_sha256(&key, region.as_bytes());
+    let key = hmac_sha256(&key, service.as_bytes());
+    hmac_sha256(&key, b"aws4_request")
+}
+
+/// HMAC-SHA256 (RFC 2104)
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+/// Percent-encode everything but unreserved characters (RFC 3986)
+fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// `YYYYMMDD'T'HHMMSS'Z'` timestamp of `time` in UTC
+fn amz_date(time: std::time::SystemTime) -> String {
+    let secs = time.duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, rest) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_sigv4_signing() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hex::encode(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // Signing key example of the AWS Signature Version 4 documentation
+        assert_eq!(
+            hex::encode(signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam")),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+        assert_eq!(amz_date(UNIX_EPOCH + Duration::from_secs(1_700_000_000)), "20231114T221320Z");
+        assert_eq!(uri_encode("amazon.titan-embed-text-v2:0"), "amazon.titan-embed-text-v2%3A0");
+
+        let credentials = AwsCredentials::from_profile(
+            "[default]\naws_access_key_id = A\naws_secret_access_key = B\n[work]\naws_access_key_id=C\naws_secret_access_key=D\naws_session_token=E\n",
+            "work",
+        )
+        .unwrap();
+        assert_eq!(credentials.access_key_id, "C");
+        assert_eq!(credentials.session_token.as_deref(), Some("E"));
+
+        let headers = [("host", "bedrock-runtime.us-east-1.amazonaws.com".to_string())];
+        let authorization = sign(&credentials, "us-east-1", "20231114T221320Z", "/model/m%3A0/invoke", &headers, b"{}");
+        assert!(authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=C/20231114/us-east-1/bedrock/aws4_request, SignedHeaders=host, Signature="
+        ));
+
+        let expired = r#"{"message":"The security token included in the request is expired"}"#;
+        assert!(is_expired_credentials(reqwest::StatusCode::FORBIDDEN, expired));
+        assert!(!is_expired_credentials(reqwest::StatusCode::FORBIDDEN, r#"{"message":"Access denied"}"#));
+
+        assert_eq!(ModelFamily::of("cohere.embed-english-v3"), ModelFamily::Cohere);
+        assert_eq!(BedrockEmbedding::new(credentials, "us-east-1", "amazon.titan-embed-text-v1", None).dimension(), 1536);
+    }
+}
@@ -0,0 +1,160 @@
+//! Persistent embedding cache: vectors already computed for a chunk text,
+//! keyed by the exact model build and the text's hash, so forced re-indexes
+//! and moved projects don't send the same text to the provider again
+
+use crate::snapshot::unix_now;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Least recently used entries dropped at once when the cache is full, so
+/// a full cache isn't trimmed on every write
+const EVICTION_BATCH: usize = 1000;
+
+/// SQLite store of embedding vectors, as little-endian `f32` blobs, holding
+/// at most `max_entries` vectors. Calls block on SQLite: run them on the
+/// blocking pool (the handle is cheap to clone).
+#[derive(Clone)]
+pub struct EmbeddingCache {
+    conn: Arc<Mutex<Connection>>,
+    max_entries: usize,
+}
+
+impl EmbeddingCache {
+    pub fn open(path: &Path, max_entries: usize) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open embedding cache: {}", path.display()))?;
+
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = NORMAL;
+             CREATE TABLE IF NOT EXISTS embeddings (
+                 model TEXT NOT NULL,
+                 hash TEXT NOT NULL,
+                 vector BLOB NOT NULL,
+                 PRIMARY KEY (model, hash)
+             );",
+        )
+        .context("Failed to initialize embedding cache schema")?;
+        // Caches created before eviction have no use times; theirs start at 0
+        let has_used_at = conn
+            .prepare("SELECT 1 FROM pragma_table_info('embeddings') WHERE name = 'used_at'")?
+            .exists([])?;
+        if !has_used_at {
+            conn.execute_batch("ALTER TABLE embeddings ADD COLUMN used_at INTEGER NOT NULL DEFAULT 0;")?;
+        }
+        conn.execute_batch("CREATE INDEX IF NOT EXISTS embeddings_used_at ON embeddings (used_at);")?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            max_entries: max_entries.max(1),
+        })
+    }
+
+    /// Cached vectors of `model` for each of `hashes`, in order; hits count
+    /// as a use, keeping them from eviction
+    pub fn get(&self, model: &str, hashes: &[&str]) -> Result<Vec<Option<Vec<f32>>>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let vectors = {
+            let mut select = tx.prepare_cached("SELECT vector FROM embeddings WHERE model = ?1 AND hash = ?2")?;
+            let mut touch = tx.prepare_cached("UPDATE embeddings SET used_at = ?3 WHERE model = ?1 AND hash = ?2")?;
+            let now = unix_now() as i64;
+            hashes
+                .iter()
+                .map(|hash| {
+                    let blob: Option<Vec<u8>> = select.query_row(params![model, hash], |row| row.get(0)).optional()?;
+                    if blob.is_some() {
+                        touch.execute(params![model, hash, now])?;
+                    }
+                    Ok(blob.map(|blob| decode(&blob)))
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+        tx.commit()?;
+        Ok(vectors)
+    }
+
+    /// Store vectors of `model` by text hash, in one transaction, dropping
+    /// the least recently used entries beyond the size bound
+    pub fn put<'a>(&self, model: &str, entries: impl IntoIterator<Item = (&'a str, &'a [f32])>) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR REPLACE INTO embeddings (model, hash, vector, used_at) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            let now = unix_now() as i64;
+            for (hash, vector) in entries {
+                stmt.execute(params![model, hash, encode(vector), now])?;
+            }
+        }
+        let count: usize = tx.query_row("SELECT COUNT(*) FROM embeddings", [], |row| row.get(0))?;
+        if count > self.max_entries {
+            let excess = count - self.max_entries + EVICTION_BATCH.min(self.max_entries / 10);
+            tx.execute(
+                "DELETE FROM embeddings WHERE rowid IN (SELECT rowid FROM embeddings ORDER BY used_at LIMIT ?1)",
+                params![excess],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn encode(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+fn decode(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_cache() {
+        let dir = std::env::temp_dir().join(format!("embedding-cache-{}", uuid::Uuid::new_v4()));
+        let cache = EmbeddingCache::open(&dir.join("embeddings.db"), 100).unwrap();
+
+        cache.put("nomic-embed-text@a1:768", [("h1", [0.5, -1.25].as_slice())]).unwrap();
+        let cached = cache.get("nomic-embed-text@a1:768", &["h1", "h2"]).unwrap();
+        assert_eq!(cached, vec![Some(vec![0.5, -1.25]), None]);
+
+        // Another build of the model doesn't see the vector
+        assert_eq!(cache.get("nomic-embed-text@b2:768", &["h1"]).unwrap(), vec![None]);
+
+        // Reopened, the vector is still there
+        drop(cache);
+        let cache = EmbeddingCache::open(&dir.join("embeddings.db"), 100).unwrap();
+        assert_eq!(cache.get("nomic-embed-text@a1:768", &["h1"]).unwrap(), vec![Some(vec![0.5, -1.25])]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_embedding_cache_is_bounded() {
+        let dir = std::env::temp_dir().join(format!("embedding-cache-{}", uuid::Uuid::new_v4()));
+        let cache = EmbeddingCache::open(&dir.join("embeddings.db"), 3).unwrap();
+        let vector = [1.0f32];
+        cache.put("m", [("old", vector.as_slice())]).unwrap();
+        {
+            // Older than anything stored now
+            let conn = cache.conn.lock().unwrap();
+            conn.execute("UPDATE embeddings SET used_at = 1", []).unwrap();
+        }
+        cache.put("m", [("b", vector.as_slice()), ("c", vector.as_slice()), ("d", vector.as_slice())]).unwrap();
+
+        let cached = cache.get("m", &["old", "b", "c", "d"]).unwrap();
+        assert_eq!(cached.iter().map(Option::is_some).collect::<Vec<_>>(), [false, true, true, true]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,162 @@
+//! Cohere embedding provider (`EMBEDDING_PROVIDER=cohere`) for the embed-v3
+//! models
+
+use super::{record_usage, Embedding, EmbeddingProvider};
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const COHERE_EMBED_URL: &str = "https://api.cohere.com/v1/embed";
+
+/// Texts Cohere accepts per request
+const MAX_BATCH_SIZE: usize = 96;
+
+/// Cohere embedding provider
+pub struct CohereEmbedding {
+    client: Client,
+    api_key: String,
+    model: String,
+    dimension: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct CohereRequest<'a> {
+    texts: &'a [&'a str],
+    model: &'a str,
+    /// Required by v3 models: `search_document` or `search_query`
+    input_type: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct CohereResponse {
+    embeddings: Vec<Vec<f32>>,
+    #[serde(default)]
+    meta: Option<CohereMeta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CohereMeta {
+    #[serde(default)]
+    billed_units: Option<CohereBilledUnits>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CohereBilledUnits {
+    #[serde(default)]
+    input_tokens: usize,
+}
+
+impl CohereEmbedding {
+    pub fn new(api_key: &str, model: &str) -> Self {
+        let dimension = if model.contains("light") { 384 } else { 1024 };
+
+        Self {
+            client: Client::new(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            dimension,
+        }
+    }
+
+    /// Send requests through a client with configured timeouts and pooling
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn embed_texts(&self, texts: &[&str], input_type: &str) -> Result<Vec<Embedding>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(MAX_BATCH_SIZE) {
+            embeddings.extend(self.embed_request(batch, input_type).await?);
+        }
+        Ok(embeddings)
+    }
+
+    async fn embed_request(&self, texts: &[&str], input_type: &str) -> Result<Vec<Embedding>> {
+        let request = CohereRequest {
+            texts,
+            model: &self.model,
+            input_type,
+        };
+        let response = self
+            .client
+            .post(COHERE_EMBED_URL)
+            .bearer_auth(&self.api_key)
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to send request to Cohere")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Cohere API error ({}): {}", status, body);
+        }
+
+        let response: CohereResponse = response.json().await.context("Failed to parse Cohere response")?;
+        if let Some(units) = response.meta.and_then(|m| m.billed_units) {
+            tracing::debug!("Cohere embedded {} texts ({} tokens)", texts.len(), units.input_tokens);
+            record_usage(units.input_tokens);
+        }
+        anyhow::ensure!(
+            response.embeddings.len() == texts.len(),
+            "Cohere returned {} embeddings for {} texts",
+            response.embeddings.len(),
+            texts.len()
+        );
+        // Embeddings come back in input order
+        Ok(response.embeddings.into_iter().map(|values| Embedding { values }).collect())
+    }
+
+    async fn embed_one(&self, text: &str, input_type: &str) -> Result<Embedding> {
+        self.embed_texts(&[text], input_type)
+            .await?
+            .into_iter()
+            .next()
+            .context("No embedding returned")
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for CohereEmbedding {
+    async fn embed(&self, text: &str) -> Result<Embedding> {
+        self.embed_one(text, "search_document").await
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        self.embed_texts(texts, "search_document").await
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Embedding> {
+        self.embed_one(text, "search_query").await
+    }
+
+    async fn embed_document(&self, text: &str) -> Result<Embedding> {
+        self.embed_one(text, "search_document").await
+    }
+
+    async fn embed_documents(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        self.embed_texts(texts, "search_document").await
+    }
+
+    fn max_batch_size(&self) -> usize {
+        MAX_BATCH_SIZE
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn with_model(&self, model: &str) -> Option<Arc<dyn EmbeddingProvider>> {
+        Some(Arc::new(CohereEmbedding::new(&self.api_key, model).with_client(self.client.clone())))
+    }
+
+    fn cost_per_million_tokens(&self) -> Option<f64> {
+        self.model.ends_with("-v3.0").then_some(0.10)
+    }
+}
@@ -0,0 +1,114 @@
+//! In-process embedding provider running a small ONNX model through
+//! fastembed, so the server works without Ollama or network access once the
+//! model is downloaded (built with `--features local-embedding`)
+
+use super::{Embedding, EmbeddingProvider};
+use anyhow::{Context, Result};
+use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// Supported models: configured name, fastembed model and vector dimension
+const MODELS: &[(&str, EmbeddingModel, usize)] = &[
+    ("all-minilm-l6-v2", EmbeddingModel::AllMiniLML6V2, 384),
+    ("bge-small-en-v1.5", EmbeddingModel::BGESmallENV15, 384),
+    ("bge-base-en-v1.5", EmbeddingModel::BGEBaseENV15, 768),
+    ("nomic-embed-text-v1.5", EmbeddingModel::NomicEmbedTextV15, 768),
+];
+
+/// Texts run through the model at a time
+const BATCH_SIZE: usize = 32;
+
+/// fastembed embedding provider; the model is downloaded to `cache_dir` and
+/// loaded on first use, not at startup
+pub struct LocalEmbedding {
+    name: String,
+    model: EmbeddingModel,
+    dimension: usize,
+    cache_dir: PathBuf,
+    loaded: OnceCell<Arc<TextEmbedding>>,
+}
+
+impl LocalEmbedding {
+    pub fn new(model: &str, cache_dir: &Path) -> Result<Self> {
+        let (name, model, dimension) = MODELS
+            .iter()
+            .find(|(name, _, _)| name.eq_ignore_ascii_case(model))
+            .with_context(|| {
+                let names: Vec<&str> = MODELS.iter().map(|(name, _, _)| *name).collect();
+                format!("Unknown local embedding model '{}': expected {}", model, names.join(", "))
+            })?;
+
+        Ok(Self {
+            name: name.to_string(),
+            model: model.clone(),
+            dimension: *dimension,
+            cache_dir: cache_dir.to_path_buf(),
+            loaded: OnceCell::new(),
+        })
+    }
+
+    async fn load(&self) -> Result<Arc<TextEmbedding>> {
+        let embedder = self
+            .loaded
+            .get_or_try_init(|| async {
+                let options = InitOptions::new(self.model.clone())
+                    .with_cache_dir(self.cache_dir.clone())
+                    .with_show_download_progress(false);
+                tracing::info!("Loading local embedding model {} from {}", self.name, self.cache_dir.display());
+                let embedder = tokio::task::spawn_blocking(move || TextEmbedding::try_new(options))
+                    .await?
+                    .with_context(|| format!("Failed to load local embedding model {}", self.name))?;
+                Ok::<_, anyhow::Error>(Arc::new(embedder))
+            })
+            .await?;
+        Ok(embedder.clone())
+    }
+
+    /// Run the model off the async runtime, as inference is CPU-bound
+    async fn embed_texts(&self, texts: Vec<String>) -> Result<Vec<Embedding>> {
+        let embedder = self.load().await?;
+        let vectors = tokio::task::spawn_blocking(move || embedder.embed(texts, Some(BATCH_SIZE)))
+            .await?
+            .context("Local embedding failed")?;
+        Ok(vectors.into_iter().map(|values| Embedding { values }).collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for LocalEmbedding {
+    async fn embed(&self, text: &str) -> Result<Embedding> {
+        self.embed_texts(vec![text.to_string()])
+            .await?
+            .pop()
+            .context("Local embedding returned no vector")
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        self.embed_texts(texts.iter().map(|text| text.to_string()).collect()).await
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn model_name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_models() {
+        let embedding = LocalEmbedding::new("BGE-small-en-v1.5", Path::new("/tmp")).unwrap();
+        assert_eq!(embedding.model_name(), "bge-small-en-v1.5");
+        assert_eq!(embedding.dimension(), 384);
+
+        let error = LocalEmbedding::new("gte-large", Path::new("/tmp")).err().unwrap();
+        assert!(error.to_string().contains("all-minilm-l6-v2"));
+    }
+}
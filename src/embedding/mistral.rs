@@ -0,0 +1,258 @@
+//! Mistral embedding provider (`EMBEDDING_PROVIDER=mistral`) for
+//! `mistral-embed` and the code-specific `codestral-embed`
+
+use super::{record_usage, Embedding, EmbeddingProvider};
+use anyhow::{Context, Result};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+const MISTRAL_EMBEDDINGS_URL: &str = "https://api.mistral.ai/v1/embeddings";
+
+/// Texts sent per request
+const MAX_BATCH_SIZE: usize = 64;
+
+/// Characters sent per request, keeping batches of long chunks under
+/// Mistral's per-request token limit (about 4 characters per token)
+const MAX_BATCH_CHARS: usize = 32_000;
+
+/// Retries of a rate-limited or failed request before giving up
+const MAX_RETRIES: u32 = 4;
+
+/// Wait before the first retry when the response says nothing; doubled for
+/// each further attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest wait honored from a `Retry-After` header
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Mistral embedding provider
+pub struct MistralEmbedding {
+    client: Client,
+    api_key: String,
+    model: String,
+    dimension: usize,
+    /// Requested vector size, for models with a choice of dimensions
+    output_dimension: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct MistralRequest<'a> {
+    model: &'a str,
+    input: &'a [&'a str],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_dimension: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MistralResponse {
+    data: Vec<MistralData>,
+    #[serde(default)]
+    usage: Option<MistralUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MistralData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MistralUsage {
+    prompt_tokens: usize,
+}
+
+/// Default vector size of a Mistral model
+fn default_dimension(model: &str) -> usize {
+    if model.starts_with("codestral-embed") {
+        1536
+    } else {
+        1024
+    }
+}
+
+/// Whether the model returns vectors of a requested size
+/// (`output_dimension`); mistral-embed rejects the parameter
+pub fn supports_output_dimension(model: &str) -> bool {
+    model.starts_with("codestral-embed")
+}
+
+/// Wait before retry `attempt` (0-based): the server's `Retry-After` when it
+/// gave one, else exponential backoff
+fn retry_delay(attempt: u32, retry_after: Option<Duration>) -> Duration {
+    retry_after.unwrap_or(INITIAL_BACKOFF * 2u32.pow(attempt)).min(MAX_BACKOFF)
+}
+
+/// Split `texts` into requests bounded by count and characters; a text
+/// longer than the character bound goes alone
+fn batches<'a>(texts: &'a [&'a str]) -> Vec<&'a [&'a str]> {
+    let mut batches = Vec::new();
+    let (mut start, mut chars) = (0, 0);
+    for (i, text) in texts.iter().enumerate() {
+        if i > start && (i - start == MAX_BATCH_SIZE || chars + text.len() > MAX_BATCH_CHARS) {
+            batches.push(&texts[start..i]);
+            (start, chars) = (i, 0);
+        }
+        chars += text.len();
+    }
+    if start < texts.len() {
+        batches.push(&texts[start..]);
+    }
+    batches
+}
+
+impl MistralEmbedding {
+    /// `dimension` picks the output size of models that support several
+    /// (codestral-embed: up to 3072); other models ignore it and use their
+    /// default, as does `None`
+    pub fn new(api_key: &str, model: &str, dimension: Option<usize>) -> Self {
+        let output_dimension = dimension.filter(|_| supports_output_dimension(model));
+        Self {
+            client: Client::new(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            dimension: output_dimension.unwrap_or_else(|| default_dimension(model)),
+            output_dimension,
+        }
+    }
+
+    /// Send requests through a client with configured timeouts and pooling
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn embed_texts(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in batches(texts) {
+            embeddings.extend(self.embed_request(batch).await?);
+        }
+        Ok(embeddings)
+    }
+
+    /// Send one request, retrying when rate limited (429) or when Mistral
+    /// fails (5xx)
+    async fn embed_request(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        let request = MistralRequest {
+            model: &self.model,
+            input: texts,
+            output_dimension: self.output_dimension,
+        };
+
+        let mut attempt = 0;
+        let response = loop {
+            let response = self
+                .client
+                .post(MISTRAL_EMBEDDINGS_URL)
+                .bearer_auth(&self.api_key)
+                .json(&request)
+                .send()
+                .await
+                .context("Failed to send request to Mistral")?;
+
+            let status = response.status();
+            if status.is_success() {
+                break response;
+            }
+            let retryable = status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+            if !retryable || attempt == MAX_RETRIES {
+                let body = response.text().await.unwrap_or_default();
+                anyhow::bail!("Mistral API error ({}): {}", status, body);
+            }
+
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+            let delay = retry_delay(attempt, retry_after);
+            tracing::warn!(
+                "Mistral returned {}, retrying in {:.1}s ({}/{})",
+                status,
+                delay.as_secs_f64(),
+                attempt + 1,
+                MAX_RETRIES
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        };
+
+        let response: MistralResponse = response.json().await.context("Failed to parse Mistral response")?;
+        if let Some(usage) = &response.usage {
+            tracing::debug!("Mistral embedded {} texts ({} tokens)", texts.len(), usage.prompt_tokens);
+            record_usage(usage.prompt_tokens);
+        }
+        anyhow::ensure!(
+            response.data.len() == texts.len(),
+            "Mistral returned {} embeddings for {} texts",
+            response.data.len(),
+            texts.len()
+        );
+
+        let mut data = response.data;
+        data.sort_by_key(|d| d.index);
+        Ok(data.into_iter().map(|d| Embedding { values: d.embedding }).collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for MistralEmbedding {
+    async fn embed(&self, text: &str) -> Result<Embedding> {
+        self.embed_texts(&[text])
+            .await?
+            .into_iter()
+            .next()
+            .context("No embedding returned")
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        self.embed_texts(texts).await
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    /// The output dimension is only kept for the configured model
+    fn with_model(&self, model: &str) -> Option<Arc<dyn EmbeddingProvider>> {
+        Some(Arc::new(MistralEmbedding::new(&self.api_key, model, None).with_client(self.client.clone())))
+    }
+
+    fn cost_per_million_tokens(&self) -> Option<f64> {
+        match self.model.as_str() {
+            "mistral-embed" => Some(0.10),
+            "codestral-embed" | "codestral-embed-2505" => Some(0.15),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mistral_request() {
+        assert_eq!(MistralEmbedding::new("key", "mistral-embed", None).dimension(), 1024);
+        assert_eq!(MistralEmbedding::new("key", "codestral-embed", None).dimension(), 1536);
+        assert_eq!(MistralEmbedding::new("key", "codestral-embed", Some(256)).dimension(), 256);
+        let fixed = MistralEmbedding::new("key", "mistral-embed", Some(256));
+        assert_eq!((fixed.dimension(), fixed.output_dimension), (1024, None));
+
+        let long = "x".repeat(MAX_BATCH_CHARS);
+        let texts: Vec<&str> = std::iter::repeat_n("fn main() {}", 100).chain([long.as_str(), "end"]).collect();
+        let sizes: Vec<usize> = batches(&texts).iter().map(|b| b.len()).collect();
+        assert_eq!(sizes, vec![64, 36, 1, 1]);
+
+        assert_eq!(retry_delay(0, None), Duration::from_secs(1));
+        assert_eq!(retry_delay(3, None), Duration::from_secs(8));
+        assert_eq!(retry_delay(0, Some(Duration::from_secs(5))), Duration::from_secs(5));
+        assert_eq!(retry_delay(0, Some(Duration::from_secs(600))), MAX_BACKOFF);
+    }
+}
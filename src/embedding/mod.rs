@@ -1,8 +1,23 @@
+pub mod adaptive;
+pub mod bedrock;
+pub mod cache;
+pub mod cohere;
+#[cfg(feature = "local-embedding")]
+pub mod local;
+pub mod mistral;
 pub mod ollama;
 pub mod openai;
+pub mod openai_compatible;
+pub mod prefix;
+pub mod rate_limit;
+pub mod truncate;
+pub mod voyage;
 
 use anyhow::Result;
 use serde::Deserialize;
+use std::cell::Cell;
+use std::future::Future;
+use std::sync::Arc;
 
 /// Embedding vector result
 #[derive(Debug, Clone, Deserialize)]
@@ -10,10 +25,114 @@ pub struct Embedding {
     pub values: Vec<f32>,
 }
 
+tokio::task_local! {
+    /// Tokens billed by the provider for the embedding call being measured
+    static BILLED_TOKENS: Cell<Option<usize>>;
+}
+
+/// Record the tokens a provider's response reports it billed, for the
+/// caller measuring the call with [`measure_usage`]
+pub fn record_usage(tokens: usize) {
+    let _ = BILLED_TOKENS.try_with(|billed| billed.set(Some(billed.get().unwrap_or(0) + tokens)));
+}
+
+/// Run an embedding call, returning the tokens the provider reported
+/// billing for it (retries included), `None` if it reported none
+pub async fn measure_usage<F: Future>(call: F) -> (F::Output, Option<usize>) {
+    BILLED_TOKENS
+        .scope(Cell::new(None), async {
+            let output = call.await;
+            (output, BILLED_TOKENS.with(|billed| billed.get()))
+        })
+        .await
+}
+
+/// Whether an embedding error says the text is longer than the model's
+/// context, in the wording of Ollama, OpenAI and compatible servers and
+/// Mistral
+pub fn is_context_overflow(error: &anyhow::Error) -> bool {
+    let message = format!("{:#}", error).to_ascii_lowercase();
+    [
+        "context length",
+        "context window",
+        "maximum context",
+        "too many tokens",
+        "input is too long",
+        "token limit",
+    ]
+    .iter()
+    .any(|wording| message.contains(wording))
+}
+
 /// Embedding provider trait
 #[async_trait::async_trait]
 pub trait EmbeddingProvider: Send + Sync {
     async fn embed(&self, text: &str) -> Result<Embedding>;
     async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>>;
+    /// Embed a search query; providers with a native query/document input
+    /// type override this (text prefixes are added by
+    /// [`PrefixedEmbedding`](prefix::PrefixedEmbedding))
+    async fn embed_query(&self, text: &str) -> Result<Embedding> {
+        self.embed(text).await
+    }
+    /// Embed a chunk being indexed (counterpart of [`embed_query`](Self::embed_query))
+    async fn embed_document(&self, text: &str) -> Result<Embedding> {
+        self.embed(text).await
+    }
+    /// Embed chunks being indexed in one request, at most
+    /// [`max_batch_size`](Self::max_batch_size) of them
+    async fn embed_documents(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for text in texts {
+            embeddings.push(self.embed_document(text).await?);
+        }
+        Ok(embeddings)
+    }
+    /// Chunks indexing sends per request: the batch limit of providers
+    /// overriding [`embed_documents`](Self::embed_documents), else 1
+    fn max_batch_size(&self) -> usize {
+        1
+    }
     fn dimension(&self) -> usize;
+    /// Dimension confirmed by the model itself; providers that can only
+    /// guess it from the model name embed a probe text once and report the
+    /// vector length from then on
+    async fn probe_dimension(&self) -> Result<usize> {
+        Ok(self.dimension())
+    }
+    /// Name of the embedding model used by this provider
+    fn model_name(&self) -> &str;
+    /// Key of the vectors [`embed_query`](Self::embed_query) returns in the
+    /// query cache: the model name, plus whatever else changes them (such
+    /// as a query prefix or a truncated dimension)
+    fn query_cache_key(&self) -> String {
+        self.model_name().to_string()
+    }
+    /// Identifier of the exact model build (such as an Ollama digest), so a
+    /// model re-pulled under the same name is noticed; `None` if unknown
+    async fn model_version(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+    /// Create a provider of the same kind for a different model, if supported
+    fn with_model(&self, _model: &str) -> Option<Arc<dyn EmbeddingProvider>> {
+        None
+    }
+    /// Price in USD per million embedded tokens (`None` for local models)
+    fn cost_per_million_tokens(&self) -> Option<f64> {
+        None
+    }
+    /// Verify the provider is reachable and the model produces vectors of
+    /// the expected dimension, returning a short description
+    async fn health_check(&self) -> Result<String> {
+        let embedding = self.embed("health check").await?;
+        if embedding.values.len() != self.dimension() {
+            anyhow::bail!(
+                "Model '{}' returned {}-dimensional vectors but {} were expected",
+                self.model_name(),
+                embedding.values.len(),
+                self.dimension()
+            );
+        }
+        Ok(format!("model '{}' returns {}-dimensional vectors", self.model_name(), embedding.values.len()))
+    }
 }
@@ -2,13 +2,20 @@ use super::{Embedding, EmbeddingProvider};
 use anyhow::{Context, Result};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+
+/// Text embedded to learn the model's dimension
+const PROBE_TEXT: &str = "dimension probe";
 
 /// Ollama embedding provider
 pub struct OllamaEmbedding {
     client: Client,
     host: String,
     model: String,
+    /// Guessed from the model name until a vector was returned
     dimension: usize,
+    /// Length of the vectors the model returned
+    probed_dimension: OnceLock<usize>,
 }
 
 #[derive(Debug, Serialize)]
@@ -22,9 +29,23 @@ struct OllamaEmbeddingResponse {
     embedding: Vec<f32>,
 }
 
+#[derive(Debug, Deserialize)]
+struct OllamaTagsResponse {
+    #[serde(default)]
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaModel {
+    name: String,
+    #[serde(default)]
+    digest: Option<String>,
+}
+
 impl OllamaEmbedding {
     pub fn new(host: &str, model: &str) -> Self {
-        // Dimension depends on model - nomic-embed-text is 768
+        // Dimension depends on model - nomic-embed-text is 768; custom
+        // models are probed (see `probe_dimension`)
         let dimension = if model.contains("nomic") {
             768
         } else if model.contains("mxbai") {
@@ -40,6 +61,47 @@ impl OllamaEmbedding {
             host: host.trim_end_matches('/').to_string(),
             model: model.to_string(),
             dimension,
+            probed_dimension: OnceLock::new(),
+        }
+    }
+
+    /// Send requests through a client with configured timeouts and pooling
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// The configured model as listed by Ollama, `None` if it isn't pulled
+    async fn pulled_model(&self) -> Result<Option<OllamaModel>> {
+        let tags: OllamaTagsResponse = self
+            .client
+            .get(format!("{}/api/tags", self.host))
+            .send()
+            .await
+            .with_context(|| format!("Ollama is not reachable at {}", self.host))?
+            .json()
+            .await
+            .context("Failed to parse Ollama model list")?;
+
+        // Pulled models are listed with a tag, e.g. "nomic-embed-text:latest"
+        Ok(tags
+            .models
+            .into_iter()
+            .find(|m| m.name == self.model || m.name.split(':').next() == Some(self.model.as_str())))
+    }
+
+    /// Remember the length of the first vector the model returned
+    fn record_dimension(&self, len: usize) {
+        if len == 0 || self.probed_dimension.get().is_some() {
+            return;
+        }
+        if self.probed_dimension.set(len).is_ok() && len != self.dimension {
+            tracing::info!(
+                "Model '{}' returns {}-dimensional vectors, not the {} guessed from its name",
+                self.model,
+                len,
+                self.dimension
+            );
         }
     }
 
@@ -69,6 +131,7 @@ impl OllamaEmbedding {
             .json()
             .await
             .context("Failed to parse Ollama response")?;
+        self.record_dimension(embedding_response.embedding.len());
 
         Ok(Embedding {
             values: embedding_response.embedding,
@@ -93,6 +156,65 @@ impl EmbeddingProvider for OllamaEmbedding {
     }
 
     fn dimension(&self) -> usize {
-        self.dimension
+        self.probed_dimension.get().copied().unwrap_or(self.dimension)
+    }
+
+    async fn probe_dimension(&self) -> Result<usize> {
+        if let Some(dimension) = self.probed_dimension.get() {
+            return Ok(*dimension);
+        }
+        let embedding = self
+            .embed_single(PROBE_TEXT)
+            .await
+            .with_context(|| format!("Failed to probe the dimension of model '{}'", self.model))?;
+        anyhow::ensure!(!embedding.values.is_empty(), "Model '{}' returned an empty vector", self.model);
+        Ok(self.dimension())
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn with_model(&self, model: &str) -> Option<Arc<dyn EmbeddingProvider>> {
+        Some(Arc::new(OllamaEmbedding::new(&self.host, model).with_client(self.client.clone())))
+    }
+
+    async fn model_version(&self) -> Result<Option<String>> {
+        Ok(self.pulled_model().await?.and_then(|m| m.digest))
+    }
+
+    async fn health_check(&self) -> Result<String> {
+        if self.pulled_model().await?.is_none() {
+            anyhow::bail!("Model '{}' is not available in Ollama at {}", self.model, self.host);
+        }
+
+        let embedding = self.embed_single("health check").await?;
+        if embedding.values.len() != self.dimension() {
+            anyhow::bail!(
+                "Model '{}' returned {}-dimensional vectors but {} were expected",
+                self.model,
+                embedding.values.len(),
+                self.dimension()
+            );
+        }
+        Ok(format!("{} reachable, model '{}' returns {}-dimensional vectors", self.host, self.model, self.dimension()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_probed_dimension_replaces_guess() {
+        // Nothing listens on this port: a probed dimension needs no request
+        let embedding = OllamaEmbedding::new("http://127.0.0.1:9", "acme-code-embedder");
+        assert_eq!(embedding.dimension(), 768);
+        assert!(embedding.probe_dimension().await.is_err());
+
+        embedding.record_dimension(1536);
+        embedding.record_dimension(1024);
+        assert_eq!(embedding.dimension(), 1536);
+        assert_eq!(embedding.probe_dimension().await.unwrap(), 1536);
     }
 }
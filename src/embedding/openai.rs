@@ -1,10 +1,12 @@
-//! OpenAI Embedding provider (reserved for future use)
-#![allow(dead_code)]
+//! OpenAI embedding provider (`EMBEDDING_PROVIDER=openai`)
 
+use super::openai_compatible::post_embeddings;
 use super::{Embedding, EmbeddingProvider};
 use anyhow::{Context, Result};
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const OPENAI_EMBEDDINGS_URL: &str = "https://api.openai.com/v1/embeddings";
 
 /// OpenAI embedding provider
 pub struct OpenAIEmbedding {
@@ -12,34 +14,20 @@ pub struct OpenAIEmbedding {
     api_key: String,
     model: String,
     dimension: usize,
+    /// Requested vector size (`dimensions`), for text-embedding-3 models
+    output_dimension: Option<usize>,
 }
 
-#[derive(Debug, Serialize)]
-struct OpenAIEmbeddingRequest {
-    model: String,
-    input: Vec<String>,
-}
-
-#[derive(Debug, Deserialize)]
-struct OpenAIEmbeddingResponse {
-    data: Vec<EmbeddingData>,
-    usage: Usage,
-}
-
-#[derive(Debug, Deserialize)]
-struct EmbeddingData {
-    index: usize,
-    embedding: Vec<f32>,
-}
-
-#[derive(Debug, Deserialize)]
-struct Usage {
-    prompt_tokens: usize,
-    total_tokens: usize,
+/// Whether the model shortens its vectors on request (`dimensions`)
+pub fn supports_dimensions(model: &str) -> bool {
+    model.starts_with("text-embedding-3")
 }
 
 impl OpenAIEmbedding {
-    pub fn new(api_key: &str, model: &str) -> Self {
+    /// `dimension` shortens the vectors of text-embedding-3 models; it is
+    /// ignored for others, which only return their full size
+    pub fn new(api_key: &str, model: &str, dimension: Option<usize>) -> Self {
+        let output_dimension = dimension.filter(|_| supports_dimensions(model));
         let dimension = match model {
             "text-embedding-3-small" => 1536,
             "text-embedding-3-large" => 3072,
@@ -51,9 +39,16 @@ impl OpenAIEmbedding {
             client: Client::new(),
             api_key: api_key.to_string(),
             model: model.to_string(),
-            dimension,
+            dimension: output_dimension.unwrap_or(dimension),
+            output_dimension,
         }
     }
+
+    /// Send requests through a client with configured timeouts and pooling
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -67,48 +62,28 @@ impl EmbeddingProvider for OpenAIEmbedding {
     }
 
     async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
-        let url = "https://api.openai.com/v1/embeddings";
-
-        let request = OpenAIEmbeddingRequest {
-            model: self.model.clone(),
-            input: texts.iter().map(|s| s.to_string()).collect(),
-        };
-
-        let response = self
-            .client
-            .post(url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&request)
-            .send()
-            .await
-            .context("Failed to send request to OpenAI")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("OpenAI API error ({}): {}", status, body);
-        }
-
-        let embedding_response: OpenAIEmbeddingResponse = response
-            .json()
-            .await
-            .context("Failed to parse OpenAI response")?;
+        post_embeddings(&self.client, OPENAI_EMBEDDINGS_URL, Some(&self.api_key), &self.model, texts, self.output_dimension, "OpenAI").await
+    }
 
-        // Sort by index to maintain order
-        let mut sorted_data = embedding_response.data;
-        sorted_data.sort_by_key(|d| d.index);
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
 
-        let embeddings: Vec<Embedding> = sorted_data
-            .into_iter()
-            .map(|d| Embedding {
-                values: d.embedding,
-            })
-            .collect();
+    fn model_name(&self) -> &str {
+        &self.model
+    }
 
-        Ok(embeddings)
+    /// The output dimension is only kept for the configured model
+    fn with_model(&self, model: &str) -> Option<Arc<dyn EmbeddingProvider>> {
+        Some(Arc::new(OpenAIEmbedding::new(&self.api_key, model, None).with_client(self.client.clone())))
     }
 
-    fn dimension(&self) -> usize {
-        self.dimension
+    fn cost_per_million_tokens(&self) -> Option<f64> {
+        match self.model.as_str() {
+            "text-embedding-3-small" => Some(0.02),
+            "text-embedding-3-large" => Some(0.13),
+            "text-embedding-ada-002" => Some(0.10),
+            _ => None,
+        }
     }
 }
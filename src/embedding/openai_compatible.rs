@@ -0,0 +1,154 @@
+//! Embedding provider for servers exposing an OpenAI-compatible
+//! `/v1/embeddings` endpoint (vLLM, LM Studio, LiteLLM, ...), selected with
+//! `EMBEDDING_PROVIDER=openai-compatible`
+
+use super::{record_usage, Embedding, EmbeddingProvider};
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+/// OpenAI-compatible embedding provider. It doesn't switch models for a
+/// project (`with_model`), since other models' dimensions aren't known.
+pub struct OpenAICompatibleEmbedding {
+    client: Client,
+    /// Base URL up to and including the API version, e.g. `http://localhost:8000/v1`
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    dimension: usize,
+    /// Whether the server is asked for `dimension` (`dimensions`); if not,
+    /// longer vectors are truncated by the caller
+    send_dimensions: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbeddingsRequest<'a> {
+    model: &'a str,
+    input: &'a [&'a str],
+    /// Output size, for models trained to shorten their vectors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+    /// Not reported by every server
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    total_tokens: usize,
+}
+
+/// POST `texts` to an OpenAI-style embeddings endpoint, returning the
+/// embeddings in input order; `service` names the server in errors and
+/// `dimensions` asks for shortened vectors
+pub(super) async fn post_embeddings(
+    client: &Client,
+    url: &str,
+    api_key: Option<&str>,
+    model: &str,
+    texts: &[&str],
+    dimensions: Option<usize>,
+    service: &str,
+) -> Result<Vec<Embedding>> {
+    let mut request = client.post(url).json(&EmbeddingsRequest {
+        model,
+        input: texts,
+        dimensions,
+    });
+    if let Some(api_key) = api_key {
+        request = request.bearer_auth(api_key);
+    }
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("Failed to send request to {}", service))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("{} API error ({}): {}", service, status, body);
+    }
+
+    let response: EmbeddingsResponse = response
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse {} response", service))?;
+    if let Some(usage) = &response.usage {
+        tracing::debug!("{} embedded {} texts ({} tokens)", service, texts.len(), usage.total_tokens);
+        record_usage(usage.total_tokens);
+    }
+    anyhow::ensure!(
+        response.data.len() == texts.len(),
+        "{} returned {} embeddings for {} texts",
+        service,
+        response.data.len(),
+        texts.len()
+    );
+
+    // Sort by index to maintain order
+    let mut data = response.data;
+    data.sort_by_key(|d| d.index);
+    Ok(data.into_iter().map(|d| Embedding { values: d.embedding }).collect())
+}
+
+impl OpenAICompatibleEmbedding {
+    pub fn new(base_url: &str, api_key: Option<&str>, model: &str, dimension: usize) -> Self {
+        Self {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.map(str::to_string),
+            model: model.to_string(),
+            dimension,
+            send_dimensions: false,
+        }
+    }
+
+    /// Ask the server for vectors of the configured dimension, for models
+    /// trained to shorten them
+    pub fn with_dimensions_sent(mut self, send_dimensions: bool) -> Self {
+        self.send_dimensions = send_dimensions;
+        self
+    }
+
+    /// Send requests through a client with configured timeouts and pooling
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for OpenAICompatibleEmbedding {
+    async fn embed(&self, text: &str) -> Result<Embedding> {
+        self.embed_batch(&[text])
+            .await?
+            .into_iter()
+            .next()
+            .context("No embedding returned")
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        let url = format!("{}/embeddings", self.base_url);
+        let dimensions = self.send_dimensions.then_some(self.dimension);
+        post_embeddings(&self.client, &url, self.api_key.as_deref(), &self.model, texts, dimensions, &self.base_url).await
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+}
@@ -0,0 +1,254 @@
+//! Task prefixes of models trained for asymmetric retrieval: the built-in
+//! ones of known model families, replaced or extended per model by
+//! `[embedding.task_prefixes]`
+
+use super::{Embedding, EmbeddingProvider};
+use crate::config::TaskPrefixConfig;
+use crate::parser::code_parser::CodeParser;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Text prefixes (query, document) that models trained for asymmetric
+/// retrieval expect, by model family. Names are matched without their
+/// organization (`intfloat/`) and Ollama tag (`:latest`), so `e5` only
+/// matches the e5 models and not any name containing those letters.
+pub fn task_prefixes(model: &str) -> Option<(&'static str, &'static str)> {
+    let model = model.to_ascii_lowercase();
+    let name = model.rsplit('/').next().unwrap_or(&model);
+    let name = name.split(':').next().unwrap_or(name);
+    let e5 = name.strip_prefix("multilingual-").unwrap_or(name);
+    if name.starts_with("nomic-embed-text") {
+        Some(("search_query: ", "search_document: "))
+    } else if e5.starts_with("e5-") && !e5.contains("instruct") {
+        Some(("query: ", "passage: "))
+    } else if name.starts_with("mxbai-embed-large")
+        || ["bge-small-en", "bge-base-en", "bge-large-en"].iter().any(|family| name.starts_with(family))
+    {
+        Some(("Represent this sentence for searching relevant passages: ", ""))
+    } else {
+        None
+    }
+}
+
+/// Embedding provider wrapper prepending the query prefix to search queries
+/// and the document prefix to indexed chunks: the configured ones of the
+/// model, else its built-in [`task_prefixes`]. The prefixed text still goes
+/// through the provider's own query and document calls, so providers with
+/// a native input type (Voyage, Cohere) keep sending it.
+pub struct PrefixedEmbedding {
+    inner: Arc<dyn EmbeddingProvider>,
+    prefixes: Arc<BTreeMap<String, TaskPrefixConfig>>,
+}
+
+impl PrefixedEmbedding {
+    pub fn new(inner: Arc<dyn EmbeddingProvider>, prefixes: Arc<BTreeMap<String, TaskPrefixConfig>>) -> Self {
+        Self { inner, prefixes }
+    }
+
+    /// (query, document) prefixes of the model, `None` if it has none
+    fn prefixes(&self) -> Option<(&str, &str)> {
+        let prefixes = match self.prefixes.get(self.inner.model_name()) {
+            Some(configured) => (configured.query.as_str(), configured.document.as_str()),
+            None => task_prefixes(self.inner.model_name())?,
+        };
+        (prefixes != ("", "")).then_some(prefixes)
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for PrefixedEmbedding {
+    async fn embed(&self, text: &str) -> Result<Embedding> {
+        self.inner.embed(text).await
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        self.inner.embed_batch(texts).await
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Embedding> {
+        match self.prefixes() {
+            Some((query, _)) => self.inner.embed_query(&format!("{}{}", query, text)).await,
+            None => self.inner.embed_query(text).await,
+        }
+    }
+
+    async fn embed_document(&self, text: &str) -> Result<Embedding> {
+        match self.prefixes() {
+            Some((_, document)) => self.inner.embed_document(&format!("{}{}", document, text)).await,
+            None => self.inner.embed_document(text).await,
+        }
+    }
+
+    async fn embed_documents(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        let Some((_, document)) = self.prefixes() else {
+            return self.inner.embed_documents(texts).await;
+        };
+        let prefixed: Vec<String> = texts.iter().map(|text| format!("{}{}", document, text)).collect();
+        self.inner.embed_documents(&prefixed.iter().map(String::as_str).collect::<Vec<_>>()).await
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.inner.max_batch_size()
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    async fn probe_dimension(&self) -> Result<usize> {
+        self.inner.probe_dimension().await
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn query_cache_key(&self) -> String {
+        match self.prefixes() {
+            Some((query, _)) => format!("{}+prefix:{}", self.inner.query_cache_key(), &CodeParser::hash_file(query)[..8]),
+            None => self.inner.query_cache_key(),
+        }
+    }
+
+    /// Both prefixes are part of the version: chunks embedded without the
+    /// document prefix (or with another one) are re-embedded and not served
+    /// from the embedding cache, and a changed query prefix changes the
+    /// index fingerprint, as the stored vectors were matched to the old one
+    async fn model_version(&self) -> Result<Option<String>> {
+        let version = self.inner.model_version().await?;
+        Ok(match self.prefixes() {
+            Some((query, document)) => {
+                let prefixes = CodeParser::hash_file(&format!("{}\n{}", query, document));
+                Some(format!("{}+prefix:{}", version.unwrap_or_default(), &prefixes[..8]))
+            }
+            None => version,
+        })
+    }
+
+    fn with_model(&self, model: &str) -> Option<Arc<dyn EmbeddingProvider>> {
+        Some(Arc::new(Self::new(self.inner.with_model(model)?, self.prefixes.clone())))
+    }
+
+    fn cost_per_million_tokens(&self) -> Option<f64> {
+        self.inner.cost_per_million_tokens()
+    }
+
+    async fn health_check(&self) -> Result<String> {
+        self.inner.health_check().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Records the texts it is asked to embed
+    #[derive(Default)]
+    struct Recorder {
+        texts: Mutex<Vec<String>>,
+        model: Option<&'static str>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmbeddingProvider for Recorder {
+        async fn embed(&self, text: &str) -> Result<Embedding> {
+            self.texts.lock().unwrap().push(text.to_string());
+            Ok(Embedding { values: vec![1.0] })
+        }
+
+        async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+            let mut embeddings = Vec::new();
+            for text in texts {
+                embeddings.push(self.embed(text).await?);
+            }
+            Ok(embeddings)
+        }
+
+        fn dimension(&self) -> usize {
+            1
+        }
+
+        /// Native input type, as Voyage and Cohere send it
+        async fn embed_query(&self, text: &str) -> Result<Embedding> {
+            self.embed(&format!("[query] {}", text)).await
+        }
+
+        fn model_name(&self) -> &str {
+            self.model.unwrap_or("custom-embed")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_configured_prefixes() {
+        let recorder = Arc::new(Recorder::default());
+        let prefixes = BTreeMap::from([(
+            "custom-embed".to_string(),
+            TaskPrefixConfig {
+                query: "search_query: ".to_string(),
+                document: "search_document: ".to_string(),
+            },
+        )]);
+        let prefixed = PrefixedEmbedding::new(recorder.clone(), Arc::new(prefixes));
+        prefixed.embed_query("parse config").await.unwrap();
+        prefixed.embed_document("fn parse() {}").await.unwrap();
+        assert_eq!(*recorder.texts.lock().unwrap(), ["[query] search_query: parse config", "search_document: fn parse() {}"]);
+        assert!(prefixed.model_version().await.unwrap().unwrap().starts_with("+prefix:"));
+        assert!(prefixed.query_cache_key().starts_with("custom-embed+prefix:"));
+
+        // Another query prefix changes the version and the query cache key
+        let requery = BTreeMap::from([(
+            "custom-embed".to_string(),
+            TaskPrefixConfig {
+                query: "query: ".to_string(),
+                document: "search_document: ".to_string(),
+            },
+        )]);
+        let requeried = PrefixedEmbedding::new(recorder.clone(), Arc::new(requery));
+        assert_ne!(requeried.model_version().await.unwrap(), prefixed.model_version().await.unwrap());
+        assert_ne!(requeried.query_cache_key(), prefixed.query_cache_key());
+
+        let unconfigured = PrefixedEmbedding::new(recorder.clone(), Arc::new(BTreeMap::new()));
+        unconfigured.embed_query("retry").await.unwrap();
+        assert_eq!(recorder.texts.lock().unwrap().last().unwrap(), "[query] retry");
+        assert_eq!(unconfigured.model_version().await.unwrap(), None);
+        assert_eq!(unconfigured.query_cache_key(), "custom-embed");
+    }
+
+    #[test]
+    fn test_task_prefixes_match_model_families() {
+        for model in ["nomic-embed-text", "nomic-embed-text:v1.5", "nomic-ai/nomic-embed-text-v1.5"] {
+            assert_eq!(task_prefixes(model).unwrap().0, "search_query: ", "{}", model);
+        }
+        for model in ["intfloat/e5-large-v2", "multilingual-e5-small", "E5-base"] {
+            assert_eq!(task_prefixes(model).unwrap().1, "passage: ", "{}", model);
+        }
+        for model in ["mxbai-embed-large:latest", "BAAI/bge-small-en-v1.5"] {
+            assert_eq!(task_prefixes(model).unwrap().1, "", "{}", model);
+        }
+        for model in ["text-embedding-3-small", "e5-mistral-7b-instruct", "bge-m3", "all-minilm", "nomic-embed-code", "voyage-code-3"] {
+            assert_eq!(task_prefixes(model), None, "{}", model);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builtin_prefixes_are_versioned() {
+        let recorder = Arc::new(Recorder {
+            model: Some("nomic-embed-text"),
+            ..Default::default()
+        });
+        let prefixed = PrefixedEmbedding::new(recorder.clone(), Arc::new(BTreeMap::new()));
+        prefixed.embed_query("retry").await.unwrap();
+        prefixed.embed_document("fn retry() {}").await.unwrap();
+        assert_eq!(*recorder.texts.lock().unwrap(), ["[query] search_query: retry", "search_document: fn retry() {}"]);
+        assert!(prefixed.model_version().await.unwrap().is_some());
+
+        // Configured empty prefixes turn the built-in ones off
+        let prefixes = BTreeMap::from([("nomic-embed-text".to_string(), TaskPrefixConfig::default())]);
+        let plain = PrefixedEmbedding::new(recorder.clone(), Arc::new(prefixes));
+        plain.embed_document("fn retry() {}").await.unwrap();
+        assert_eq!(recorder.texts.lock().unwrap().last().unwrap(), "fn retry() {}");
+        assert_eq!(plain.model_version().await.unwrap(), None);
+    }
+}
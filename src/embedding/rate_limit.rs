@@ -0,0 +1,159 @@
+use crate::config::RateLimitConfig;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Rough size of a token, for budgeting text before the provider counts it
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Spaces out requests so that no more than `requests_per_second` start per second
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: f64) -> Self {
+        Self {
+            interval: Duration::from_secs_f64(1.0 / requests_per_second.max(0.001)),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait until the next request slot is available
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let now = Instant::now();
+            let slot = (*next_slot).max(now);
+            *next_slot = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+/// Per-minute quota refilled continuously: a full minute's worth may be
+/// spent at once, after which callers wait for the bucket to refill
+pub struct TokenBucket {
+    capacity: f64,
+    per_second: f64,
+    /// Units available and when they were last refilled; negative while
+    /// waiting callers have reserved more than is available
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    pub fn per_minute(quota: u32) -> Self {
+        let capacity = f64::from(quota.max(1));
+        Self {
+            capacity,
+            per_second: capacity / 60.0,
+            state: Mutex::new((capacity, Instant::now())),
+        }
+    }
+
+    /// Wait until `amount` units are available and spend them; more than
+    /// the whole quota waits for a full bucket
+    pub async fn acquire(&self, amount: usize) {
+        let amount = (amount as f64).min(self.capacity);
+        let wait = {
+            let mut state = self.state.lock().await;
+            let (available, updated) = &mut *state;
+            let now = Instant::now();
+            *available = (*available + now.duration_since(*updated).as_secs_f64() * self.per_second).min(self.capacity);
+            *updated = now;
+            // Reserve the units now so later callers queue behind this one
+            *available -= amount;
+            Duration::from_secs_f64((-*available).max(0.0) / self.per_second)
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Requests and estimated tokens the embedding provider may be sent per
+/// minute, so concurrent indexing waits instead of being rejected.
+/// Callers acquire the quota before timing a request, so waiting for it
+/// doesn't count as provider latency.
+pub struct EmbeddingQuota {
+    requests: Option<TokenBucket>,
+    tokens: Option<TokenBucket>,
+    max_request_tokens: Option<usize>,
+}
+
+impl EmbeddingQuota {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            requests: config.requests_per_minute.map(TokenBucket::per_minute),
+            tokens: config.tokens_per_minute.map(TokenBucket::per_minute),
+            max_request_tokens: config.max_tokens_per_request.map(|max| max.max(1) as usize),
+        }
+    }
+
+    /// Wait for one request carrying `texts`
+    pub async fn acquire(&self, texts: &[&str]) {
+        if let Some(requests) = &self.requests {
+            requests.acquire(1).await;
+        }
+        if let Some(tokens) = &self.tokens {
+            tokens.acquire(texts.iter().map(|text| estimate_tokens(text)).sum()).await;
+        }
+    }
+
+    /// Most estimated tokens one request may carry; a longer text is still
+    /// sent, alone
+    pub fn max_request_tokens(&self) -> Option<usize> {
+        self.max_request_tokens
+    }
+}
+
+/// Tokens of `text` at four characters per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(CHARS_PER_TOKEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limiter_spaces_requests() {
+        let limiter = RateLimiter::new(20.0);
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+        // First request is immediate, the next two are 50ms apart
+        assert!(start.elapsed() >= Duration::from_millis(95));
+    }
+
+    #[tokio::test]
+    async fn test_token_bucket_per_minute() {
+        // 6000 tokens per minute refill at 100 per second
+        let bucket = TokenBucket::per_minute(6000);
+        let start = Instant::now();
+        bucket.acquire(6000).await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        // The bucket is empty: 50 more tokens take half a second
+        bucket.acquire(50).await;
+        assert!(start.elapsed() >= Duration::from_millis(450));
+    }
+
+    #[tokio::test]
+    async fn test_embedding_quota() {
+        let quota = EmbeddingQuota::new(RateLimitConfig {
+            tokens_per_minute: Some(6000),
+            max_tokens_per_request: Some(100),
+            ..Default::default()
+        });
+        assert_eq!(quota.max_request_tokens(), Some(100));
+        assert_eq!(estimate_tokens("12345"), 2);
+
+        // 24000 characters spend the minute's tokens; the next 200 wait
+        let start = Instant::now();
+        quota.acquire(&[&"x".repeat(24000)]).await;
+        quota.acquire(&[&"x".repeat(200)]).await;
+        assert!(start.elapsed() >= Duration::from_millis(450));
+    }
+}
@@ -0,0 +1,139 @@
+//! Matryoshka truncation of the vectors of providers that can't be asked
+//! for a smaller dimension (`EMBEDDING_DIMENSION` with Ollama, Cohere, local
+//! models, text-embedding-ada-002, mistral-embed or an OpenAI-compatible
+//! server not sent it): only the leading components are kept, rescaled to
+//! unit length
+
+use super::{Embedding, EmbeddingProvider};
+use anyhow::Result;
+use std::sync::Arc;
+
+/// Embedding provider wrapper storing the first `dimension` components of
+/// each vector. Models trained for it (nomic-embed-text v1.5,
+/// mxbai-embed-large, ...) keep most of their recall; others lose more.
+pub struct TruncatedEmbedding {
+    inner: Arc<dyn EmbeddingProvider>,
+    dimension: usize,
+}
+
+impl TruncatedEmbedding {
+    pub fn new(inner: Arc<dyn EmbeddingProvider>, dimension: usize) -> Self {
+        Self { inner, dimension }
+    }
+
+    /// Keep the leading components and renormalize them; vectors already
+    /// no longer than the dimension are left alone
+    fn truncate(&self, mut embedding: Embedding) -> Embedding {
+        if embedding.values.len() > self.dimension {
+            embedding.values.truncate(self.dimension);
+            let norm = embedding.values.iter().map(|v| v * v).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                embedding.values.iter_mut().for_each(|v| *v /= norm);
+            }
+        }
+        embedding
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for TruncatedEmbedding {
+    async fn embed(&self, text: &str) -> Result<Embedding> {
+        Ok(self.truncate(self.inner.embed(text).await?))
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        Ok(self.inner.embed_batch(texts).await?.into_iter().map(|embedding| self.truncate(embedding)).collect())
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Embedding> {
+        Ok(self.truncate(self.inner.embed_query(text).await?))
+    }
+
+    async fn embed_document(&self, text: &str) -> Result<Embedding> {
+        Ok(self.truncate(self.inner.embed_document(text).await?))
+    }
+
+    async fn embed_documents(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        Ok(self.inner.embed_documents(texts).await?.into_iter().map(|embedding| self.truncate(embedding)).collect())
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.inner.max_batch_size()
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension().min(self.dimension)
+    }
+
+    async fn probe_dimension(&self) -> Result<usize> {
+        Ok(self.inner.probe_dimension().await?.min(self.dimension))
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn query_cache_key(&self) -> String {
+        format!("{}/{}", self.inner.query_cache_key(), self.dimension)
+    }
+
+    async fn model_version(&self) -> Result<Option<String>> {
+        self.inner.model_version().await
+    }
+
+    fn with_model(&self, model: &str) -> Option<Arc<dyn EmbeddingProvider>> {
+        Some(Arc::new(Self::new(self.inner.with_model(model)?, self.dimension)))
+    }
+
+    fn cost_per_million_tokens(&self) -> Option<f64> {
+        self.inner.cost_per_million_tokens()
+    }
+
+    async fn health_check(&self) -> Result<String> {
+        let status = self.inner.health_check().await?;
+        Ok(format!("{}, truncated to {}", status, self.dimension()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed;
+
+    #[async_trait::async_trait]
+    impl EmbeddingProvider for Fixed {
+        async fn embed(&self, _text: &str) -> Result<Embedding> {
+            Ok(Embedding { values: vec![3.0, 4.0, 12.0, 0.0] })
+        }
+
+        async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+            let mut embeddings = Vec::new();
+            for text in texts {
+                embeddings.push(self.embed(text).await?);
+            }
+            Ok(embeddings)
+        }
+
+        fn dimension(&self) -> usize {
+            4
+        }
+
+        fn model_name(&self) -> &str {
+            "fixed"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_truncate_and_renormalize() {
+        let truncated = TruncatedEmbedding::new(Arc::new(Fixed), 2);
+        assert_eq!(truncated.dimension(), 2);
+        assert_eq!(truncated.embed("x").await.unwrap().values, vec![0.6, 0.8]);
+        assert_eq!(truncated.embed_batch(&["x", "y"]).await.unwrap().len(), 2);
+
+        // A dimension above the model's leaves its vectors as they are
+        let wide = TruncatedEmbedding::new(Arc::new(Fixed), 8);
+        assert_eq!(wide.dimension(), 4);
+        assert_eq!(wide.embed("x").await.unwrap().values, vec![3.0, 4.0, 12.0, 0.0]);
+    }
+}
@@ -0,0 +1,223 @@
+//! Voyage AI embedding provider (`EMBEDDING_PROVIDER=voyage`), whose
+//! `voyage-code-*` models are trained for code retrieval
+
+use super::{record_usage, Embedding, EmbeddingProvider};
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const VOYAGE_EMBEDDINGS_URL: &str = "https://api.voyageai.com/v1/embeddings";
+
+/// Texts sent per request (Voyage accepts up to 1000, but also caps the
+/// tokens of a request)
+const MAX_BATCH_SIZE: usize = 128;
+
+/// Voyage AI embedding provider
+pub struct VoyageEmbedding {
+    client: Client,
+    api_key: String,
+    model: String,
+    dimension: usize,
+    /// Requested vector size, for models with a choice of dimensions
+    output_dimension: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct VoyageRequest<'a> {
+    input: &'a [&'a str],
+    model: &'a str,
+    /// `query` or `document`; omitted for plain embeddings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    input_type: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_dimension: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VoyageResponse {
+    data: Vec<VoyageData>,
+    #[serde(default)]
+    usage: Option<VoyageUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VoyageData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VoyageUsage {
+    total_tokens: usize,
+}
+
+/// Default vector size of a Voyage model
+fn default_dimension(model: &str) -> usize {
+    match model {
+        "voyage-code-2" => 1536,
+        "voyage-3-lite" | "voyage-3.5-lite" => 512,
+        // voyage-code-3, voyage-3, voyage-3-large, voyage-3.5
+        _ => 1024,
+    }
+}
+
+impl VoyageEmbedding {
+    /// `dimension` picks the output size of models that support several
+    /// (voyage-code-3: 256, 512, 1024 or 2048); `None` uses the model's default
+    pub fn new(api_key: &str, model: &str, dimension: Option<usize>) -> Self {
+        Self {
+            client: Client::new(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            dimension: dimension.unwrap_or_else(|| default_dimension(model)),
+            output_dimension: dimension,
+        }
+    }
+
+    /// Send requests through a client with configured timeouts and pooling
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn embed_texts(&self, texts: &[&str], input_type: Option<&str>) -> Result<Vec<Embedding>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(MAX_BATCH_SIZE) {
+            embeddings.extend(self.embed_request(batch, input_type).await?);
+        }
+        Ok(embeddings)
+    }
+
+    async fn embed_request(&self, texts: &[&str], input_type: Option<&str>) -> Result<Vec<Embedding>> {
+        let request = VoyageRequest {
+            input: texts,
+            model: &self.model,
+            input_type,
+            output_dimension: self.output_dimension,
+        };
+        let response = self
+            .client
+            .post(VOYAGE_EMBEDDINGS_URL)
+            .bearer_auth(&self.api_key)
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to send request to Voyage AI")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Voyage AI API error ({}): {}", status, body);
+        }
+
+        let response: VoyageResponse = response.json().await.context("Failed to parse Voyage AI response")?;
+        if let Some(usage) = &response.usage {
+            tracing::debug!("Voyage AI embedded {} texts ({} tokens)", texts.len(), usage.total_tokens);
+            record_usage(usage.total_tokens);
+        }
+        anyhow::ensure!(
+            response.data.len() == texts.len(),
+            "Voyage AI returned {} embeddings for {} texts",
+            response.data.len(),
+            texts.len()
+        );
+
+        let mut data = response.data;
+        data.sort_by_key(|d| d.index);
+        Ok(data.into_iter().map(|d| Embedding { values: d.embedding }).collect())
+    }
+
+    async fn embed_one(&self, text: &str, input_type: Option<&str>) -> Result<Embedding> {
+        self.embed_texts(&[text], input_type)
+            .await?
+            .into_iter()
+            .next()
+            .context("No embedding returned")
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for VoyageEmbedding {
+    async fn embed(&self, text: &str) -> Result<Embedding> {
+        self.embed_one(text, None).await
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        self.embed_texts(texts, None).await
+    }
+
+    /// Voyage prepends its own retrieval prompt for `input_type: query`
+    async fn embed_query(&self, text: &str) -> Result<Embedding> {
+        self.embed_one(text, Some("query")).await
+    }
+
+    async fn embed_document(&self, text: &str) -> Result<Embedding> {
+        self.embed_one(text, Some("document")).await
+    }
+
+    async fn embed_documents(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        self.embed_texts(texts, Some("document")).await
+    }
+
+    fn max_batch_size(&self) -> usize {
+        MAX_BATCH_SIZE
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    /// The output dimension is only kept for the configured model
+    fn with_model(&self, model: &str) -> Option<Arc<dyn EmbeddingProvider>> {
+        Some(Arc::new(VoyageEmbedding::new(&self.api_key, model, None).with_client(self.client.clone())))
+    }
+
+    fn cost_per_million_tokens(&self) -> Option<f64> {
+        match self.model.as_str() {
+            "voyage-code-3" | "voyage-3-large" => Some(0.18),
+            "voyage-code-2" => Some(0.12),
+            "voyage-3.5" | "voyage-3" => Some(0.06),
+            "voyage-3.5-lite" | "voyage-3-lite" => Some(0.02),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_voyage_request() {
+        let embedding = VoyageEmbedding::new("key", "voyage-code-3", Some(512));
+        assert_eq!(embedding.dimension(), 512);
+        assert_eq!(VoyageEmbedding::new("key", "voyage-code-2", None).dimension(), 1536);
+
+        let request = VoyageRequest {
+            input: &["fn main() {}"],
+            model: "voyage-code-3",
+            input_type: Some("document"),
+            output_dimension: embedding.output_dimension,
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "input": ["fn main() {}"],
+                "model": "voyage-code-3",
+                "input_type": "document",
+                "output_dimension": 512,
+            })
+        );
+        let plain = VoyageRequest {
+            input_type: None,
+            output_dimension: None,
+            ..request
+        };
+        assert!(serde_json::to_value(&plain).unwrap().get("input_type").is_none());
+    }
+}
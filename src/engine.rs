@@ -0,0 +1,353 @@
+//! Embeddable indexing/search engine
+
+use crate::audit::AuditLog;
+use crate::config::{Config, EmbeddingBackend, RateLimitConfig, VectorDbBackend, DEFAULT_REQUEST_TIMEOUT_SECS};
+use crate::embedding::bedrock::{AwsCredentials, BedrockEmbedding};
+use crate::embedding::cache::EmbeddingCache;
+use crate::embedding::cohere::CohereEmbedding;
+use crate::embedding::mistral::MistralEmbedding;
+use crate::embedding::ollama::OllamaEmbedding;
+use crate::embedding::openai::OpenAIEmbedding;
+use crate::embedding::openai_compatible::OpenAICompatibleEmbedding;
+use crate::embedding::prefix::PrefixedEmbedding;
+use crate::embedding::rate_limit::EmbeddingQuota;
+use crate::embedding::truncate::TruncatedEmbedding;
+use crate::embedding::voyage::VoyageEmbedding;
+use crate::embedding::EmbeddingProvider;
+use crate::fulltext::{FullTextIndex, FullTextVectorDatabase};
+use crate::handlers::doctor::CheckResult;
+use crate::handlers::tool_handlers::{SearchOptions, ToolHandlers};
+use crate::mcp::types::{Content, ToolOutput};
+use crate::metrics::{InstrumentedEmbedding, InstrumentedVectorDatabase, Metrics};
+use crate::snapshot::{EvictionPolicy, SnapshotManager};
+use crate::vector_db::limiter::LimitedVectorDatabase;
+use crate::vector_db::milvus::MilvusVectorDatabase;
+use crate::vector_db::qdrant::QdrantVectorDatabase;
+use crate::vector_db::router::{RoutedVectorDatabase, DEFAULT_INSTANCE};
+use crate::vector_db::{SearchResult, VectorDatabase};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::Instrument;
+
+/// Tools served by [`CodeContextEngine::call_tool`]
+const TOOLS: &[&str] = &["index_codebase", "search_code", "clear_index", "get_indexing_status", "get_config", "self_check", "get_server_stats", "rate_result", "get_chunk_context", "get_recent_search_quality", "diff_index", "export_symbols", "get_project_config", "set_project_config", "discover_projects"];
+
+/// Code indexing and semantic search engine.
+///
+/// This is what the MCP server runs on; editors, bots and other Rust tools
+/// can embed it directly:
+///
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// use code_context_mcp::{CodeContextEngine, config::Config};
+///
+/// let engine = CodeContextEngine::from_config(Config::load()?)?;
+/// engine.load().await?;
+/// engine.index("/path/to/project".as_ref(), false).await?;
+/// let results = engine.search("/path/to/project", "parse config file", Default::default()).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct CodeContextEngine {
+    handlers: Arc<ToolHandlers>,
+    snapshot_manager: Arc<SnapshotManager>,
+    config: Arc<Config>,
+    metrics: Arc<Metrics>,
+    audit_log: Option<Arc<AuditLog>>,
+}
+
+impl CodeContextEngine {
+    /// Build the engine with the embedding provider (Ollama, OpenAI or OpenAI-compatible) and vector database
+    /// (Milvus or Qdrant) described by `config`
+    pub fn from_config(config: Config) -> Result<Self> {
+        let config = Arc::new(config);
+        let max_projects = config.limits.max_indexed_projects;
+
+        // Eviction policy: lru (default), size, disabled or confirm
+        let eviction_policy = EvictionPolicy::parse(&config.limits.eviction_policy, config.limits.max_indexed_chunks)?;
+
+        // Timeouts keep a hung provider from stalling an index run
+        let client = config.embedding.http_client()?;
+        let embedding: Arc<dyn EmbeddingProvider> = match config.embedding.backend()? {
+            EmbeddingBackend::Ollama { host, model } => Arc::new(OllamaEmbedding::new(&host, &model).with_client(client)),
+            EmbeddingBackend::OpenAI { api_key, model, dimension } => {
+                Arc::new(OpenAIEmbedding::new(&api_key, &model, dimension).with_client(client))
+            }
+            EmbeddingBackend::OpenAICompatible {
+                base_url,
+                api_key,
+                model,
+                dimension,
+                send_dimensions,
+            } => Arc::new(
+                OpenAICompatibleEmbedding::new(&base_url, api_key.as_deref(), &model, dimension)
+                    .with_dimensions_sent(send_dimensions)
+                    .with_client(client),
+            ),
+            EmbeddingBackend::Voyage { api_key, model, dimension } => {
+                Arc::new(VoyageEmbedding::new(&api_key, &model, dimension).with_client(client))
+            }
+            EmbeddingBackend::Cohere { api_key, model } => Arc::new(CohereEmbedding::new(&api_key, &model).with_client(client)),
+            EmbeddingBackend::Mistral { api_key, model, dimension } => {
+                Arc::new(MistralEmbedding::new(&api_key, &model, dimension).with_client(client))
+            }
+            EmbeddingBackend::Bedrock { region, model, dimension } => {
+                Arc::new(BedrockEmbedding::new(AwsCredentials::load()?, &region, &model, dimension).with_client(client))
+            }
+            EmbeddingBackend::Local { model, cache_dir } => local_embedding(&model, &cache_dir)?,
+        };
+        let embedding: Arc<dyn EmbeddingProvider> =
+            Arc::new(PrefixedEmbedding::new(embedding, Arc::new(config.embedding.task_prefixes.clone())));
+        let embedding: Arc<dyn EmbeddingProvider> = match config.embedding.truncated_dimension() {
+            Some(dimension) => Arc::new(TruncatedEmbedding::new(embedding, dimension)),
+            None => embedding,
+        };
+        let snapshot_manager = Arc::new(
+            SnapshotManager::new_with_max_projects(config.snapshot.path.clone(), max_projects)?
+                .with_sqlite_threshold(config.snapshot.sqlite_threshold)
+                .with_eviction_policy(eviction_policy),
+        );
+
+        // Named instances get the collections of the projects indexed into them,
+        // and run the same backend as the default one
+        let vector_db_client = config.vector_db.http.client(DEFAULT_REQUEST_TIMEOUT_SECS)?;
+        let backend = config.vector_db.backend()?;
+        let connect = |address: &str| -> Arc<dyn VectorDatabase> {
+            match &backend {
+                VectorDbBackend::Milvus { .. } => Arc::new(MilvusVectorDatabase::new(address).with_client(vector_db_client.clone())),
+                VectorDbBackend::Qdrant { api_key, .. } => Arc::new(
+                    QdrantVectorDatabase::new(address)
+                        .with_client(vector_db_client.clone())
+                        .with_api_key(api_key.clone()),
+                ),
+            }
+        };
+        let default = connect(config.vector_db.address());
+        let vector_db: Arc<dyn VectorDatabase> = if config.vector_db.instances.is_empty() {
+            default
+        } else {
+            anyhow::ensure!(
+                !config.vector_db.instances.contains_key(DEFAULT_INSTANCE),
+                "[vector_db.instances] can't name an instance '{}': that is {}",
+                DEFAULT_INSTANCE,
+                config.vector_db.address()
+            );
+            let instances = config
+                .vector_db
+                .instances
+                .iter()
+                .map(|(name, address)| (name.clone(), connect(address)))
+                .collect();
+            Arc::new(RoutedVectorDatabase::new(default, instances, snapshot_manager.collection_routes()))
+        };
+
+        let mut engine = Self::new(embedding, vector_db, snapshot_manager, config.clone());
+        if let Some(path) = &config.audit.log_path {
+            engine = engine.with_audit_log(AuditLog::open(path)?);
+        }
+        Ok(engine)
+    }
+
+    /// Build the engine from custom components
+    pub fn new(
+        embedding: Arc<dyn EmbeddingProvider>,
+        vector_db: Arc<dyn VectorDatabase>,
+        snapshot_manager: Arc<SnapshotManager>,
+        config: Arc<Config>,
+    ) -> Self {
+        // Backend requests and errors are counted for get_server_stats
+        let metrics = Arc::new(Metrics::default());
+        let embedding = Arc::new(InstrumentedEmbedding::new(embedding, metrics.clone()));
+        let vector_db = Arc::new(LimitedVectorDatabase::new(vector_db, config.vector_db.max_concurrent_requests));
+        let vector_db: Arc<dyn VectorDatabase> = Arc::new(InstrumentedVectorDatabase::new(vector_db, metrics.clone()));
+
+        // Without the index, the full-text search modes are unavailable
+        let fulltext = if config.fulltext.enabled {
+            FullTextIndex::open(&config.fulltext.path)
+                .map_err(|e| tracing::warn!("Full-text index disabled: {:#}", e))
+                .ok()
+                .map(Arc::new)
+        } else {
+            None
+        };
+        let vector_db: Arc<dyn VectorDatabase> = match &fulltext {
+            Some(index) => Arc::new(FullTextVectorDatabase::new(vector_db, index.clone())),
+            None => vector_db,
+        };
+
+        let mut handlers = ToolHandlers::new(
+            embedding,
+            vector_db,
+            snapshot_manager.clone(),
+            snapshot_manager.max_projects(),
+        )
+        .with_config(config.clone())
+        .with_metrics(metrics.clone());
+
+        if let Some(index) = fulltext {
+            handlers = handlers.with_fulltext_index(index);
+        }
+
+        let rate_limit = config.embedding.rate_limit();
+        if rate_limit != RateLimitConfig::default() {
+            handlers = handlers.with_embedding_quota(EmbeddingQuota::new(rate_limit));
+        }
+
+        // Without the cache, indexing still works, just re-embedding more
+        if config.embedding.cache {
+            match EmbeddingCache::open(&config.embedding.cache_path, config.embedding.cache_max_entries) {
+                Ok(cache) => handlers = handlers.with_embedding_cache(cache),
+                Err(e) => tracing::warn!("Embedding cache disabled: {:#}", e),
+            }
+        }
+
+        Self {
+            handlers: Arc::new(handlers),
+            snapshot_manager,
+            config,
+            metrics,
+            audit_log: None,
+        }
+    }
+
+    /// Record every tool call in an audit log
+    pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(Arc::new(audit_log));
+        self
+    }
+
+    /// Load the persisted snapshot; call once before using the engine
+    pub async fn load(&self) -> Result<()> {
+        self.snapshot_manager.load().await
+    }
+
+    /// Index (or incrementally re-index with `force`) a project directory
+    pub async fn index(&self, path: &Path, force: bool) -> Result<String> {
+        let args = json!({ "path": path, "force": force });
+        self.call_tool("index_codebase", &args).await.map(content_text)
+    }
+
+    /// Semantic search over a project path or alias ("all" searches every project)
+    pub async fn search(&self, path: &str, query: &str, options: SearchOptions) -> Result<Vec<SearchResult>> {
+        Ok(self.handlers.search(path, query, &options).await?.results)
+    }
+
+    /// Drop the index of a project ("all" clears every project)
+    pub async fn clear(&self, path: &str) -> Result<String> {
+        self.call_tool("clear_index", &json!({ "path": path })).await.map(content_text)
+    }
+
+    /// Indexing status of a project ("all" lists every project)
+    pub async fn status(&self, path: &str) -> Result<String> {
+        self.call_tool("get_indexing_status", &json!({ "path": path })).await.map(content_text)
+    }
+
+    /// Check configuration, backends, snapshot store and grammars
+    pub async fn self_check(&self) -> Vec<CheckResult> {
+        self.handlers.self_check().await
+    }
+
+    /// Whether `name` is a tool served by [`call_tool`](Self::call_tool)
+    pub fn has_tool(name: &str) -> bool {
+        TOOLS.contains(&name)
+    }
+
+    /// Run a tool by its MCP name with JSON arguments
+    pub async fn call_tool(&self, name: &str, args: &Value) -> Result<ToolOutput> {
+        let span = tracing::info_span!("tool_call", tool = name, project = tracing::field::Empty);
+        if let Some(project) = args.get("path").and_then(|v| v.as_str()) {
+            span.record("project", project);
+        }
+        self.call_tool_inner(name, args).instrument(span).await
+    }
+
+    async fn call_tool_inner(&self, name: &str, args: &Value) -> Result<ToolOutput> {
+        let started = Instant::now();
+        let handlers = &self.handlers;
+        let result = match name {
+            "index_codebase" => handlers.handle_index_codebase(args).await.map(ToolOutput::from),
+            "search_code" => handlers.handle_search_code(args).await,
+            "clear_index" => handlers.handle_clear_index(args).await.map(ToolOutput::from),
+            "get_indexing_status" => handlers.handle_get_indexing_status(args).await,
+            "get_config" => handlers.handle_get_config(args).await.map(ToolOutput::from),
+            "self_check" => handlers.handle_self_check(args).await.map(ToolOutput::from),
+            "get_server_stats" => handlers.handle_get_server_stats(args).await.map(ToolOutput::from),
+            "rate_result" => handlers.handle_rate_result(args).await.map(ToolOutput::from),
+            "get_chunk_context" => handlers.handle_get_chunk_context(args).await.map(ToolOutput::from),
+            "get_recent_search_quality" => handlers.handle_get_recent_search_quality(args).await.map(ToolOutput::from),
+            "diff_index" => handlers.handle_diff_index(args).await,
+            "export_symbols" => handlers.handle_export_symbols(args).await.map(ToolOutput::from),
+            "get_project_config" => handlers.handle_get_project_config(args).await,
+            "set_project_config" => handlers.handle_set_project_config(args).await,
+            "discover_projects" => handlers.handle_discover_projects(args).await,
+            _ => anyhow::bail!("Unknown tool: {}", name),
+        };
+        let elapsed = started.elapsed();
+        self.metrics.record_tool_call(name, elapsed, result.is_ok());
+        let duration_ms = elapsed.as_millis() as u64;
+        match &result {
+            Ok(_) => tracing::info!(duration_ms, "Tool call finished"),
+            Err(e) => tracing::warn!(duration_ms, error = %format!("{:#}", e), "Tool call failed"),
+        }
+        if let Some(audit_log) = &self.audit_log {
+            let outcome = match &result {
+                Ok(output) => Ok(output
+                    .content
+                    .iter()
+                    .map(|c| match c {
+                        Content::Text { text } => text.len(),
+                        Content::Error { .. } => 0,
+                    })
+                    .sum()),
+                Err(e) => Err(format!("{:#}", e)),
+            };
+            audit_log.record(name, args, elapsed, outcome);
+        }
+        result
+    }
+
+    /// Shared tool handlers (used by the MCP server and background tasks)
+    pub fn handlers(&self) -> Arc<ToolHandlers> {
+        self.handlers.clone()
+    }
+
+    pub fn snapshot_manager(&self) -> Arc<SnapshotManager> {
+        self.snapshot_manager.clone()
+    }
+
+    pub fn config(&self) -> Arc<Config> {
+        self.config.clone()
+    }
+
+    /// Runtime metrics (tool calls, backend errors, cache hit rates)
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+}
+
+/// Join the text parts of a tool result
+fn content_text(output: ToolOutput) -> String {
+    output
+        .content
+        .into_iter()
+        .filter_map(|c| match c {
+            Content::Text { text } => Some(text),
+            Content::Error { .. } => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(feature = "local-embedding")]
+fn local_embedding(model: &str, cache_dir: &Path) -> Result<Arc<dyn EmbeddingProvider>> {
+    Ok(Arc::new(crate::embedding::local::LocalEmbedding::new(model, cache_dir)?))
+}
+
+#[cfg(not(feature = "local-embedding"))]
+fn local_embedding(_model: &str, _cache_dir: &Path) -> Result<Arc<dyn EmbeddingProvider>> {
+    anyhow::bail!("EMBEDDING_PROVIDER=local requires a server built with `--features local-embedding`")
+}
@@ -0,0 +1,575 @@
+//! Embedded full-text index of chunk text (`[fulltext]`), one tantivy index
+//! per project collection kept next to the vectors: it answers exact
+//! keyword, identifier and regex searches without an embedding call, stands
+//! in when the vector database is down, and is the lexical side of hybrid
+//! ranking.
+//!
+//! Each collection keeps one writer open. Changes are committed at the end
+//! of an index run ([`FullTextIndex::commit`]), or before a read that needs
+//! them, rather than per upsert batch; tantivy's work is blocking, so the
+//! async callers run it with [`blocking`].
+
+use crate::handlers::compression;
+use crate::handlers::encryption::ContentCipher;
+use crate::vector_db::{copied_id, moved_id, moved_metadata, Metric, SearchResult, VectorDatabase};
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tantivy::collector::{DocSetCollector, TopDocs};
+use tantivy::query::{AllQuery, Query, QueryParser, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, Value, STORED, STRING, TEXT};
+use tantivy::{doc, DocAddress, Index, IndexReader, IndexWriter, ReloadPolicy, Searcher, TantivyDocument, Term};
+
+/// Heap of an index writer, tantivy's minimum for one indexing thread
+const WRITER_HEAP_BYTES: usize = 15_000_000;
+
+/// Fields of a collection's index
+#[derive(Clone, Copy)]
+struct Fields {
+    id: Field,
+    /// Exact path, for deleting and moving a file's chunks
+    file_path: Field,
+    /// Path words, so `config loader` finds `src/config/loader.rs`
+    path: Field,
+    symbol_name: Field,
+    content: Field,
+    /// The chunk's metadata as JSON, returned with results
+    metadata: Field,
+}
+
+impl Fields {
+    fn schema() -> Schema {
+        let mut builder = Schema::builder();
+        builder.add_i64_field("id", tantivy::schema::INDEXED | STORED);
+        builder.add_text_field("file_path", STRING);
+        builder.add_text_field("path", TEXT);
+        builder.add_text_field("symbol_name", TEXT);
+        builder.add_text_field("content", TEXT);
+        builder.add_text_field("metadata", STORED);
+        builder.build()
+    }
+
+    fn of(schema: &Schema) -> Result<Self> {
+        Ok(Self {
+            id: schema.get_field("id")?,
+            file_path: schema.get_field("file_path")?,
+            path: schema.get_field("path")?,
+            symbol_name: schema.get_field("symbol_name")?,
+            content: schema.get_field("content")?,
+            metadata: schema.get_field("metadata")?,
+        })
+    }
+}
+
+/// An open collection index
+struct CollectionIndex {
+    index: Index,
+    reader: IndexReader,
+    fields: Fields,
+    /// The collection's writer, opened on the first change (tantivy allows
+    /// one per index), and whether it holds uncommitted changes
+    writer: Mutex<(Option<IndexWriter>, bool)>,
+}
+
+impl CollectionIndex {
+    fn open(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let directory = tantivy::directory::MmapDirectory::open(dir)?;
+        let index = Index::open_or_create(directory, Fields::schema())
+            .with_context(|| format!("Failed to open full-text index {}", dir.display()))?;
+        let fields = Fields::of(&index.schema())?;
+        let reader = index.reader_builder().reload_policy(ReloadPolicy::Manual).try_into()?;
+        Ok(Self {
+            index,
+            reader,
+            fields,
+            writer: Mutex::new((None, false)),
+        })
+    }
+
+    /// Apply `change` with the collection's writer; it becomes searchable
+    /// once committed
+    fn write(&self, change: impl FnOnce(&IndexWriter, &Fields) -> Result<()>) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        if writer.0.is_none() {
+            writer.0 = Some(self.index.writer_with_num_threads(1, WRITER_HEAP_BYTES)?);
+        }
+        writer.1 = true;
+        change(writer.0.as_ref().unwrap(), &self.fields)
+    }
+
+    /// Commit the uncommitted changes, if any, and make them searchable
+    fn commit(&self) -> Result<()> {
+        let mut guard = self.writer.lock().unwrap();
+        let (writer, pending) = &mut *guard;
+        if let (Some(writer), true) = (writer, *pending) {
+            writer.commit()?;
+            self.reader.reload()?;
+            *pending = false;
+        }
+        Ok(())
+    }
+
+    /// A searcher seeing every change made so far
+    fn searcher(&self) -> Result<Searcher> {
+        self.commit()?;
+        Ok(self.reader.searcher())
+    }
+
+    /// Stored (id, metadata) of the chunks of a file
+    fn file_rows(&self, file_path: &str) -> Result<Vec<(i64, serde_json::Value)>> {
+        let query = TermQuery::new(Term::from_field_text(self.fields.file_path, file_path), IndexRecordOption::Basic);
+        self.rows(&query)
+    }
+
+    fn rows(&self, query: &dyn Query) -> Result<Vec<(i64, serde_json::Value)>> {
+        let searcher = self.searcher()?;
+        let mut addresses: Vec<DocAddress> = searcher.search(query, &DocSetCollector)?.into_iter().collect();
+        addresses.sort();
+        addresses.into_iter().map(|address| self.row(&searcher, address)).collect()
+    }
+
+    fn row(&self, searcher: &Searcher, address: DocAddress) -> Result<(i64, serde_json::Value)> {
+        let document: TantivyDocument = searcher.doc(address)?;
+        let id = document.get_first(self.fields.id).and_then(|v| v.as_i64()).unwrap_or_default();
+        let metadata = document.get_first(self.fields.metadata).and_then(|v| v.as_str()).unwrap_or("null");
+        Ok((id, serde_json::from_str(metadata)?))
+    }
+}
+
+/// Add a chunk, replacing the one stored under its id; chunks whose content
+/// is encrypted aren't indexed, as the index would keep it in plain text
+fn add_row(writer: &IndexWriter, fields: &Fields, id: i64, metadata: &serde_json::Value) -> Result<()> {
+    writer.delete_term(Term::from_field_i64(fields.id, id));
+    let stored = metadata.get("content").and_then(|v| v.as_str()).unwrap_or_default();
+    if ContentCipher::is_encrypted(stored) {
+        return Ok(());
+    }
+    let mut metadata = metadata.clone();
+    if compression::is_compressed(stored) {
+        metadata["content"] = serde_json::Value::from(compression::decompress(stored)?);
+    }
+    let text = |field: &str| metadata.get(field).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    writer.add_document(doc!(
+        fields.id => id,
+        fields.file_path => text("file_path"),
+        fields.path => text("file_path"),
+        fields.symbol_name => text("symbol_name"),
+        fields.content => text("content"),
+        fields.metadata => metadata.to_string(),
+    ))?;
+    Ok(())
+}
+
+/// Full-text indexes of the project collections, under one directory
+pub struct FullTextIndex {
+    dir: PathBuf,
+    collections: Mutex<HashMap<String, Arc<CollectionIndex>>>,
+}
+
+impl FullTextIndex {
+    pub fn open(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            collections: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn collection_dir(&self, collection: &str) -> PathBuf {
+        self.dir.join(collection)
+    }
+
+    /// Whether a collection has been indexed (projects indexed before the
+    /// full-text index was enabled haven't, until re-indexed with `force`)
+    pub fn contains(&self, collection: &str) -> bool {
+        self.collections.lock().unwrap().contains_key(collection) || self.collection_dir(collection).join("meta.json").is_file()
+    }
+
+    /// A collection's index, created if `create` is set
+    fn collection(&self, collection: &str, create: bool) -> Result<Option<Arc<CollectionIndex>>> {
+        let mut collections = self.collections.lock().unwrap();
+        if let Some(index) = collections.get(collection) {
+            return Ok(Some(index.clone()));
+        }
+        let dir = self.collection_dir(collection);
+        if !create && !dir.join("meta.json").is_file() {
+            return Ok(None);
+        }
+        let index = Arc::new(CollectionIndex::open(&dir)?);
+        collections.insert(collection.to_string(), index.clone());
+        Ok(Some(index))
+    }
+
+    /// Index chunks by their vector database ids
+    pub fn upsert(&self, collection: &str, ids: &[i64], metadata: &[serde_json::Value]) -> Result<()> {
+        let Some(index) = self.collection(collection, true)? else {
+            return Ok(());
+        };
+        index.write(|writer, fields| {
+            for (id, metadata) in ids.iter().zip(metadata) {
+                add_row(writer, fields, *id, metadata)?;
+            }
+            Ok(())
+        })
+    }
+
+    pub fn delete_file(&self, collection: &str, file_path: &str) -> Result<()> {
+        let Some(index) = self.collection(collection, false)? else {
+            return Ok(());
+        };
+        index.write(|writer, fields| {
+            writer.delete_term(Term::from_field_text(fields.file_path, file_path));
+            Ok(())
+        })
+    }
+
+    /// Delete the chunks stored with any of these file hashes
+    pub fn delete_file_hashes(&self, collection: &str, file_hashes: &[String]) -> Result<()> {
+        let Some(index) = self.collection(collection, false)? else {
+            return Ok(());
+        };
+        let ids: Vec<i64> = index
+            .rows(&AllQuery)?
+            .into_iter()
+            .filter(|(_, metadata)| {
+                let hash = metadata.get("file_hash").and_then(|v| v.as_str());
+                hash.is_some_and(|hash| file_hashes.iter().any(|f| f == hash))
+            })
+            .map(|(id, _)| id)
+            .collect();
+        if ids.is_empty() {
+            return Ok(());
+        }
+        index.write(|writer, fields| {
+            for id in ids {
+                writer.delete_term(Term::from_field_i64(fields.id, id));
+            }
+            Ok(())
+        })
+    }
+
+    /// Re-point the chunks of a renamed file (see [`VectorDatabase::move_file`])
+    pub fn move_file(&self, collection: &str, old_path: &str, new_path: &str, updates: &serde_json::Value) -> Result<()> {
+        let Some(index) = self.collection(collection, false)? else {
+            return Ok(());
+        };
+        let rows = index.file_rows(old_path)?;
+        index.write(|writer, fields| {
+            writer.delete_term(Term::from_field_text(fields.file_path, old_path));
+            for (id, mut metadata) in rows {
+                moved_metadata(&mut metadata, old_path, new_path, updates);
+                add_row(writer, fields, moved_id(id, new_path), &metadata)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Copy the chunks of a file into `target` (see [`VectorDatabase::copy_file`])
+    pub fn copy_file(&self, collection: &str, file_path: &str, target: &str, updates: &serde_json::Value, salt: i64) -> Result<()> {
+        let Some(index) = self.collection(collection, false)? else {
+            return Ok(());
+        };
+        let rows = index.file_rows(file_path)?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let (ids, metadata): (Vec<i64>, Vec<serde_json::Value>) = rows
+            .into_iter()
+            .map(|(id, mut metadata)| {
+                if let (Some(fields), Some(updates)) = (metadata.as_object_mut(), updates.as_object()) {
+                    fields.extend(updates.clone());
+                }
+                (copied_id(id, salt), metadata)
+            })
+            .unzip();
+        self.upsert(target, &ids, &metadata)
+    }
+
+    /// Commit the changes of every collection, making them durable and
+    /// searchable
+    pub fn commit(&self) -> Result<()> {
+        let collections: Vec<_> = self.collections.lock().unwrap().values().cloned().collect();
+        for index in collections {
+            index.commit()?;
+        }
+        Ok(())
+    }
+
+    pub fn drop_collection(&self, collection: &str) -> Result<()> {
+        if let Some(index) = self.collections.lock().unwrap().remove(collection) {
+            // Release the writer's lock on the directory, discarding its changes
+            index.writer.lock().unwrap().0.take();
+        }
+        let dir = self.collection_dir(collection);
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Best BM25 matches of a query over chunk text, symbol names and paths.
+    /// Words match whole tokens; an identifier like `load_user_config` or a
+    /// `"quoted phrase"` matches its words in sequence.
+    pub fn search(&self, collection: &str, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        let Some(index) = self.collection(collection, false)? else {
+            return Ok(Vec::new());
+        };
+        let fields = index.fields;
+        let parser = QueryParser::for_index(&index.index, vec![fields.content, fields.symbol_name, fields.path]);
+        let (query, errors) = parser.parse_query_lenient(query);
+        if !errors.is_empty() {
+            tracing::debug!("Full-text query parsed leniently: {:?}", errors);
+        }
+        let searcher = index.searcher()?;
+        searcher
+            .search(&query, &TopDocs::with_limit(limit.max(1)))?
+            .into_iter()
+            .map(|(score, address)| {
+                let (_, metadata) = index.row(&searcher, address)?;
+                Ok(SearchResult::new(score, Metric::Lexical, metadata))
+            })
+            .collect()
+    }
+
+    /// Chunks whose text matches a regular expression, most matches first
+    pub fn search_regex(&self, collection: &str, pattern: &Regex, limit: usize) -> Result<Vec<SearchResult>> {
+        let Some(index) = self.collection(collection, false)? else {
+            return Ok(Vec::new());
+        };
+        let mut results: Vec<SearchResult> = index
+            .rows(&AllQuery)?
+            .into_iter()
+            .filter_map(|(_, metadata)| {
+                let matches = pattern.find_iter(metadata.get("content")?.as_str()?).count();
+                (matches > 0).then(|| SearchResult::new(matches as f32, Metric::Lexical, metadata))
+            })
+            .collect();
+        results.sort_by(|a, b| b.raw_score.partial_cmp(&a.raw_score).unwrap_or(std::cmp::Ordering::Equal));
+        results.truncate(limit);
+        Ok(results)
+    }
+}
+
+/// Run blocking index work (writing, committing, searching) on tokio's
+/// blocking threads
+pub async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(work).await.context("Full-text index task failed")?
+}
+
+/// Vector database wrapper keeping the full-text index in step with every
+/// change to the stored chunks. The vector database stays the source of
+/// truth: a change it rejects isn't indexed, and a failure to index one it
+/// made is logged, not returned.
+pub struct FullTextVectorDatabase {
+    inner: Arc<dyn VectorDatabase>,
+    index: Arc<FullTextIndex>,
+}
+
+impl FullTextVectorDatabase {
+    pub fn new(inner: Arc<dyn VectorDatabase>, index: Arc<FullTextIndex>) -> Self {
+        Self { inner, index }
+    }
+
+    /// Apply a change to the index off the async runtime
+    async fn mirror(&self, collection: &str, change: impl FnOnce(&FullTextIndex) -> Result<()> + Send + 'static) {
+        let index = self.index.clone();
+        if let Err(e) = blocking(move || change(&index)).await {
+            tracing::warn!("Failed to update the full-text index of {}: {:#}", collection, e);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl VectorDatabase for FullTextVectorDatabase {
+    async fn create_collection(&self, name: &str, dimension: usize) -> Result<()> {
+        self.inner.create_collection(name, dimension).await
+    }
+
+    async fn collection_dimension(&self, name: &str) -> Result<Option<usize>> {
+        self.inner.collection_dimension(name).await
+    }
+
+    async fn upsert(&self, collection: &str, ids: &[i64], vectors: &[Vec<f32>], metadata: &[serde_json::Value]) -> Result<()> {
+        self.inner.upsert(collection, ids, vectors, metadata).await?;
+        let (target, ids, metadata) = (collection.to_string(), ids.to_vec(), metadata.to_vec());
+        self.mirror(collection, move |index| index.upsert(&target, &ids, &metadata)).await;
+        Ok(())
+    }
+
+    async fn search(&self, collection: &str, vector: &[f32], limit: usize) -> Result<Vec<SearchResult>> {
+        self.inner.search(collection, vector, limit).await
+    }
+
+    async fn drop_collection(&self, name: &str) -> Result<()> {
+        self.inner.drop_collection(name).await?;
+        let target = name.to_string();
+        self.mirror(name, move |index| index.drop_collection(&target)).await;
+        Ok(())
+    }
+
+    async fn delete_by_file(&self, collection: &str, file_path: &str) -> Result<()> {
+        self.inner.delete_by_file(collection, file_path).await?;
+        let (target, file_path) = (collection.to_string(), file_path.to_string());
+        self.mirror(collection, move |index| index.delete_file(&target, &file_path)).await;
+        Ok(())
+    }
+
+    async fn delete_by_file_hashes(&self, collection: &str, file_hashes: &[String]) -> Result<()> {
+        self.inner.delete_by_file_hashes(collection, file_hashes).await?;
+        let (target, file_hashes) = (collection.to_string(), file_hashes.to_vec());
+        self.mirror(collection, move |index| index.delete_file_hashes(&target, &file_hashes)).await;
+        Ok(())
+    }
+
+    async fn health_check(&self) -> Result<String> {
+        self.inner.health_check().await
+    }
+
+    fn metric(&self) -> Metric {
+        self.inner.metric()
+    }
+
+    async fn create_partition(&self, collection: &str, partition: &str) -> Result<()> {
+        self.inner.create_partition(collection, partition).await
+    }
+
+    /// The full-text index isn't partitioned
+    async fn upsert_partition(
+        &self,
+        collection: &str,
+        partition: &str,
+        ids: &[i64],
+        vectors: &[Vec<f32>],
+        metadata: &[serde_json::Value],
+    ) -> Result<()> {
+        self.inner.upsert_partition(collection, partition, ids, vectors, metadata).await?;
+        let (target, ids, metadata) = (collection.to_string(), ids.to_vec(), metadata.to_vec());
+        self.mirror(collection, move |index| index.upsert(&target, &ids, &metadata)).await;
+        Ok(())
+    }
+
+    async fn search_partitions(
+        &self,
+        collection: &str,
+        partitions: &[String],
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        self.inner.search_partitions(collection, partitions, vector, limit).await
+    }
+
+    async fn move_file(
+        &self,
+        collection: &str,
+        partition: Option<&str>,
+        old_path: &str,
+        new_path: &str,
+        updates: &serde_json::Value,
+    ) -> Result<bool> {
+        let moved = self.inner.move_file(collection, partition, old_path, new_path, updates).await?;
+        if moved {
+            let (target, old_path, new_path, updates) = (collection.to_string(), old_path.to_string(), new_path.to_string(), updates.clone());
+            self.mirror(collection, move |index| index.move_file(&target, &old_path, &new_path, &updates)).await;
+        }
+        Ok(moved)
+    }
+
+    async fn copy_file(
+        &self,
+        collection: &str,
+        file_path: &str,
+        target: &str,
+        updates: &serde_json::Value,
+        salt: i64,
+    ) -> Result<usize> {
+        let copied = self.inner.copy_file(collection, file_path, target, updates, salt).await?;
+        if copied > 0 {
+            let (source, file_path, copy, updates) = (collection.to_string(), file_path.to_string(), target.to_string(), updates.clone());
+            self.mirror(target, move |index| index.copy_file(&source, &file_path, &copy, &updates, salt)).await;
+        }
+        Ok(copied)
+    }
+
+    async fn search_keywords(
+        &self,
+        collection: &str,
+        partitions: Option<&[String]>,
+        vector: &[f32],
+        limit: usize,
+        keywords: &[String],
+    ) -> Result<Vec<SearchResult>> {
+        self.inner.search_keywords(collection, partitions, vector, limit, keywords).await
+    }
+
+    async fn count_rows(&self, collection: &str) -> Result<Option<usize>> {
+        self.inner.count_rows(collection).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fulltext_index() {
+        let dir = std::env::temp_dir().join(format!("fulltext_test_{}", uuid::Uuid::new_v4()));
+        let index = FullTextIndex::open(&dir).unwrap();
+        let chunk = |path: &str, symbol: &str, content: &str| json!({"file_path": path, "symbol_name": symbol, "content": content});
+        index
+            .upsert(
+                "code_test",
+                &[1, 2, 3],
+                &[
+                    chunk("src/config.rs", "load_user_config", "fn load_user_config(path: &Path) -> Config { todo!() }"),
+                    chunk("src/server.rs", "serve", "fn serve(port: u16) { listen(port).unwrap() }"),
+                    chunk("src/secret.rs", "key", "enc:v1:AAAA"),
+                ],
+            )
+            .unwrap();
+        assert!(index.contains("code_test"));
+        assert!(!index.contains("code_other"));
+        // Changes are searchable at once, and durable once committed
+        let reopened = || FullTextIndex::open(&dir).unwrap().search("code_test", "serve", 10).unwrap().len();
+        assert_eq!(reopened(), 0);
+        index.commit().unwrap();
+        assert_eq!(reopened(), 1);
+
+        // Identifiers match their words in sequence
+        let found = index.search("code_test", "load_user_config", 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].metadata["file_path"], "src/config.rs");
+        assert_eq!(found[0].metric, Metric::Lexical);
+        assert!(index.search("code_test", "user_load", 10).unwrap().is_empty());
+        // Encrypted content isn't indexed
+        assert!(index.search("code_test", "key", 10).unwrap().is_empty());
+
+        let pattern = Regex::new(r"listen\(\w+\)").unwrap();
+        let found = index.search_regex("code_test", &pattern, 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].metadata["symbol_name"], "serve");
+
+        index.move_file("code_test", "src/server.rs", "src/http.rs", &json!({"module": "http"})).unwrap();
+        let found = index.search("code_test", "serve", 10).unwrap();
+        assert_eq!(found[0].metadata["file_path"], "src/http.rs");
+        assert_eq!(found[0].metadata["module"], "http");
+
+        index.delete_file("code_test", "src/config.rs").unwrap();
+        assert!(index.search("code_test", "load_user_config", 10).unwrap().is_empty());
+
+        let mut vendored = chunk("vendor/left_pad.js", "leftPad", "function leftPad(text, width) {}");
+        vendored["file_hash"] = json!("h1");
+        index.upsert("code_test", &[4], &[vendored]).unwrap();
+        index.delete_file_hashes("code_test", &["h0".to_string()]).unwrap();
+        assert_eq!(index.search("code_test", "leftPad", 10).unwrap().len(), 1);
+        index.delete_file_hashes("code_test", &["h1".to_string()]).unwrap();
+        assert!(index.search("code_test", "leftPad", 10).unwrap().is_empty());
+
+        index.drop_collection("code_test").unwrap();
+        assert!(!index.contains("code_test"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,70 @@
+//! Periodic background refresh of pinned projects and remote clones
+
+use super::tool_handlers::ToolHandlers;
+use crate::mcp::protocol::Notifier;
+use crate::snapshot::SnapshotManager;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Default time budget per project for one background refresh round
+pub const DEFAULT_BACKGROUND_REFRESH_BUDGET_SECS: u64 = 60;
+
+const LOGGER: &str = "background-refresh";
+
+/// Background refresh settings (see `BACKGROUND_REFRESH_INTERVAL_SECS`)
+#[derive(Debug, Clone, Copy)]
+pub struct BackgroundRefreshConfig {
+    /// Time between refresh rounds
+    pub interval: Duration,
+    /// Time budget per project within a round
+    pub budget: Duration,
+}
+
+/// Spawn a task that re-walks pinned projects and fetches and re-walks
+/// remote clones every `config.interval`.
+///
+/// Tool calls run alongside a round; a project that a tool call is indexing
+/// or clearing is skipped until the next round, so refreshing never waits
+/// for (or delays) changes made by interactive requests. Progress is
+/// reported to the client as MCP logging notifications.
+pub fn spawn_background_refresh(
+    handlers: Arc<ToolHandlers>,
+    snapshot_manager: Arc<SnapshotManager>,
+    config: BackgroundRefreshConfig,
+    notifier: Notifier,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + config.interval, config.interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            let projects = snapshot_manager.get_refreshed_projects().await;
+            if projects.is_empty() {
+                continue;
+            }
+
+            for project in projects {
+                let (level, message) = match handlers.background_refresh(&project, config.budget).await {
+                    Ok(Some(summary)) => ("info", format!("Refreshed {}: {}", project.display(), summary)),
+                    Ok(None) => {
+                        tracing::debug!("Background refresh of {} deferred: tool call in progress", project.display());
+                        continue;
+                    }
+                    Err(e) => ("warning", format!("Failed to refresh {}: {:#}", project.display(), e)),
+                };
+
+                tracing::info!("{}", message);
+                if let Err(e) = notifier
+                    .log_message(level, LOGGER, json!({ "project": project, "message": message }))
+                    .await
+                {
+                    tracing::warn!("Failed to send refresh notification: {}", e);
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,266 @@
+use crate::parser::SymbolKind;
+use crate::vector_db::SearchResult;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Default number of distinct chunks remembered per index run
+pub const DEFAULT_DEDUP_CAPACITY: usize = 20_000;
+
+/// A chunk vector as stored in the vector database
+#[derive(Debug, Clone)]
+pub struct StoredChunk {
+    pub id: i64,
+    pub vector: Vec<f32>,
+    pub metadata: Value,
+}
+
+impl StoredChunk {
+    /// Approximate memory held by this chunk
+    pub fn size_bytes(&self) -> usize {
+        self.vector.len() * std::mem::size_of::<f32>() + self.metadata.to_string().len()
+    }
+
+    /// Record another location holding the same content
+    pub fn add_location(&mut self, location: Value) {
+        add_location(&mut self.metadata, location);
+    }
+}
+
+/// Add a location to the `locations` list of chunk metadata
+fn add_location(metadata: &mut Value, location: Value) {
+    if let Some(locations) = metadata.get_mut("locations").and_then(|l| l.as_array_mut()) {
+        if !locations.contains(&location) {
+            locations.push(location);
+        }
+    }
+}
+
+/// Content-hash → stored vector map for one index run.
+///
+/// Identical chunk content (vendored or copied files) is embedded once; each
+/// file still gets its own row (see [`ChunkDeduplicator::row_id`]), so
+/// changing or deleting one copy leaves the others searchable. Repeats
+/// within a file share a row listing every location in its metadata.
+pub struct ChunkDeduplicator {
+    entries: HashMap<String, StoredChunk>,
+    capacity: usize,
+    max_bytes: usize,
+    bytes: usize,
+}
+
+impl ChunkDeduplicator {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity,
+            max_bytes: usize::MAX,
+            bytes: 0,
+        }
+    }
+
+    /// Also stop remembering new chunks once they hold `max_bytes`
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Stable vector id derived from a content hash
+    pub fn chunk_id(content_hash: &str) -> i64 {
+        let prefix = u64::from_str_radix(&content_hash[..16], 16).unwrap_or_default();
+        (prefix & i64::MAX as u64) as i64
+    }
+
+    /// Id of the row holding `content_hash` in `file_path`: identical
+    /// content in one file maps to the same row, in other files to others
+    pub fn row_id(file_path: &str, content_hash: &str) -> i64 {
+        use sha2::{Digest, Sha256};
+        let digest = Sha256::new()
+            .chain_update(file_path.as_bytes())
+            .chain_update([0])
+            .chain_update(content_hash.as_bytes())
+            .finalize();
+        let prefix = u64::from_le_bytes(digest[..8].try_into().unwrap());
+        (prefix & i64::MAX as u64) as i64
+    }
+
+    /// Location entry stored in the `locations` metadata list
+    pub fn location(file_path: &str, start_line: usize, end_line: usize) -> Value {
+        json!({
+            "file_path": file_path,
+            "start_line": start_line,
+            "end_line": end_line,
+        })
+    }
+
+    pub fn contains(&self, content_hash: &str) -> bool {
+        self.entries.contains_key(content_hash)
+    }
+
+    pub fn get(&self, content_hash: &str) -> Option<&StoredChunk> {
+        self.entries.get(content_hash)
+    }
+
+    /// Remember a stored chunk; new entries are dropped once the map is full
+    pub fn insert(&mut self, content_hash: String, chunk: StoredChunk) {
+        let size = chunk.size_bytes();
+        if let Some(existing) = self.entries.get_mut(&content_hash) {
+            self.bytes = self.bytes - existing.size_bytes() + size;
+            *existing = chunk;
+        } else if self.entries.len() < self.capacity && self.bytes + size <= self.max_bytes {
+            self.bytes += size;
+            self.entries.insert(content_hash, chunk);
+        }
+    }
+}
+
+/// Merge search results of identical content in different files into the
+/// best-scoring one, which then lists every location
+pub fn merge_identical(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut merged: Vec<SearchResult> = Vec::with_capacity(results.len());
+    for result in results {
+        let hash = result.metadata.get("content_hash").and_then(|v| v.as_str());
+        let kept = hash.and_then(|hash| {
+            merged
+                .iter_mut()
+                .find(|r| r.metadata.get("content_hash").and_then(|v| v.as_str()) == Some(hash))
+        });
+        match kept {
+            Some(kept) => {
+                let locations = result.metadata.get("locations").and_then(|l| l.as_array()).cloned().unwrap_or_default();
+                for location in locations {
+                    add_location(&mut kept.metadata, location);
+                }
+            }
+            None => merged.push(result),
+        }
+    }
+    merged
+}
+
+/// Drop the results of large containers (classes, impls, interfaces) when
+/// one of their members is also returned: they are indexed whole and member
+/// by member, so both would show the same lines
+pub fn drop_split_containers(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let field = |result: &SearchResult, name: &str| result.metadata.get(name).and_then(|v| v.as_str()).map(str::to_string);
+    let line = |result: &SearchResult, name: &str| result.metadata.get(name).and_then(|v| v.as_u64()).unwrap_or(0);
+    // (file, enclosing symbol, start line, end line) of each member
+    let members: Vec<(String, String, u64, u64)> = results
+        .iter()
+        .filter_map(|result| {
+            let parent = result.metadata.get("parents")?.as_array()?.last()?.as_str()?.to_string();
+            Some((field(result, "file_path")?, parent, line(result, "start_line"), line(result, "end_line")))
+        })
+        .collect();
+    if members.is_empty() {
+        return results;
+    }
+
+    results
+        .into_iter()
+        .filter(|result| {
+            let kind = field(result, "symbol_kind");
+            if kind.as_deref() != Some(SymbolKind::Class.as_str()) && kind.as_deref() != Some(SymbolKind::Interface.as_str()) {
+                return true;
+            }
+            let (Some(file), Some(name)) = (field(result, "file_path"), field(result, "symbol_name")) else {
+                return true;
+            };
+            let (start, end) = (line(result, "start_line"), line(result, "end_line"));
+            !members
+                .iter()
+                .any(|(member_file, parent, member_start, member_end)| {
+                    *member_file == file && *parent == name && *member_start <= end && *member_end >= start
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_db::Metric;
+
+    #[test]
+    fn test_duplicate_locations_are_merged() {
+        let hash = "00000000000000ff".repeat(4);
+        let mut dedup = ChunkDeduplicator::new(10);
+        let mut chunk = StoredChunk {
+            id: ChunkDeduplicator::chunk_id(&hash),
+            vector: vec![0.1, 0.2],
+            metadata: json!({ "locations": [ChunkDeduplicator::location("a.rs", 0, 5)] }),
+        };
+        chunk.add_location(ChunkDeduplicator::location("vendor/a.rs", 0, 5));
+        chunk.add_location(ChunkDeduplicator::location("vendor/a.rs", 0, 5));
+        dedup.insert(hash.clone(), chunk);
+
+        let stored = dedup.get(&hash).unwrap();
+        assert_eq!(stored.id, 255);
+        assert_eq!(stored.metadata["locations"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_identical_content_in_other_files() {
+        let hash = "ab".repeat(32);
+        assert_eq!(ChunkDeduplicator::row_id("a.rs", &hash), ChunkDeduplicator::row_id("a.rs", &hash));
+        assert_ne!(ChunkDeduplicator::row_id("a.rs", &hash), ChunkDeduplicator::row_id("b.rs", &hash));
+
+        let result = |file: &str, hash: &str, score| {
+            SearchResult::new(
+                score,
+                Metric::Cosine,
+                json!({
+                    "file_path": file,
+                    "content_hash": hash,
+                    "locations": [ChunkDeduplicator::location(file, 0, 5)],
+                }),
+            )
+        };
+        let other = "cd".repeat(32);
+        let merged = merge_identical(vec![result("a.rs", &hash, 0.9), result("c.rs", &other, 0.8), result("b.rs", &hash, 0.7)]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].metadata["file_path"], "a.rs");
+        assert_eq!(merged[0].metadata["locations"][1]["file_path"], "b.rs");
+        assert_eq!(merged[1].metadata["locations"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_containers_with_a_returned_member_are_dropped() {
+        let result = |name: &str, kind: &str, parents: &[&str], lines: (u64, u64)| {
+            SearchResult::new(
+                0.5,
+                Metric::Cosine,
+                json!({
+                    "file_path": "src/engine.rs",
+                    "symbol_name": name,
+                    "symbol_kind": kind,
+                    "parents": parents,
+                    "start_line": lines.0,
+                    "end_line": lines.1,
+                }),
+            )
+        };
+        let results = drop_split_containers(vec![
+            result("Engine", "class", &[], (10, 80)),
+            result("search", "function", &["Engine"], (20, 30)),
+            result("Cache", "class", &[], (90, 120)),
+        ]);
+        let names: Vec<_> = results.iter().map(|r| r.metadata["symbol_name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["search", "Cache"]);
+    }
+
+    #[test]
+    fn test_memory_limit_stops_new_entries() {
+        let chunk = |id| StoredChunk {
+            id,
+            vector: vec![0.0; 256],
+            metadata: json!({}),
+        };
+        let mut dedup = ChunkDeduplicator::new(10).with_max_bytes(1500);
+        dedup.insert("a".to_string(), chunk(1));
+        dedup.insert("b".to_string(), chunk(2));
+        dedup.insert("a".to_string(), chunk(3));
+
+        assert_eq!(dedup.get("a").unwrap().id, 3);
+        assert!(!dedup.contains("b"));
+    }
+}
@@ -0,0 +1,54 @@
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+/// Prefix marking compressed `content` metadata
+const COMPRESSED_PREFIX: &str = "zstd:v1:";
+
+/// zstd level: fast, and most of the gain on source code
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Content shorter than this is stored as is, as base64 would outweigh the
+/// saving
+const MIN_COMPRESSED_LEN: usize = 256;
+
+pub fn is_compressed(text: &str) -> bool {
+    text.starts_with(COMPRESSED_PREFIX)
+}
+
+/// Compress `text` with zstd and encode it as base64, returning it unchanged
+/// when that would not make it shorter
+pub fn compress(text: &str) -> Result<String> {
+    if text.len() < MIN_COMPRESSED_LEN {
+        return Ok(text.to_string());
+    }
+    let compressed = zstd::bulk::compress(text.as_bytes(), COMPRESSION_LEVEL).context("Failed to compress content")?;
+    let packed = format!("{}{}", COMPRESSED_PREFIX, STANDARD.encode(compressed));
+    Ok(if packed.len() < text.len() { packed } else { text.to_string() })
+}
+
+/// Decompress text produced by [`compress`]
+pub fn decompress(text: &str) -> Result<String> {
+    let encoded = text.strip_prefix(COMPRESSED_PREFIX).context("Content is not compressed")?;
+    let compressed = STANDARD.decode(encoded).context("Invalid compressed content")?;
+    let bytes = zstd::stream::decode_all(compressed.as_slice()).context("Failed to decompress content")?;
+    String::from_utf8(bytes).context("Decompressed content is not UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_roundtrip() {
+        let source = "fn handle(request: &Request) -> Response {\n    Response::ok()\n}\n".repeat(20);
+        let packed = compress(&source).unwrap();
+        assert!(is_compressed(&packed));
+        assert!(packed.len() < source.len() / 2);
+        assert_eq!(decompress(&packed).unwrap(), source);
+
+        // Short content isn't worth compressing
+        assert_eq!(compress("fn main() {}").unwrap(), "fn main() {}");
+        assert!(decompress("fn main() {}").is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! Vendored dependencies (committed `node_modules`, `vendor/`,
+//! `third_party/`) of projects indexed with `shared_deps`: stored once per
+//! index fingerprint in a collection shared by all projects, so identical
+//! dependency files across a monorepo are embedded once. Encrypted projects
+//! can't share their dependencies, which would be stored in plain text.
+
+use crate::parser::code_parser::CodeParser;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+/// Directories holding vendored dependency code
+const VENDORED_DIRS: &[&str] = &["node_modules", "vendor", "third_party", "third-party"];
+
+/// Vendored files met by an index run of a `shared_deps` project
+#[derive(Debug, Default)]
+pub struct DepsRun {
+    pub collection_name: String,
+    /// Hashes of the files already in the collection, stored by any project
+    pub known: HashSet<String>,
+    /// Content hash of each vendored file walked, by relative path
+    pub files: HashMap<PathBuf, String>,
+    /// Files embedded by this run
+    pub embedded_files: usize,
+    pub embedded_chunks: usize,
+}
+
+/// Whether a file (path relative to the project root) is inside a vendored
+/// dependency directory
+pub fn is_vendored(relative_path: &Path) -> bool {
+    let Some(parent) = relative_path.parent() else {
+        return false;
+    };
+    parent.components().any(|component| match component {
+        Component::Normal(name) => name.to_str().is_some_and(|name| VENDORED_DIRS.contains(&name)),
+        _ => false,
+    })
+}
+
+/// Shared collection of the dependencies of projects with the same index
+/// fingerprint: embedded by the same model build, chunked and redacted
+/// alike, so a row one project stored is the row any other would have
+pub fn deps_collection_name(fingerprint: &str) -> String {
+    let hash = CodeParser::hash_file(fingerprint);
+    format!("code_deps_{}", &hash[..16])
+}
+
+/// Row id of a chunk of a dependency file: a file's chunks are stored once
+/// for all projects vendoring the same file, and kept apart from those of
+/// other versions of it
+pub fn chunk_id(file_hash: &str, text_hash: &str) -> i64 {
+    let hash = CodeParser::hash_file(&format!("{}:{}", file_hash, text_hash));
+    super::chunk_dedup::ChunkDeduplicator::chunk_id(&hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vendored_paths() {
+        assert!(is_vendored(Path::new("node_modules/lodash/lodash.js")));
+        assert!(is_vendored(Path::new("web/node_modules/react/index.js")));
+        assert!(is_vendored(Path::new("vendor/github.com/pkg/errors/errors.go")));
+        assert!(is_vendored(Path::new("third_party/zlib/inflate.c")));
+        assert!(!is_vendored(Path::new("src/vendor.rs")));
+        assert!(!is_vendored(Path::new("src/vendors/client.rs")));
+
+        let collection = deps_collection_name("0123456789abcdef");
+        assert_eq!(collection, deps_collection_name("0123456789abcdef"));
+        assert_ne!(collection, deps_collection_name("0123456789abcdef-fedcba9876543210"));
+        assert_ne!(chunk_id("a1", "t1"), chunk_id("b2", "t1"));
+    }
+}
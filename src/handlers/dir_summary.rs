@@ -0,0 +1,182 @@
+//! Synthetic directory summary chunks (file names and key symbols of a
+//! directory), indexed at the end of an index run so high-level queries such
+//! as "where is the billing subsystem" match the directory rather than a
+//! random function inside it
+
+use crate::archive;
+use crate::parser::{CodeChunk, SymbolKind};
+use crate::paths;
+use crate::snapshot::FileEntry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Directories with fewer indexed files aren't summarized
+const MIN_SUMMARY_FILES: usize = 2;
+
+/// File names listed per summary
+const MAX_SUMMARY_FILES: usize = 50;
+
+/// Symbols listed per summary
+const MAX_SUMMARY_SYMBOLS: usize = 40;
+
+/// Symbol kinds listed first, as they name a directory's main concepts
+const KEY_SYMBOL_KINDS: &[&str] = &["module", "interface", "class", "struct", "function"];
+
+/// Directory of an indexed file (empty for the project root)
+pub fn parent_dir(relative_path: &Path) -> PathBuf {
+    relative_path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+/// Stored `file_path` of a directory's summary; the trailing slash keeps it
+/// apart from files
+pub fn summary_path(dir: &Path) -> String {
+    format!("{}/", paths::to_slash(dir))
+}
+
+/// Whether a directory has enough indexed files of its own to keep a summary
+pub fn is_summarized(files: &HashMap<PathBuf, FileEntry>, dir: &Path) -> bool {
+    files
+        .keys()
+        .filter(|path| archive::split_virtual(path).is_none() && parent_dir(path) == dir)
+        .count()
+        >= MIN_SUMMARY_FILES
+}
+
+/// Summary chunks of the given directories, built from the indexed files of
+/// the project (archive entries are left out)
+pub fn build(files: &HashMap<PathBuf, FileEntry>, dirs: &BTreeSet<PathBuf>) -> Vec<CodeChunk> {
+    let mut by_dir: BTreeMap<PathBuf, Vec<(&Path, &FileEntry)>> = BTreeMap::new();
+    let mut subdirs: BTreeMap<PathBuf, BTreeSet<String>> = BTreeMap::new();
+    for (path, entry) in files {
+        if archive::split_virtual(path).is_some() {
+            continue;
+        }
+        let dir = parent_dir(path);
+        // Every ancestor lists the child directory this file is under
+        for ancestor in dir.ancestors().skip(1) {
+            let child = dir.strip_prefix(ancestor).ok().and_then(|rest| rest.iter().next());
+            if let Some(child) = child {
+                subdirs
+                    .entry(ancestor.to_path_buf())
+                    .or_default()
+                    .insert(child.to_string_lossy().into_owned());
+            }
+        }
+        by_dir.entry(dir).or_default().push((path.as_path(), entry));
+    }
+
+    dirs.iter()
+        .filter_map(|dir| {
+            let mut files = by_dir.remove(dir).filter(|files| files.len() >= MIN_SUMMARY_FILES)?;
+            files.sort_by_key(|(path, _)| *path);
+            let children = subdirs.remove(dir).unwrap_or_default();
+            Some(summary_chunk(dir, &files, &children))
+        })
+        .collect()
+}
+
+fn summary_chunk(dir: &Path, files: &[(&Path, &FileEntry)], subdirs: &BTreeSet<String>) -> CodeChunk {
+    let name = if dir.as_os_str().is_empty() {
+        "(project root)".to_string()
+    } else {
+        paths::to_slash(dir)
+    };
+
+    let mut lines = vec![format!("Directory {}", name)];
+    let file_names: Vec<String> = files
+        .iter()
+        .take(MAX_SUMMARY_FILES)
+        .filter_map(|(path, _)| Some(path.file_name()?.to_string_lossy().into_owned()))
+        .collect();
+    let more = files.len().saturating_sub(MAX_SUMMARY_FILES);
+    lines.push(format!(
+        "Files: {}{}",
+        file_names.join(", "),
+        if more > 0 { format!(" and {} more", more) } else { String::new() }
+    ));
+    if !subdirs.is_empty() {
+        lines.push(format!("Subdirectories: {}", subdirs.iter().cloned().collect::<Vec<_>>().join(", ")));
+    }
+
+    // Key symbols first, then the rest, in file order
+    let rank = |kind: &str| KEY_SYMBOL_KINDS.iter().position(|k| *k == kind).unwrap_or(KEY_SYMBOL_KINDS.len());
+    let mut symbols: Vec<(&str, &str)> = files
+        .iter()
+        .flat_map(|(_, entry)| entry.symbols.iter())
+        .filter(|s| s.kind != "section")
+        .map(|s| (s.name.as_str(), s.kind.as_str()))
+        .collect();
+    symbols.sort_by_key(|(_, kind)| rank(kind));
+    symbols.dedup();
+    if !symbols.is_empty() {
+        let listed: Vec<String> = symbols
+            .iter()
+            .take(MAX_SUMMARY_SYMBOLS)
+            .map(|(name, kind)| format!("{} ({})", name, kind))
+            .collect();
+        lines.push(format!("Symbols: {}", listed.join(", ")));
+    }
+
+    CodeChunk {
+        file_path: summary_path(dir),
+        end_line: lines.len() - 1,
+        content: lines.join("\n"),
+        start_line: 0,
+        symbol_name: Some(name),
+        symbol_kind: SymbolKind::Directory,
+        parents: Vec::new(),
+        language: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::SymbolEntry;
+
+    fn file(symbols: &[(&str, &str)]) -> FileEntry {
+        FileEntry {
+            symbols: symbols
+                .iter()
+                .map(|(name, kind)| SymbolEntry {
+                    name: name.to_string(),
+                    kind: kind.to_string(),
+                    hash: String::new(),
+                    start_line: 0,
+                    end_line: None,
+                })
+                .collect(),
+            ..FileEntry::new(String::new(), 1)
+        }
+    }
+
+    #[test]
+    fn test_directory_summaries() {
+        let files = HashMap::from([
+            (PathBuf::from("src/billing/invoice.rs"), file(&[("total", "method"), ("Invoice", "struct")])),
+            (PathBuf::from("src/billing/payment.rs"), file(&[("charge", "function")])),
+            (PathBuf::from("src/billing/stripe/client.rs"), file(&[])),
+            (PathBuf::from("src/main.rs"), file(&[("main", "function")])),
+        ]);
+        let dirs = BTreeSet::from([PathBuf::from("src/billing"), PathBuf::from("src")]);
+
+        let chunks = build(&files, &dirs);
+        // src has a single file of its own
+        assert_eq!(chunks.len(), 1);
+        let summary = &chunks[0];
+        assert_eq!(summary.file_path, "src/billing/");
+        assert_eq!(summary.symbol_kind, SymbolKind::Directory);
+        assert_eq!(
+            summary.content,
+            "Directory src/billing\n\
+             Files: invoice.rs, payment.rs\n\
+             Subdirectories: stripe\n\
+             Symbols: Invoice (struct), charge (function), total (method)"
+        );
+        assert_eq!(summary.end_line, 3);
+
+        assert!(is_summarized(&files, Path::new("src/billing")));
+        assert!(!is_summarized(&files, Path::new("src")));
+        assert!(!is_summarized(&files, Path::new("docs")));
+    }
+}
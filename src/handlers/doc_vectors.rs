@@ -0,0 +1,153 @@
+//! Dual-vector chunks: projects indexed with `doc_vectors` also embed the
+//! doc comments and docstrings of each chunk on their own, into a companion
+//! collection searched along with the code vectors, as natural-language
+//! queries match prose far better than code tokens
+
+use crate::vector_db::SearchResult;
+use std::collections::HashMap;
+
+/// Suffix of the collection holding a project's doc text vectors
+const DOCS_SUFFIX: &str = "_docs";
+
+/// Shortest doc text (in characters) worth a vector of its own
+const MIN_DOC_CHARS: usize = 16;
+
+/// Most lines of doc text embedded per chunk
+const MAX_DOC_LINES: usize = 40;
+
+/// Collection holding the doc text vectors of a project's collection
+pub fn docs_collection(collection_name: &str) -> String {
+    format!("{}{}", collection_name, DOCS_SUFFIX)
+}
+
+/// Doc comments, comment lines and docstrings of a chunk with their comment
+/// markers stripped, `None` if there is too little prose to embed
+pub fn doc_text(content: &str) -> Option<String> {
+    let mut lines = Vec::new();
+    let mut block: Option<&str> = None;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if let Some(end) = block {
+            match trimmed.find(end) {
+                Some(at) => {
+                    lines.push(strip_block_line(&trimmed[..at]));
+                    block = None;
+                }
+                None => lines.push(strip_block_line(trimmed)),
+            }
+            continue;
+        }
+        if let Some(quote) = ["\"\"\"", "'''"].into_iter().find(|quote| trimmed.starts_with(quote)) {
+            let rest = &trimmed[quote.len()..];
+            match rest.find(quote) {
+                Some(at) => lines.push(&rest[..at]),
+                None => {
+                    lines.push(rest);
+                    block = Some(quote);
+                }
+            }
+        } else if let Some(rest) = trimmed.strip_prefix("/*") {
+            match rest.find("*/") {
+                Some(at) => lines.push(strip_block_line(&rest[..at])),
+                None => {
+                    lines.push(strip_block_line(rest));
+                    block = Some("*/");
+                }
+            }
+        } else if let Some(comment) = line_comment(trimmed) {
+            lines.push(comment);
+        }
+    }
+
+    let lines: Vec<&str> = lines
+        .into_iter()
+        .map(str::trim)
+        .filter(|line| line.chars().any(char::is_alphanumeric))
+        .take(MAX_DOC_LINES)
+        .collect();
+    let text = lines.join("\n");
+    (text.chars().filter(|c| c.is_alphanumeric()).count() >= MIN_DOC_CHARS).then_some(text)
+}
+
+/// Text of a line comment (`//`, `///`, `//!`, `#`, `--`), `None` for code.
+/// `#` and `--` need a space after them, so attributes, preprocessor lines
+/// and decrements aren't taken for comments.
+fn line_comment(line: &str) -> Option<&str> {
+    if let Some(rest) = line.strip_prefix("//") {
+        return Some(rest.trim_start_matches(['/', '!']));
+    }
+    ["#", "--"]
+        .into_iter()
+        .find_map(|marker| line.strip_prefix(marker))
+        .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+}
+
+/// Line of a block comment without its leading `*` (and the `*` of `/**`)
+fn strip_block_line(line: &str) -> &str {
+    line.trim_start().trim_start_matches('*')
+}
+
+/// Code and doc text results of a search merged per chunk: a chunk scores
+/// the better of its code and doc text similarity. Both vectors come from
+/// the same model, so their scores compare, and the fused scores stay
+/// comparable with projects indexed without doc vectors.
+pub fn fuse(code: Vec<SearchResult>, docs: Vec<SearchResult>, limit: usize) -> Vec<SearchResult> {
+    let key = |r: &SearchResult| {
+        let text = |field: &str| r.metadata.get(field).map(|v| v.to_string()).unwrap_or_default();
+        (text("file_path"), text("start_line"))
+    };
+    let mut fused: Vec<SearchResult> = Vec::with_capacity(code.len() + docs.len());
+    let mut positions: HashMap<(String, String), usize> = HashMap::new();
+    for result in code.into_iter().chain(docs) {
+        match positions.get(&key(&result)) {
+            Some(&i) => {
+                if result.score > fused[i].score {
+                    fused[i].score = result.score;
+                    fused[i].raw_score = result.raw_score;
+                }
+            }
+            None => {
+                positions.insert(key(&result), fused.len());
+                fused.push(result);
+            }
+        }
+    }
+    fused.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    fused.truncate(limit);
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_db::Metric;
+    use serde_json::json;
+
+    #[test]
+    fn test_doc_text() {
+        let rust = "/// Load the configuration from disk,\n/// falling back to the defaults\n#[must_use]\npub fn load() -> Config {\n    // read the file\n    x -= 1;\n}";
+        assert_eq!(
+            doc_text(rust).unwrap(),
+            "Load the configuration from disk,\nfalling back to the defaults\nread the file"
+        );
+
+        let python = "def retry(call):\n    \"\"\"Call again with exponential backoff\n    until it succeeds.\n    \"\"\"\n    return call()";
+        assert_eq!(doc_text(python).unwrap(), "Call again with exponential backoff\nuntil it succeeds.");
+
+        let java = "/**\n * Parse a version string.\n * @param text the version\n */\nVersion parse(String text) {}";
+        assert_eq!(doc_text(java).unwrap(), "Parse a version string.\n@param text the version");
+
+        assert_eq!(doc_text("#include <stdio.h>\nint main() { return 0; } // ok"), None);
+    }
+
+    #[test]
+    fn test_fuse_doc_scores() {
+        let result = |path: &str, raw: f32| SearchResult::new(raw, Metric::Cosine, json!({ "file_path": path, "start_line": 0 }));
+        let code = vec![result("a.rs", 0.6), result("b.rs", 0.5)];
+        let docs = vec![result("b.rs", 0.9), result("c.rs", 0.4)];
+        let fused = fuse(code, docs, 2);
+        let paths: Vec<&str> = fused.iter().map(|r| r.metadata["file_path"].as_str().unwrap()).collect();
+        assert_eq!(paths, ["b.rs", "a.rs"]);
+        assert_eq!(fused[0].score, 0.9);
+    }
+}
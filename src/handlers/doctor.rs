@@ -0,0 +1,58 @@
+//! Self-check of the server environment (`self_check` tool and `doctor` command)
+
+use anyhow::Result;
+
+/// Outcome of a single self-check
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+    /// How to fix a failed check
+    pub hint: Option<String>,
+}
+
+impl CheckResult {
+    /// Build a check result, attaching `hint` only when the check failed
+    pub fn from_result(name: &'static str, result: Result<String>, hint: impl FnOnce() -> String) -> Self {
+        match result {
+            Ok(detail) => Self {
+                name,
+                passed: true,
+                detail,
+                hint: None,
+            },
+            Err(e) => Self {
+                name,
+                passed: false,
+                detail: format!("{:#}", e),
+                hint: Some(hint()),
+            },
+        }
+    }
+}
+
+/// Whether every check passed
+pub fn all_passed(checks: &[CheckResult]) -> bool {
+    checks.iter().all(|c| c.passed)
+}
+
+/// Render check results as a pass/fail report
+pub fn format_report(checks: &[CheckResult]) -> String {
+    let mut report = String::from("Self-check:\n\n");
+    for check in checks {
+        let status = if check.passed { "PASS" } else { "FAIL" };
+        report.push_str(&format!("[{}] {}: {}\n", status, check.name, check.detail));
+        if let Some(hint) = &check.hint {
+            report.push_str(&format!("       Fix: {}\n", hint));
+        }
+    }
+
+    let failed = checks.iter().filter(|c| !c.passed).count();
+    if failed == 0 {
+        report.push_str("\nAll checks passed.");
+    } else {
+        report.push_str(&format!("\n{} of {} checks failed.", failed, checks.len()));
+    }
+    report
+}
@@ -0,0 +1,153 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Prefix marking encrypted `content` metadata
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+/// Nonce length of AES-GCM
+const NONCE_LEN: usize = 12;
+
+/// Block size of SHA-256, for HMAC
+const HMAC_BLOCK_LEN: usize = 64;
+
+/// AES-256-GCM cipher for chunk content stored in the vector database,
+/// keyed by a file that never leaves this machine
+pub struct ContentCipher {
+    cipher: Aes256Gcm,
+    /// HMAC key of chunk hashes, derived from the content key
+    hash_key: [u8; 32],
+}
+
+impl ContentCipher {
+    /// Load the key at `key_path`, generating it (readable by the owner
+    /// only) on first use
+    pub fn load_or_create(key_path: &Path) -> Result<Self> {
+        let key = if key_path.exists() {
+            let encoded = std::fs::read_to_string(key_path)
+                .with_context(|| format!("Failed to read encryption key: {}", key_path.display()))?;
+            let bytes = STANDARD
+                .decode(encoded.trim())
+                .with_context(|| format!("Invalid encryption key: {}", key_path.display()))?;
+            if bytes.len() != 32 {
+                anyhow::bail!("Invalid encryption key (expected 32 bytes): {}", key_path.display());
+            }
+            *Key::<Aes256Gcm>::from_slice(&bytes)
+        } else {
+            let key = Aes256Gcm::generate_key(OsRng);
+            write_key(key_path, &STANDARD.encode(key))?;
+            tracing::info!("Generated content encryption key: {}", key_path.display());
+            key
+        };
+
+        let hash_key = Sha256::new().chain_update(b"code-context chunk hashes\0").chain_update(key).finalize().into();
+        Ok(Self {
+            cipher: Aes256Gcm::new(&key),
+            hash_key,
+        })
+    }
+
+    /// Hex HMAC-SHA256 of `text`: stands in for its plain hash in ids and
+    /// metadata, which would otherwise confirm guesses of the content
+    pub fn keyed_hash(&self, text: &str) -> String {
+        let mut inner_pad = [0x36u8; HMAC_BLOCK_LEN];
+        let mut outer_pad = [0x5cu8; HMAC_BLOCK_LEN];
+        for (i, byte) in self.hash_key.iter().enumerate() {
+            inner_pad[i] ^= byte;
+            outer_pad[i] ^= byte;
+        }
+        let inner = Sha256::new().chain_update(inner_pad).chain_update(text.as_bytes()).finalize();
+        hex::encode(Sha256::new().chain_update(outer_pad).chain_update(inner).finalize())
+    }
+
+    pub fn is_encrypted(text: &str) -> bool {
+        text.starts_with(ENCRYPTED_PREFIX)
+    }
+
+    /// Encrypt `plaintext` with a fresh nonce
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt content"))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, STANDARD.encode(sealed)))
+    }
+
+    /// Decrypt text produced by [`encrypt`](Self::encrypt)
+    pub fn decrypt(&self, text: &str) -> Result<String> {
+        let encoded = text.strip_prefix(ENCRYPTED_PREFIX).context("Content is not encrypted")?;
+        let sealed = STANDARD.decode(encoded).context("Invalid encrypted content")?;
+        if sealed.len() < NONCE_LEN {
+            anyhow::bail!("Invalid encrypted content");
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow::anyhow!("Failed to decrypt content (wrong key?)"))?;
+        String::from_utf8(plaintext).context("Decrypted content is not UTF-8")
+    }
+}
+
+fn write_key(key_path: &Path, encoded: &str) -> Result<()> {
+    if let Some(dir) = key_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(key_path)
+            .with_context(|| format!("Failed to create encryption key: {}", key_path.display()))?;
+        file.write_all(encoded.as_bytes())?;
+    }
+
+    #[cfg(not(unix))]
+    std::fs::write(key_path, encoded)
+        .with_context(|| format!("Failed to create encryption key: {}", key_path.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_with_persisted_key() {
+        let dir = std::env::temp_dir().join(format!("code-context-key-{}", std::process::id()));
+        let key_path = dir.join("project.key");
+
+        let cipher = ContentCipher::load_or_create(&key_path).unwrap();
+        let sealed = cipher.encrypt("fn main() {}").unwrap();
+        assert!(ContentCipher::is_encrypted(&sealed));
+        assert!(!sealed.contains("main"));
+
+        let reloaded = ContentCipher::load_or_create(&key_path).unwrap();
+        assert_eq!(reloaded.decrypt(&sealed).unwrap(), "fn main() {}");
+
+        // Keyed hashes are stable per key, and not the plain hash
+        let hash = cipher.keyed_hash("fn main() {}");
+        assert_eq!(hash, reloaded.keyed_hash("fn main() {}"));
+        assert_eq!(hash.len(), 64);
+        assert_ne!(hash, hex::encode(Sha256::digest(b"fn main() {}")));
+        let other = ContentCipher::load_or_create(&dir.join("other.key")).unwrap();
+        assert_ne!(hash, other.keyed_hash("fn main() {}"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+//! Health score of an indexed project, shown by `get_indexing_status`:
+//! how much of the index still matches the files on disk, whether the
+//! vector database holds the vectors the snapshot expects, and whether it
+//! is reachable at all
+
+/// What a project's health is computed from
+#[derive(Debug, Clone, Default)]
+pub struct IndexHealth {
+    /// Files recorded in the snapshot
+    pub total_files: usize,
+    /// Recorded files modified or deleted on disk since they were indexed
+    pub stale_files: usize,
+    /// Chunks recorded in the snapshot
+    pub chunks: usize,
+    /// Rows the recorded chunks should take: distinct chunk ids per file
+    pub expected_vectors: usize,
+    /// Rows in the project's collection, `None` if they couldn't be counted
+    pub vectors: Option<usize>,
+    /// Why the vector database couldn't be reached
+    pub backend_error: Option<String>,
+}
+
+impl IndexHealth {
+    /// Fraction of indexed files unchanged on disk
+    pub fn freshness(&self) -> f64 {
+        if self.total_files == 0 {
+            return 1.0;
+        }
+        1.0 - self.stale_files as f64 / self.total_files as f64
+    }
+
+    /// Stored vectors relative to the rows the snapshot expects, capped at
+    /// 1.0 since directory summaries add rows of their own
+    pub fn vector_coverage(&self) -> Option<f64> {
+        let vectors = self.vectors?;
+        if self.expected_vectors == 0 {
+            return Some(1.0);
+        }
+        Some((vectors as f64 / self.expected_vectors as f64).min(1.0))
+    }
+
+    /// Overall score between 0 and 1: zero while the vector database is
+    /// unreachable, else freshness scaled by vector coverage
+    pub fn score(&self) -> f64 {
+        if self.backend_error.is_some() {
+            return 0.0;
+        }
+        self.freshness() * self.vector_coverage().unwrap_or(1.0)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self.score() {
+            score if score >= 0.9 => "good",
+            score if score >= 0.6 => "degraded",
+            _ => "poor",
+        }
+    }
+
+    /// Status lines: the score, each of its parts, and what to do about
+    /// the weak ones
+    pub fn describe(&self) -> String {
+        let mut text = format!("Health: {:.0}% ({})", self.score() * 100.0, self.label());
+        text.push_str(&format!(
+            "\n  Freshness: {}/{} files modified or deleted since indexing",
+            self.stale_files, self.total_files
+        ));
+        match (&self.backend_error, self.vectors) {
+            (Some(error), _) => text.push_str(&format!("\n  Vector database: unreachable ({})", error)),
+            (None, Some(vectors)) => text.push_str(&format!(
+                "\n  Vectors: {}/{} stored ({} chunks)",
+                vectors, self.expected_vectors, self.chunks
+            )),
+            (None, None) => text.push_str("\n  Vectors: not counted by this vector database"),
+        }
+
+        if self.stale_files > 0 {
+            text.push_str("\n  Re-run index_codebase, or search with refresh: true, to catch up with the changes");
+        }
+        if self.vector_coverage().is_some_and(|coverage| coverage < 1.0) {
+            text.push_str("\n  Vectors are missing from the collection: re-index with force: true");
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_health() {
+        let healthy = IndexHealth {
+            total_files: 100,
+            stale_files: 0,
+            chunks: 800,
+            expected_vectors: 700,
+            vectors: Some(700),
+            backend_error: None,
+        };
+        assert_eq!(healthy.score(), 1.0);
+        assert_eq!(healthy.label(), "good");
+
+        let stale = IndexHealth { stale_files: 25, ..healthy.clone() };
+        assert_eq!(stale.score(), 0.75);
+        assert!(stale.describe().starts_with("Health: 75% (degraded)"));
+
+        let lost = IndexHealth { vectors: Some(600), ..healthy.clone() };
+        assert!(lost.score() < 0.9);
+        assert!(lost.describe().contains("Vectors: 600/700 stored"));
+
+        let summarized = IndexHealth { vectors: Some(720), ..healthy.clone() };
+        assert_eq!(summarized.score(), 1.0);
+
+        let emptied = IndexHealth { vectors: Some(0), ..healthy.clone() };
+        assert_eq!(emptied.label(), "poor");
+        assert!(emptied.describe().contains("force: true"));
+
+        let unreachable = IndexHealth {
+            backend_error: Some("connection refused".to_string()),
+            ..healthy
+        };
+        assert_eq!(unreachable.score(), 0.0);
+        assert!(unreachable.describe().contains("unreachable (connection refused)"));
+    }
+}
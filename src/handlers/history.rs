@@ -0,0 +1,135 @@
+//! Time-travel index: the superseded chunk versions of projects indexed
+//! with `keep_history`, kept in a companion collection and searched with
+//! `as_of`
+
+use crate::snapshot::IndexPoint;
+use anyhow::Result;
+use serde_json::Value;
+
+/// Suffix of the collection holding a project's superseded chunks
+const HISTORY_SUFFIX: &str = "_history";
+
+/// Shortest commit id prefix accepted as `as_of`
+const MIN_COMMIT_PREFIX: usize = 4;
+
+/// Collection holding the superseded chunks of a project's collection
+pub fn history_collection(collection_name: &str) -> String {
+    format!("{}{}", collection_name, HISTORY_SUFFIX)
+}
+
+/// Whether a chunk was part of the index at `as_of`: indexed at or before
+/// it and not yet superseded. Chunks stored before the project kept history
+/// have no `indexed_at` and count as always indexed.
+pub fn visible_at(metadata: &Value, as_of: u64) -> bool {
+    let indexed_at = metadata.get("indexed_at").and_then(|v| v.as_u64()).unwrap_or(0);
+    let superseded_at = metadata.get("superseded_at").and_then(|v| v.as_u64()).unwrap_or(u64::MAX);
+    indexed_at <= as_of && as_of < superseded_at
+}
+
+/// Resolve an `as_of` argument to Unix seconds: a number of seconds, a UTC
+/// date or date and time (`2024-05-01`, `2024-05-01T12:30:00Z`), or the id
+/// of a commit recorded with one of `points` (its latest index run)
+pub fn resolve_as_of(text: &str, points: &[IndexPoint]) -> Result<u64> {
+    let text = text.trim();
+    if let Ok(secs) = text.parse::<u64>() {
+        return Ok(secs);
+    }
+    if let Some(secs) = parse_datetime(text) {
+        return Ok(secs);
+    }
+
+    let is_commit = text.len() >= MIN_COMMIT_PREFIX && text.chars().all(|c| c.is_ascii_hexdigit());
+    if is_commit {
+        let wanted = text.to_ascii_lowercase();
+        if let Some(point) = points
+            .iter()
+            .rev()
+            .find(|p| p.commit.as_deref().is_some_and(|commit| commit.starts_with(&wanted)))
+        {
+            return Ok(point.at);
+        }
+        let commits: Vec<&str> = points.iter().filter_map(|p| p.commit.as_deref()).collect();
+        anyhow::bail!(
+            "Invalid 'as_of': no index run at commit {} (indexed commits: {})",
+            text,
+            if commits.is_empty() { "none".to_string() } else { commits.join(", ") }
+        );
+    }
+    anyhow::bail!(
+        "Invalid 'as_of' '{}': expected Unix seconds, a date such as 2024-05-01 or 2024-05-01T12:30:00Z, or an indexed commit",
+        text
+    )
+}
+
+/// Seconds since the Unix epoch of `YYYY-MM-DD`, optionally followed by
+/// `THH:MM[:SS]` and `Z`, in UTC
+fn parse_datetime(text: &str) -> Option<u64> {
+    let text = text.strip_suffix('Z').unwrap_or(text);
+    let (date, time) = match text.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+
+    let mut date_parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date_parts.next()??, date_parts.next()??, date_parts.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let mut seconds = 0;
+    if let Some(time) = time {
+        let parts: Vec<i64> = time.split(':').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+        let (hour, minute, second) = match parts[..] {
+            [hour, minute] => (hour, minute, 0),
+            [hour, minute, second] => (hour, minute, second),
+            _ => return None,
+        };
+        if !(0..24).contains(&hour) || !(0..60).contains(&minute) || !(0..=60).contains(&second) {
+            return None;
+        }
+        seconds = hour * 3600 + minute * 60 + second;
+    }
+
+    u64::try_from(days_from_civil(year, month, day) * 86400 + seconds).ok()
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date (Howard Hinnant's
+/// `days_from_civil`)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_as_of() {
+        assert_eq!(history_collection("code_abc"), "code_abc_history");
+
+        let current = json!({ "indexed_at": 200 });
+        let superseded = json!({ "indexed_at": 100, "superseded_at": 200 });
+        assert!(!visible_at(&current, 150) && visible_at(&superseded, 150));
+        assert!(visible_at(&current, 200) && !visible_at(&superseded, 200));
+        assert!(visible_at(&json!({}), 0));
+
+        let points = [
+            IndexPoint { at: 100, commit: Some("0a1b2c3d4e5f".to_string()) },
+            IndexPoint { at: 200, commit: None },
+        ];
+        assert_eq!(resolve_as_of("1714566600", &points).unwrap(), 1714566600);
+        assert_eq!(resolve_as_of("2024-05-01", &points).unwrap(), 1714521600);
+        assert_eq!(resolve_as_of("2024-05-01T12:30:00Z", &points).unwrap(), 1714566600);
+        assert_eq!(resolve_as_of("1970-01-01 00:01", &points).unwrap(), 60);
+        assert_eq!(resolve_as_of("0A1B2C", &points).unwrap(), 100);
+        assert!(resolve_as_of("ffff", &points).unwrap_err().to_string().contains("0a1b2c3d4e5f"));
+        assert!(resolve_as_of("2024-13-01", &points).is_err());
+        assert!(resolve_as_of("yesterday", &points).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+//! In-flight `index_codebase` jobs: a second request for a project that is
+//! already being indexed with the same arguments is answered with the
+//! running job's progress instead of walking the project again
+
+use crate::paths;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Progress of one running index job
+#[derive(Debug)]
+pub struct IndexJob {
+    started: Instant,
+    files: AtomicUsize,
+    chunks: AtomicUsize,
+    /// Requests merged into this job
+    merged: AtomicUsize,
+    /// Arguments of the request running the job, without its `path`
+    args: Value,
+    finished: Notify,
+}
+
+impl IndexJob {
+    /// Count a file indexed by the job
+    pub fn record_file(&self, chunks: usize) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+        self.chunks.fetch_add(chunks, Ordering::Relaxed);
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Files and chunks indexed so far
+    pub fn progress(&self) -> (usize, usize) {
+        (self.files.load(Ordering::Relaxed), self.chunks.load(Ordering::Relaxed))
+    }
+
+    pub fn merged(&self) -> usize {
+        self.merged.load(Ordering::Relaxed)
+    }
+
+    /// Merge a request into this job if it has the same arguments (other
+    /// ones, such as `force` or a time budget, would be silently dropped)
+    pub fn merge(&self, args: &Value) -> bool {
+        if job_args(args) != self.args {
+            return false;
+        }
+        self.merged.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+}
+
+/// Arguments of an index request, without the `path` that may spell the
+/// same project differently
+fn job_args(args: &Value) -> Value {
+    let mut args = args.clone();
+    if let Some(args) = args.as_object_mut() {
+        args.remove("path");
+    }
+    args
+}
+
+#[derive(Default)]
+pub struct IndexJobs {
+    jobs: Arc<Mutex<HashMap<PathBuf, Arc<IndexJob>>>>,
+}
+
+impl IndexJobs {
+    /// Register a job for the project run with `args`, or return the one
+    /// already running
+    pub fn start(&self, project_root: &Path, args: &Value) -> Result<JobHandle, Arc<IndexJob>> {
+        let key = paths::normalize(project_root);
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(running) = jobs.get(&key) {
+            return Err(running.clone());
+        }
+        let job = Arc::new(IndexJob {
+            started: Instant::now(),
+            files: AtomicUsize::new(0),
+            chunks: AtomicUsize::new(0),
+            merged: AtomicUsize::new(0),
+            args: job_args(args),
+            finished: Notify::new(),
+        });
+        jobs.insert(key.clone(), job);
+        Ok(JobHandle {
+            jobs: self.jobs.clone(),
+            key,
+        })
+    }
+
+    /// The job running for the project, if any
+    pub fn get(&self, project_root: &Path) -> Option<Arc<IndexJob>> {
+        self.jobs.lock().unwrap().get(&paths::normalize(project_root)).cloned()
+    }
+
+    /// Wait until a job returned by [`start`](Self::start) has finished
+    pub async fn finished(&self, job: &Arc<IndexJob>) {
+        let finished = job.finished.notified();
+        tokio::pin!(finished);
+        // Registered before checking, so a job finishing meanwhile still wakes us
+        finished.as_mut().enable();
+        let running = self.jobs.lock().unwrap().values().any(|running| Arc::ptr_eq(running, job));
+        if running {
+            finished.await;
+        }
+    }
+}
+
+/// A registered job, removed from the registry on drop
+pub struct JobHandle {
+    jobs: Arc<Mutex<HashMap<PathBuf, Arc<IndexJob>>>>,
+    key: PathBuf,
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        let job = self.jobs.lock().unwrap().remove(&self.key);
+        if let Some(job) = job {
+            job.finished.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_jobs_are_merged() {
+        let jobs = IndexJobs::default();
+        let args = serde_json::json!({ "path": "/work/app" });
+        let handle = jobs.start(Path::new("/work/app"), &args).unwrap();
+        jobs.get(Path::new("/work/app")).unwrap().record_file(3);
+
+        let Err(running) = jobs.start(Path::new("/work/./app"), &args) else {
+            panic!("duplicate job was started");
+        };
+        assert!(running.merge(&serde_json::json!({ "path": "/work/./app" })));
+        assert_eq!(running.progress(), (1, 3));
+        assert_eq!(running.merged(), 1);
+        assert!(jobs.start(Path::new("/work/lib"), &args).is_ok());
+
+        drop(handle);
+        assert!(jobs.get(Path::new("/work/app")).is_none());
+        assert!(jobs.start(Path::new("/work/app"), &args).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_requests_with_other_arguments_wait() {
+        let jobs = Arc::new(IndexJobs::default());
+        let handle = jobs.start(Path::new("/work/app"), &serde_json::json!({ "path": "/work/app" })).unwrap();
+
+        let forced = serde_json::json!({ "path": "/work/app", "force": true });
+        let Err(running) = jobs.start(Path::new("/work/app"), &forced) else {
+            panic!("duplicate job was started");
+        };
+        assert!(!running.merge(&forced));
+        assert_eq!(running.merged(), 0);
+
+        let waiter = tokio::spawn({
+            let jobs = jobs.clone();
+            async move { jobs.finished(&running).await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+        drop(handle);
+        waiter.await.unwrap();
+        assert!(jobs.start(Path::new("/work/app"), &forced).is_ok());
+    }
+}
@@ -1 +1,19 @@
 pub mod tool_handlers;
+pub mod chunk_dedup;
+pub mod compression;
+pub mod deps;
+pub mod background;
+pub mod dir_summary;
+pub mod doc_vectors;
+pub mod doctor;
+pub mod encryption;
+pub mod health;
+pub mod history;
+pub mod index_jobs;
+pub mod priority;
+pub mod project_locks;
+pub mod query;
+pub mod query_cache;
+pub mod redaction;
+pub mod report;
+pub mod snippet;
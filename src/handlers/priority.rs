@@ -0,0 +1,98 @@
+//! Order an index run walks a project in: source directories first, tests
+//! and vendored code last, and the most recently modified files first
+//! within each tier, so a run interrupted or stopped at its time budget has
+//! already made the most valuable code searchable
+
+use super::deps;
+use ignore::DirEntry;
+use std::cmp::Reverse;
+use std::path::{Component, Path};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directories holding a project's own source code
+const SOURCE_DIRS: &[&str] = &["src", "lib", "app", "pkg", "cmd", "internal", "source"];
+
+/// Directories holding tests, fixtures and benchmarks
+const TEST_DIRS: &[&str] = &["test", "tests", "__tests__", "spec", "specs", "testdata", "fixtures", "e2e", "benches"];
+
+/// How early a file is indexed, lowest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tier {
+    Source,
+    Other,
+    Test,
+    Vendored,
+}
+
+/// Tier of a file (path relative to the project root)
+pub fn tier(relative_path: &Path) -> Tier {
+    if deps::is_vendored(relative_path) {
+        return Tier::Vendored;
+    }
+    let dirs: Vec<&str> = relative_path
+        .parent()
+        .into_iter()
+        .flat_map(|parent| parent.components())
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .collect();
+    let name = relative_path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    if dirs.iter().any(|dir| TEST_DIRS.contains(dir)) || is_test_file(name) {
+        Tier::Test
+    } else if dirs.iter().any(|dir| SOURCE_DIRS.contains(dir)) {
+        Tier::Source
+    } else {
+        Tier::Other
+    }
+}
+
+/// Test file by naming convention (`foo_test.go`, `foo.spec.ts`,
+/// `test_foo.py`, `FooTest.java`)
+fn is_test_file(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name);
+    stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_spec")
+        || stem.ends_with("Test")
+        || stem.ends_with("Tests")
+        || name.contains(".test.")
+        || name.contains(".spec.")
+}
+
+/// Entries of a walk in index order. Entries of the same tier and time keep
+/// their walk order.
+pub fn index_order(entries: impl Iterator<Item = DirEntry>, project_root: &Path) -> Vec<DirEntry> {
+    let mut keyed: Vec<((Tier, Reverse<SystemTime>), DirEntry)> = entries
+        .map(|entry| {
+            let relative_path = entry.path().strip_prefix(project_root).unwrap_or(entry.path());
+            let modified = entry.metadata().ok().and_then(|m| m.modified().ok()).unwrap_or(UNIX_EPOCH);
+            ((tier(relative_path), Reverse(modified)), entry)
+        })
+        .collect();
+    keyed.sort_by_key(|(key, _)| *key);
+    keyed.into_iter().map(|(_, entry)| entry).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_tiers() {
+        assert_eq!(tier(Path::new("src/main.rs")), Tier::Source);
+        assert_eq!(tier(Path::new("crates/core/src/lib.rs")), Tier::Source);
+        assert_eq!(tier(Path::new("README.md")), Tier::Other);
+        assert_eq!(tier(Path::new("scripts/build.sh")), Tier::Other);
+        assert_eq!(tier(Path::new("tests/integration.rs")), Tier::Test);
+        assert_eq!(tier(Path::new("src/parser/__tests__/parse.ts")), Tier::Test);
+        assert_eq!(tier(Path::new("pkg/server/handler_test.go")), Tier::Test);
+        assert_eq!(tier(Path::new("app/models/user.spec.ts")), Tier::Test);
+        assert_eq!(tier(Path::new("lib/test_utils.py")), Tier::Test);
+        assert_eq!(tier(Path::new("src/main/java/ParserTest.java")), Tier::Test);
+        assert_eq!(tier(Path::new("vendor/github.com/pkg/errors/errors.go")), Tier::Vendored);
+        assert_eq!(tier(Path::new("web/node_modules/react/test/index.js")), Tier::Vendored);
+        assert!(Tier::Source < Tier::Other && Tier::Other < Tier::Test && Tier::Test < Tier::Vendored);
+    }
+}
@@ -0,0 +1,54 @@
+//! Per-project locks: tool calls that change a project's index (indexing,
+//! refreshing, clearing) take its lock, so they never interleave, while
+//! calls on other projects and searches run concurrently
+
+use crate::paths;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::OwnedMutexGuard;
+
+/// Exclusive access to one project, released on drop
+pub type ProjectGuard = OwnedMutexGuard<()>;
+
+#[derive(Default)]
+pub struct ProjectLocks {
+    locks: Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl ProjectLocks {
+    fn lock_for(&self, project_root: &Path) -> Arc<tokio::sync::Mutex<()>> {
+        self.locks
+            .lock()
+            .unwrap()
+            .entry(paths::normalize(project_root))
+            .or_default()
+            .clone()
+    }
+
+    /// Wait until no other call is changing the project
+    pub async fn lock(&self, project_root: &Path) -> ProjectGuard {
+        self.lock_for(project_root).lock_owned().await
+    }
+
+    /// Exclusive access to the project, or `None` if another call holds it
+    pub fn try_lock(&self, project_root: &Path) -> Option<ProjectGuard> {
+        self.lock_for(project_root).try_lock_owned().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_locks_are_per_project() {
+        let locks = ProjectLocks::default();
+        let guard = locks.lock(Path::new("/work/app")).await;
+        assert!(locks.try_lock(Path::new("/work/app")).is_none());
+        assert!(locks.try_lock(Path::new("/work/./app")).is_none());
+        assert!(locks.try_lock(Path::new("/work/lib")).is_some());
+        drop(guard);
+        assert!(locks.try_lock(Path::new("/work/app")).is_some());
+    }
+}
@@ -0,0 +1,177 @@
+use crate::config::QueryConfig;
+
+/// Conversational openers carrying no meaning for retrieval (lowercase,
+/// longest first so "where is the" wins over "where is")
+const FILLER_PREFIXES: &[&str] = &[
+    "can you find the",
+    "can you find",
+    "where is the",
+    "where are the",
+    "where do we",
+    "where is",
+    "where are",
+    "how do we",
+    "how do i",
+    "show me the",
+    "show me",
+    "search for",
+    "look for",
+    "find the",
+    "find all",
+    "find",
+];
+
+/// Abbreviations common in code and agent queries, with their expansions
+const SYNONYMS: &[(&str, &str)] = &[
+    ("auth", "authentication authorization"),
+    ("authn", "authentication"),
+    ("authz", "authorization"),
+    ("cfg", "configuration"),
+    ("config", "configuration"),
+    ("conn", "connection"),
+    ("ctx", "context"),
+    ("db", "database"),
+    ("deps", "dependencies"),
+    ("env", "environment"),
+    ("err", "error"),
+    ("fn", "function"),
+    ("func", "function"),
+    ("impl", "implementation"),
+    ("init", "initialize"),
+    ("msg", "message"),
+    ("param", "parameter"),
+    ("params", "parameters"),
+    ("repo", "repository"),
+    ("req", "request"),
+    ("resp", "response"),
+    ("sync", "synchronize"),
+    ("util", "utility"),
+    ("utils", "utilities"),
+];
+
+/// Normalize a search query before embedding: drop conversational
+/// openers, spell out camelCase/snake_case identifiers and (optionally)
+/// expand common abbreviations. Original terms are kept, so exact
+/// identifiers still match.
+pub fn preprocess(query: &str, config: &QueryConfig) -> String {
+    let trimmed = query.trim().trim_end_matches(['?', '.', '!']);
+    let stripped = strip_filler(trimmed);
+    let query = if stripped.is_empty() { trimmed } else { stripped };
+
+    let mut extra: Vec<String> = Vec::new();
+    for token in query.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+        let words = split_identifier(token);
+        if words.len() > 1 {
+            extra.push(words.join(" "));
+        }
+        if config.expand_synonyms {
+            let lower = token.to_lowercase();
+            if let Some((_, expansion)) = SYNONYMS.iter().find(|(abbr, _)| *abbr == lower) {
+                extra.push(expansion.to_string());
+            }
+        }
+    }
+    extra.dedup();
+
+    if extra.is_empty() {
+        query.to_string()
+    } else {
+        format!("{} ({})", query, extra.join(", "))
+    }
+}
+
+/// Identifiers of a query (camelCase, PascalCase or snake_case tokens), as
+/// written, for matching symbol names and file paths
+pub fn keywords(query: &str) -> Vec<String> {
+    let mut keywords: Vec<String> = Vec::new();
+    for token in query.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+        if split_identifier(token).len() > 1 && !keywords.iter().any(|k| k == token) {
+            keywords.push(token.to_string());
+        }
+    }
+    keywords
+}
+
+/// Distinct lowercase words of a query, with identifiers split into their
+/// words, for matching text lexically; words under three letters are left out
+pub fn terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for token in query.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+        for word in split_identifier(token) {
+            if word.chars().count() >= 3 && !terms.contains(&word) {
+                terms.push(word);
+            }
+        }
+    }
+    terms
+}
+
+fn strip_filler(query: &str) -> &str {
+    let lower = query.to_lowercase();
+    for prefix in FILLER_PREFIXES {
+        let at_word_end = lower
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '));
+        // Lowercasing may change byte lengths outside ASCII
+        if at_word_end && query.is_char_boundary(prefix.len()) {
+            return query[prefix.len()..].trim_start();
+        }
+    }
+    query
+}
+
+/// Words of a camelCase, PascalCase or snake_case identifier (lowercase);
+/// a single word for anything else
+fn split_identifier(token: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for part in token.split('_').filter(|p| !p.is_empty()) {
+        let chars: Vec<char> = part.chars().collect();
+        let mut word = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            let boundary = i > 0
+                && c.is_uppercase()
+                && (chars[i - 1].is_lowercase()
+                    || chars[i - 1].is_ascii_digit()
+                    || chars.get(i + 1).is_some_and(|n| n.is_lowercase()));
+            if boundary && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            word.extend(c.to_lowercase());
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preprocess_query() {
+        let config = QueryConfig::default();
+        assert_eq!(preprocess("Where is the db conn pool?", &config), "db conn pool (database, connection)");
+        assert_eq!(
+            preprocess("parseHTTPRequest and load_user_config", &config),
+            "parseHTTPRequest and load_user_config (parse http request, load user config)"
+        );
+        assert_eq!(preprocess("find", &config), "find");
+
+        let no_synonyms = QueryConfig {
+            expand_synonyms: false,
+            ..Default::default()
+        };
+        assert_eq!(preprocess("show me auth middleware", &no_synonyms), "auth middleware");
+    }
+
+    #[test]
+    fn test_query_keywords() {
+        assert_eq!(
+            keywords("where is parseHTTPRequest called from load_user_config, parseHTTPRequest"),
+            vec!["parseHTTPRequest", "load_user_config"]
+        );
+        assert!(keywords("database connection pool").is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+//! Query embeddings of recent searches, so an agent repeating or paginating
+//! a query doesn't wait for the embedding provider again
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Distinct (model, query) pairs remembered
+pub const DEFAULT_QUERY_CACHE_CAPACITY: usize = 256;
+
+#[derive(Default)]
+struct Entries {
+    /// Vector and last use by model and query
+    vectors: HashMap<(String, String), (Vec<f32>, u64)>,
+    /// Incremented on every use
+    clock: u64,
+}
+
+/// Least-recently-used map from (model, query) to the query's vector
+pub struct QueryEmbeddingCache {
+    entries: Mutex<Entries>,
+    capacity: usize,
+}
+
+impl QueryEmbeddingCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(Entries::default()),
+            capacity,
+        }
+    }
+
+    pub fn get(&self, model: &str, query: &str) -> Option<Vec<f32>> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let now = entries.clock;
+        let (vector, used) = entries.vectors.get_mut(&(model.to_string(), query.to_string()))?;
+        *used = now;
+        Some(vector.clone())
+    }
+
+    /// Remember a query's vector, evicting the least recently used entry
+    /// when full
+    pub fn insert(&self, model: &str, query: &str, vector: Vec<f32>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let now = entries.clock;
+        let key = (model.to_string(), query.to_string());
+        if entries.vectors.len() >= self.capacity && !entries.vectors.contains_key(&key) {
+            let oldest = entries.vectors.iter().min_by_key(|(_, (_, used))| *used).map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.vectors.remove(&oldest);
+            }
+        }
+        entries.vectors.insert(key, (vector, now));
+    }
+}
+
+impl Default for QueryEmbeddingCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_QUERY_CACHE_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_cache_lru() {
+        let cache = QueryEmbeddingCache::new(2);
+        cache.insert("nomic-embed-text", "parse config", vec![1.0]);
+        cache.insert("nomic-embed-text", "retry logic", vec![2.0]);
+        assert_eq!(cache.get("nomic-embed-text", "parse config"), Some(vec![1.0]));
+        assert_eq!(cache.get("text-embedding-3-small", "parse config"), None);
+
+        // "retry logic" is now the least recently used
+        cache.insert("nomic-embed-text", "auth middleware", vec![3.0]);
+        assert_eq!(cache.get("nomic-embed-text", "retry logic"), None);
+        assert_eq!(cache.get("nomic-embed-text", "parse config"), Some(vec![1.0]));
+        assert_eq!(cache.get("nomic-embed-text", "auth middleware"), Some(vec![3.0]));
+    }
+}
@@ -0,0 +1,155 @@
+//! Machine-readable report of an index run (`[output] index_report_path`):
+//! the files indexed, skipped with their reason or failed with an error,
+//! and the time spent in each stage, so CI pipelines pre-building indexes
+//! can assert on indexing quality
+
+use crate::snapshot::SkipReason;
+use anyhow::{Context, Result};
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Placeholder of the report path replaced by the project's collection
+const COLLECTION_PLACEHOLDER: &str = "{collection}";
+
+/// Time an index run spent in each stage, summed over its files
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct StageTimes {
+    /// Parsing and chunking
+    #[serde(rename = "parse_ms", serialize_with = "millis")]
+    pub parse: Duration,
+    /// Waiting for the embedding provider (or the embedding cache)
+    #[serde(rename = "embed_ms", serialize_with = "millis")]
+    pub embed: Duration,
+    /// Upserting into the vector database
+    #[serde(rename = "store_ms", serialize_with = "millis")]
+    pub store: Duration,
+}
+
+fn millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexedFile {
+    pub path: String,
+    pub chunks: usize,
+    #[serde(serialize_with = "millis")]
+    pub duration_ms: Duration,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileError {
+    pub path: String,
+    pub error: String,
+}
+
+/// Report of one index run, filled in while the run walks the project
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IndexReport {
+    pub project: PathBuf,
+    pub collection: String,
+    pub embedding_model: String,
+    /// Unix seconds
+    pub started_at: u64,
+    #[serde(serialize_with = "millis")]
+    pub duration_ms: Duration,
+    /// Whether the run stopped at its time budget or a storage quota
+    pub stopped_early: bool,
+    pub total_files: usize,
+    pub total_chunks: usize,
+    pub deduplicated_chunks: usize,
+    pub stages: StageTimes,
+    /// Files (re-)indexed by this run; unchanged files aren't listed
+    pub files: Vec<IndexedFile>,
+    pub skipped: Vec<SkippedFile>,
+    pub errors: Vec<FileError>,
+}
+
+impl IndexReport {
+    pub fn new(project: &Path, collection: &str, embedding_model: &str, started_at: u64) -> Self {
+        Self {
+            project: project.to_path_buf(),
+            collection: collection.to_string(),
+            embedding_model: embedding_model.to_string(),
+            started_at,
+            ..Self::default()
+        }
+    }
+
+    pub fn record_file(&mut self, path: &Path, chunks: usize, duration: Duration) {
+        self.files.push(IndexedFile {
+            path: crate::paths::to_slash(path),
+            chunks,
+            duration_ms: duration,
+        });
+    }
+
+    pub fn record_error(&mut self, path: &Path, error: String) {
+        self.errors.push(FileError {
+            path: crate::paths::to_slash(path),
+            error,
+        });
+    }
+
+    /// List the skipped files, sorted by path
+    pub fn set_skipped(&mut self, skipped: &HashMap<PathBuf, SkipReason>) {
+        self.skipped = skipped
+            .iter()
+            .map(|(path, reason)| SkippedFile {
+                path: crate::paths::to_slash(path),
+                reason: *reason,
+            })
+            .collect();
+        self.skipped.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    /// Write the report as JSON to `path`, where `{collection}` stands for
+    /// the project's collection, returning the file written
+    pub fn write(&self, path: &Path) -> Result<PathBuf> {
+        let path = PathBuf::from(path.to_string_lossy().replace(COLLECTION_PLACEHOLDER, &self.collection));
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        // Written aside and renamed, so a reader never sees half a report
+        let partial = path.with_extension("json.tmp");
+        std::fs::write(&partial, serde_json::to_vec_pretty(self)?).with_context(|| format!("Failed to write {}", partial.display()))?;
+        std::fs::rename(&partial, &path).with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_report() {
+        let mut report = IndexReport::new(Path::new("/repo"), "code_chunks_1234", "nomic-embed-text", 1_700_000_000);
+        report.record_file(Path::new("src/main.rs"), 4, Duration::from_millis(120));
+        report.record_error(Path::new("src/broken.rs"), "Failed to generate embeddings".to_string());
+        report.set_skipped(&HashMap::from([
+            (PathBuf::from("logo.png"), SkipReason::Binary),
+            (PathBuf::from("data/big.json"), SkipReason::TooLarge),
+        ]));
+        report.stages.embed = Duration::from_millis(1500);
+
+        let dir = std::env::temp_dir().join(format!("report_test_{}", uuid::Uuid::new_v4()));
+        let written = report.write(&dir.join("reports/{collection}.json")).unwrap();
+        assert_eq!(written, dir.join("reports/code_chunks_1234.json"));
+
+        let json: serde_json::Value = serde_json::from_slice(&std::fs::read(&written).unwrap()).unwrap();
+        assert_eq!(json["files"][0], serde_json::json!({"path": "src/main.rs", "chunks": 4, "duration_ms": 120}));
+        assert_eq!(json["skipped"][0], serde_json::json!({"path": "data/big.json", "reason": "too_large"}));
+        assert_eq!(json["errors"][0]["path"], "src/broken.rs");
+        assert_eq!(json["stages"]["embed_ms"], 1500);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
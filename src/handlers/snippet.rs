@@ -0,0 +1,180 @@
+//! Part of a chunk shown for each search result: its start, its first
+//! lines, its symbol signature with the doc comment, or the window of lines
+//! sharing the most words with the query
+
+use super::query;
+use super::tool_handlers::truncate;
+
+/// Bytes shown by the `head` strategy
+pub const DEFAULT_SNIPPET_BYTES: usize = 500;
+
+/// Lines shown by the `lines` and `best_window` strategies
+pub const DEFAULT_SNIPPET_LINES: usize = 10;
+
+/// Most declaration lines `signature` looks through for the body to open
+const MAX_SIGNATURE_LINES: usize = 8;
+
+/// Most lines of a Python docstring `signature` shows
+const MAX_DOCSTRING_LINES: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SnippetStrategy {
+    /// The chunk's first `DEFAULT_SNIPPET_BYTES` bytes
+    #[default]
+    Head,
+    /// The chunk's first lines
+    Lines,
+    /// Doc comment, attributes and declaration, up to where the body opens
+    Signature,
+    /// The run of lines sharing the most words with the query
+    BestWindow,
+}
+
+impl SnippetStrategy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "head" => Some(SnippetStrategy::Head),
+            "lines" => Some(SnippetStrategy::Lines),
+            "signature" => Some(SnippetStrategy::Signature),
+            "best_window" => Some(SnippetStrategy::BestWindow),
+            _ => None,
+        }
+    }
+}
+
+/// Snippet settings of one search
+#[derive(Debug, Clone, Default)]
+pub struct Snippet {
+    pub strategy: SnippetStrategy,
+    /// Lines shown by `lines` and `best_window`
+    pub lines: usize,
+    /// Words of the query, for `best_window`
+    terms: Vec<String>,
+}
+
+/// Text shown for a chunk
+#[derive(Debug, Clone, PartialEq)]
+pub struct Excerpt {
+    pub text: String,
+    /// Lines of the chunk above the excerpt
+    pub skipped_lines: usize,
+}
+
+impl Snippet {
+    pub fn new(strategy: SnippetStrategy, lines: usize, query: &str) -> Self {
+        Self {
+            strategy,
+            lines: lines.max(1),
+            terms: query::terms(query),
+        }
+    }
+
+    pub fn extract(&self, content: &str) -> Excerpt {
+        let lines: Vec<&str> = content.lines().collect();
+        let (text, skipped_lines) = match self.strategy {
+            SnippetStrategy::Head => (truncate(content, DEFAULT_SNIPPET_BYTES).to_string(), 0),
+            SnippetStrategy::Lines => (lines[..lines.len().min(self.lines)].join("\n"), 0),
+            SnippetStrategy::Signature => (lines[..signature_end(&lines)].join("\n"), 0),
+            SnippetStrategy::BestWindow => {
+                let start = self.best_window(&lines);
+                (lines[start..lines.len().min(start + self.lines)].join("\n"), start)
+            }
+        };
+        Excerpt { text, skipped_lines }
+    }
+
+    /// First line of the window of `self.lines` lines matching the most
+    /// query words (counted per line); the earliest of equals, so a chunk
+    /// matching nothing shows its start
+    fn best_window(&self, lines: &[&str]) -> usize {
+        if lines.len() <= self.lines || self.terms.is_empty() {
+            return 0;
+        }
+        let hits: Vec<usize> = lines
+            .iter()
+            .map(|line| {
+                let line = line.to_lowercase();
+                self.terms.iter().filter(|term| line.contains(term.as_str())).count()
+            })
+            .collect();
+
+        let mut score: usize = hits[..self.lines].iter().sum();
+        let (mut best, mut best_score) = (0, score);
+        for start in 1..=lines.len() - self.lines {
+            score = score + hits[start + self.lines - 1] - hits[start - 1];
+            if score > best_score {
+                (best, best_score) = (start, score);
+            }
+        }
+        best
+    }
+}
+
+/// Lines making up the doc comment, attributes and declaration at the top
+/// of a chunk, with a Python docstring right below the declaration
+fn signature_end(lines: &[&str]) -> usize {
+    let mut end = lines.iter().take_while(|line| is_preamble(line)).count();
+
+    let declaration_start = end;
+    while end < lines.len() && end - declaration_start < MAX_SIGNATURE_LINES {
+        end += 1;
+        if opens_body(lines[end - 1].trim_end()) {
+            break;
+        }
+    }
+
+    if let Some(first) = lines.get(end).map(|line| line.trim_start()) {
+        if let Some(quote) = ["\"\"\"", "'''"].into_iter().find(|quote| first.starts_with(quote)) {
+            let closed_inline = first.len() >= 6 && first.trim_end().ends_with(quote);
+            let docstring = if closed_inline {
+                1
+            } else {
+                lines[end + 1..]
+                    .iter()
+                    .position(|line| line.contains(quote))
+                    .map_or(lines.len() - end, |closing| closing + 2)
+            };
+            end += docstring.min(MAX_DOCSTRING_LINES);
+        }
+    }
+    end
+}
+
+/// Blank, comment, attribute or decorator line above a declaration
+fn is_preamble(line: &str) -> bool {
+    let line = line.trim_start();
+    line.is_empty() || ["//", "/*", "*", "#", "@", "--", ";;"].iter().any(|prefix| line.starts_with(prefix))
+}
+
+/// Whether a declaration line opens the body or ends the declaration
+fn opens_body(line: &str) -> bool {
+    line.contains('{') || [":", ";", "=", "=>", " do", " where"].iter().any(|end| line.ends_with(end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHUNK: &str = "/// Load the configuration\n#[must_use]\npub fn load_config(\n    path: &Path,\n) -> Result<Config> {\n    let text = read(path)?;\n    let config = toml::from_str(&text)?;\n    validate(&config)?;\n    Ok(config)\n}";
+
+    #[test]
+    fn test_snippet_strategies() {
+        let excerpt = |strategy, lines, query| Snippet::new(strategy, lines, query).extract(CHUNK);
+
+        assert_eq!(excerpt(SnippetStrategy::Head, 10, "").text, CHUNK);
+        assert_eq!(excerpt(SnippetStrategy::Lines, 2, "").text, "/// Load the configuration\n#[must_use]");
+        assert_eq!(
+            excerpt(SnippetStrategy::Signature, 10, "").text,
+            "/// Load the configuration\n#[must_use]\npub fn load_config(\n    path: &Path,\n) -> Result<Config> {"
+        );
+
+        let window = excerpt(SnippetStrategy::BestWindow, 2, "validateConfig");
+        assert_eq!(window.text, "    let config = toml::from_str(&text)?;\n    validate(&config)?;");
+        assert_eq!(window.skipped_lines, 6);
+        assert_eq!(excerpt(SnippetStrategy::BestWindow, 2, "unrelated").skipped_lines, 0);
+
+        let python = "@cache\ndef parse(text):\n    \"\"\"Parse the text.\n\n    Returns a tree.\n    \"\"\"\n    return Tree(text)";
+        let signature = Snippet::new(SnippetStrategy::Signature, 10, "").extract(python);
+        assert_eq!(signature.text, python.rsplit_once('\n').unwrap().0);
+    }
+}
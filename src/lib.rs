@@ -0,0 +1,26 @@
+//! Code Context: semantic code search over tree-sitter chunks, embeddings
+//! and a vector database.
+//!
+//! [`CodeContextEngine`] is the embeddable entry point; the `mcp` module is
+//! the MCP front-end built on top of it.
+
+pub mod archive;
+pub mod audit;
+pub mod blame;
+pub mod config;
+pub mod embedding;
+pub mod fulltext;
+pub mod handlers;
+pub mod lsp;
+pub mod mcp;
+pub mod metrics;
+pub mod parser;
+pub mod paths;
+pub mod remote;
+pub mod snapshot;
+pub mod vector_db;
+pub mod workspace;
+
+mod engine;
+
+pub use engine::CodeContextEngine;
@@ -0,0 +1,358 @@
+//! Symbol names from language servers: chunks tree-sitter couldn't name
+//! (anonymous functions, default exports, ...) get the name and container
+//! hierarchy a running LSP server reports for them (`textDocument/documentSymbol`)
+
+use crate::parser::{CodeChunk, SymbolKind};
+use crate::paths;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+/// A symbol reported by a language server, with 0-based inclusive lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct LspSymbol {
+    pub name: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Names of the enclosing symbols, outermost first
+    pub parents: Vec<String>,
+}
+
+/// Language servers of one index run, started on first use per language;
+/// a server that fails to start or answer is not asked again in the run
+pub struct LanguageServers {
+    /// Server command line by language id (`rust`, `typescript`, ...)
+    commands: BTreeMap<String, String>,
+    project_root: PathBuf,
+    timeout: Duration,
+    sessions: HashMap<String, Option<LspSession>>,
+}
+
+impl LanguageServers {
+    pub fn new(commands: BTreeMap<String, String>, project_root: &Path, timeout: Duration) -> Self {
+        Self {
+            commands,
+            project_root: project_root.to_path_buf(),
+            timeout,
+            sessions: HashMap::new(),
+        }
+    }
+
+    /// Name the chunks of a file that tree-sitter left unnamed from its
+    /// language server's symbols, returning how many were named
+    pub async fn enrich(&mut self, relative_path: &Path, content: &str, chunks: &mut [CodeChunk]) -> usize {
+        let Some(language) = chunks.iter().find(|c| needs_name(c)).and_then(|c| c.language.clone()) else {
+            return 0;
+        };
+        let Some(command) = self.commands.get(&language) else {
+            return 0;
+        };
+
+        if !self.sessions.contains_key(&language) {
+            let session = match LspSession::start(command, &self.project_root, self.timeout).await {
+                Ok(session) => Some(session),
+                Err(e) => {
+                    tracing::warn!("Failed to start language server for {} ({}): {:#}", language, command, e);
+                    None
+                }
+            };
+            self.sessions.insert(language.clone(), session);
+        }
+        let Some(session) = self.sessions.get_mut(&language).and_then(Option::as_mut) else {
+            return 0;
+        };
+
+        let uri = paths::file_uri(&self.project_root.join(relative_path));
+        match session.document_symbols(&uri, &language, content).await {
+            Ok(symbols) => apply_symbols(&symbols, chunks),
+            Err(e) => {
+                tracing::warn!("Language server for {} failed on {:?}, not using it again: {:#}", language, relative_path, e);
+                if let Some(session) = self.sessions.insert(language, None).flatten() {
+                    session.shutdown().await;
+                }
+                0
+            }
+        }
+    }
+
+    /// Shut the started servers down
+    pub async fn shutdown(self) {
+        for session in self.sessions.into_values().flatten() {
+            session.shutdown().await;
+        }
+    }
+}
+
+/// Whether tree-sitter found a symbol chunk but no usable name for it
+fn needs_name(chunk: &CodeChunk) -> bool {
+    let named = chunk
+        .symbol_name
+        .as_deref()
+        .and_then(|name| name.chars().next())
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$');
+    let is_symbol = matches!(
+        chunk.symbol_kind,
+        SymbolKind::Function | SymbolKind::Class | SymbolKind::Method | SymbolKind::Interface | SymbolKind::Struct
+    );
+    !named && is_symbol
+}
+
+/// Name the chunks that need it after the symbol best covering them: the
+/// outermost symbol inside the chunk, or else the innermost one around it
+fn apply_symbols(symbols: &[LspSymbol], chunks: &mut [CodeChunk]) -> usize {
+    let mut named = 0;
+    for chunk in chunks.iter_mut().filter(|c| needs_name(c)) {
+        let span = |s: &&LspSymbol| s.end_line - s.start_line;
+        let inside = symbols
+            .iter()
+            .filter(|s| chunk.start_line <= s.start_line && s.end_line <= chunk.end_line)
+            .max_by_key(span);
+        let around = || {
+            symbols
+                .iter()
+                .filter(|s| s.start_line <= chunk.start_line && chunk.end_line <= s.end_line)
+                .min_by_key(span)
+        };
+        if let Some(symbol) = inside.or_else(around) {
+            chunk.symbol_name = Some(symbol.name.clone());
+            chunk.parents = symbol.parents.clone();
+            named += 1;
+        }
+    }
+    named
+}
+
+/// Flatten a `textDocument/documentSymbol` result, either hierarchical
+/// `DocumentSymbol`s or flat `SymbolInformation`s
+fn parse_symbols(result: &Value) -> Vec<LspSymbol> {
+    fn line(range: &Value, end: &str) -> Option<usize> {
+        range.get(end)?.get("line")?.as_u64().map(|l| l as usize)
+    }
+    fn walk(items: &[Value], parents: &mut Vec<String>, symbols: &mut Vec<LspSymbol>) {
+        for item in items {
+            let Some(name) = item.get("name").and_then(|n| n.as_str()) else {
+                continue;
+            };
+            // SymbolInformation has a location and at most a container name
+            let range = item.get("range").or_else(|| item.get("location").and_then(|l| l.get("range")));
+            let container = item.get("containerName").and_then(|c| c.as_str()).filter(|c| !c.is_empty());
+            if let Some((start_line, end_line)) = range.and_then(|r| Some((line(r, "start")?, line(r, "end")?))) {
+                symbols.push(LspSymbol {
+                    name: name.to_string(),
+                    start_line,
+                    end_line: end_line.max(start_line),
+                    parents: parents.iter().cloned().chain(container.map(str::to_string)).collect(),
+                });
+            }
+            if let Some(children) = item.get("children").and_then(|c| c.as_array()) {
+                parents.push(name.to_string());
+                walk(children, parents, symbols);
+                parents.pop();
+            }
+        }
+    }
+
+    let mut symbols = Vec::new();
+    if let Some(items) = result.as_array() {
+        walk(items, &mut Vec::new(), &mut symbols);
+    }
+    symbols
+}
+
+/// A running language server, spoken to over stdio
+struct LspSession {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: i64,
+    timeout: Duration,
+}
+
+impl LspSession {
+    /// Start `command` (arguments separated by spaces) and initialize it
+    /// for the project
+    async fn start(command: &str, project_root: &Path, timeout: Duration) -> Result<Self> {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().context("Empty language server command")?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .current_dir(project_root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to run {}", program))?;
+
+        let mut session = Self {
+            stdin: child.stdin.take().context("No stdin")?,
+            stdout: BufReader::new(child.stdout.take().context("No stdout")?),
+            child,
+            next_id: 0,
+            timeout,
+        };
+        let root_uri = paths::file_uri(project_root);
+        let params = json!({
+            "processId": std::process::id(),
+            "rootUri": root_uri,
+            "workspaceFolders": [{ "uri": root_uri, "name": "root" }],
+            "capabilities": {
+                "textDocument": {
+                    "documentSymbol": { "hierarchicalDocumentSymbolSupport": true }
+                }
+            }
+        });
+        session.request("initialize", params).await?;
+        session.notify("initialized", json!({})).await?;
+        Ok(session)
+    }
+
+    async fn document_symbols(&mut self, uri: &str, language: &str, text: &str) -> Result<Vec<LspSymbol>> {
+        let document = json!({ "uri": uri });
+        self.notify(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": uri, "languageId": language, "version": 1, "text": text } }),
+        )
+        .await?;
+        let result = self.request("textDocument/documentSymbol", json!({ "textDocument": document })).await;
+        self.notify("textDocument/didClose", json!({ "textDocument": document })).await?;
+        Ok(parse_symbols(&result?))
+    }
+
+    /// Send a request and wait for its response, answering the server's own
+    /// requests in the meantime
+    async fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })).await?;
+
+        let timeout = self.timeout;
+        tokio::time::timeout(timeout, async {
+            loop {
+                let message = read_message(&mut self.stdout).await?;
+                match (message.get("id"), message.get("method")) {
+                    (Some(message_id), None) if message_id == id => {
+                        if let Some(error) = message.get("error") {
+                            anyhow::bail!("{} failed: {}", method, error);
+                        }
+                        return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+                    }
+                    (Some(request_id), Some(request)) => {
+                        let result = server_request_result(request.as_str().unwrap_or_default(), &message);
+                        self.send(&json!({ "jsonrpc": "2.0", "id": request_id, "result": result })).await?;
+                    }
+                    // Notifications (diagnostics, progress, logs) aren't used
+                    _ => {}
+                }
+            }
+        })
+        .await
+        .with_context(|| format!("{} timed out after {:.1}s", method, timeout.as_secs_f64()))?
+    }
+
+    async fn notify(&mut self, method: &str, params: Value) -> Result<()> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params })).await
+    }
+
+    async fn send(&mut self, message: &Value) -> Result<()> {
+        let body = serde_json::to_vec(message)?;
+        self.stdin.write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes()).await?;
+        self.stdin.write_all(&body).await?;
+        self.stdin.flush().await?;
+        Ok(())
+    }
+
+    /// Ask the server to exit, killing it if it doesn't in time
+    async fn shutdown(mut self) {
+        if self.request("shutdown", Value::Null).await.is_ok() {
+            let _ = self.notify("exit", Value::Null).await;
+        }
+        if tokio::time::timeout(self.timeout, self.child.wait()).await.is_err() {
+            let _ = self.child.kill().await;
+        }
+    }
+}
+
+/// Answer to a request from the server: no configuration, and nothing else
+/// it asks for (progress tokens, capability registration) needs a result
+fn server_request_result(method: &str, message: &Value) -> Value {
+    match method {
+        "workspace/configuration" => {
+            let items = message.pointer("/params/items").and_then(|i| i.as_array()).map_or(0, Vec::len);
+            Value::Array(vec![Value::Null; items])
+        }
+        _ => Value::Null,
+    }
+}
+
+/// Read one `Content-Length` framed message
+async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Value> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 {
+            anyhow::bail!("Language server closed its output");
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = Some(value.trim().parse::<usize>().context("Invalid Content-Length")?);
+            }
+        }
+    }
+    let mut body = vec![0; length.context("Message without Content-Length")?];
+    reader.read_exact(&mut body).await?;
+    serde_json::from_slice(&body).context("Invalid message from language server")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(symbol_name: Option<&str>, start_line: usize, end_line: usize) -> CodeChunk {
+        CodeChunk {
+            file_path: "src/app.ts".to_string(),
+            content: String::new(),
+            start_line,
+            end_line,
+            symbol_name: symbol_name.map(str::to_string),
+            symbol_kind: SymbolKind::Function,
+            parents: Vec::new(),
+            language: Some("typescript".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lsp_symbols() {
+        let body = r#"{"jsonrpc":"2.0","id":1,"result":[
+            {"name":"routes","kind":13,"range":{"start":{"line":0,"character":0},"end":{"line":20,"character":1}},
+             "selectionRange":{"start":{"line":0,"character":6},"end":{"line":0,"character":12}},
+             "children":[{"name":"get /users callback","kind":12,
+                          "range":{"start":{"line":2,"character":14},"end":{"line":6,"character":3}},
+                          "selectionRange":{"start":{"line":2,"character":14},"end":{"line":2,"character":20}}}]},
+            {"name":"default","kind":12,"location":{"uri":"file:///app.ts","range":{"start":{"line":22,"character":0},"end":{"line":25,"character":1}}},
+             "containerName":"app"}]}"#;
+        let framed = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        let message = read_message(&mut framed.as_bytes()).await.unwrap();
+        let symbols = parse_symbols(&message["result"]);
+        assert_eq!(symbols.len(), 3);
+        assert_eq!(symbols[1].parents, vec!["routes"]);
+        assert_eq!(symbols[2].parents, vec!["app"]);
+
+        let mut chunks = [chunk(None, 2, 6), chunk(Some("handler"), 8, 12), chunk(None, 22, 25), chunk(None, 30, 31)];
+        assert_eq!(apply_symbols(&symbols, &mut chunks), 2);
+        assert_eq!(chunks[0].symbol_name.as_deref(), Some("get /users callback"));
+        assert_eq!(chunks[0].parents, vec!["routes"]);
+        assert_eq!(chunks[1].symbol_name.as_deref(), Some("handler"));
+        assert_eq!(chunks[2].symbol_name.as_deref(), Some("default"));
+        assert_eq!(chunks[3].symbol_name, None);
+    }
+}
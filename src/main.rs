@@ -1,19 +1,15 @@
-mod mcp;
-mod embedding;
-mod vector_db;
-mod parser;
-mod snapshot;
-mod handlers;
-
 use anyhow::Result;
-use std::path::PathBuf;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
-use mcp::server::McpServer;
+use code_context_mcp::config::Config;
+use code_context_mcp::handlers::doctor;
+use code_context_mcp::mcp::server::McpServer;
+use code_context_mcp::paths;
+use code_context_mcp::CodeContextEngine;
 
 /// Load .env files from multiple locations with priority order:
 /// 1. Current working directory (project-specific config)
-/// 2. XDG config directory ~/.config/code-context-mcp/.env (global default config)
+/// 2. Config directory ~/.config/code-context-mcp/.env (%APPDATA% on Windows; global default config)
 ///
 /// Environment variables set directly in the shell always take highest priority.
 fn load_env_files() {
@@ -31,7 +27,7 @@ fn load_env_files() {
     }
 
     // Try XDG config directory (global default)
-    if let Some(config_dir) = get_xdg_config_dir() {
+    if let Some(config_dir) = paths::config_dir() {
         let xdg_env = config_dir.join("code-context-mcp").join(".env");
         if xdg_env.exists() {
             if let Ok(_) = dotenv::from_path(&xdg_env) {
@@ -45,13 +41,23 @@ fn load_env_files() {
     tracing::debug!("No .env file found, using environment variables only");
 }
 
-/// Get XDG config directory, fallback to ~/.config
-fn get_xdg_config_dir() -> Option<PathBuf> {
-    std::env::var_os("XDG_CONFIG_HOME")
-        .map(PathBuf::from)
-        .or_else(|| {
-            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
-        })
+/// `code-context-mcp doctor`: run the self-check and exit non-zero on failure
+async fn run_doctor() -> Result<()> {
+    let engine = match Config::load().and_then(CodeContextEngine::from_config) {
+        Ok(engine) => engine,
+        Err(e) => {
+            println!("[FAIL] config: {:#}", e);
+            println!("       Fix: correct the reported setting in config.toml or the environment");
+            std::process::exit(1);
+        }
+    };
+
+    let checks = engine.self_check().await;
+    println!("{}", doctor::format_report(&checks));
+    if !doctor::all_passed(&checks) {
+        std::process::exit(1);
+    }
+    Ok(())
 }
 
 #[tokio::main]
@@ -65,11 +71,25 @@ async fn main() -> Result<()> {
     let env_filter = EnvFilter::try_from_env("RUST_LOG")
         .unwrap_or_else(|_| EnvFilter::new("error"));
 
+    // LOG_FORMAT=json emits one JSON object per event (with span fields such
+    // as the tool name and project) for log pipelines
+    let json = std::env::var("LOG_FORMAT").is_ok_and(|f| f.eq_ignore_ascii_case("json"));
+    let (json_layer, text_layer) = if json {
+        (Some(fmt::layer().json().with_current_span(true).with_span_list(false).with_writer(std::io::stderr)), None)
+    } else {
+        (None, Some(fmt::layer().with_writer(std::io::stderr)))
+    };
+
     tracing_subscriber::registry()
-        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(json_layer)
+        .with(text_layer)
         .with(env_filter)
         .init();
 
+    if std::env::args().nth(1).as_deref() == Some("doctor") {
+        return run_doctor().await;
+    }
+
     tracing::info!("Starting Code Context MCP server...");
 
     // Create and start MCP server
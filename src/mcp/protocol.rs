@@ -1,20 +1,78 @@
 use super::types::*;
 use anyhow::Result;
 use serde::Serialize;
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::Mutex;
 
 /// MCP Protocol handler for JSON-RPC over stdio
 pub struct Protocol {
     reader: BufReader<tokio::io::Stdin>,
-    writer: tokio::io::Stdout,
+    writer: Arc<Mutex<tokio::io::Stdout>>,
+}
+
+/// Cloneable handle for sending notifications from background tasks.
+/// Shares the protocol's stdout so messages never interleave mid-line.
+#[derive(Clone)]
+pub struct Notifier {
+    writer: Arc<Mutex<tokio::io::Stdout>>,
+}
+
+impl Notifier {
+    /// Send a JSON-RPC notification
+    pub async fn send_notification(&self, method: &str, params: Value) -> Result<()> {
+        let notification = Notification {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params,
+        };
+        write_line(&self.writer, &notification).await
+    }
+
+    /// Send an MCP logging notification (`notifications/message`)
+    pub async fn log_message(&self, level: &str, logger: &str, data: Value) -> Result<()> {
+        self.send_notification(
+            "notifications/message",
+            json!({ "level": level, "logger": logger, "data": data }),
+        )
+        .await
+    }
+
+    /// Send the response to a request handled outside the request loop
+    pub async fn send_response(&self, response: JsonRpcResponse) -> Result<()> {
+        write_line(&self.writer, &response).await
+    }
+}
+
+/// Serialize a message as one line of JSON on the shared writer
+async fn write_line<T: Serialize>(writer: &Mutex<tokio::io::Stdout>, message: &T) -> Result<()> {
+    let json = serde_json::to_string(message)?;
+    let mut writer = writer.lock().await;
+    writer.write_all(json.as_bytes()).await?;
+    writer.write_all(b"\n").await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+impl Default for Protocol {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Protocol {
     pub fn new() -> Self {
         Self {
             reader: BufReader::new(tokio::io::stdin()),
-            writer: tokio::io::stdout(),
+            writer: Arc::new(Mutex::new(tokio::io::stdout())),
+        }
+    }
+
+    /// Handle for sending notifications outside the request loop
+    pub fn notifier(&self) -> Notifier {
+        Notifier {
+            writer: self.writer.clone(),
         }
     }
 
@@ -39,45 +97,16 @@ impl Protocol {
 
     /// Send JSON-RPC response to stdout
     pub async fn send_response(&mut self, response: JsonRpcResponse) -> Result<()> {
-        let json = serde_json::to_string(&response)?;
-        self.writer.write_all(json.as_bytes()).await?;
-        self.writer.write_all(b"\n").await?;
-        self.writer.flush().await?;
-        Ok(())
-    }
-
-    /// Send notification (reserved for future use)
-    #[allow(dead_code)]
-    pub async fn send_notification(&mut self, method: &str, params: Value) -> Result<()> {
-        let notification = Notification {
-            jsonrpc: "2.0".to_string(),
-            method: method.to_string(),
-            params,
-        };
-        let json = serde_json::to_string(&notification)?;
-        self.writer.write_all(json.as_bytes()).await?;
-        self.writer.write_all(b"\n").await?;
-        self.writer.flush().await?;
-        Ok(())
+        write_line(&self.writer, &response).await
     }
 
     /// Create success response
     pub fn success_response<T: Serialize>(&self, id: Value, result: T) -> JsonRpcResponse<T> {
-        JsonRpcResponse {
-            jsonrpc: "2.0".to_string(),
-            id,
-            result: Some(result),
-            error: None,
-        }
+        JsonRpcResponse::success(id, result)
     }
 
     /// Create error response
     pub fn error_response(&self, id: Value, error: JsonRpcError) -> JsonRpcResponse {
-        JsonRpcResponse {
-            jsonrpc: "2.0".to_string(),
-            id,
-            result: None,
-            error: Some(error),
-        }
+        JsonRpcResponse::error(id, error)
     }
 }
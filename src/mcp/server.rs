@@ -1,16 +1,13 @@
 use super::protocol::Protocol;
 use super::types::*;
-use crate::embedding::ollama::OllamaEmbedding;
-use crate::embedding::EmbeddingProvider;
-use crate::handlers::tool_handlers::ToolHandlers;
-use crate::snapshot::{SnapshotManager, DEFAULT_MAX_PROJECTS};
-use crate::vector_db::milvus::MilvusVectorDatabase;
-use crate::vector_db::VectorDatabase;
+use super::validation::validate_arguments;
+use crate::config::Config;
+use crate::handlers::background::{spawn_background_refresh, BackgroundRefreshConfig};
+use crate::CodeContextEngine;
 use anyhow::Result;
 use serde_json::json;
-use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::sync::OnceLock;
+use std::time::Duration;
 
 const PROTOCOL_VERSION: &str = "2024-11-05";
 const SERVER_NAME: &str = "code-context-mcp";
@@ -19,69 +16,73 @@ const SERVER_VERSION: &str = "0.1.0";
 /// Main MCP Server
 pub struct McpServer {
     protocol: Protocol,
-    #[allow(dead_code)] // Used internally by tool_handlers via Arc
-    embedding: Arc<dyn EmbeddingProvider>,
-    #[allow(dead_code)] // Used internally by tool_handlers via Arc
-    vector_db: Arc<dyn VectorDatabase>,
-    snapshot_manager: Arc<SnapshotManager>,
-    tool_handlers: Arc<Mutex<ToolHandlers>>,
+    engine: CodeContextEngine,
+    background_refresh: Option<BackgroundRefreshConfig>,
 }
 
 impl McpServer {
     pub fn new() -> Result<Self> {
-        // Get configuration from environment
-        let ollama_host = std::env::var("OLLAMA_HOST").unwrap_or_else(|_| "http://127.0.0.1:11434".to_string());
-        let embedding_model = std::env::var("EMBEDDING_MODEL").unwrap_or_else(|_| "nomic-embed-text".to_string());
-        let milvus_address = std::env::var("MILVUS_ADDRESS").unwrap_or_else(|_| "http://127.0.0.1:19530".to_string());
-        
-        // Maximum number of indexed projects (LRU eviction when exceeded)
-        let max_projects = std::env::var("MAX_INDEXED_PROJECTS")
-            .ok()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(DEFAULT_MAX_PROJECTS);
-
-        // Initialize embedding provider
-        let embedding = Arc::new(OllamaEmbedding::new(&ollama_host, &embedding_model));
-
-        // Initialize vector database
-        let vector_db = Arc::new(MilvusVectorDatabase::new(&milvus_address));
-
-        // Initialize snapshot manager with max projects limit
-        let snapshot_path = std::env::var("SNAPSHOT_PATH")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| {
-                let home = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
-                PathBuf::from(home).join(".code-context/snapshot.json")
+        // Configuration from config.toml files, overridden by environment variables
+        let config = Config::load()?;
+
+        // Periodic refresh of pinned projects (disabled unless an interval is set)
+        let background_refresh = config
+            .background_refresh
+            .interval_secs
+            .filter(|secs| *secs > 0)
+            .map(|secs| BackgroundRefreshConfig {
+                interval: Duration::from_secs(secs),
+                budget: Duration::from_secs(config.background_refresh.budget_secs),
             });
 
-        let snapshot_manager = Arc::new(SnapshotManager::new_with_max_projects(snapshot_path, max_projects)?);
-
-        // Initialize tool handlers
-        let tool_handlers = Arc::new(Mutex::new(ToolHandlers::new(
-            embedding.clone(),
-            vector_db.clone(),
-            snapshot_manager.clone(),
-            max_projects,
-        )));
-
         Ok(Self {
             protocol: Protocol::new(),
-            embedding,
-            vector_db,
-            snapshot_manager,
-            tool_handlers,
+            engine: CodeContextEngine::from_config(config)?,
+            background_refresh,
         })
     }
 
     pub async fn start(mut self) -> Result<()> {
         // Load existing snapshot
-        self.snapshot_manager.load().await?;
+        self.engine.load().await?;
+
+        // In the background, so a provider that is still starting doesn't
+        // delay the server
+        let handlers = self.engine.handlers();
+        tokio::spawn(async move { handlers.probe_embedding_dimension().await });
+
+        if let Some(config) = self.background_refresh {
+            tracing::info!(
+                "Background refresh of pinned projects every {}s",
+                config.interval.as_secs()
+            );
+            spawn_background_refresh(
+                self.engine.handlers(),
+                self.engine.snapshot_manager(),
+                config,
+                self.protocol.notifier(),
+            );
+        }
 
         tracing::info!("MCP server started, waiting for requests...");
 
         // Main request loop
         loop {
             match self.protocol.read_request().await {
+                // Tool calls run concurrently, each answering when it
+                // finishes; calls changing the same project wait for each
+                // other on its lock
+                Ok(Some(request)) if request.method == "tools/call" => {
+                    tracing::debug!("Received request: method={}, id={:?}", request.method, request.id);
+                    let engine = self.engine.clone();
+                    let notifier = self.protocol.notifier();
+                    tokio::spawn(async move {
+                        let response = call_tool(&engine, request.id, request.params).await;
+                        if let Err(e) = notifier.send_response(response).await {
+                            tracing::error!("Failed to send response: {}", e);
+                        }
+                    });
+                }
                 Ok(Some(request)) => {
                     let response = self.handle_request(request).await;
                     if let Err(e) = self.protocol.send_response(response).await {
@@ -122,7 +123,7 @@ impl McpServer {
                 }
             }
             "tools/list" => self.handle_tools_list(request.id).await,
-            "tools/call" => self.handle_tools_call(request.id, request.params).await,
+            "tools/call" => call_tool(&self.engine, request.id, request.params).await,
             _ => {
                 self.protocol.error_response(request.id, JsonRpcError::method_not_found())
             }
@@ -163,6 +164,7 @@ impl McpServer {
                 tools: ToolsCapability {
                     listChanged: Some(true),
                 },
+                logging: self.background_refresh.map(|_| json!({})),
             },
             serverInfo: ServerInfo {
                 name: SERVER_NAME.to_string(),
@@ -174,7 +176,38 @@ impl McpServer {
     }
 
     async fn handle_tools_list(&self, id: serde_json::Value) -> JsonRpcResponse {
-        let tools = vec![
+        let config = self.engine.config();
+        let tools = tool_definitions()
+            .iter()
+            .cloned()
+            .map(|mut tool| {
+                if tool.name == "self_check" {
+                    tool.description = self_check_description(&config);
+                }
+                tool
+            })
+            .collect();
+        let response = ListToolsResponse { tools };
+        self.protocol.success_response(id, json!(response))
+    }
+}
+
+/// Description of `self_check`, naming the configured embedding provider
+/// and vector database it checks
+fn self_check_description(config: &Config) -> String {
+    format!(
+        "Diagnose the server setup: validates the configuration, reachability and model availability of the {} embedding provider, {} connectivity, snapshot readability and tree-sitter grammars. Reports pass/fail per check with a hint on how to fix each failure.",
+        config.embedding.provider.to_ascii_lowercase(),
+        config.vector_db.backend.to_ascii_lowercase()
+    )
+}
+
+/// Tools listed by `tools/list`; `tools/call` arguments are checked against
+/// their input schemas
+fn tool_definitions() -> &'static [Tool] {
+    static TOOLS: OnceLock<Vec<Tool>> = OnceLock::new();
+    TOOLS.get_or_init(|| {
+        vec![
             Tool {
                 name: "index_codebase".to_string(),
                 description: r#"Index a codebase directory to enable semantic search.
@@ -189,17 +222,27 @@ impl McpServer {
 
 📁 **Multi-Project Support**:
 - Each project is indexed independently with its own collection.
-- You can index multiple projects simultaneously."#.to_string(),
+- You can index multiple projects simultaneously.
+- Settings (splitter, ignore globs, embedding model, pinned) are remembered per project and reused on re-index.
+- Set `alias` to give the project a short name usable as `path` in the other tools.
+- Set `sub_projects: true` on a monorepo root to index each workspace member separately; searching the monorepo root then searches all members.
+
+🌐 **Remote Repositories**:
+- Pass a git URL (https://, ssh:// or git@host:repo) as `path` to shallow-clone a dependency repository and index it; the URL is accepted as `path` by the other tools too.
+- Indexing the same URL again fetches it and updates the index.
+- Only available when the server enables remote repositories, for URLs on its allowed hosts."#.to_string(),
                 inputSchema: json!({
                     "type": "object",
                     "properties": {
                         "path": {
                             "type": "string",
-                            "description": "ABSOLUTE path to the codebase directory to index."
+                            "minLength": 1,
+                            "maxLength": 4096,
+                            "description": "ABSOLUTE path to the codebase directory to index, or a git URL to clone and index"
                         },
                         "force": {
                             "type": "boolean",
-                            "description": "Force re-indexing even if already indexed",
+                            "description": "Force re-indexing even if already indexed; also drops and rebuilds a collection whose vector dimension no longer matches the embedding model",
                             "default": false
                         },
                         "splitter": {
@@ -207,6 +250,142 @@ impl McpServer {
                             "description": "Code splitter to use: 'ast' or 'langchain'",
                             "enum": ["ast", "langchain"],
                             "default": "ast"
+                        },
+                        "chunk_lines": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "description": "Lines per chunk for the 'langchain' splitter",
+                            "default": 60
+                        },
+                        "overlap_lines": {
+                            "type": "integer",
+                            "minimum": 0,
+                            "description": "Lines of surrounding code added before and after each symbol chunk with the 'ast' splitter",
+                            "default": 0
+                        },
+                        "strip_boilerplate": {
+                            "type": "boolean",
+                            "description": "Leave license headers and comment banners out of embedded chunks (remembered for the project)",
+                            "default": true
+                        },
+                        "doc_chunk_lines": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "description": "Maximum lines per section chunk of documentation (Markdown, reStructuredText, files under docs/; remembered for the project)",
+                            "default": 150
+                        },
+                        "ignore": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Additional glob patterns to exclude from indexing (e.g. 'generated/**')"
+                        },
+                        "embedding_model": {
+                            "type": "string",
+                            "description": "Embedding model for this project (defaults to the configured model)"
+                        },
+                        "pinned": {
+                            "type": "boolean",
+                            "description": "Pin the project so it is never evicted"
+                        },
+                        "archives": {
+                            "type": "boolean",
+                            "description": "Also index text files inside archives (.jar, .zip, .tar, .tar.gz) under virtual paths like 'lib.jar!/com/example/Util.java' (remembered for the project)",
+                            "default": false
+                        },
+                        "submodules": {
+                            "type": "boolean",
+                            "description": "Descend into git submodules listed in .gitmodules (remembered for the project)",
+                            "default": true
+                        },
+                        "blame": {
+                            "type": "boolean",
+                            "description": "Store the last commit, author and date of each chunk from git history, enabling author and recency filters (remembered for the project; slower indexing)",
+                            "default": false
+                        },
+                        "lsp": {
+                            "type": "boolean",
+                            "description": "Ask the server's configured language servers (LSP_SERVERS) to name chunks tree-sitter couldn't, such as anonymous functions (remembered for the project)",
+                            "default": false
+                        },
+                        "keep_history": {
+                            "type": "boolean",
+                            "description": "Keep the previous versions of changed and deleted chunks in a companion collection, so search_code can search the index as it was with as_of (remembered for the project)",
+                            "default": false
+                        },
+                        "shared_deps": {
+                            "type": "boolean",
+                            "description": "Index vendored dependencies (node_modules, vendor/, third_party/ directories) into a collection shared by all projects using the same embedding model and chunking, embedding each distinct file once; search them with include_deps (remembered for the project). Not available with encrypt",
+                            "default": false
+                        },
+                        "doc_vectors": {
+                            "type": "boolean",
+                            "description": "Also embed each chunk's doc comments and docstrings on their own, so natural-language queries match the prose describing code and not only its tokens; costs an extra embedding per documented chunk (remembered for the project)",
+                            "default": false
+                        },
+                        "vector_db": {
+                            "type": "string",
+                            "description": "Named vector database instance from [vector_db.instances] to store the project's collection on ('default' is MILVUS_ADDRESS, or QDRANT_ADDRESS with VECTOR_DB=qdrant). Remembered for the project; moving it to another instance needs clear_index first"
+                        },
+                        "partition_by_language": {
+                            "type": "boolean",
+                            "description": "Store each language's chunks in its own Milvus partition, so searches with a language filter only scan that language (fixed once the project is indexed)",
+                            "default": false
+                        },
+                        "max_file_size_mb": {
+                            "type": "number",
+                            "minimum": 0,
+                            "description": "Skip files larger than this many MB (remembered for the project; defaults to the server's MAX_FILE_SIZE_MB)"
+                        },
+                        "max_file_size_by_extension": {
+                            "type": "object",
+                            "additionalProperties": { "type": "number" },
+                            "description": "Size limits in MB by file extension, e.g. {\"sql\": 100, \"json\": 1} (remembered for the project)"
+                        },
+                        "encrypt": {
+                            "type": "boolean",
+                            "description": "Encrypt chunk content (AES-256-GCM, key kept on this machine) before storing it in the vector database; decrypted at search time. Defaults to the server's encryption setting; saved for future re-indexes"
+                        },
+                        "redact": {
+                            "type": "boolean",
+                            "description": "Redact secrets (built-in token/credential detectors and configured patterns) from chunks before they are embedded or stored. Defaults to the server's redaction setting; saved for future re-indexes"
+                        },
+                        "alias": {
+                            "type": "string",
+                            "description": "Short name (e.g. 'backend') that other tools accept in place of the path"
+                        },
+                        "composite": {
+                            "type": "string",
+                            "description": "Add 'path' (and 'paths') to a named composite project (e.g. 'checkout' for a service plus its shared proto repo). search_code and get_indexing_status accept the name to cover all member directories"
+                        },
+                        "paths": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Additional ABSOLUTE directory paths indexed into the same 'composite' project"
+                        },
+                        "sub_projects": {
+                            "type": "boolean",
+                            "description": "Detect a monorepo workspace (Cargo, pnpm, yarn/npm, go.work) and index each member as its own project",
+                            "default": false
+                        },
+                        "dry_run": {
+                            "type": "boolean",
+                            "description": "Report what would be indexed (files, per-language breakdown, chunks, estimated tokens, cost and time) without embedding or storing anything",
+                            "default": false
+                        },
+                        "max_duration_secs": {
+                            "type": "number",
+                            "minimum": 1,
+                            "description": "Stop indexing after this many seconds; calling index_codebase again resumes where it stopped"
+                        },
+                        "max_requests_per_second": {
+                            "type": "number",
+                            "minimum": 0,
+                            "description": "Limit embedding requests per second to avoid saturating a shared provider"
+                        },
+                        "confirm_evict": {
+                            "type": "boolean",
+                            "description": "Confirm evicting other projects when EVICTION_POLICY=confirm",
+                            "default": false
                         }
                     },
                     "required": ["path"]
@@ -222,28 +401,131 @@ impl McpServer {
 
 ✨ **Multi-Project Support**:
 - Set `cross_project: true` to search across all indexed projects.
-- Or use path "all" to search all projects."#.to_string(),
+- Or use path "all" to search all projects.
+- Set `refresh: true` to re-index files edited since the last index before searching."#.to_string(),
                 inputSchema: json!({
                     "type": "object",
                     "properties": {
                         "path": {
                             "type": "string",
-                            "description": "ABSOLUTE path to the codebase directory to search in. Use 'all' to search all projects."
+                            "minLength": 1,
+                            "maxLength": 4096,
+                            "description": "ABSOLUTE path to the codebase directory to search in, a project alias or a composite project name. Use 'all' to search all projects."
                         },
                         "query": {
                             "type": "string",
+                            "minLength": 1,
+                            "maxLength": 2000,
                             "description": "Natural language query to search for in the codebase"
                         },
                         "limit": {
-                            "type": "number",
+                            "type": "integer",
                             "description": "Maximum number of results to return",
                             "default": 10,
+                            "minimum": 1,
                             "maximum": 50
                         },
                         "cross_project": {
                             "type": "boolean",
                             "description": "Search across all indexed projects",
                             "default": false
+                        },
+                        "refresh": {
+                            "type": "boolean",
+                            "description": "Re-index files changed since the last index (bounded by refresh_budget_secs) before searching",
+                            "default": false
+                        },
+                        "refresh_budget_secs": {
+                            "type": "number",
+                            "minimum": 0,
+                            "description": "Time budget for the refresh before searching",
+                            "default": 5
+                        },
+                        "language": {
+                            "type": "string",
+                            "description": "Only return code in this language, e.g. 'rust', 'python' or a file extension like 'ts'"
+                        },
+                        "scope": {
+                            "type": "string",
+                            "enum": ["code", "docs", "all"],
+                            "description": "Return only code, only documentation, or both",
+                            "default": "all"
+                        },
+                        "mode": {
+                            "type": "string",
+                            "enum": ["semantic", "prefilter", "keyword", "regex", "hybrid"],
+                            "description": "'prefilter' first narrows candidates to chunks whose symbol name or file path contains an identifier of the query, then ranks them semantically. With the full-text index enabled: 'keyword' finds exact words, identifiers and \"quoted phrases\" (BM25), 'regex' chunks matching the query as a regular expression, and 'hybrid' fuses semantic and keyword rankings",
+                            "default": "semantic"
+                        },
+                        "author": {
+                            "type": "string",
+                            "description": "Only return code last changed by this author (name or email substring, or 'me' for the configured git user). Needs a project indexed with blame: true"
+                        },
+                        "modified_within_days": {
+                            "type": "number",
+                            "minimum": 0,
+                            "description": "Only return code last changed within this many days. Needs a project indexed with blame: true"
+                        },
+                        "as_of": {
+                            "type": "string",
+                            "description": "Search the index as it was at this time: Unix seconds, a UTC date such as 2024-05-01 or 2024-05-01T12:30:00Z, or a commit the project was indexed at. Needs a project indexed with keep_history: true"
+                        },
+                        "module": {
+                            "type": "string",
+                            "description": "Only return code in this module or package, or nested inside it (e.g. 'crate::handlers', 'com.example', 'app.models')"
+                        },
+                        "embedding_model": {
+                            "type": "string",
+                            "description": "Embedding model the project was indexed with. Searches refuse a model other than the indexed one (multi-project searches leave such projects out); projects indexed before models were recorded are searched with it"
+                        },
+                        "raw_query": {
+                            "type": "boolean",
+                            "description": "Embed the query exactly as given, skipping filler removal, identifier splitting and abbreviation expansion",
+                            "default": false
+                        },
+                        "verify": {
+                            "type": "boolean",
+                            "description": "Check each result against the file on disk: results whose code moved get updated line numbers, results whose code is gone are marked stale",
+                            "default": false
+                        },
+                        "files_only": {
+                            "type": "boolean",
+                            "description": "Return ranked file paths with their best relevance and matching chunk count instead of code snippets (limit counts files)",
+                            "default": false
+                        },
+                        "include_deps": {
+                            "type": "boolean",
+                            "description": "Also search the project's vendored dependencies, for projects indexed with shared_deps",
+                            "default": false
+                        },
+                        "snippet": {
+                            "type": "string",
+                            "enum": ["head", "lines", "signature", "best_window"],
+                            "description": "Code shown for each result: 'head' the first 500 bytes, 'lines' the first snippet_lines lines, 'signature' the doc comment and declaration, 'best_window' the snippet_lines lines sharing the most words with the query",
+                            "default": "head"
+                        },
+                        "snippet_lines": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "description": "Lines shown by the 'lines' and 'best_window' snippets",
+                            "default": 10
+                        },
+                        "auto_limit": {
+                            "type": "boolean",
+                            "description": "Return results up to the first sharp drop in score instead of a fixed count: few for a targeted query, more for a broad one. limit (default 30) caps them",
+                            "default": false
+                        },
+                        "auto_limit_drop": {
+                            "type": "number",
+                            "minimum": 0,
+                            "maximum": 1,
+                            "description": "Score drop from one result to the next, as a fraction of the higher score, that ends auto_limit results",
+                            "default": 0.1
+                        },
+                        "reindex": {
+                            "type": "boolean",
+                            "description": "If the project was indexed with an embedding model or dimension the server can no longer search with, re-index it with the configured model first instead of failing with reindex_required",
+                            "default": false
                         }
                     },
                     "required": ["path", "query"]
@@ -260,7 +542,9 @@ impl McpServer {
                     "properties": {
                         "path": {
                             "type": "string",
-                            "description": "ABSOLUTE path to the codebase directory to clear. Use 'all' to clear all projects."
+                            "minLength": 1,
+                            "maxLength": 4096,
+                            "description": "ABSOLUTE path to the codebase directory to clear, or a project alias. Use 'all' to clear all projects."
                         }
                     },
                     "required": ["path"]
@@ -271,66 +555,366 @@ impl McpServer {
                 description: r#"Get the current indexing status of a codebase.
 
 📁 **Multi-Project Support**:
-- Use path "all" to see status of all indexed projects."#.to_string(),
+- Use path "all" to see status of all indexed projects.
+- Set `check_freshness: true` to see how stale the index is, and `prune: true` to drop vanished files."#.to_string(),
                 inputSchema: json!({
                     "type": "object",
                     "properties": {
                         "path": {
                             "type": "string",
-                            "description": "ABSOLUTE path to the codebase directory. Use 'all' to see all projects."
+                            "minLength": 1,
+                            "maxLength": 4096,
+                            "description": "ABSOLUTE path to the codebase directory, a project alias or a composite project name. Use 'all' to see all projects."
+                        },
+                        "check_freshness": {
+                            "type": "boolean",
+                            "description": "Scan the project and report how many indexed files are missing or changed on disk",
+                            "default": false
+                        },
+                        "prune": {
+                            "type": "boolean",
+                            "description": "Remove index entries for files that no longer exist (implies check_freshness)",
+                            "default": false
+                        },
+                        "show_skipped": {
+                            "type": "boolean",
+                            "description": "List files left out of the index with the reason (too large, binary, parse failure, ignored, ...)",
+                            "default": false
                         }
                     },
                     "required": ["path"]
                 }),
             },
-        ];
-
-        let response = ListToolsResponse { tools };
-        self.protocol.success_response(id, json!(response))
-    }
+            Tool {
+                name: "get_config".to_string(),
+                description: "Show the effective server configuration (config.toml files merged with environment overrides) and where each setting came from.".to_string(),
+                inputSchema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+            },
+            Tool {
+                name: "get_project_config".to_string(),
+                description: "Show the settings remembered for an indexed project: ignore globs, chunking, pinned flag, file size limits, embedding model and default search filters.".to_string(),
+                inputSchema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "minLength": 1,
+                            "maxLength": 4096,
+                            "description": "ABSOLUTE path or alias of the indexed project"
+                        }
+                    },
+                    "required": ["path"]
+                }),
+            },
+            Tool {
+                name: "set_project_config".to_string(),
+                description: "Change settings of an indexed project without restarting the server. Indexing settings apply from the next index_codebase run; default filters apply to search_code calls on the project that don't set them. The embedding model, chunking, language server naming, doc vectors, encryption and language partitions can only change by re-indexing with force=true.".to_string(),
+                inputSchema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "minLength": 1,
+                            "maxLength": 4096,
+                            "description": "ABSOLUTE path or alias of the indexed project"
+                        },
+                        "ignore": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Glob patterns excluded from indexing, replacing the current ones"
+                        },
+                        "pinned": {
+                            "type": "boolean",
+                            "description": "Never evict the project"
+                        },
+                        "redact": {
+                            "type": "boolean",
+                            "description": "Redact secrets from chunks"
+                        },
+                        "archives": {
+                            "type": "boolean",
+                            "description": "Index text files inside archives"
+                        },
+                        "submodules": {
+                            "type": "boolean",
+                            "description": "Descend into git submodules"
+                        },
+                        "blame": {
+                            "type": "boolean",
+                            "description": "Store git blame information with each chunk"
+                        },
+                        "keep_history": {
+                            "type": "boolean",
+                            "description": "Keep previous versions of changed chunks for as_of searches"
+                        },
+                        "shared_deps": {
+                            "type": "boolean",
+                            "description": "Index vendored dependencies into the collection shared by all projects"
+                        },
+                        "max_file_size_mb": {
+                            "type": "number",
+                            "minimum": 0,
+                            "description": "Skip files larger than this many MB"
+                        },
+                        "max_file_size_by_extension": {
+                            "type": "object",
+                            "additionalProperties": { "type": "number" },
+                            "description": "Size limits in MB by file extension, replacing the current ones"
+                        },
+                        "default_language": {
+                            "type": "string",
+                            "description": "Language filter of searches that don't set one ('' clears it)"
+                        },
+                        "default_scope": {
+                            "type": "string",
+                            "enum": ["code", "docs", "all", ""],
+                            "description": "Scope of searches that don't set one ('' clears it)"
+                        },
+                        "default_module": {
+                            "type": "string",
+                            "description": "Module filter of searches that don't set one ('' clears it)"
+                        },
+                        "default_limit": {
+                            "type": "integer",
+                            "minimum": 0,
+                            "maximum": 50,
+                            "description": "Result limit of searches that don't set one (0 clears it)"
+                        }
+                    },
+                    "required": ["path"]
+                }),
+            },
+            Tool {
+                name: "get_server_stats".to_string(),
+                description: "Show runtime statistics: uptime, per-tool call counts and latency percentiles (p50/p95/p99), embedding and vector database error counts, chunk dedup cache hit rate and memory usage.".to_string(),
+                inputSchema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+            },
+            Tool {
+                name: "rate_result".to_string(),
+                description: "Mark a search_code result as relevant or irrelevant. Ratings are stored per project, nudge that chunk's ranking in later searches and feed get_recent_search_quality.".to_string(),
+                inputSchema: json!({
+                    "type": "object",
+                    "properties": {
+                        "chunk_id": {
+                            "type": "string",
+                            "description": "Chunk id shown with the result (also `chunk_id` in structuredContent)"
+                        },
+                        "relevant": {
+                            "type": "boolean",
+                            "description": "Whether the result was relevant to the query"
+                        },
+                        "path": {
+                            "type": "string",
+                            "minLength": 1,
+                            "maxLength": 4096,
+                            "description": "ABSOLUTE path or alias of the result's project; optional for results of a recent search"
+                        },
+                        "query": {
+                            "type": "string",
+                            "description": "The query that returned the result, kept with the rating"
+                        }
+                    },
+                    "required": ["chunk_id", "relevant"]
+                }),
+            },
+            Tool {
+                name: "get_chunk_context".to_string(),
+                description: "Return a search_code result's chunk together with the chunks before and after it in its file, in file order, e.g. to read the code following a matched function.".to_string(),
+                inputSchema: json!({
+                    "type": "object",
+                    "properties": {
+                        "chunk_id": {
+                            "type": "string",
+                            "description": "Chunk id shown with the result (also `chunk_id` in structuredContent)"
+                        },
+                        "path": {
+                            "type": "string",
+                            "minLength": 1,
+                            "maxLength": 4096,
+                            "description": "ABSOLUTE path or alias of the result's project or file; optional for results of a recent search"
+                        },
+                        "before": {
+                            "type": "integer",
+                            "minimum": 0,
+                            "maximum": 10,
+                            "description": "Chunks to include before the requested one",
+                            "default": 1
+                        },
+                        "after": {
+                            "type": "integer",
+                            "minimum": 0,
+                            "maximum": 10,
+                            "description": "Chunks to include after the requested one",
+                            "default": 1
+                        }
+                    },
+                    "required": ["chunk_id"]
+                }),
+            },
+            Tool {
+                name: "get_recent_search_quality".to_string(),
+                description: "Report retrieval quality from rate_result feedback: rating counts and precision overall and over the most recent ratings, and the chunks most often rated irrelevant.".to_string(),
+                inputSchema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "minLength": 1,
+                            "maxLength": 4096,
+                            "description": "ABSOLUTE path or alias of a project, or 'all'",
+                            "default": "all"
+                        },
+                        "window": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "description": "Number of most recent ratings for the recent precision",
+                            "default": 50
+                        }
+                    }
+                }),
+            },
+            Tool {
+                name: "diff_index".to_string(),
+                description: "Compare a project's index with an earlier export or with another indexed copy of the project (e.g. a checkout of the base branch), reporting added, removed and changed files and symbols. Use it to find the semantic surface of a change.".to_string(),
+                inputSchema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "minLength": 1,
+                            "maxLength": 4096,
+                            "description": "ABSOLUTE path or alias of the indexed project (the newer side)"
+                        },
+                        "against": {
+                            "type": "string",
+                            "description": "Index export file written by export_to, or the ABSOLUTE path or alias of another indexed copy (the older side)"
+                        },
+                        "export_to": {
+                            "type": "string",
+                            "description": "Write the project's current index (files and symbols) to this JSON file, to compare against later"
+                        }
+                    },
+                    "required": ["path"]
+                }),
+            },
+            Tool {
+                name: "export_symbols".to_string(),
+                description: "Export every indexed symbol (name, kind, file, lines) of a project as JSON or a ctags file, for editors and other tooling. The summary counts symbols per kind and files without symbols, to check parser coverage.".to_string(),
+                inputSchema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "minLength": 1,
+                            "maxLength": 4096,
+                            "description": "ABSOLUTE path or alias of the indexed project"
+                        },
+                        "format": {
+                            "type": "string",
+                            "enum": ["json", "ctags"],
+                            "description": "JSON array of symbols, or a sorted extended-format tags file",
+                            "default": "json"
+                        },
+                        "output": {
+                            "type": "string",
+                            "description": "Write the export to this file instead of returning it (required for more than 2000 symbols)"
+                        }
+                    },
+                    "required": ["path"]
+                }),
+            },
+            Tool {
+                name: "discover_projects".to_string(),
+                description: "List the projects under a directory of repositories: folders with a .git directory or a build manifest (Cargo.toml, package.json, go.mod, pyproject.toml, ...), with their workspace kind and whether each is indexed. Pass `index` to index the chosen ones in the same call.".to_string(),
+                inputSchema: json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "ABSOLUTE path to the directory to search"
+                        },
+                        "max_depth": {
+                            "type": "integer",
+                            "description": "Directory levels to search below path",
+                            "minimum": 1,
+                            "default": 3
+                        },
+                        "index": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Discovered projects to index, by path relative to path (as listed) or absolute"
+                        }
+                    },
+                    "required": ["path"]
+                }),
+            },
+            Tool {
+                name: "self_check".to_string(),
+                // Named after the configured provider and backend by tools/list
+                description: "Diagnose the server setup: validates the configuration, embedding provider reachability and model availability, vector database connectivity, snapshot readability and tree-sitter grammars. Reports pass/fail per check with a hint on how to fix each failure.".to_string(),
+                inputSchema: json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+            },
+        ]
+    })
+}
 
-    async fn handle_tools_call(&self, id: serde_json::Value, params: serde_json::Value) -> JsonRpcResponse {
-        let call_request: CallToolRequest = match serde_json::from_value(params) {
-            Ok(req) => req,
-            Err(e) => {
-                return self.protocol.error_response(
-                    id,
-                    JsonRpcError::internal_error(format!("Invalid params: {}", e)),
-                );
-            }
-        };
+async fn call_tool(engine: &CodeContextEngine, id: serde_json::Value, params: serde_json::Value) -> JsonRpcResponse {
+    let call_request: CallToolRequest = match serde_json::from_value(params) {
+        Ok(req) => req,
+        Err(e) => {
+            return JsonRpcResponse::error(
+                id,
+                JsonRpcError::internal_error(format!("Invalid params: {}", e)),
+            );
+        }
+    };
 
-        let handlers = self.tool_handlers.lock().await;
-        let result = match call_request.name.as_str() {
-            "index_codebase" => handlers.handle_index_codebase(&call_request.arguments).await,
-            "search_code" => handlers.handle_search_code(&call_request.arguments).await,
-            "clear_index" => handlers.handle_clear_index(&call_request.arguments).await,
-            "get_indexing_status" => handlers.handle_get_indexing_status(&call_request.arguments).await,
-            _ => {
-                return self.protocol.error_response(
-                    id,
-                    JsonRpcError::internal_error(format!("Unknown tool: {}", call_request.name)),
-                );
-            }
-        };
+    if !CodeContextEngine::has_tool(&call_request.name) {
+        return JsonRpcResponse::error(
+            id,
+            JsonRpcError::internal_error(format!("Unknown tool: {}", call_request.name)),
+        );
+    }
 
-        match result {
-            Ok(content) => {
-                let response = CallToolResponse {
-                    content,
-                    isError: None,
-                };
-                self.protocol.success_response(id, json!(response))
-            }
-            Err(e) => {
-                let response = CallToolResponse {
-                    content: vec![Content::Text {
+    let schema = tool_definitions()
+        .iter()
+        .find(|tool| tool.name == call_request.name)
+        .map(|tool| &tool.inputSchema);
+    let result = match schema.map(|schema| validate_arguments(schema, &call_request.arguments)) {
+        Some(Err(error)) => Err(error.into()),
+        _ => engine.call_tool(&call_request.name, &call_request.arguments).await,
+    };
+
+    match result {
+        Ok(output) => {
+            let response = CallToolResponse {
+                content: output.content,
+                structuredContent: output.structured,
+                isError: None,
+            };
+            JsonRpcResponse::success(id, json!(response))
+        }
+        Err(e) => {
+            let response = CallToolResponse {
+                content: vec![
+                    Content::Text {
                         text: format!("Error: {}", e),
-                    }],
-                    isError: Some(true),
-                };
-                self.protocol.success_response(id, json!(response))
-            }
+                    },
+                    Content::error(&e),
+                ],
+                structuredContent: e.downcast_ref::<ToolError>().and_then(|err| err.details.clone()),
+                isError: Some(true),
+            };
+            JsonRpcResponse::success(id, json!(response))
         }
     }
 }
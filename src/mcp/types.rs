@@ -24,6 +24,26 @@ pub struct JsonRpcResponse<T = serde_json::Value> {
     pub error: Option<JsonRpcError>,
 }
 
+impl<T> JsonRpcResponse<T> {
+    pub fn success(id: serde_json::Value, result: T) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: serde_json::Value, error: JsonRpcError) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: None,
+            error: Some(error),
+        }
+    }
+}
+
 /// JSON-RPC Error
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcError {
@@ -106,6 +126,9 @@ pub struct InitializeResponse {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerCapabilities {
     pub tools: ToolsCapability,
+    /// Present when the server emits `notifications/message` log messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -147,22 +170,204 @@ pub struct CallToolRequest {
 pub struct CallToolResponse {
     pub content: Vec<Content>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub structuredContent: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub isError: Option<bool>,
 }
 
+/// Result of a tool call: text content plus optional machine-readable data
+#[derive(Debug, Clone)]
+pub struct ToolOutput {
+    pub content: Vec<Content>,
+    pub structured: Option<serde_json::Value>,
+}
+
+impl From<Vec<Content>> for ToolOutput {
+    fn from(content: Vec<Content>) -> Self {
+        Self {
+            content,
+            structured: None,
+        }
+    }
+}
+
+/// What kind of failure a tool call ran into, so clients can decide how to
+/// recover
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// Missing or malformed arguments
+    InvalidArguments,
+    /// The path has no index yet
+    NotIndexed,
+    /// A file, directory or project doesn't exist
+    NotFound,
+    /// The path is outside the allowed roots
+    PermissionDenied,
+    /// A storage quota or project limit was reached
+    LimitExceeded,
+    /// The embedding provider or vector database could not be reached
+    Unavailable,
+    /// A backend didn't answer in time
+    Timeout,
+    /// The project's index was built with an embedding model or dimension
+    /// the server can no longer search with
+    ReindexRequired,
+    Internal,
+}
+
+impl ErrorKind {
+    /// Whether the same call may succeed when retried later
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ErrorKind::Unavailable | ErrorKind::Timeout)
+    }
+
+    /// What a client can generally do about this kind of failure
+    pub fn suggested_action(&self) -> Option<&'static str> {
+        let action = match self {
+            ErrorKind::InvalidArguments => "Check the arguments against the tool's input schema",
+            ErrorKind::NotIndexed => "Index the project with index_codebase, then retry",
+            ErrorKind::NotFound => "Check that the path exists",
+            ErrorKind::PermissionDenied => "Use a path inside the allowed roots",
+            ErrorKind::LimitExceeded => "Free space with clear_index or raise the limit",
+            ErrorKind::Unavailable => "Check the embedding provider and vector database with self_check, then retry",
+            ErrorKind::Timeout => "Retry, possibly with a smaller limit or time budget",
+            ErrorKind::ReindexRequired => "Re-index the project with index_codebase force=true, or search with reindex=true",
+            ErrorKind::Internal => return None,
+        };
+        Some(action)
+    }
+
+    /// Kind of an error, from the `ToolError` it carries or else from its
+    /// causes (HTTP timeouts and connection failures, missing files)
+    pub fn of(error: &anyhow::Error) -> Self {
+        for cause in error.chain() {
+            if let Some(err) = cause.downcast_ref::<ToolError>() {
+                return err.kind;
+            }
+            if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+                if err.is_timeout() {
+                    return ErrorKind::Timeout;
+                }
+                if err.is_connect() {
+                    return ErrorKind::Unavailable;
+                }
+            }
+            if let Some(err) = cause.downcast_ref::<std::io::Error>() {
+                match err.kind() {
+                    std::io::ErrorKind::NotFound => return ErrorKind::NotFound,
+                    std::io::ErrorKind::PermissionDenied => return ErrorKind::PermissionDenied,
+                    std::io::ErrorKind::TimedOut => return ErrorKind::Timeout,
+                    _ => {}
+                }
+            }
+        }
+        // Argument checks report with plain messages
+        let message = error.to_string();
+        if ["Missing '", "Invalid ", "Unknown "].iter().any(|prefix| message.starts_with(prefix)) {
+            return ErrorKind::InvalidArguments;
+        }
+        ErrorKind::Internal
+    }
+}
+
+/// A tool failure with a kind and optional machine-readable details; the
+/// details are returned as the `structuredContent` of the error result so
+/// clients can recover without parsing the message
+#[derive(Debug, Clone)]
+pub struct ToolError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub details: Option<serde_json::Value>,
+    /// Overrides the kind's generic suggested action
+    pub suggested_action: Option<String>,
+}
+
+impl ToolError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            details: None,
+            suggested_action: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn with_suggested_action(mut self, action: impl Into<String>) -> Self {
+        self.suggested_action = Some(action.into());
+        self
+    }
+}
+
+impl std::fmt::Display for ToolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ToolError {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Content {
     #[serde(rename = "text")]
     Text { text: String },
+    /// Machine-readable summary of a failed tool call, sent after the text
+    /// message when `isError` is set
+    #[serde(rename = "error")]
+    Error {
+        kind: ErrorKind,
+        retryable: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        suggested_action: Option<String>,
+    },
+}
+
+impl Content {
+    /// Error content describing a failed tool call
+    pub fn error(error: &anyhow::Error) -> Self {
+        let kind = ErrorKind::of(error);
+        let action = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<ToolError>()?.suggested_action.clone())
+            .or_else(|| kind.suggested_action().map(str::to_string));
+        Content::Error {
+            kind,
+            retryable: kind.is_retryable(),
+            suggested_action: action,
+        }
+    }
 }
 
-/// Notification types (reserved for future use)
+/// JSON-RPC notification (no id, no response expected)
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[allow(dead_code)]
 pub struct Notification {
     pub jsonrpc: String,
     pub method: String,
     #[serde(default)]
     pub params: serde_json::Value,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_content() {
+        let error = anyhow::Error::new(ToolError::new(ErrorKind::NotIndexed, "No indexed codebase"))
+            .context("Search failed");
+        assert_eq!(ErrorKind::of(&error), ErrorKind::NotIndexed);
+
+        let missing = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        assert_eq!(ErrorKind::of(&anyhow::Error::new(missing).context("Failed to read")), ErrorKind::NotFound);
+        assert_eq!(ErrorKind::of(&anyhow::anyhow!("Missing 'query' argument")), ErrorKind::InvalidArguments);
+
+        let content = serde_json::to_value(Content::error(&anyhow::anyhow!("boom"))).unwrap();
+        assert_eq!(content, serde_json::json!({ "type": "error", "kind": "internal", "retryable": false }));
+    }
+}
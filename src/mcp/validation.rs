@@ -0,0 +1,156 @@
+//! Server-side check of tool arguments against the tools' declared input
+//! schemas, covering the JSON Schema keywords those schemas use: `type`,
+//! `properties`, `required`, `enum`, `minimum`/`maximum`,
+//! `minLength`/`maxLength`, `items` and `additionalProperties`
+
+use super::types::{ErrorKind, ToolError};
+use serde_json::{json, Value};
+
+/// Check `args` against `schema`, reporting every violation in one error
+pub fn validate_arguments(schema: &Value, args: &Value) -> Result<(), ToolError> {
+    // Clients may omit the arguments of tools that take none
+    let empty = json!({});
+    let args = if args.is_null() { &empty } else { args };
+
+    let mut errors = Vec::new();
+    check(schema, args, "arguments", &mut errors);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(ToolError::new(ErrorKind::InvalidArguments, format!("Invalid arguments: {}", errors.join("; ")))
+        .with_details(json!({ "errors": errors })))
+}
+
+fn check(schema: &Value, value: &Value, name: &str, errors: &mut Vec<String>) {
+    if let Some(expected) = schema.get("type").and_then(|t| t.as_str()) {
+        if !has_type(value, expected) {
+            errors.push(format!("{} must be {}", name, type_name(expected)));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+            errors.push(format!("{} must be one of {}", name, allowed.join(", ")));
+        }
+    }
+
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = schema.get("minimum").and_then(|m| m.as_f64()) {
+            if number < minimum {
+                errors.push(format!("{} must be at least {}", name, minimum));
+            }
+        }
+        if let Some(maximum) = schema.get("maximum").and_then(|m| m.as_f64()) {
+            if number > maximum {
+                errors.push(format!("{} must be at most {}", name, maximum));
+            }
+        }
+    }
+
+    if let Some(text) = value.as_str() {
+        let length = text.chars().count() as u64;
+        if schema.get("minLength").and_then(|m| m.as_u64()).is_some_and(|min| length < min) {
+            if text.is_empty() {
+                errors.push(format!("{} must not be empty", name));
+            } else {
+                errors.push(format!("{} is too short", name));
+            }
+        }
+        if let Some(max) = schema.get("maxLength").and_then(|m| m.as_u64()).filter(|max| length > *max) {
+            errors.push(format!("{} must be at most {} characters", name, max));
+        }
+    }
+
+    if let Some(items) = value.as_array() {
+        if let Some(item_schema) = schema.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                check(item_schema, item, &format!("{}[{}]", name, i), errors);
+            }
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        let properties = schema.get("properties").and_then(|p| p.as_object());
+        for required in schema.get("required").and_then(|r| r.as_array()).into_iter().flatten() {
+            if let Some(key) = required.as_str().filter(|key| !object.contains_key(*key)) {
+                errors.push(format!("'{}' is required", key));
+            }
+        }
+        for (key, field) in object {
+            // Undeclared top-level arguments are ignored, as JSON Schema allows
+            let field_schema = properties
+                .and_then(|p| p.get(key))
+                .or_else(|| schema.get("additionalProperties").filter(|s| s.is_object()));
+            if let Some(field_schema) = field_schema {
+                check(field_schema, field, &format!("'{}'", key), errors);
+            }
+        }
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(expected: &str) -> &str {
+    match expected {
+        "string" => "a string",
+        "number" => "a number",
+        "integer" => "an integer",
+        "boolean" => "true or false",
+        "array" => "an array",
+        "object" => "an object",
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_arguments() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "minLength": 1 },
+                "limit": { "type": "integer", "minimum": 1, "maximum": 50 },
+                "scope": { "type": "string", "enum": ["code", "docs"] },
+                "ignore": { "type": "array", "items": { "type": "string" } },
+                "sizes": { "type": "object", "additionalProperties": { "type": "number" } }
+            },
+            "required": ["path"]
+        });
+
+        assert!(validate_arguments(&schema, &json!({ "path": "/src", "limit": 10, "extra": true })).is_ok());
+
+        let error = validate_arguments(&schema, &json!({ "limit": 10000, "scope": "tests" })).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidArguments);
+        assert_eq!(
+            error.message,
+            "Invalid arguments: 'path' is required; 'limit' must be at most 50; 'scope' must be one of \"code\", \"docs\""
+        );
+
+        let error = validate_arguments(
+            &schema,
+            &json!({ "path": "", "limit": 2.5, "ignore": ["a", 1], "sizes": { "sql": "big" } }),
+        )
+        .unwrap_err();
+        assert_eq!(error.details.unwrap()["errors"].as_array().unwrap().len(), 4);
+        assert!(error.message.contains("'path' must not be empty"));
+        assert!(error.message.contains("'ignore'[1] must be a string"));
+
+        assert!(validate_arguments(&json!({ "type": "object", "properties": {} }), &Value::Null).is_ok());
+    }
+}
@@ -0,0 +1,446 @@
+//! Lightweight runtime metrics shared across handlers (`get_server_stats`)
+
+use crate::embedding::{Embedding, EmbeddingProvider};
+use crate::vector_db::{Metric, SearchResult, VectorDatabase};
+use anyhow::Result;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Latency samples kept per tool for percentiles
+const MAX_LATENCY_SAMPLES: usize = 1000;
+
+#[derive(Debug, Default)]
+struct ToolStats {
+    calls: u64,
+    errors: u64,
+    /// Most recent call latencies
+    latencies: VecDeque<Duration>,
+}
+
+/// Counters and latency samples for one server process
+#[derive(Debug)]
+pub struct Metrics {
+    started: Instant,
+    tools: Mutex<BTreeMap<String, ToolStats>>,
+    embedding_requests: AtomicU64,
+    embedding_errors: AtomicU64,
+    /// Most recent embedding request latencies
+    embedding_latencies: Mutex<VecDeque<Duration>>,
+    vector_db_requests: AtomicU64,
+    vector_db_errors: AtomicU64,
+    dedup_hits: AtomicU64,
+    dedup_misses: AtomicU64,
+    embedding_cache_hits: AtomicU64,
+    embedding_cache_misses: AtomicU64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            tools: Mutex::new(BTreeMap::new()),
+            embedding_requests: AtomicU64::new(0),
+            embedding_errors: AtomicU64::new(0),
+            embedding_latencies: Mutex::new(VecDeque::new()),
+            vector_db_requests: AtomicU64::new(0),
+            vector_db_errors: AtomicU64::new(0),
+            dedup_hits: AtomicU64::new(0),
+            dedup_misses: AtomicU64::new(0),
+            embedding_cache_hits: AtomicU64::new(0),
+            embedding_cache_misses: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Metrics {
+    /// Record a finished tool call
+    pub fn record_tool_call(&self, tool: &str, elapsed: Duration, success: bool) {
+        let mut tools = self.tools.lock().unwrap();
+        let stats = tools.entry(tool.to_string()).or_default();
+        stats.calls += 1;
+        if !success {
+            stats.errors += 1;
+        }
+        if stats.latencies.len() == MAX_LATENCY_SAMPLES {
+            stats.latencies.pop_front();
+        }
+        stats.latencies.push_back(elapsed);
+    }
+
+    /// Record chunk dedup cache lookups: `hits` reused a stored vector,
+    /// `misses` needed an embedding
+    pub fn record_dedup(&self, hits: usize, misses: usize) {
+        self.dedup_hits.fetch_add(hits as u64, Ordering::Relaxed);
+        self.dedup_misses.fetch_add(misses as u64, Ordering::Relaxed);
+    }
+
+    /// Record embedding cache lookups: `hits` were read from disk, `misses`
+    /// went to the provider
+    pub fn record_embedding_cache(&self, hits: usize, misses: usize) {
+        self.embedding_cache_hits.fetch_add(hits as u64, Ordering::Relaxed);
+        self.embedding_cache_misses.fetch_add(misses as u64, Ordering::Relaxed);
+    }
+
+    fn record_embedding<T>(&self, result: &Result<T>, elapsed: Duration) {
+        self.embedding_requests.fetch_add(1, Ordering::Relaxed);
+        if result.is_err() {
+            self.embedding_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        let mut latencies = self.embedding_latencies.lock().unwrap();
+        if latencies.len() == MAX_LATENCY_SAMPLES {
+            latencies.pop_front();
+        }
+        latencies.push_back(elapsed);
+    }
+
+    fn record_vector_db<T>(&self, result: &Result<T>) {
+        self.vector_db_requests.fetch_add(1, Ordering::Relaxed);
+        if result.is_err() {
+            self.vector_db_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Human-readable statistics report
+    pub fn report(&self) -> String {
+        let uptime = self.started.elapsed().as_secs();
+        let mut text = format!(
+            "Server statistics:\n\nUptime: {}h {}m {}s",
+            uptime / 3600,
+            uptime / 60 % 60,
+            uptime % 60
+        );
+
+        text.push_str("\n\nTool calls:");
+        let tools = self.tools.lock().unwrap();
+        if tools.is_empty() {
+            text.push_str("\n- none yet");
+        }
+        for (name, stats) in tools.iter() {
+            let mut latencies: Vec<Duration> = stats.latencies.iter().copied().collect();
+            latencies.sort();
+            text.push_str(&format!(
+                "\n- {}: {} calls, {} errors, latency p50 {} / p95 {} / p99 {}",
+                name,
+                stats.calls,
+                stats.errors,
+                format_ms(percentile(&latencies, 50.0)),
+                format_ms(percentile(&latencies, 95.0)),
+                format_ms(percentile(&latencies, 99.0))
+            ));
+        }
+        drop(tools);
+
+        let mut latencies: Vec<Duration> = self.embedding_latencies.lock().unwrap().iter().copied().collect();
+        latencies.sort();
+        let requests = self.embedding_requests.load(Ordering::Relaxed);
+        let errors = self.embedding_errors.load(Ordering::Relaxed);
+        text.push_str(&format!(
+            "\n\nEmbedding requests: {} ({} errors{}), latency p50 {} / p95 {} / p99 {}\nVector DB requests: {} ({} errors)",
+            requests,
+            errors,
+            if requests > 0 {
+                format!(", {:.1}% error rate", errors as f64 * 100.0 / requests as f64)
+            } else {
+                String::new()
+            },
+            format_ms(percentile(&latencies, 50.0)),
+            format_ms(percentile(&latencies, 95.0)),
+            format_ms(percentile(&latencies, 99.0)),
+            self.vector_db_requests.load(Ordering::Relaxed),
+            self.vector_db_errors.load(Ordering::Relaxed)
+        ));
+
+        let hits = self.dedup_hits.load(Ordering::Relaxed);
+        let lookups = hits + self.dedup_misses.load(Ordering::Relaxed);
+        text.push_str(&format!("\nChunk dedup cache: {} hits / {} lookups", hits, lookups));
+        if lookups > 0 {
+            text.push_str(&format!(" ({:.1}% hit rate)", hits as f64 * 100.0 / lookups as f64));
+        }
+
+        let hits = self.embedding_cache_hits.load(Ordering::Relaxed);
+        let lookups = hits + self.embedding_cache_misses.load(Ordering::Relaxed);
+        if lookups > 0 {
+            text.push_str(&format!(
+                "\nEmbedding cache: {} hits / {} lookups ({:.1}% hit rate)",
+                hits,
+                lookups,
+                hits as f64 * 100.0 / lookups as f64
+            ));
+        }
+
+        text.push_str("\nMemory (RSS): ");
+        match resident_memory_bytes() {
+            Some(bytes) => text.push_str(&format!("{:.1} MB", bytes as f64 / 1024.0 / 1024.0)),
+            None => text.push_str("unavailable on this platform"),
+        }
+        text
+    }
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[Duration], pct: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+fn format_ms(duration: Option<Duration>) -> String {
+    duration.map_or("-".to_string(), |d| format!("{:.0}ms", d.as_secs_f64() * 1000.0))
+}
+
+/// Resident set size of this process (Linux only)
+fn resident_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Embedding provider wrapper counting requests and errors
+pub struct InstrumentedEmbedding {
+    inner: Arc<dyn EmbeddingProvider>,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedEmbedding {
+    pub fn new(inner: Arc<dyn EmbeddingProvider>, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for InstrumentedEmbedding {
+    async fn embed(&self, text: &str) -> Result<Embedding> {
+        let started = Instant::now();
+        let result = self.inner.embed(text).await;
+        self.metrics.record_embedding(&result, started.elapsed());
+        result
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        let started = Instant::now();
+        let result = self.inner.embed_batch(texts).await;
+        self.metrics.record_embedding(&result, started.elapsed());
+        result
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Embedding> {
+        let started = Instant::now();
+        let result = self.inner.embed_query(text).await;
+        self.metrics.record_embedding(&result, started.elapsed());
+        result
+    }
+
+    async fn embed_document(&self, text: &str) -> Result<Embedding> {
+        let started = Instant::now();
+        let result = self.inner.embed_document(text).await;
+        self.metrics.record_embedding(&result, started.elapsed());
+        result
+    }
+
+    async fn embed_documents(&self, texts: &[&str]) -> Result<Vec<Embedding>> {
+        let started = Instant::now();
+        let result = self.inner.embed_documents(texts).await;
+        self.metrics.record_embedding(&result, started.elapsed());
+        result
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.inner.max_batch_size()
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    async fn probe_dimension(&self) -> Result<usize> {
+        self.inner.probe_dimension().await
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn query_cache_key(&self) -> String {
+        self.inner.query_cache_key()
+    }
+
+    async fn model_version(&self) -> Result<Option<String>> {
+        self.inner.model_version().await
+    }
+
+    fn with_model(&self, model: &str) -> Option<Arc<dyn EmbeddingProvider>> {
+        let inner = self.inner.with_model(model)?;
+        Some(Arc::new(Self::new(inner, self.metrics.clone())))
+    }
+
+    fn cost_per_million_tokens(&self) -> Option<f64> {
+        self.inner.cost_per_million_tokens()
+    }
+
+    async fn health_check(&self) -> Result<String> {
+        self.inner.health_check().await
+    }
+}
+
+/// Vector database wrapper counting requests and errors
+pub struct InstrumentedVectorDatabase {
+    inner: Arc<dyn VectorDatabase>,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedVectorDatabase {
+    pub fn new(inner: Arc<dyn VectorDatabase>, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait::async_trait]
+impl VectorDatabase for InstrumentedVectorDatabase {
+    async fn create_collection(&self, name: &str, dimension: usize) -> Result<()> {
+        let result = self.inner.create_collection(name, dimension).await;
+        self.metrics.record_vector_db(&result);
+        result
+    }
+
+    async fn collection_dimension(&self, name: &str) -> Result<Option<usize>> {
+        let result = self.inner.collection_dimension(name).await;
+        self.metrics.record_vector_db(&result);
+        result
+    }
+
+    async fn upsert(&self, collection: &str, ids: &[i64], vectors: &[Vec<f32>], metadata: &[serde_json::Value]) -> Result<()> {
+        let result = self.inner.upsert(collection, ids, vectors, metadata).await;
+        self.metrics.record_vector_db(&result);
+        result
+    }
+
+    async fn search(&self, collection: &str, vector: &[f32], limit: usize) -> Result<Vec<SearchResult>> {
+        let result = self.inner.search(collection, vector, limit).await;
+        self.metrics.record_vector_db(&result);
+        result
+    }
+
+    async fn drop_collection(&self, name: &str) -> Result<()> {
+        let result = self.inner.drop_collection(name).await;
+        self.metrics.record_vector_db(&result);
+        result
+    }
+
+    async fn delete_by_file(&self, collection: &str, file_path: &str) -> Result<()> {
+        let result = self.inner.delete_by_file(collection, file_path).await;
+        self.metrics.record_vector_db(&result);
+        result
+    }
+
+    async fn delete_by_file_hashes(&self, collection: &str, file_hashes: &[String]) -> Result<()> {
+        let result = self.inner.delete_by_file_hashes(collection, file_hashes).await;
+        self.metrics.record_vector_db(&result);
+        result
+    }
+
+    async fn health_check(&self) -> Result<String> {
+        self.inner.health_check().await
+    }
+
+    fn metric(&self) -> Metric {
+        self.inner.metric()
+    }
+
+    async fn create_partition(&self, collection: &str, partition: &str) -> Result<()> {
+        let result = self.inner.create_partition(collection, partition).await;
+        self.metrics.record_vector_db(&result);
+        result
+    }
+
+    async fn upsert_partition(
+        &self,
+        collection: &str,
+        partition: &str,
+        ids: &[i64],
+        vectors: &[Vec<f32>],
+        metadata: &[serde_json::Value],
+    ) -> Result<()> {
+        let result = self.inner.upsert_partition(collection, partition, ids, vectors, metadata).await;
+        self.metrics.record_vector_db(&result);
+        result
+    }
+
+    async fn search_partitions(
+        &self,
+        collection: &str,
+        partitions: &[String],
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let result = self.inner.search_partitions(collection, partitions, vector, limit).await;
+        self.metrics.record_vector_db(&result);
+        result
+    }
+
+    async fn move_file(
+        &self,
+        collection: &str,
+        partition: Option<&str>,
+        old_path: &str,
+        new_path: &str,
+        updates: &serde_json::Value,
+    ) -> Result<bool> {
+        let result = self.inner.move_file(collection, partition, old_path, new_path, updates).await;
+        self.metrics.record_vector_db(&result);
+        result
+    }
+
+    async fn copy_file(
+        &self,
+        collection: &str,
+        file_path: &str,
+        target: &str,
+        updates: &serde_json::Value,
+        salt: i64,
+    ) -> Result<usize> {
+        let result = self.inner.copy_file(collection, file_path, target, updates, salt).await;
+        self.metrics.record_vector_db(&result);
+        result
+    }
+
+    async fn search_keywords(
+        &self,
+        collection: &str,
+        partitions: Option<&[String]>,
+        vector: &[f32],
+        limit: usize,
+        keywords: &[String],
+    ) -> Result<Vec<SearchResult>> {
+        let result = self.inner.search_keywords(collection, partitions, vector, limit, keywords).await;
+        self.metrics.record_vector_db(&result);
+        result
+    }
+
+    async fn count_rows(&self, collection: &str) -> Result<Option<usize>> {
+        let result = self.inner.count_rows(collection).await;
+        self.metrics.record_vector_db(&result);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_latency_percentiles() {
+        let metrics = Metrics::default();
+        for ms in 1..=100 {
+            metrics.record_tool_call("search_code", Duration::from_millis(ms), ms != 100);
+        }
+
+        let report = metrics.report();
+        assert!(report.contains("search_code: 100 calls, 1 errors, latency p50 50ms / p95 95ms / p99 99ms"));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+}
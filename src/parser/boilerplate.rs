@@ -0,0 +1,89 @@
+//! Detection of license headers and comment banners at the start of a chunk
+
+/// Phrases identifying a license or copyright header (matched lowercase)
+const LICENSE_MARKERS: &[&str] = &[
+    "spdx-license-identifier",
+    "copyright",
+    "all rights reserved",
+    "licensed under",
+    "permission is hereby granted",
+    "gnu general public license",
+    "apache license",
+    "mit license",
+    "this program is free software",
+    "without warranties or conditions",
+];
+
+/// Comment blocks at least this long are treated as a banner when framed by
+/// decoration lines (`// ======`, `/*******`, `# ------`)
+const BANNER_MIN_LINES: usize = 3;
+
+/// Number of leading lines of `content` that form a license header or a
+/// comment banner (including the blank lines after it), or 0 if there is none
+pub fn leading_boilerplate_lines(content: &str) -> usize {
+    let lines: Vec<&str> = content.lines().collect();
+
+    // Leading comment block
+    let mut end = 0;
+    let mut in_block_comment = false;
+    while end < lines.len() {
+        let line = lines[end].trim();
+        if in_block_comment {
+            in_block_comment = !line.contains("*/");
+        } else if let Some(rest) = line.strip_prefix("/*") {
+            in_block_comment = !rest.contains("*/");
+        } else if !is_line_comment(line) {
+            break;
+        }
+        end += 1;
+    }
+    if end == 0 || in_block_comment {
+        return 0;
+    }
+
+    let block = &lines[..end];
+    let text = block.join("\n").to_lowercase();
+    let is_license = LICENSE_MARKERS.iter().any(|m| text.contains(m));
+    let is_banner =
+        block.len() >= BANNER_MIN_LINES && is_decoration(block[0]) && is_decoration(block[block.len() - 1]);
+    if !is_license && !is_banner {
+        return 0;
+    }
+
+    while end < lines.len() && lines[end].trim().is_empty() {
+        end += 1;
+    }
+    end
+}
+
+fn is_line_comment(line: &str) -> bool {
+    ["//", "#", "--", ";;"].iter().any(|p| line.starts_with(p)) && !line.starts_with("#!") && !line.starts_with("#[")
+}
+
+/// A comment line made only of repeated punctuation
+fn is_decoration(line: &str) -> bool {
+    let body = line.trim().trim_start_matches(['/', '#', '-', ';']);
+    let body = body.trim();
+    body.len() >= 3 && body.chars().all(|c| "=*-#~+/".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_license_headers_and_banners() {
+        let licensed = "// Copyright 2024 Example Corp.\n// SPDX-License-Identifier: MIT\n\nfn main() {}";
+        assert_eq!(leading_boilerplate_lines(licensed), 3);
+
+        let block = "/*\n * Licensed under the Apache License, Version 2.0\n */\npackage main";
+        assert_eq!(leading_boilerplate_lines(block), 3);
+
+        let banner = "# ==========\n# Utilities\n# ==========\ndef helper(): pass";
+        assert_eq!(leading_boilerplate_lines(banner), 3);
+
+        let doc = "/// Parse the input\nfn parse() {}";
+        assert_eq!(leading_boilerplate_lines(doc), 0);
+        assert_eq!(leading_boilerplate_lines("#!/usr/bin/env python\n# Copyright 2024"), 0);
+    }
+}
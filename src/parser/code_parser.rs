@@ -1,13 +1,26 @@
-use super::{CodeChunk, SymbolKind};
+use super::{boilerplate, docs, ChunkingOptions, CodeChunk, ParseFallback, ParsedFile, Splitter, SymbolKind, DEFAULT_PARSE_TIMEOUT_SECS};
+use crate::paths;
 use anyhow::{Context, Result};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::Path;
-use tree_sitter::{Language, Parser, TreeCursor};
+use std::time::Duration;
+use tree_sitter::{Language, Node, Parser, TreeCursor};
+
+/// Share of a file's bytes inside ERROR nodes above which its tree is not
+/// trusted for chunking
+const MAX_ERROR_RATIO: f64 = 0.5;
+
+/// Named scopes that aren't chunked themselves but name the symbols inside
+/// them (Rust modules and traits, C++/C#/TypeScript namespaces)
+const SCOPE_KINDS: &[&str] = &["mod_item", "trait_item", "namespace_definition", "namespace_declaration", "internal_module"];
 
 /// Code parser using tree-sitter for AST-based code chunking
 pub struct CodeParser {
     languages: HashMap<String, Language>,
+    /// Time a grammar may take on one file (unlimited when zero)
+    parse_timeout: Duration,
 }
 
 impl CodeParser {
@@ -26,7 +39,70 @@ impl CodeParser {
         languages.insert("java".to_string(), tree_sitter_java::language());
         languages.insert("cs".to_string(), tree_sitter_c_sharp::language());
 
-        Self { languages }
+        Self {
+            languages,
+            parse_timeout: Duration::from_secs_f64(DEFAULT_PARSE_TIMEOUT_SECS),
+        }
+    }
+
+    /// Fall back to line windows for files a grammar takes longer than
+    /// `timeout` on
+    pub fn with_parse_timeout(mut self, timeout: Duration) -> Self {
+        self.parse_timeout = timeout;
+        self
+    }
+
+    /// Try loading every registered grammar, returning failures as
+    /// (extension, error) pairs
+    pub fn check_grammars(&self) -> Vec<(String, String)> {
+        let mut failures: Vec<(String, String)> = self
+            .languages
+            .iter()
+            .filter_map(|(ext, language)| {
+                Parser::new()
+                    .set_language(*language)
+                    .err()
+                    .map(|e| (ext.clone(), e.to_string()))
+            })
+            .collect();
+        failures.sort();
+        failures
+    }
+
+    /// Language identifier of a file by its extension, as used for Markdown
+    /// code fences (`rust`, `python`, ...)
+    pub fn language_id(file_path: &Path) -> Option<&'static str> {
+        let id = match file_path.extension().and_then(|e| e.to_str())?.to_ascii_lowercase().as_str() {
+            "rs" => "rust",
+            "ts" | "tsx" => "typescript",
+            "js" | "jsx" | "mjs" | "cjs" => "javascript",
+            "py" => "python",
+            "go" => "go",
+            "cpp" | "cc" | "cxx" | "hpp" | "hh" => "cpp",
+            "c" | "h" => "c",
+            "java" => "java",
+            "cs" => "csharp",
+            "kt" => "kotlin",
+            "rb" => "ruby",
+            "php" => "php",
+            "swift" => "swift",
+            "sh" | "bash" => "bash",
+            "sql" => "sql",
+            "md" | "markdown" | "mdx" => "markdown",
+            "rst" => "rst",
+            "toml" => "toml",
+            "yaml" | "yml" => "yaml",
+            "json" => "json",
+            "html" => "html",
+            "css" => "css",
+            _ => return None,
+        };
+        Some(id)
+    }
+
+    /// Number of registered grammars (by file extension)
+    pub fn language_count(&self) -> usize {
+        self.languages.len()
     }
 
     /// Get file hash for change detection
@@ -38,6 +114,67 @@ impl CodeParser {
 
     /// Parse code and extract chunks
     pub fn parse(&self, file_path: &Path, content: &str) -> Result<Vec<CodeChunk>> {
+        self.parse_with_options(file_path, content, &ChunkingOptions::default())
+    }
+
+    /// Parse code and extract chunks using the given chunking options
+    pub fn parse_with_options(&self, file_path: &Path, content: &str, options: &ChunkingOptions) -> Result<Vec<CodeChunk>> {
+        self.parse_file(file_path, content, options).map(|parsed| parsed.chunks)
+    }
+
+    /// Parse code and extract chunks, reporting when the file's grammar
+    /// panicked, timed out or misparsed it and line windows were used instead
+    pub fn parse_file(&self, file_path: &Path, content: &str, options: &ChunkingOptions) -> Result<ParsedFile> {
+        if self.is_doc_file(file_path) {
+            return Ok(ParsedFile {
+                chunks: docs::split_sections(file_path, content, options.doc_chunk_lines),
+                fallback: None,
+            });
+        }
+
+        let (chunks, fallback) = self.chunk_file(file_path, content, options)?;
+        let chunks = if options.strip_boilerplate {
+            chunks.into_iter().filter_map(Self::strip_boilerplate).collect()
+        } else {
+            chunks
+        };
+        Ok(ParsedFile { chunks, fallback })
+    }
+
+    /// Documentation without a grammar, chunked by section (code examples
+    /// under `docs/` are still parsed as code)
+    fn is_doc_file(&self, file_path: &Path) -> bool {
+        let extension = file_path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        docs::is_documentation(file_path) && !self.languages.contains_key(extension)
+    }
+
+    /// Drop a leading license header or comment banner from a chunk, moving
+    /// its start line past it; chunks with nothing else are dropped entirely
+    fn strip_boilerplate(mut chunk: CodeChunk) -> Option<CodeChunk> {
+        let skip = boilerplate::leading_boilerplate_lines(&chunk.content);
+        if skip == 0 {
+            return Some(chunk);
+        }
+
+        let offset: usize = chunk.content.split_inclusive('\n').take(skip).map(str::len).sum();
+        if chunk.content[offset..].trim().is_empty() {
+            return None;
+        }
+        chunk.content.drain(..offset);
+        chunk.start_line += skip;
+        Some(chunk)
+    }
+
+    fn chunk_file(
+        &self,
+        file_path: &Path,
+        content: &str,
+        options: &ChunkingOptions,
+    ) -> Result<(Vec<CodeChunk>, Option<ParseFallback>)> {
+        if options.splitter == Splitter::Langchain {
+            return Ok((Self::split_lines(file_path, content, options.chunk_lines), None));
+        }
+
         let extension = file_path
             .extension()
             .and_then(|e| e.to_str())
@@ -49,14 +186,16 @@ impl CodeParser {
             Some(lang) => lang,
             None => {
                 // Fallback: treat entire file as one chunk
-                return Ok(vec![CodeChunk {
-                    file_path: file_path.to_string_lossy().to_string(),
+                return Ok((vec![CodeChunk {
+                    file_path: paths::to_slash(file_path),
                     content: content.to_string(),
                     start_line: 0,
-                    end_line: content.lines().count(),
+                    end_line: content.lines().count().saturating_sub(1),
                     symbol_name: None,
                     symbol_kind: SymbolKind::Other,
-                }]);
+                    parents: Vec::new(),
+                    language: Self::language_id(file_path).map(str::to_string),
+                }], None));
             }
         };
 
@@ -64,70 +203,152 @@ impl CodeParser {
         parser
             .set_language(*language)
             .context("Failed to set language")?;
+        parser.set_timeout_micros(self.parse_timeout.as_micros().try_into().unwrap_or(u64::MAX));
 
-        let tree = parser
-            .parse(content, None)
-            .context("Failed to parse code")?;
-
-        let mut chunks = Vec::new();
-        let root = tree.root_node();
+        // A grammar that panics, hangs or mostly fails on a file must not
+        // take indexing down with it; the file is chunked by lines instead
+        let extracted = panic::catch_unwind(AssertUnwindSafe(|| {
+            let Some(tree) = parser.parse(content, None) else {
+                return Err(ParseFallback::TimedOut);
+            };
+            let root = tree.root_node();
+            if root.has_error() && error_bytes(root) as f64 > content.len() as f64 * MAX_ERROR_RATIO {
+                return Err(ParseFallback::SyntaxErrors);
+            }
+            let mut chunks = Vec::new();
+            self.extract_chunks(&mut chunks, &mut root.walk(), content, file_path, &mut Vec::new(), options.chunk_lines);
+            Ok(chunks)
+        }))
+        .unwrap_or(Err(ParseFallback::Panicked));
+        let mut chunks = match extracted {
+            Ok(chunks) => chunks,
+            Err(fallback) => {
+                tracing::warn!("Chunking {:?} by lines: {}", file_path, fallback.as_str());
+                return Ok((Self::split_lines(file_path, content, options.chunk_lines), Some(fallback)));
+            }
+        };
 
-        self.extract_chunks(&mut chunks, &mut root.walk(), content, file_path);
+        if options.overlap_lines > 0 {
+            Self::add_overlap(&mut chunks, content, options.overlap_lines);
+        }
 
         if chunks.is_empty() {
             // Fallback: entire file as one chunk
             chunks.push(CodeChunk {
-                file_path: file_path.to_string_lossy().to_string(),
+                file_path: paths::to_slash(file_path),
                 content: content.to_string(),
                 start_line: 0,
-                end_line: content.lines().count(),
+                end_line: content.lines().count().saturating_sub(1),
                 symbol_name: None,
                 symbol_kind: SymbolKind::Other,
+                parents: Vec::new(),
+                language: Self::language_id(file_path).map(str::to_string),
             });
         }
 
-        Ok(chunks)
+        Ok((chunks, None))
+    }
+
+    /// Split content into fixed-size line windows
+    pub fn split_lines(file_path: &Path, content: &str, chunk_lines: usize) -> Vec<CodeChunk> {
+        let lines: Vec<&str> = content.lines().collect();
+        let chunk_lines = chunk_lines.max(1);
+
+        lines
+            .chunks(chunk_lines)
+            .enumerate()
+            .map(|(i, window)| {
+                let start_line = i * chunk_lines;
+                CodeChunk {
+                    file_path: paths::to_slash(file_path),
+                    content: window.join("\n"),
+                    start_line,
+                    end_line: start_line + window.len().saturating_sub(1),
+                    symbol_name: None,
+                    symbol_kind: SymbolKind::Other,
+                    parents: Vec::new(),
+                    language: Self::language_id(file_path).map(str::to_string),
+                }
+            })
+            .collect()
     }
 
+    /// Extend each symbol chunk by `overlap` whole lines of surrounding
+    /// source on both sides, so context straddling symbol boundaries (a type
+    /// right above its impl, constants above a function) is embedded too
+    fn add_overlap(chunks: &mut [CodeChunk], source: &str, overlap: usize) {
+        let lines: Vec<&str> = source.lines().collect();
+        let Some(last_line) = lines.len().checked_sub(1) else {
+            return;
+        };
+
+        for chunk in chunks.iter_mut() {
+            let start = chunk.start_line.saturating_sub(overlap);
+            let end = chunk.end_line.saturating_add(overlap).min(last_line);
+            if start > end {
+                continue;
+            }
+            chunk.content = lines[start..=end].join("\n");
+            chunk.start_line = start;
+            chunk.end_line = end;
+        }
+    }
+
+    /// Chunk the symbols at the cursor and its siblings. Containers (classes,
+    /// impls, interfaces) longer than `split_lines` are chunked whole
+    /// and also per member, with `parents` naming where each member lives.
     fn extract_chunks(
         &self,
         chunks: &mut Vec<CodeChunk>,
         cursor: &mut TreeCursor,
         source: &str,
         file_path: &Path,
+        parents: &mut Vec<String>,
+        split_lines: usize,
     ) {
         loop {
             let node = cursor.node();
             let kind = node.kind();
 
-            // Extract based on node type
-            if let Some(symbol_kind) = self.identify_symbol(kind) {
-                let start_byte = node.start_byte();
-                let end_byte = node.end_byte();
-                let content = &source[start_byte..end_byte];
-
+            // Extract based on node type. Ranges are byte offsets; one not on
+            // a character boundary (a malformed tree) is skipped, not sliced
+            let content = source.get(node.start_byte()..node.end_byte());
+            if let (Some(symbol_kind), Some(content)) = (self.identify_symbol(kind), content) {
                 // Get symbol name
                 let symbol_name = self.extract_symbol_name(cursor, source);
+                let is_container = matches!(symbol_kind, SymbolKind::Class | SymbolKind::Interface);
+                let lines = node.end_position().row - node.start_position().row + 1;
 
                 chunks.push(CodeChunk {
-                    file_path: file_path.to_string_lossy().to_string(),
+                    file_path: paths::to_slash(file_path),
                     content: content.to_string(),
                     start_line: node.start_position().row,
                     end_line: node.end_position().row,
-                    symbol_name,
-                    symbol_kind: symbol_kind.clone(),
+                    symbol_name: symbol_name.clone(),
+                    symbol_kind,
+                    parents: parents.clone(),
+                    language: Self::language_id(file_path).map(str::to_string),
                 });
 
-                // Don't recurse into this node, we've captured it
+                // Members of a large container are also matched on their own
+                if is_container && lines > split_lines && cursor.goto_first_child() {
+                    self.extract_scope(chunks, cursor, source, file_path, parents, split_lines, symbol_name);
+                    cursor.goto_parent();
+                }
+
                 if !cursor.goto_next_sibling() {
                     break;
                 }
                 continue;
             }
 
-            // Recurse into children
+            // Recurse into children, naming the scope they are in
+            let scope = SCOPE_KINDS
+                .contains(&kind)
+                .then(|| self.extract_symbol_name(cursor, source))
+                .flatten();
             if cursor.goto_first_child() {
-                self.extract_chunks(chunks, cursor, source, file_path);
+                self.extract_scope(chunks, cursor, source, file_path, parents, split_lines, scope);
                 cursor.goto_parent();
             }
 
@@ -137,6 +358,27 @@ impl CodeParser {
         }
     }
 
+    /// [`extract_chunks`](Self::extract_chunks) for the children of a
+    /// node, inside the scope `name` if it has one
+    #[allow(clippy::too_many_arguments)]
+    fn extract_scope(
+        &self,
+        chunks: &mut Vec<CodeChunk>,
+        cursor: &mut TreeCursor,
+        source: &str,
+        file_path: &Path,
+        parents: &mut Vec<String>,
+        split_lines: usize,
+        name: Option<String>,
+    ) {
+        let named = name.is_some();
+        parents.extend(name);
+        self.extract_chunks(chunks, cursor, source, file_path, parents, split_lines);
+        if named {
+            parents.pop();
+        }
+    }
+
     fn identify_symbol(&self, node_kind: &str) -> Option<SymbolKind> {
         match node_kind {
             "function_definition"
@@ -160,9 +402,7 @@ impl CodeParser {
             loop {
                 let node = name_cursor.node();
                 if node.kind().contains("identifier") || node.kind().contains("name") {
-                    let start = node.start_byte();
-                    let end = node.end_byte();
-                    return Some(source[start..end].to_string());
+                    return source.get(node.start_byte()..node.end_byte()).map(str::to_string);
                 }
 
                 if !name_cursor.goto_next_sibling() {
@@ -175,6 +415,18 @@ impl CodeParser {
     }
 }
 
+/// Bytes covered by ERROR nodes below `node`
+fn error_bytes(node: Node) -> usize {
+    if node.is_error() {
+        return node.end_byte() - node.start_byte();
+    }
+    if !node.has_error() {
+        return 0;
+    }
+    let mut cursor = node.walk();
+    node.children(&mut cursor).map(error_bytes).sum()
+}
+
 impl Default for CodeParser {
     fn default() -> Self {
         Self::new()
@@ -184,6 +436,7 @@ impl Default for CodeParser {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_parse_rust_function() {
@@ -197,5 +450,115 @@ mod tests {
         let chunks = parser.parse(Path::new("test.rs"), code).unwrap();
         assert!(!chunks.is_empty());
         assert_eq!(chunks[0].symbol_kind, SymbolKind::Function);
+        assert_eq!(chunks[0].language.as_deref(), Some("rust"));
+    }
+
+    #[test]
+    fn test_split_lines_windows() {
+        let content = (0..25).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n");
+        let chunks = CodeParser::split_lines(Path::new("notes.txt"), &content, 10);
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (10, 19));
+        assert_eq!((chunks[2].start_line, chunks[2].end_line), (20, 24));
+    }
+
+    #[test]
+    fn test_overlap_includes_neighbouring_lines() {
+        let parser = CodeParser::new();
+        let code = "const LIMIT: usize = 10;\n\nfn first() {}\n\nfn second() {}\n";
+        let options = ChunkingOptions {
+            overlap_lines: 2,
+            ..Default::default()
+        };
+
+        let chunks = parser.parse_with_options(Path::new("lib.rs"), code, &options).unwrap();
+        let first = chunks.iter().find(|c| c.symbol_name.as_deref() == Some("first")).unwrap();
+        assert_eq!((first.start_line, first.end_line), (0, 4));
+        assert!(first.content.starts_with("const LIMIT"));
+        assert!(first.content.ends_with("fn second() {}"));
+
+        // An overlap past the file's ends takes the whole file
+        let options = ChunkingOptions {
+            overlap_lines: usize::MAX,
+            ..Default::default()
+        };
+        let chunks = parser.parse_with_options(Path::new("lib.rs"), code, &options).unwrap();
+        assert!(chunks.iter().all(|c| (c.start_line, c.end_line) == (0, 4)));
+    }
+
+    #[test]
+    fn test_nested_symbols_name_their_parents() {
+        let parser = CodeParser::new();
+        let code = "mod handlers {\n    impl Engine {\n        fn search() {\n            run();\n        }\n    }\n    fn helper() {}\n}\n";
+        let options = ChunkingOptions {
+            chunk_lines: 3,
+            ..Default::default()
+        };
+
+        let chunks = parser.parse_with_options(Path::new("lib.rs"), code, &options).unwrap();
+        let parents = |name: &str| chunks.iter().find(|c| c.symbol_name.as_deref() == Some(name)).unwrap().parents.clone();
+        assert_eq!(parents("Engine"), vec!["handlers"]);
+        assert_eq!(parents("search"), vec!["handlers", "Engine"]);
+        assert_eq!(parents("helper"), vec!["handlers"]);
+
+        // Containers within `chunk_lines` are not split
+        let chunks = parser.parse(Path::new("lib.rs"), code).unwrap();
+        assert!(chunks.iter().all(|c| c.symbol_name.as_deref() != Some("search")));
+    }
+
+    #[test]
+    fn test_misparsed_file_falls_back_to_line_windows() {
+        let parser = CodeParser::new();
+        let code = format!("fn ok() {{}}\n{}", "} ) ] @@ <<< ;; \\ ### >>\n".repeat(40));
+
+        let parsed = parser.parse_file(Path::new("broken.rs"), &code, &ChunkingOptions::default()).unwrap();
+        assert_eq!(parsed.fallback, Some(ParseFallback::SyntaxErrors));
+        assert!(parsed.chunks.iter().all(|c| c.symbol_kind == SymbolKind::Other));
+        assert_eq!(parsed.chunks.last().unwrap().end_line, 40);
+
+        let parsed = parser.parse_file(Path::new("ok.rs"), "fn ok() {}\n", &ChunkingOptions::default()).unwrap();
+        assert_eq!(parsed.fallback, None);
+    }
+
+    #[test]
+    fn test_license_header_excluded_from_chunk() {
+        let parser = CodeParser::new();
+        let code = "// Copyright 2024 Example Corp.\n// Licensed under the MIT License.\n\nfn main() {}\n";
+        let options = ChunkingOptions {
+            splitter: Splitter::Langchain,
+            ..Default::default()
+        };
+
+        let chunks = parser.parse_with_options(Path::new("main.rs"), code, &options).unwrap();
+        assert_eq!(chunks[0].content, "fn main() {}");
+        assert_eq!((chunks[0].start_line, chunks[0].end_line), (3, 3));
+    }
+
+    proptest! {
+        #[test]
+        fn test_non_ascii_sources_chunk_on_line_boundaries(
+            comments in proptest::collection::vec("[a-z 中文注释日本語한국어😀é]{0,12}", 1..8),
+            name in "[a-z]{1,6}[é日]?",
+            splitter in prop_oneof![Just(Splitter::Ast), Just(Splitter::Langchain)],
+        ) {
+            let mut code = String::new();
+            for (i, comment) in comments.iter().enumerate() {
+                code.push_str(&format!("// {}\nfn {}{}() {{ let s = \"{}\"; }}\n", comment, name, i, comment));
+            }
+            let options = ChunkingOptions { splitter, overlap_lines: 1, ..Default::default() };
+            let chunks = CodeParser::new().parse_with_options(Path::new("lib.rs"), &code, &options).unwrap();
+
+            let lines: Vec<&str> = code.lines().collect();
+            prop_assert!(!chunks.is_empty());
+            for chunk in &chunks {
+                prop_assert!(chunk.start_line <= chunk.end_line && chunk.end_line < lines.len());
+                prop_assert_eq!(chunk.content.lines().count(), chunk.end_line - chunk.start_line + 1);
+                prop_assert!(lines[chunk.start_line].ends_with(chunk.content.lines().next().unwrap_or("")));
+                if let Some(symbol) = &chunk.symbol_name {
+                    prop_assert!(symbol.starts_with(name.as_str()));
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,170 @@
+//! Documentation files (Markdown, reStructuredText, anything under `docs/`),
+//! chunked by section instead of by symbol
+
+use super::code_parser::CodeParser;
+use super::{CodeChunk, SymbolKind};
+use crate::paths;
+use std::path::Path;
+
+/// Extensions of documentation formats (matched lowercase)
+const DOC_EXTENSIONS: &[&str] = &["md", "markdown", "mdx", "rst", "adoc"];
+
+/// Directories whose files are all documentation (matched lowercase)
+const DOC_DIRS: &[&str] = &["docs", "doc", "documentation"];
+
+/// Whether a file is documentation: a documentation format, or any file
+/// inside a `docs/` directory
+pub fn is_documentation(path: &Path) -> bool {
+    let is_doc_format = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| DOC_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()));
+    let in_doc_dir = path.parent().is_some_and(|dir| {
+        dir.components().any(|c| {
+            c.as_os_str()
+                .to_str()
+                .is_some_and(|name| DOC_DIRS.contains(&name.to_ascii_lowercase().as_str()))
+        })
+    });
+    is_doc_format || in_doc_dir
+}
+
+/// Split documentation into one chunk per section (at Markdown or
+/// reStructuredText headings); sections longer than `max_lines` are split
+/// further at paragraph breaks
+pub fn split_sections(file_path: &Path, content: &str, max_lines: usize) -> Vec<CodeChunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    let max_lines = max_lines.max(1);
+
+    let mut starts = vec![0];
+    let mut in_fence = false;
+    for i in 0..lines.len() {
+        let line = lines[i].trim();
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let start = if is_atx_heading(line) {
+            i
+        } else if !line.is_empty() && !is_underline(line) && lines.get(i + 1).is_some_and(|next| is_underline(next.trim())) {
+            // An overline belongs to the heading too
+            if i > 0 && is_underline(lines[i - 1].trim()) {
+                i - 1
+            } else {
+                i
+            }
+        } else {
+            continue;
+        };
+        if starts.last() != Some(&start) {
+            starts.push(start);
+        }
+    }
+    starts.push(lines.len());
+
+    let mut chunks = Vec::new();
+    for section in starts.windows(2) {
+        let (start, end) = (section[0], section[1]);
+        let heading = if is_heading_at(&lines, start) {
+            lines[start..end]
+                .iter()
+                .map(|l| l.trim())
+                .find(|l| !l.is_empty() && !is_underline(l))
+                .map(|l| l.trim_start_matches('#').trim().to_string())
+        } else {
+            None
+        };
+
+        let mut piece_start = start;
+        let mut last_break = None;
+        for i in start..end {
+            if lines[i].trim().is_empty() {
+                last_break = Some(i);
+            }
+            if i + 1 - piece_start > max_lines {
+                let cut = last_break.filter(|&b| b > piece_start).unwrap_or(i);
+                push_piece(&mut chunks, file_path, &lines, piece_start, cut, &heading);
+                piece_start = cut;
+                last_break = None;
+            }
+        }
+        push_piece(&mut chunks, file_path, &lines, piece_start, end, &heading);
+    }
+    chunks
+}
+
+/// Whether the section starting at `index` opens with a heading (the first
+/// section may be untitled text)
+fn is_heading_at(lines: &[&str], index: usize) -> bool {
+    let Some(line) = lines.get(index).map(|l| l.trim()) else {
+        return false;
+    };
+    is_atx_heading(line) || is_underline(line) || lines.get(index + 1).is_some_and(|next| is_underline(next.trim()))
+}
+
+/// Markdown `# Heading`
+fn is_atx_heading(line: &str) -> bool {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    (1..=6).contains(&level) && line[level..].starts_with(' ')
+}
+
+/// Setext or reStructuredText heading underline (`=====`, `-----`, `~~~~~`)
+fn is_underline(line: &str) -> bool {
+    let mut chars = line.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    line.len() >= 3 && "=-~^*+#".contains(first) && chars.all(|c| c == first)
+}
+
+/// Add lines `start..end` as a chunk, without leading and trailing blank lines
+fn push_piece(chunks: &mut Vec<CodeChunk>, file_path: &Path, lines: &[&str], start: usize, end: usize, heading: &Option<String>) {
+    let (mut start, mut end) = (start, end);
+    while start < end && lines[start].trim().is_empty() {
+        start += 1;
+    }
+    while end > start && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+    if start == end {
+        return;
+    }
+    chunks.push(CodeChunk {
+        file_path: paths::to_slash(file_path),
+        content: lines[start..end].join("\n"),
+        start_line: start,
+        end_line: end - 1,
+        symbol_name: heading.clone(),
+        symbol_kind: SymbolKind::Section,
+        parents: Vec::new(),
+        language: CodeParser::language_id(file_path).map(str::to_string),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_markdown_and_rst_sections() {
+        let markdown = "Intro text\n\n# Install\n\nRun cargo build.\n\n```sh\n# not a heading\n```\n\n## Usage\n\nSee below.\n";
+        let chunks = split_sections(Path::new("README.md"), markdown, 100);
+        let headings: Vec<_> = chunks.iter().map(|c| c.symbol_name.as_deref()).collect();
+        assert_eq!(headings, vec![None, Some("Install"), Some("Usage")]);
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (2, 8));
+        assert_eq!(chunks[1].symbol_kind, SymbolKind::Section);
+
+        let rst = "=====\nGuide\n=====\n\nFirst paragraph.\n\nSecond paragraph.\n\nSetup\n-----\n\nSteps.\n";
+        let chunks = split_sections(Path::new("docs/guide.rst"), rst, 6);
+        let headings: Vec<_> = chunks.iter().map(|c| c.symbol_name.as_deref()).collect();
+        assert_eq!(headings, vec![Some("Guide"), Some("Guide"), Some("Setup")]);
+        assert_eq!(chunks[1].content, "Second paragraph.");
+
+        assert!(is_documentation(Path::new("docs/api/overview.txt")));
+        assert!(is_documentation(Path::new("CHANGELOG.md")));
+        assert!(!is_documentation(Path::new("src/docs.rs")));
+    }
+}
@@ -1,14 +1,154 @@
+pub mod boilerplate;
 pub mod code_parser;
+pub mod docs;
+pub mod modules;
+
+use serde::{Deserialize, Serialize};
+
+/// Default number of lines per chunk for window-based splitting
+pub const DEFAULT_CHUNK_LINES: usize = 60;
+
+/// Default maximum lines per documentation section chunk
+pub const DEFAULT_DOC_CHUNK_LINES: usize = 150;
+
+/// Default time a grammar may take to parse one file
+pub const DEFAULT_PARSE_TIMEOUT_SECS: f64 = 5.0;
+
+/// Strategy used to split a file into chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Splitter {
+    /// Split on tree-sitter symbols (functions, classes, ...)
+    #[default]
+    Ast,
+    /// Split into fixed-size line windows
+    Langchain,
+}
+
+impl Splitter {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "ast" => Some(Splitter::Ast),
+            "langchain" => Some(Splitter::Langchain),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Splitter::Ast => "ast",
+            Splitter::Langchain => "langchain",
+        }
+    }
+}
+
+/// Chunking options for a project
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkingOptions {
+    pub splitter: Splitter,
+    /// Lines per chunk when splitting into windows
+    pub chunk_lines: usize,
+    /// Lines of surrounding source added before and after each symbol chunk
+    /// (AST splitter only)
+    pub overlap_lines: usize,
+    /// Leave license headers and comment banners out of chunk content
+    pub strip_boilerplate: bool,
+    /// Maximum lines per section chunk of documentation files
+    pub doc_chunk_lines: usize,
+}
+
+impl Default for ChunkingOptions {
+    fn default() -> Self {
+        Self {
+            splitter: Splitter::Ast,
+            chunk_lines: DEFAULT_CHUNK_LINES,
+            overlap_lines: 0,
+            strip_boilerplate: true,
+            doc_chunk_lines: DEFAULT_DOC_CHUNK_LINES,
+        }
+    }
+}
 
 /// Code chunk representing a semantic unit (function, class, etc.)
 #[derive(Debug, Clone)]
 pub struct CodeChunk {
+    /// Path of the source file, relative to the project root when indexed
     pub file_path: String,
     pub content: String,
     pub start_line: usize,
     pub end_line: usize,
     pub symbol_name: Option<String>,
     pub symbol_kind: SymbolKind,
+    /// Names of the enclosing symbols and scopes, outermost first (a method
+    /// chunk of a large class lists the class)
+    pub parents: Vec<String>,
+    /// Language identifier (`rust`, `python`, ...) used for code fences and
+    /// language filters
+    pub language: Option<String>,
+}
+
+impl CodeChunk {
+    /// Two chunks covering this one's lines, the first half and the rest,
+    /// for text too long to embed at once. A single line is split at its
+    /// middle character instead; `None` if there is nothing left to split.
+    pub fn split_in_half(&self) -> Option<(CodeChunk, CodeChunk)> {
+        let lines: Vec<&str> = self.content.split_inclusive('\n').collect();
+        let (first, second, second_start) = if lines.len() > 1 {
+            let half = lines.len() / 2;
+            (lines[..half].concat(), lines[half..].concat(), self.start_line + half)
+        } else {
+            let chars = self.content.chars().count();
+            if chars < 2 {
+                return None;
+            }
+            let middle = self.content.char_indices().nth(chars / 2).map_or(0, |(i, _)| i);
+            let (first, second) = self.content.split_at(middle);
+            (first.to_string(), second.to_string(), self.start_line)
+        };
+
+        let first = CodeChunk {
+            content: first,
+            end_line: second_start.saturating_sub(1).max(self.start_line).min(self.end_line),
+            ..self.clone()
+        };
+        let second = CodeChunk {
+            content: second,
+            start_line: second_start.min(self.end_line),
+            ..self.clone()
+        };
+        Some((first, second))
+    }
+}
+
+/// Chunks of a file, and why its grammar wasn't used if it was chunked
+/// into line windows instead
+#[derive(Debug, Clone)]
+pub struct ParsedFile {
+    pub chunks: Vec<CodeChunk>,
+    pub fallback: Option<ParseFallback>,
+}
+
+/// Why a file with a grammar was chunked into line windows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseFallback {
+    /// The grammar (or chunking its tree) panicked
+    Panicked,
+    /// Parsing took longer than the parse timeout
+    TimedOut,
+    /// Most of the tree was syntax errors
+    SyntaxErrors,
+}
+
+impl ParseFallback {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParseFallback::Panicked => "grammar panicked",
+            ParseFallback::TimedOut => "parse timed out",
+            ParseFallback::SyntaxErrors => "mostly syntax errors",
+        }
+    }
 }
 
 /// Type of code symbol
@@ -20,6 +160,10 @@ pub enum SymbolKind {
     Interface,
     Struct,
     Module,
+    /// Section of a documentation file
+    Section,
+    /// Synthetic summary of a directory's files and symbols
+    Directory,
     #[allow(dead_code)] // Reserved for future language support
     Variable,
     Other,
@@ -34,6 +178,8 @@ impl SymbolKind {
             SymbolKind::Interface => "interface",
             SymbolKind::Struct => "struct",
             SymbolKind::Module => "module",
+            SymbolKind::Section => "section",
+            SymbolKind::Directory => "directory",
             SymbolKind::Variable => "variable",
             SymbolKind::Other => "other",
         }
@@ -0,0 +1,89 @@
+//! Module or package of a source file derived from its path, such as
+//! `crate::handlers::tool_handlers`, `com.example.util` or `app.models.user`
+
+use super::code_parser::CodeParser;
+use crate::archive;
+use std::path::Path;
+
+/// Module path of a file (Rust, Python, Go) or its package (Java, Kotlin),
+/// `None` for other languages and files outside any module
+pub fn module_path(file_path: &Path) -> Option<String> {
+    // Entries of archives are named by their path inside the archive
+    let entry = archive::split_virtual(file_path).map(|(_, entry)| entry);
+    let file_path = entry.as_deref().unwrap_or(file_path);
+
+    let language = CodeParser::language_id(file_path)?;
+    let stem = file_path.file_stem()?.to_str()?;
+    let dirs: Vec<&str> = file_path
+        .parent()?
+        .components()
+        .filter_map(|c| match c {
+            std::path::Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .collect();
+    // Directories below the last of `roots`, or all of them
+    let below = |roots: &[&str]| -> Vec<&str> {
+        let start = dirs.iter().rposition(|d| roots.contains(d)).map_or(0, |i| i + 1);
+        dirs[start..].to_vec()
+    };
+
+    let parts = match language {
+        // Module paths start at the crate's `src` directory
+        "rust" => {
+            let start = dirs.iter().rposition(|d| *d == "src")? + 1;
+            let mut parts = vec!["crate"];
+            parts.extend(&dirs[start..]);
+            if !matches!(stem, "lib" | "main" | "mod") {
+                parts.push(stem);
+            }
+            return Some(parts.join("::"));
+        }
+        // The package is the directory, below `src/main/java` and the like
+        "java" | "kotlin" => below(&["java", "kotlin", "src"]).join("."),
+        "python" => {
+            let mut parts = below(&["src"]);
+            if stem != "__init__" {
+                parts.push(stem);
+            }
+            parts.join(".")
+        }
+        // Import path relative to the module root
+        "go" => dirs.join("/"),
+        _ => return None,
+    };
+    (!parts.is_empty()).then_some(parts)
+}
+
+/// Whether `module` is `prefix` or nested inside it
+pub fn is_within(module: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches([':', '.', '/']);
+    match module.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::") || rest.starts_with('.') || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_paths() {
+        let module = |path: &str| module_path(Path::new(path));
+        assert_eq!(module("src/handlers/tool_handlers.rs").as_deref(), Some("crate::handlers::tool_handlers"));
+        assert_eq!(module("crates/core/src/snapshot/mod.rs").as_deref(), Some("crate::snapshot"));
+        assert_eq!(module("src/lib.rs").as_deref(), Some("crate"));
+        assert_eq!(module("build.rs"), None);
+        assert_eq!(module("src/main/java/com/example/Util.java").as_deref(), Some("com.example"));
+        assert_eq!(module("vendor/lib.jar!/com/example/Util.java").as_deref(), Some("com.example"));
+        assert_eq!(module("src/app/models/user.py").as_deref(), Some("app.models.user"));
+        assert_eq!(module("app/models/__init__.py").as_deref(), Some("app.models"));
+        assert_eq!(module("internal/server/http.go").as_deref(), Some("internal/server"));
+        assert_eq!(module("README.md"), None);
+
+        assert!(is_within("crate::handlers::tool_handlers", "crate::handlers"));
+        assert!(is_within("com.example.util", "com.example."));
+        assert!(!is_within("crate::handlers_extra", "crate::handlers"));
+    }
+}
@@ -0,0 +1,192 @@
+//! Platform-aware directories and path normalization
+
+use std::path::{Component, Path, PathBuf, Prefix};
+
+/// Directory holding the snapshot by default: `%APPDATA%\code-context` on
+/// Windows, `~/.code-context` elsewhere
+pub fn data_dir() -> PathBuf {
+    if cfg!(windows) {
+        if let Some(dir) = dirs::data_dir() {
+            return dir.join("code-context");
+        }
+    }
+    dirs::home_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(".code-context")
+}
+
+/// Base config directory: `XDG_CONFIG_HOME`, `%APPDATA%` on Windows,
+/// `~/.config` elsewhere
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(dir));
+    }
+    if cfg!(windows) {
+        return dirs::config_dir();
+    }
+    dirs::home_dir().map(|home| home.join(".config"))
+}
+
+/// Lexically normalize an absolute path so equal locations compare equal:
+/// `.` components are dropped and, on Windows, verbatim (`\\?\C:\`) prefixes
+/// and lowercase drive letters are rewritten to the plain `C:\` form
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(prefix) => match prefix.kind() {
+                Prefix::VerbatimDisk(letter) | Prefix::Disk(letter) => {
+                    normalized.push(format!("{}:", letter.to_ascii_uppercase() as char));
+                }
+                _ => normalized.push(component.as_os_str()),
+            },
+            Component::CurDir => {}
+            _ => normalized.push(component.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Canonical form of an absolute path, used for project roots: symlinks are
+/// resolved and the result normalized, so `/home/me/./proj` and a link to it
+/// name the same project. Components that don't exist (yet) are kept as
+/// given below their deepest existing ancestor.
+pub fn canonical(path: &Path) -> PathBuf {
+    let path = normalize(path);
+    let mut existing = path.as_path();
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            let mut canonical = normalize(&resolved);
+            canonical.extend(missing.iter().rev());
+            return canonical;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+    path
+}
+
+/// Whether `path` lies inside one of `roots`, after resolving symlinks so a
+/// link inside an allowed root cannot point outside of it
+pub fn is_within(path: &Path, roots: &[PathBuf]) -> bool {
+    let resolve = |p: &Path| p.canonicalize().map(|p| normalize(&p)).unwrap_or_else(|_| normalize(p));
+    let path = resolve(path);
+    roots.iter().any(|root| path.starts_with(resolve(root)))
+}
+
+/// The deepest of `roots` containing `path`, compared component-wise (so
+/// `/work/app-b` is not inside `/work/app`). Nested roots resolve to the
+/// innermost one; equally deep matches to the lexically smallest.
+pub fn deepest_root<'a>(path: &Path, roots: impl IntoIterator<Item = &'a PathBuf>) -> Option<&'a PathBuf> {
+    let path = normalize(path);
+    roots
+        .into_iter()
+        .map(|root| (root, normalize(root)))
+        .filter(|(_, normalized)| path.starts_with(normalized))
+        .max_by(|(a, na), (b, nb)| {
+            na.components()
+                .count()
+                .cmp(&nb.components().count())
+                .then_with(|| b.cmp(a))
+        })
+        .map(|(root, _)| root)
+}
+
+/// Relative path as stored in the index: `/`-separated on every platform
+pub fn to_slash(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `file://` URI of an absolute path (`C:\a b` becomes `file:///C:/a%20b`)
+pub fn file_uri(path: &Path) -> String {
+    format!("file://{}", uri_path(path))
+}
+
+/// Editor link opening an absolute path at a 1-based line, in the form VS
+/// Code and its forks register (`vscode://file/abs/path:10`)
+pub fn editor_uri(scheme: &str, path: &Path, line: usize) -> String {
+    format!("{}://file{}:{}", scheme, uri_path(path), line)
+}
+
+/// Absolute path as a `/`-separated, percent-encoded URI path
+fn uri_path(path: &Path) -> String {
+    let mut slashed = normalize(path).to_string_lossy().into_owned();
+    if cfg!(windows) {
+        slashed = slashed.replace('\\', "/");
+    }
+    if !slashed.starts_with('/') {
+        slashed.insert(0, '/');
+    }
+    let mut encoded = String::with_capacity(slashed.len());
+    for byte in slashed.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~:@!$&'()*+,;=".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Whether a tool `path` argument is the magic "all" (bare or as the last
+/// component, with either separator)
+pub fn is_all(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == "all")
+        || path.to_string_lossy().rsplit(['/', '\\']).next() == Some("all")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_helpers() {
+        assert_eq!(normalize(Path::new("/a/./b/c")), PathBuf::from("/a/b/c"));
+        assert_eq!(to_slash(Path::new("src/parser/mod.rs")), "src/parser/mod.rs");
+        assert!(is_all(Path::new("all")));
+        assert!(is_all(Path::new("/work/all")));
+        assert!(is_all(Path::new("C:\\work\\all")));
+        assert!(!is_all(Path::new("/work/allocator")));
+        assert_eq!(file_uri(Path::new("/work/my app/lib#1.rs")), "file:///work/my%20app/lib%231.rs");
+        assert_eq!(editor_uri("vscode", Path::new("/work/app/main.rs"), 10), "vscode://file/work/app/main.rs:10");
+
+        let roots = [PathBuf::from("/work/projects")];
+        assert!(is_within(Path::new("/work/projects/app"), &roots));
+        assert!(!is_within(Path::new("/work/projects-old"), &roots));
+        assert!(!is_within(Path::new("/etc"), &roots));
+
+        let indexed = [PathBuf::from("/work/app"), PathBuf::from("/work/app/crates/core"), PathBuf::from("/work")];
+        let deepest = |p: &str| deepest_root(Path::new(p), &indexed).cloned();
+        assert_eq!(deepest("/work/app/crates/core/src/lib.rs"), Some(PathBuf::from("/work/app/crates/core")));
+        assert_eq!(deepest("/work/app/./src"), Some(PathBuf::from("/work/app")));
+        assert_eq!(deepest("/work/app-b"), Some(PathBuf::from("/work")));
+        assert_eq!(deepest("/other"), None);
+    }
+
+    #[test]
+    fn test_canonical_paths() {
+        let dir = std::env::temp_dir().join(format!("canonical-{}", uuid::Uuid::new_v4()));
+        let project = dir.join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        let resolved = canonical(&project);
+
+        assert_eq!(canonical(&dir.join(".").join("project")), resolved);
+        assert_eq!(canonical(&project.join("missing/src")), resolved.join("missing").join("src"));
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&project, dir.join("link")).unwrap();
+            assert_eq!(canonical(&dir.join("link")), resolved);
+            assert_eq!(canonical(&dir.join("link/new")), resolved.join("new"));
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
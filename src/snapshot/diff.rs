@@ -0,0 +1,205 @@
+//! Symbol-level comparison of two indexes of a project (`diff_index`)
+
+use super::{unix_now, FileEntry, SymbolEntry};
+use crate::paths;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Indexed files and symbols of a project, as exported for later comparison
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexManifest {
+    pub project_root: PathBuf,
+    pub exported_at: u64,
+    /// By slash-separated path relative to the project root
+    pub files: BTreeMap<String, ManifestFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub hash: String,
+    pub symbols: Vec<SymbolEntry>,
+}
+
+impl IndexManifest {
+    pub fn new(project_root: &Path, files: &HashMap<PathBuf, FileEntry>) -> Self {
+        Self {
+            project_root: project_root.to_path_buf(),
+            exported_at: unix_now(),
+            files: files
+                .iter()
+                .map(|(path, entry)| {
+                    let file = ManifestFile {
+                        hash: entry.hash.clone(),
+                        symbols: entry.symbols.clone(),
+                    };
+                    (paths::to_slash(path), file)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = std::fs::read_to_string(path).with_context(|| format!("Failed to read index export: {}", path.display()))?;
+        serde_json::from_str(&data).with_context(|| format!("Not an index export: {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write index export: {}", path.display()))
+    }
+}
+
+/// A symbol that was added, removed or changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SymbolChange {
+    pub file: String,
+    pub kind: String,
+    pub name: String,
+    /// Line in the newer index (the older one for removed symbols)
+    pub start_line: usize,
+}
+
+/// Differences between an older and a newer index of a project
+#[derive(Debug, Default, Serialize)]
+pub struct IndexDiff {
+    pub added_files: Vec<String>,
+    pub removed_files: Vec<String>,
+    pub changed_files: Vec<String>,
+    pub added_symbols: Vec<SymbolChange>,
+    pub removed_symbols: Vec<SymbolChange>,
+    pub changed_symbols: Vec<SymbolChange>,
+}
+
+impl IndexDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_files.is_empty() && self.removed_files.is_empty() && self.changed_files.is_empty()
+    }
+}
+
+/// Compare two indexes file by file, then symbol by symbol within changed
+/// files. Symbols are matched by kind and name (and by order among
+/// same-named ones, such as `new` in several impls).
+pub fn diff(old: &IndexManifest, new: &IndexManifest) -> IndexDiff {
+    let mut result = IndexDiff::default();
+    let empty = Vec::new();
+
+    for (path, new_file) in &new.files {
+        let old_symbols = match old.files.get(path) {
+            Some(old_file) if old_file.hash == new_file.hash => continue,
+            Some(old_file) => {
+                result.changed_files.push(path.clone());
+                &old_file.symbols
+            }
+            None => {
+                result.added_files.push(path.clone());
+                &empty
+            }
+        };
+        diff_symbols(&mut result, path, old_symbols, &new_file.symbols);
+    }
+    for (path, old_file) in &old.files {
+        if !new.files.contains_key(path) {
+            result.removed_files.push(path.clone());
+            diff_symbols(&mut result, path, &old_file.symbols, &empty);
+        }
+    }
+    result
+}
+
+fn diff_symbols(result: &mut IndexDiff, file: &str, old: &[SymbolEntry], new: &[SymbolEntry]) {
+    let old = keyed(old);
+    let new = keyed(new);
+    let change = |s: &SymbolEntry| SymbolChange {
+        file: file.to_string(),
+        kind: s.kind.clone(),
+        name: s.name.clone(),
+        start_line: s.start_line,
+    };
+
+    for (key, symbol) in &new {
+        match old.get(key) {
+            Some(previous) if previous.hash != symbol.hash => result.changed_symbols.push(change(symbol)),
+            Some(_) => {}
+            None => result.added_symbols.push(change(symbol)),
+        }
+    }
+    for (key, symbol) in &old {
+        if !new.contains_key(key) {
+            result.removed_symbols.push(change(symbol));
+        }
+    }
+}
+
+/// Symbols by (kind, name, occurrence), in source order
+fn keyed(symbols: &[SymbolEntry]) -> BTreeMap<(&str, &str, usize), &SymbolEntry> {
+    let mut seen: HashMap<(&str, &str), usize> = HashMap::new();
+    let mut ordered: Vec<&SymbolEntry> = symbols.iter().collect();
+    ordered.sort_by_key(|s| s.start_line);
+    ordered
+        .into_iter()
+        .map(|s| {
+            let occurrence = seen.entry((s.kind.as_str(), s.name.as_str())).or_default();
+            *occurrence += 1;
+            ((s.kind.as_str(), s.name.as_str(), *occurrence), s)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str, hash: &str, start_line: usize) -> SymbolEntry {
+        SymbolEntry {
+            name: name.to_string(),
+            kind: "function".to_string(),
+            hash: hash.to_string(),
+            start_line,
+            end_line: None,
+        }
+    }
+
+    fn manifest(files: &[(&str, &str, Vec<SymbolEntry>)]) -> IndexManifest {
+        IndexManifest {
+            project_root: PathBuf::from("/project"),
+            exported_at: 0,
+            files: files
+                .iter()
+                .map(|(path, hash, symbols)| {
+                    let file = ManifestFile {
+                        hash: hash.to_string(),
+                        symbols: symbols.clone(),
+                    };
+                    (path.to_string(), file)
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_diff_symbols() {
+        let old = manifest(&[
+            ("src/lib.rs", "a", vec![symbol("parse", "1", 0), symbol("render", "2", 10)]),
+            ("src/old.rs", "b", vec![symbol("legacy", "3", 0)]),
+            ("src/same.rs", "c", vec![symbol("keep", "4", 0)]),
+        ]);
+        let new = manifest(&[
+            ("src/lib.rs", "a2", vec![symbol("parse", "1", 0), symbol("render", "5", 12), symbol("layout", "6", 30)]),
+            ("src/same.rs", "c", vec![symbol("keep", "4", 0)]),
+        ]);
+
+        let diff = diff(&old, &new);
+        assert_eq!(diff.changed_files, vec!["src/lib.rs"]);
+        assert_eq!(diff.removed_files, vec!["src/old.rs"]);
+        assert!(diff.added_files.is_empty());
+        let names = |changes: &[SymbolChange]| changes.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&diff.changed_symbols), vec!["render"]);
+        assert_eq!(names(&diff.added_symbols), vec!["layout"]);
+        assert_eq!(names(&diff.removed_symbols), vec!["legacy"]);
+    }
+}
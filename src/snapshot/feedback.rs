@@ -0,0 +1,136 @@
+//! Relevance feedback on search results (`rate_result`)
+
+use super::unix_now;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Largest score change feedback can apply to a chunk
+pub const MAX_FEEDBACK_ADJUSTMENT: f32 = 0.05;
+
+/// Ratings kept for recent-quality statistics
+const MAX_RECENT_RATINGS: usize = 200;
+
+/// Hex characters of the content hash identifying a rated chunk
+pub const CHUNK_ID_LEN: usize = 12;
+
+/// Ratings of one chunk
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChunkFeedback {
+    pub relevant: u32,
+    pub irrelevant: u32,
+    /// Location of the chunk when last rated, for reporting
+    pub location: String,
+}
+
+/// A single rating, kept for recent-quality statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rating {
+    pub timestamp: u64,
+    pub chunk_id: String,
+    pub relevant: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+}
+
+/// Feedback collected for one project
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchFeedback {
+    /// Ratings per chunk id (content hash prefix)
+    pub chunks: HashMap<String, ChunkFeedback>,
+    /// Most recent ratings, oldest first
+    pub recent: VecDeque<Rating>,
+}
+
+impl SearchFeedback {
+    /// Chunk id of a content hash (or of an id that is already short)
+    pub fn chunk_id(content_hash: &str) -> String {
+        content_hash.chars().take(CHUNK_ID_LEN).collect()
+    }
+
+    pub fn record(&mut self, chunk_id: &str, location: String, relevant: bool, query: Option<String>) {
+        let chunk = self.chunks.entry(chunk_id.to_string()).or_default();
+        if relevant {
+            chunk.relevant += 1;
+        } else {
+            chunk.irrelevant += 1;
+        }
+        chunk.location = location;
+
+        if self.recent.len() == MAX_RECENT_RATINGS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(Rating {
+            timestamp: unix_now(),
+            chunk_id: chunk_id.to_string(),
+            relevant,
+            query,
+        });
+    }
+
+    /// Score adjustment for a chunk: the net vote share, scaled so a chunk
+    /// only approaches the maximum after several consistent ratings
+    pub fn adjustment(&self, chunk_id: &str) -> f32 {
+        match self.chunks.get(chunk_id) {
+            Some(c) => {
+                let net = c.relevant as f32 - c.irrelevant as f32;
+                MAX_FEEDBACK_ADJUSTMENT * net / (c.relevant + c.irrelevant + 1) as f32
+            }
+            None => 0.0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Totals over all ratings: (relevant, irrelevant)
+    pub fn totals(&self) -> (u32, u32) {
+        self.chunks
+            .values()
+            .fold((0, 0), |(r, i), c| (r + c.relevant, i + c.irrelevant))
+    }
+
+    /// Relevant share of the last `window` ratings: (relevant, rated)
+    pub fn recent_precision(&self, window: usize) -> (usize, usize) {
+        let recent: Vec<_> = self.recent.iter().rev().take(window).collect();
+        (recent.iter().filter(|r| r.relevant).count(), recent.len())
+    }
+
+    /// Chunks rated irrelevant more often than relevant, worst first
+    pub fn most_downvoted(&self, limit: usize) -> Vec<(&str, &ChunkFeedback)> {
+        let mut chunks: Vec<_> = self
+            .chunks
+            .iter()
+            .filter(|(_, c)| c.irrelevant > c.relevant)
+            .map(|(id, c)| (id.as_str(), c))
+            .collect();
+        chunks.sort_by_key(|(id, c)| (std::cmp::Reverse(c.irrelevant - c.relevant), *id));
+        chunks.truncate(limit);
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feedback_adjustment_and_precision() {
+        let mut feedback = SearchFeedback::default();
+        let id = SearchFeedback::chunk_id("0123456789abcdef0123");
+        assert_eq!(id, "0123456789ab");
+        assert_eq!(feedback.adjustment(&id), 0.0);
+
+        for _ in 0..3 {
+            feedback.record(&id, "src/lib.rs:1-10".to_string(), false, None);
+        }
+        feedback.record("other", "src/main.rs:5-8".to_string(), true, Some("entry point".to_string()));
+
+        assert!(feedback.adjustment(&id) < 0.0);
+        assert!(feedback.adjustment(&id) > -MAX_FEEDBACK_ADJUSTMENT);
+        assert!(feedback.adjustment("other") > 0.0);
+        assert_eq!(feedback.totals(), (1, 3));
+        assert_eq!(feedback.recent_precision(2), (1, 2));
+        assert_eq!(feedback.most_downvoted(5)[0].0, id);
+    }
+}
@@ -0,0 +1,51 @@
+use super::{Snapshot, SnapshotChanges, SnapshotStore};
+use anyhow::Result;
+use std::path::PathBuf;
+use tokio::fs;
+
+/// Snapshot store that keeps the whole snapshot in a single JSON file.
+///
+/// Every save rewrites the file, which is fine for typical projects but
+/// gets expensive for very large monorepos (see `SqliteSnapshotStore`).
+pub struct JsonSnapshotStore {
+    path: PathBuf,
+}
+
+impl JsonSnapshotStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+#[async_trait::async_trait]
+impl SnapshotStore for JsonSnapshotStore {
+    async fn load(&self) -> Result<Snapshot> {
+        if !self.path.exists() {
+            return Ok(Snapshot::default());
+        }
+        let data = fs::read_to_string(&self.path).await?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    async fn save(&self, snapshot: &Snapshot, _changes: &SnapshotChanges) -> Result<()> {
+        let data = serde_json::to_string_pretty(snapshot)?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        fs::write(&self.path, data).await?;
+        Ok(())
+    }
+
+    async fn remove(&self) -> Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path).await?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "json"
+    }
+}
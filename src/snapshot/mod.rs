@@ -1,13 +1,103 @@
-use anyhow::Result;
+pub mod diff;
+pub mod feedback;
+pub mod json;
+pub mod sqlite;
+pub mod staleness;
+pub mod symbols;
+pub mod usage;
+
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use tokio::fs;
-use tokio::sync::RwLock;
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::parser::{ChunkingOptions, ParseFallback};
+use crate::paths;
+use crate::vector_db::router::CollectionRoutes;
+use feedback::SearchFeedback;
+use usage::{EmbeddingUsage, ModelUsage};
+use json::JsonSnapshotStore;
+use sqlite::SqliteSnapshotStore;
 
 /// Default maximum number of indexed projects
 pub const DEFAULT_MAX_PROJECTS: usize = 10;
 
+/// Policy applied when indexing a new project would exceed the configured limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evict the least recently used project once `max_projects` is reached
+    Lru,
+    /// Evict least recently used projects while the total chunk count exceeds the budget
+    Size { max_chunks: usize },
+    /// Never evict; indexing a new project fails once `max_projects` is reached
+    Disabled,
+    /// Like `Lru`, but the caller must confirm before a project is dropped
+    Confirm,
+}
+
+impl EvictionPolicy {
+    /// Parse a policy name (`lru`, `size`, `disabled`, `confirm`)
+    pub fn parse(name: &str, max_chunks: Option<usize>) -> Result<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "lru" => Ok(EvictionPolicy::Lru),
+            "size" => {
+                let max_chunks = max_chunks
+                    .ok_or_else(|| anyhow::anyhow!("EVICTION_POLICY=size requires MAX_INDEXED_CHUNKS"))?;
+                Ok(EvictionPolicy::Size { max_chunks })
+            }
+            "disabled" | "none" => Ok(EvictionPolicy::Disabled),
+            "confirm" => Ok(EvictionPolicy::Confirm),
+            other => anyhow::bail!("Unknown eviction policy '{}', expected lru, size, disabled or confirm", other),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EvictionPolicy::Lru => "lru",
+            EvictionPolicy::Size { .. } => "size",
+            EvictionPolicy::Disabled => "disabled",
+            EvictionPolicy::Confirm => "confirm",
+        }
+    }
+}
+
+/// Overview of an indexed project, for the status of all projects
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectSummary {
+    pub project_root: PathBuf,
+    pub collection_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    pub files: usize,
+    pub chunks: usize,
+    /// Unix seconds
+    pub last_accessed_at: u64,
+    pub pinned: bool,
+    #[serde(skip_serializing_if = "EmbeddingUsage::is_empty")]
+    pub usage: EmbeddingUsage,
+}
+
+/// Which projects the eviction policy drops next
+#[derive(Debug, Clone, Serialize)]
+pub struct EvictionForecast {
+    /// `lru`, `size`, `disabled` or `confirm`
+    pub policy: &'static str,
+    pub max_projects: usize,
+    /// Chunk budget of the `size` policy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_chunks: Option<usize>,
+    /// First in line: the least recently accessed unpinned project, `None`
+    /// when every project is pinned or eviction is disabled
+    pub next: Option<PathBuf>,
+    /// Projects evicted if another project were indexed now, oldest first
+    pub on_next_index: Vec<PathBuf>,
+}
+
+/// Default number of indexed files above which the snapshot moves to SQLite
+pub const DEFAULT_SQLITE_THRESHOLD: usize = 50_000;
+
 /// Snapshot of indexed files with their hashes
 /// Supports multiple projects (roots), each with its own collection
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -16,39 +106,311 @@ pub struct Snapshot {
     pub roots: HashMap<PathBuf, RootInfo>,
 }
 
+impl Snapshot {
+    /// Rewrite file keys stored as absolute paths (older snapshots) to paths
+    /// relative to their project root. Returns true if anything changed.
+    pub fn relativize_file_paths(&mut self) -> bool {
+        let mut changed = false;
+        for (root_path, root) in self.roots.iter_mut() {
+            if !root.files.keys().any(|p| p.is_absolute()) {
+                continue;
+            }
+            root.files = std::mem::take(&mut root.files)
+                .into_iter()
+                .map(|(path, entry)| match path.strip_prefix(root_path) {
+                    Ok(relative) => (relative.to_path_buf(), entry),
+                    Err(_) => (path, entry),
+                })
+                .collect();
+            changed = true;
+        }
+        changed
+    }
+
+    /// Re-key project roots recorded under a symlinked or `.` spelling
+    /// (older snapshots) by their canonical path. When two roots turn out to
+    /// be the same project, the more recently indexed one is kept and the
+    /// other's collection is returned, as it is no longer referenced.
+    pub fn canonicalize_roots(&mut self) -> (bool, Vec<String>) {
+        let stale: Vec<PathBuf> = self
+            .roots
+            .keys()
+            .filter(|root| paths::canonical(root) != **root)
+            .cloned()
+            .collect();
+        let mut orphaned = Vec::new();
+        for old in &stale {
+            let Some(mut root) = self.roots.remove(old) else { continue };
+            root.workspace_root = root.workspace_root.map(|w| paths::canonical(&w));
+            let canonical = paths::canonical(old);
+            match self.roots.remove(&canonical) {
+                Some(existing) if existing.indexed_at >= root.indexed_at => {
+                    orphaned.push(root.collection_name);
+                    self.roots.insert(canonical, existing);
+                }
+                Some(existing) => {
+                    orphaned.push(existing.collection_name);
+                    self.roots.insert(canonical, root);
+                }
+                None => {
+                    self.roots.insert(canonical, root);
+                }
+            }
+        }
+        (!stale.is_empty(), orphaned)
+    }
+}
+
+/// Per-project settings captured at index time and honored by later
+/// searches and re-indexes of the same project
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectSettings {
+    /// Embedding model the project was indexed with
+    pub embedding_model: Option<String>,
+    /// Extra glob patterns excluded from indexing
+    pub ignore_globs: Vec<String>,
+    /// How files are split into chunks
+    pub chunking: ChunkingOptions,
+    /// Pinned projects are never evicted
+    pub pinned: bool,
+    /// Redact secrets from chunk content (`None` follows the server config)
+    pub redact: Option<bool>,
+    /// Encrypt stored chunk content (`None` follows the server config)
+    pub encrypt: Option<bool>,
+    /// Index text files inside archives (`.jar`, `.zip`, `.tar.gz`, ...)
+    pub archives: bool,
+    /// Descend into git submodules (listed in `.gitmodules`); `None` does
+    pub submodules: Option<bool>,
+    /// Store the last commit, author and date of each chunk (git blame)
+    pub blame: bool,
+    /// Ask the configured language servers to name symbols tree-sitter
+    /// couldn't
+    pub lsp: bool,
+    /// Store each language's chunks in its own partition of the collection,
+    /// so language-filtered searches only scan that language
+    pub partition_by_language: bool,
+    /// Maximum indexed file size in MB (`None` follows the server config)
+    pub max_file_size_mb: Option<f64>,
+    /// Per-extension size limits in MB, on top of the server's
+    pub max_file_size_by_extension: BTreeMap<String, f64>,
+    /// Filters `search_code` applies to this project when the call doesn't
+    pub search_defaults: SearchDefaults,
+    /// Git URL of a remote repository; the project root is its managed clone
+    pub remote: Option<String>,
+    /// Keep the superseded versions of changed chunks, searchable with `as_of`
+    pub keep_history: bool,
+    /// Index vendored dependencies into the collection shared by all
+    /// projects instead of the project's own
+    pub shared_deps: bool,
+    /// Also embed each chunk's doc comments on their own, searched along
+    /// with the code
+    pub doc_vectors: bool,
+}
+
+/// An index run of a project that keeps history, a point `as_of` searches
+/// can go back to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexPoint {
+    /// When the run started (Unix seconds), the `indexed_at` of its chunks
+    pub at: u64,
+    /// Git commit checked out at the time, if the project is a repository
+    #[serde(default)]
+    pub commit: Option<String>,
+}
+
+/// Vendored dependency files of a project indexed with `shared_deps`,
+/// stored in a collection shared with other projects instead of its own
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VendoredDeps {
+    /// Shared collection of the project's embedding model
+    pub collection_name: String,
+    /// Content hash of each vendored file, keyed by path relative to the root
+    pub files: HashMap<PathBuf, String>,
+}
+
+/// Default `search_code` arguments of a project (set with `set_project_config`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchDefaults {
+    pub language: Option<String>,
+    /// `code`, `docs` or `all`
+    pub scope: Option<String>,
+    pub module: Option<String>,
+    pub limit: Option<usize>,
+}
+
 /// Information about a single project root
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RootInfo {
     /// Collection name for this project in Milvus
     pub collection_name: String,
-    /// Files indexed in this project
+    /// Files indexed in this project, keyed by path relative to the root
     pub files: HashMap<PathBuf, FileEntry>,
     /// Last index timestamp
     pub indexed_at: u64,
     /// Last access timestamp (for LRU eviction)
     pub last_accessed_at: u64,
+    /// Settings captured at index time
+    #[serde(default)]
+    pub settings: ProjectSettings,
+    /// Monorepo root this project was indexed from as a sub-project
+    #[serde(default)]
+    pub workspace_root: Option<PathBuf>,
+    /// The last index run stopped early (time budget); indexing again resumes it
+    #[serde(default)]
+    pub partial: bool,
+    /// Short name accepted in place of the root path by the tools
+    #[serde(default)]
+    pub alias: Option<String>,
+    /// Files left out of the index in the last walk, keyed by relative path
+    #[serde(default)]
+    pub skipped: HashMap<PathBuf, SkipReason>,
+    /// Named composite projects this root belongs to
+    #[serde(default)]
+    pub composites: BTreeSet<String>,
+    /// Relevance ratings of this project's search results
+    #[serde(default)]
+    pub feedback: SearchFeedback,
+    /// Index runs recorded while the project keeps history, oldest first
+    #[serde(default)]
+    pub index_points: Vec<IndexPoint>,
+    /// Vendored files stored in the shared dependency collection
+    #[serde(default)]
+    pub deps: Option<VendoredDeps>,
+    /// Named vector database instance holding the collection, the default
+    /// one (`milvus_address`) if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector_db: Option<String>,
+    /// Vector database backend (`VECTOR_DB`) the collection was created on,
+    /// so a project isn't taken as indexed after a switch to another one
+    /// (unknown for projects indexed before it was recorded)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector_db_backend: Option<String>,
+    /// Dimension of the stored vectors, recorded with the embedding model
+    /// (`settings.embedding_model`) so searches with a model producing
+    /// other vectors are refused (unknown for projects indexed before it
+    /// was recorded)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_dimension: Option<usize>,
+    /// Embedding requests and tokens spent indexing the project, per model
+    #[serde(default, skip_serializing_if = "EmbeddingUsage::is_empty")]
+    pub usage: EmbeddingUsage,
 }
 
 impl RootInfo {
     pub fn new(collection_name: String) -> Self {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        let now = unix_now();
         Self {
             collection_name,
             files: HashMap::new(),
             indexed_at: now,
             last_accessed_at: now,
+            settings: ProjectSettings::default(),
+            workspace_root: None,
+            partial: false,
+            alias: None,
+            skipped: HashMap::new(),
+            composites: BTreeSet::new(),
+            feedback: SearchFeedback::default(),
+            index_points: Vec::new(),
+            deps: None,
+            vector_db: None,
+            vector_db_backend: None,
+            embedding_dimension: None,
+            usage: EmbeddingUsage::default(),
         }
     }
 
+    /// Copy of the root metadata without the (potentially huge) file map
+    pub fn clone_without_files(&self) -> Self {
+        let RootInfo {
+            collection_name,
+            files: _,
+            indexed_at,
+            last_accessed_at,
+            settings,
+            workspace_root,
+            partial,
+            alias,
+            skipped,
+            composites,
+            feedback,
+            index_points,
+            deps,
+            vector_db,
+            vector_db_backend,
+            embedding_dimension,
+            usage,
+        } = self;
+        Self {
+            collection_name: collection_name.clone(),
+            files: HashMap::new(),
+            indexed_at: *indexed_at,
+            last_accessed_at: *last_accessed_at,
+            settings: settings.clone(),
+            workspace_root: workspace_root.clone(),
+            partial: *partial,
+            alias: alias.clone(),
+            skipped: skipped.clone(),
+            composites: composites.clone(),
+            feedback: feedback.clone(),
+            index_points: index_points.clone(),
+            deps: deps.clone(),
+            vector_db: vector_db.clone(),
+            vector_db_backend: vector_db_backend.clone(),
+            embedding_dimension: *embedding_dimension,
+            usage: usage.clone(),
+        }
+    }
+
+    /// Total number of chunks indexed for this project
+    pub fn chunk_count(&self) -> usize {
+        self.files.values().map(|f| f.chunk_count).sum()
+    }
+
+    /// Approximate vector database storage used by this project, in bytes
+    pub fn stored_bytes(&self) -> u64 {
+        self.files.values().map(|f| f.stored_bytes).sum()
+    }
+
     /// Update last accessed timestamp
     pub fn touch(&mut self) {
-        self.last_accessed_at = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        self.last_accessed_at = unix_now();
+    }
+}
+
+/// Why a file was left out of the index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Larger than the maximum indexed file size
+    TooLarge,
+    /// Not valid UTF-8 text
+    Binary,
+    /// Metadata could not be read
+    Unreadable,
+    /// The parser failed on the file
+    ParseFailure,
+    /// Parsed into zero chunks
+    Empty,
+    /// No chunk could be embedded or stored
+    EmbeddingFailed,
+    /// Matched one of the project's ignore globs
+    Ignored,
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::TooLarge => "too large",
+            SkipReason::Binary => "binary",
+            SkipReason::Unreadable => "unreadable",
+            SkipReason::ParseFailure => "parse failure",
+            SkipReason::Empty => "no chunks",
+            SkipReason::EmbeddingFailed => "embedding failed",
+            SkipReason::Ignored => "ignored",
+        }
     }
 }
 
@@ -56,14 +418,137 @@ impl RootInfo {
 pub struct FileEntry {
     pub hash: String,
     pub chunk_count: usize,
+    /// Approximate bytes the file's chunks take in the vector database
+    /// (unknown, counted as 0, for files indexed before it was recorded)
+    #[serde(default)]
+    pub stored_bytes: u64,
     pub indexed_at: u64,
+    /// Named symbols of the file, for `diff_index`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<SymbolEntry>,
+    /// Embedding model, chunking, content transforms (such as redaction) and
+    /// extra metadata (blame) the file was stored with (unknown for files indexed before it was
+    /// recorded)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// Why the file was chunked into line windows although it has a grammar
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parse_fallback: Option<ParseFallback>,
+    /// Stored chunks in file order, each linked to the previous and next
+    /// one for `get_chunk_context` (empty for files indexed before it was
+    /// recorded)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<ChunkSpan>,
+}
+
+impl FileEntry {
+    /// Entry for a file indexed now
+    pub fn new(hash: String, chunk_count: usize) -> Self {
+        Self {
+            hash,
+            chunk_count,
+            stored_bytes: 0,
+            indexed_at: unix_now(),
+            symbols: Vec::new(),
+            fingerprint: None,
+            parse_fallback: None,
+            chunks: Vec::new(),
+        }
+    }
+
+    /// Position of a chunk in `chunks`
+    pub fn chunk_position(&self, chunk_id: &str) -> Option<usize> {
+        self.chunks.iter().position(|c| c.id == chunk_id)
+    }
+
+    /// Rows the file takes in the collection: one per distinct chunk id,
+    /// since repeats of a chunk share a row. Files recorded before chunk
+    /// ids were kept count one row per chunk.
+    pub fn stored_rows(&self) -> usize {
+        if self.chunks.is_empty() {
+            return self.chunk_count;
+        }
+        self.chunks.iter().map(|c| c.id.as_str()).collect::<HashSet<_>>().len()
+    }
+
+    /// Whether the file is older than `max_age` seconds and due to be
+    /// re-validated. Each file's deadline is pushed back by up to half of
+    /// `max_age` (derived from its hash), so files indexed together don't
+    /// all fall due in the same refresh.
+    pub fn is_due(&self, now: u64, max_age: u64) -> bool {
+        let seed = u64::from_str_radix(self.hash.get(..8).unwrap_or_default(), 16).unwrap_or_default();
+        let jitter = seed % (max_age / 2).max(1);
+        now.saturating_sub(self.indexed_at) >= max_age.saturating_add(jitter)
+    }
+}
+
+/// A stored chunk of an indexed file, by the id search results show
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkSpan {
+    pub id: String,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// A named symbol chunk of an indexed file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolEntry {
+    pub name: String,
+    pub kind: String,
+    /// Hash prefix of the symbol's content
+    pub hash: String,
+    pub start_line: usize,
+    /// Unknown for files indexed before it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<usize>,
+}
+
+/// Changes made to the snapshot since the last save
+///
+/// Stores that rewrite everything on save can ignore this; incremental
+/// stores use it to only touch the affected rows.
+#[derive(Debug, Default)]
+pub struct SnapshotChanges {
+    /// Rewrite everything regardless of the tracked changes
+    pub full: bool,
+    /// (project root, file path) pairs that were added, updated or removed
+    pub files: HashSet<(PathBuf, PathBuf)>,
+    /// Project roots whose metadata (everything but the files) changed
+    pub roots: HashSet<PathBuf>,
+    /// Project roots that were removed (their files must be dropped)
+    pub removed_roots: HashSet<PathBuf>,
+}
+
+impl SnapshotChanges {
+    fn merge(&mut self, other: SnapshotChanges) {
+        self.full |= other.full;
+        self.files.extend(other.files);
+        self.roots.extend(other.roots);
+        self.removed_roots.extend(other.removed_roots);
+    }
+}
+
+/// Persistent storage backend for snapshots
+#[async_trait::async_trait]
+pub trait SnapshotStore: Send + Sync {
+    async fn load(&self) -> Result<Snapshot>;
+    async fn save(&self, snapshot: &Snapshot, changes: &SnapshotChanges) -> Result<()>;
+    /// Delete all persisted data (used after migrating to another store)
+    async fn remove(&self) -> Result<()>;
+    fn name(&self) -> &'static str;
 }
 
 /// Manages snapshots for incremental indexing with multi-project support
 pub struct SnapshotManager {
     snapshot_path: PathBuf,
     snapshot: RwLock<Snapshot>,
+    store: RwLock<Box<dyn SnapshotStore>>,
+    changes: Mutex<SnapshotChanges>,
     max_projects: usize,
+    eviction_policy: EvictionPolicy,
+    sqlite_threshold: usize,
+    /// Vector database instance of each collection not on the default one
+    routes: Arc<CollectionRoutes>,
 }
 
 impl SnapshotManager {
@@ -73,34 +558,146 @@ impl SnapshotManager {
     }
 
     pub fn new_with_max_projects(snapshot_path: PathBuf, max_projects: usize) -> Result<Self> {
+        let store = Self::open_store(&snapshot_path)?;
+        tracing::debug!("Using {} snapshot store", store.name());
+
         Ok(Self {
             snapshot_path,
             snapshot: RwLock::new(Snapshot::default()),
+            store: RwLock::new(store),
+            changes: Mutex::new(SnapshotChanges::default()),
             max_projects,
+            eviction_policy: EvictionPolicy::Lru,
+            sqlite_threshold: DEFAULT_SQLITE_THRESHOLD,
+            routes: Arc::new(CollectionRoutes::default()),
         })
     }
 
+    /// Routes of the collections on named vector database instances, kept
+    /// up to date from the snapshot for a `RoutedVectorDatabase`
+    pub fn collection_routes(&self) -> Arc<CollectionRoutes> {
+        self.routes.clone()
+    }
+
+    /// Set the policy used when new projects exceed the configured limits
+    pub fn with_eviction_policy(mut self, eviction_policy: EvictionPolicy) -> Self {
+        self.eviction_policy = eviction_policy;
+        self
+    }
+
+    pub fn eviction_policy(&self) -> EvictionPolicy {
+        self.eviction_policy
+    }
+
+    /// Set the file count above which a JSON snapshot is migrated to SQLite
+    pub fn with_sqlite_threshold(mut self, sqlite_threshold: usize) -> Self {
+        self.sqlite_threshold = sqlite_threshold;
+        self
+    }
+
+    /// Pick the store for a snapshot path: an explicit `.db` path or an
+    /// existing SQLite database next to the JSON file wins over JSON.
+    fn open_store(snapshot_path: &Path) -> Result<Box<dyn SnapshotStore>> {
+        let db_path = Self::sqlite_path(snapshot_path);
+        if snapshot_path.extension().is_some_and(|e| e == "db") || db_path.exists() {
+            Ok(Box::new(SqliteSnapshotStore::open(&db_path)?))
+        } else {
+            Ok(Box::new(JsonSnapshotStore::new(snapshot_path.to_path_buf())))
+        }
+    }
+
+    fn sqlite_path(snapshot_path: &Path) -> PathBuf {
+        snapshot_path.with_extension("db")
+    }
+
     pub async fn load(&self) -> Result<()> {
-        if self.snapshot_path.exists() {
-            let data = fs::read_to_string(&self.snapshot_path).await?;
-            let snapshot = serde_json::from_str(&data)?;
-            *self.snapshot.write().await = snapshot;
+        let mut snapshot = self.store.read().await.load().await?;
+        if snapshot.relativize_file_paths() {
+            tracing::info!("Converted absolute file paths in snapshot to project-relative paths");
+            self.changes.lock().await.full = true;
+        }
+        let (rekeyed, orphaned) = snapshot.canonicalize_roots();
+        if rekeyed {
+            tracing::info!("Converted project roots in snapshot to canonical paths");
+            self.changes.lock().await.full = true;
         }
+        for collection in orphaned {
+            tracing::warn!(
+                "Collection {} belonged to a duplicate of another project's root and is no longer used; drop it from the vector database to reclaim its space",
+                collection
+            );
+        }
+        for root in snapshot.roots.values().filter(|r| r.vector_db.is_some()) {
+            self.routes.set(&root.collection_name, root.vector_db.as_deref());
+        }
+        *self.snapshot.write().await = snapshot;
         Ok(())
     }
 
     pub async fn save(&self) -> Result<()> {
         let snapshot = self.snapshot.read().await;
-        let data = serde_json::to_string_pretty(&*snapshot)?;
-        
-        if let Some(parent) = self.snapshot_path.parent() {
-            fs::create_dir_all(parent).await?;
+        let mut changes = std::mem::take(&mut *self.changes.lock().await);
+
+        let result = async {
+            self.migrate_if_large(&snapshot, &mut changes).await?;
+            self.store.read().await.save(&snapshot, &changes).await
         }
-        
-        fs::write(&self.snapshot_path, data).await?;
+        .await;
+
+        if result.is_err() {
+            // Keep the pending changes so the next save retries them
+            self.changes.lock().await.merge(changes);
+        }
+        result
+    }
+
+    /// Switch from the JSON store to SQLite once the snapshot gets too big
+    /// to comfortably rewrite on every save.
+    async fn migrate_if_large(&self, snapshot: &Snapshot, changes: &mut SnapshotChanges) -> Result<()> {
+        let mut store = self.store.write().await;
+        if store.name() != "json" {
+            return Ok(());
+        }
+
+        let file_count: usize = snapshot.roots.values().map(|r| r.files.len()).sum();
+        if file_count <= self.sqlite_threshold {
+            return Ok(());
+        }
+
+        let db_path = Self::sqlite_path(&self.snapshot_path);
+        tracing::info!(
+            "Snapshot has {} files (threshold {}), migrating to SQLite: {}",
+            file_count,
+            self.sqlite_threshold,
+            db_path.display()
+        );
+
+        let sqlite = SqliteSnapshotStore::open(&db_path)?;
+        changes.full = true;
+        sqlite.save(snapshot, changes).await?;
+        if let Err(e) = store.remove().await {
+            tracing::warn!("Failed to remove old JSON snapshot: {}", e);
+        }
+        *store = Box::new(sqlite);
         Ok(())
     }
 
+    async fn mark_file_changed(&self, project_root: &Path, file_path: &Path) {
+        self.changes
+            .lock()
+            .await
+            .files
+            .insert((project_root.to_path_buf(), file_path.to_path_buf()));
+    }
+
+    async fn mark_root_changed(&self, project_root: &Path) {
+        self.changes.lock().await.roots.insert(project_root.to_path_buf());
+    }
+
+    async fn mark_root_removed(&self, project_root: &Path) {
+        self.changes.lock().await.removed_roots.insert(project_root.to_path_buf());
+    }
+
     /// Get file hash for a specific project
     pub async fn get_file_hash(&self, project_root: &Path, file_path: &Path) -> Option<String> {
         let snapshot = self.snapshot.read().await;
@@ -111,61 +708,526 @@ impl SnapshotManager {
     }
 
     /// Update file info for a specific project
-    pub async fn update_file(&self, project_root: &Path, file_path: PathBuf, hash: String, chunk_count: usize) {
+    pub async fn update_file(&self, project_root: &Path, file_path: PathBuf, entry: FileEntry) {
         let mut snapshot = self.snapshot.write().await;
         if let Some(root) = snapshot.roots.get_mut(project_root) {
-            root.files.insert(file_path, FileEntry {
-                hash,
-                chunk_count,
-                indexed_at: std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs(),
-            });
+            self.mark_file_changed(project_root, &file_path).await;
+            if root.skipped.remove(&file_path).is_some() {
+                self.mark_root_changed(project_root).await;
+            }
+            root.files.insert(file_path, entry);
+        }
+    }
+
+    /// Get the entry recorded for a file of a project
+    pub async fn get_file(&self, project_root: &Path, file_path: &Path) -> Option<FileEntry> {
+        let snapshot = self.snapshot.read().await;
+        snapshot.roots.get(project_root)?.files.get(file_path).cloned()
+    }
+
+    /// File of a project holding a chunk, looked up in `prefer` first (a
+    /// chunk shared by several files is found in the first by path)
+    pub async fn find_chunk(&self, project_root: &Path, chunk_id: &str, prefer: Option<&Path>) -> Option<(PathBuf, FileEntry)> {
+        let snapshot = self.snapshot.read().await;
+        let files = &snapshot.roots.get(project_root)?.files;
+        if let Some((path, entry)) = prefer.and_then(|path| files.get_key_value(path)) {
+            if entry.chunk_position(chunk_id).is_some() {
+                return Some((path.clone(), entry.clone()));
+            }
         }
+        files
+            .iter()
+            .filter(|(_, entry)| entry.chunk_position(chunk_id).is_some())
+            .min_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(path, entry)| (path.clone(), entry.clone()))
     }
 
-    /// Create or get root info for a project
-    /// If max_projects is exceeded, returns the oldest project to evict
-    pub async fn get_or_create_root(&self, project_root: &Path, collection_name: &str) -> (RootInfo, Option<PathBuf>) {
+    /// Record that an unchanged file's chunks were checked and are current,
+    /// restarting its age
+    pub async fn revalidate_file(&self, project_root: &Path, file_path: &Path) {
+        let mut snapshot = self.snapshot.write().await;
+        if let Some(entry) = snapshot.roots.get_mut(project_root).and_then(|r| r.files.get_mut(file_path)) {
+            entry.indexed_at = unix_now();
+            self.mark_file_changed(project_root, file_path).await;
+        }
+    }
+
+    /// Record that a file was left out of the index and why
+    pub async fn record_skipped(&self, project_root: &Path, file_path: PathBuf, reason: SkipReason) {
         let mut snapshot = self.snapshot.write().await;
-        
-        // Check if project already exists
         if let Some(root) = snapshot.roots.get_mut(project_root) {
-            root.touch();
-            return (root.clone(), None);
+            self.mark_root_changed(project_root).await;
+            root.skipped.insert(file_path, reason);
         }
-        
-        // Check if we need to evict oldest project
-        let mut to_evict = None;
-        if snapshot.roots.len() >= self.max_projects {
-            // Find the oldest project (by last_accessed_at)
-            to_evict = snapshot
+    }
+
+    /// Forget recorded skips before a fresh walk re-evaluates every file
+    pub async fn clear_skipped(&self, project_root: &Path) {
+        let mut snapshot = self.snapshot.write().await;
+        if let Some(root) = snapshot.roots.get_mut(project_root) {
+            self.mark_root_changed(project_root).await;
+            root.skipped.clear();
+        }
+    }
+
+    /// Get the files skipped in the last walk of a project
+    pub async fn get_skipped(&self, project_root: &Path) -> HashMap<PathBuf, SkipReason> {
+        let snapshot = self.snapshot.read().await;
+        snapshot
+            .roots
+            .get(project_root)
+            .map(|r| r.skipped.clone())
+            .unwrap_or_default()
+    }
+
+    /// Projects that would have to be evicted to index `project_root` (a
+    /// member of the monorepo at `workspace_root`, if any), oldest first.
+    /// Pinned projects are never candidates, and a monorepo's members don't
+    /// evict each other.
+    pub async fn eviction_candidates(&self, project_root: &Path, workspace_root: Option<&Path>) -> Vec<PathBuf> {
+        let snapshot = self.snapshot.read().await;
+        if !adds_project(&snapshot, project_root, workspace_root) {
+            return Vec::new();
+        }
+        self.evictions_for_new_project(&snapshot)
+    }
+
+    /// Whether indexing `project_root` (a member of the monorepo at
+    /// `workspace_root`, if any) adds a project against `max_projects`
+    pub async fn adds_project(&self, project_root: &Path, workspace_root: Option<&Path>) -> bool {
+        adds_project(&*self.snapshot.read().await, project_root, workspace_root)
+    }
+
+    /// Summary of every indexed project, least recently accessed first
+    pub async fn project_summaries(&self) -> Vec<ProjectSummary> {
+        let snapshot = self.snapshot.read().await;
+        let mut summaries: Vec<ProjectSummary> = snapshot
+            .roots
+            .iter()
+            .map(|(path, root)| ProjectSummary {
+                project_root: path.clone(),
+                collection_name: root.collection_name.clone(),
+                alias: root.alias.clone(),
+                files: root.files.len(),
+                chunks: root.chunk_count(),
+                last_accessed_at: root.last_accessed_at,
+                pinned: root.settings.pinned,
+                usage: root.usage.clone(),
+            })
+            .collect();
+        summaries.sort_by(|a, b| a.last_accessed_at.cmp(&b.last_accessed_at).then_with(|| a.project_root.cmp(&b.project_root)));
+        summaries
+    }
+
+    /// Which projects eviction drops next, and which indexing another
+    /// project now would drop
+    pub async fn eviction_forecast(&self) -> EvictionForecast {
+        let snapshot = self.snapshot.read().await;
+        let next = match self.eviction_policy {
+            EvictionPolicy::Disabled => None,
+            _ => snapshot
                 .roots
                 .iter()
-                .min_by_key(|(_, root)| root.last_accessed_at)
-                .map(|(path, _)| path.clone());
+                .filter(|(_, root)| !root.settings.pinned)
+                .min_by_key(|(path, root)| (root.last_accessed_at, (*path).clone()))
+                .map(|(path, _)| path.clone()),
+        };
+        EvictionForecast {
+            policy: self.eviction_policy.as_str(),
+            max_projects: self.max_projects,
+            max_chunks: match self.eviction_policy {
+                EvictionPolicy::Size { max_chunks } => Some(max_chunks),
+                _ => None,
+            },
+            next,
+            on_next_index: self.evictions_for_new_project(&snapshot),
+        }
+    }
+
+    /// Projects evicted to make room for a project not indexed yet
+    fn evictions_for_new_project(&self, snapshot: &Snapshot) -> Vec<PathBuf> {
+        let mut unpinned: Vec<_> = snapshot
+            .roots
+            .iter()
+            .filter(|(_, root)| !root.settings.pinned)
+            .collect();
+        unpinned.sort_by_key(|(_, root)| root.last_accessed_at);
+
+        match self.eviction_policy {
+            EvictionPolicy::Disabled => Vec::new(),
+            EvictionPolicy::Lru | EvictionPolicy::Confirm => {
+                if project_count(snapshot) < self.max_projects {
+                    return Vec::new();
+                }
+                // A monorepo is evicted with all of its members, unless one is pinned
+                let Some((oldest_path, oldest)) = unpinned.into_iter().find(|(path, root)| {
+                    let unit = project_unit(path, root);
+                    !snapshot.roots.iter().any(|(p, r)| r.settings.pinned && project_unit(p, r) == unit)
+                }) else {
+                    return Vec::new();
+                };
+                let unit = project_unit(oldest_path, oldest);
+                let mut members: Vec<PathBuf> = snapshot
+                    .roots
+                    .iter()
+                    .filter(|(path, root)| project_unit(path, root) == unit)
+                    .map(|(path, _)| path.clone())
+                    .collect();
+                members.sort();
+                members
+            }
+            EvictionPolicy::Size { max_chunks } => {
+                let mut total: usize = snapshot.roots.values().map(|r| r.chunk_count()).sum();
+                let mut to_evict = Vec::new();
+                for (path, root) in unpinned {
+                    if total < max_chunks {
+                        break;
+                    }
+                    total -= root.chunk_count();
+                    to_evict.push(path.clone());
+                }
+                to_evict
+            }
+        }
+    }
+
+    /// Get a copy of the file entries recorded for a project
+    pub async fn get_files(&self, project_root: &Path) -> Option<HashMap<PathBuf, FileEntry>> {
+        let snapshot = self.snapshot.read().await;
+        snapshot.roots.get(project_root).map(|root| root.files.clone())
+    }
+
+    /// Remove a file entry from a project
+    pub async fn remove_file(&self, project_root: &Path, file_path: &Path) -> Option<FileEntry> {
+        let mut snapshot = self.snapshot.write().await;
+        let removed = snapshot.roots.get_mut(project_root)?.files.remove(file_path);
+        if removed.is_some() {
+            self.mark_file_changed(project_root, file_path).await;
+        }
+        removed
+    }
+
+    /// Forget all indexed files of a project, so they are all indexed again,
+    /// and the index points of its history
+    pub async fn clear_files(&self, project_root: &Path) {
+        let mut snapshot = self.snapshot.write().await;
+        let Some(root) = snapshot.roots.get_mut(project_root) else {
+            return;
+        };
+        root.index_points.clear();
+        self.mark_root_changed(project_root).await;
+        for (file_path, _) in root.files.drain() {
+            self.mark_file_changed(project_root, &file_path).await;
+        }
+    }
+
+    /// Create or get root info for a project (an existing one keeps its
+    /// access time; see [`touch_project`](Self::touch_project))
+    pub async fn get_or_create_root(&self, project_root: &Path, collection_name: &str) -> RootInfo {
+        let mut snapshot = self.snapshot.write().await;
+        
+        // Check if project already exists
+        self.mark_root_changed(project_root).await;
+        if let Some(root) = snapshot.roots.get(project_root) {
+            return root.clone();
         }
         
         let new_root = RootInfo::new(collection_name.to_string());
         snapshot.roots.insert(project_root.to_path_buf(), new_root.clone());
         
-        (new_root, to_evict)
+        new_root
     }
 
-    /// Update last accessed time for a project (called on search)
-    #[allow(dead_code)] // Reserved for future use - could be called on search
+    /// Update last accessed time for a project (called on index)
     pub async fn touch_project(&self, project_root: &Path) {
         let mut snapshot = self.snapshot.write().await;
         if let Some(root) = snapshot.roots.get_mut(project_root) {
+            self.mark_root_changed(project_root).await;
             root.touch();
         }
     }
 
+    /// Get the persisted settings for a project
+    pub async fn get_settings(&self, project_root: &Path) -> Option<ProjectSettings> {
+        let snapshot = self.snapshot.read().await;
+        snapshot.roots.get(project_root).map(|root| root.settings.clone())
+    }
+
+    /// Get the roots refreshed in the background (pinned projects and
+    /// remote clones), sorted
+    pub async fn get_refreshed_projects(&self) -> Vec<PathBuf> {
+        let snapshot = self.snapshot.read().await;
+        let mut projects: Vec<PathBuf> = snapshot
+            .roots
+            .iter()
+            .filter(|(_, r)| r.settings.pinned || r.settings.remote.is_some())
+            .map(|(path, _)| path.clone())
+            .collect();
+        projects.sort();
+        projects
+    }
+
+    /// Replace the persisted settings for a project
+    pub async fn set_settings(&self, project_root: &Path, settings: ProjectSettings) {
+        let mut snapshot = self.snapshot.write().await;
+        if let Some(root) = snapshot.roots.get_mut(project_root) {
+            self.mark_root_changed(project_root).await;
+            root.settings = settings;
+        }
+    }
+
+    /// Whether the last index run of a project stopped before finishing
+    pub async fn is_partial(&self, project_root: &Path) -> bool {
+        let snapshot = self.snapshot.read().await;
+        snapshot.roots.get(project_root).is_some_and(|root| root.partial)
+    }
+
+    /// Record whether the last index run of a project stopped before finishing
+    pub async fn set_partial(&self, project_root: &Path, partial: bool) {
+        let mut snapshot = self.snapshot.write().await;
+        if let Some(root) = snapshot.roots.get_mut(project_root) {
+            self.mark_root_changed(project_root).await;
+            root.partial = partial;
+        }
+    }
+
+    /// Record an index run of a project that keeps history
+    pub async fn record_index_point(&self, project_root: &Path, point: IndexPoint) {
+        let mut snapshot = self.snapshot.write().await;
+        if let Some(root) = snapshot.roots.get_mut(project_root) {
+            self.mark_root_changed(project_root).await;
+            root.index_points.push(point);
+        }
+    }
+
+    /// Index runs recorded for a project, oldest first
+    pub async fn get_index_points(&self, project_root: &Path) -> Vec<IndexPoint> {
+        let snapshot = self.snapshot.read().await;
+        snapshot.roots.get(project_root).map(|r| r.index_points.clone()).unwrap_or_default()
+    }
+
+    /// Named vector database instance of a project, `None` for the default
+    pub async fn get_vector_db(&self, project_root: &Path) -> Option<String> {
+        let snapshot = self.snapshot.read().await;
+        snapshot.roots.get(project_root).and_then(|r| r.vector_db.clone())
+    }
+
+    /// Place a project's collection on a named vector database instance
+    /// (`None` for the default); set before the collection is created
+    pub async fn set_vector_db(&self, project_root: &Path, instance: Option<String>) {
+        let mut snapshot = self.snapshot.write().await;
+        if let Some(root) = snapshot.roots.get_mut(project_root) {
+            self.mark_root_changed(project_root).await;
+            self.routes.set(&root.collection_name, instance.as_deref());
+            root.vector_db = instance;
+        }
+    }
+
+    pub async fn get_vector_db_backend(&self, project_root: &Path) -> Option<String> {
+        let snapshot = self.snapshot.read().await;
+        snapshot.roots.get(project_root).and_then(|r| r.vector_db_backend.clone())
+    }
+
+    pub async fn set_vector_db_backend(&self, project_root: &Path, backend: &str) {
+        let mut snapshot = self.snapshot.write().await;
+        if let Some(root) = snapshot.roots.get_mut(project_root) {
+            if root.vector_db_backend.as_deref() != Some(backend) {
+                self.mark_root_changed(project_root).await;
+                root.vector_db_backend = Some(backend.to_string());
+            }
+        }
+    }
+
+    pub async fn get_embedding_dimension(&self, project_root: &Path) -> Option<usize> {
+        let snapshot = self.snapshot.read().await;
+        snapshot.roots.get(project_root).and_then(|r| r.embedding_dimension)
+    }
+
+    pub async fn set_embedding_dimension(&self, project_root: &Path, dimension: usize) {
+        let mut snapshot = self.snapshot.write().await;
+        if let Some(root) = snapshot.roots.get_mut(project_root) {
+            self.mark_root_changed(project_root).await;
+            root.embedding_dimension = Some(dimension);
+        }
+    }
+
+    pub async fn get_usage(&self, project_root: &Path) -> EmbeddingUsage {
+        let snapshot = self.snapshot.read().await;
+        snapshot.roots.get(project_root).map(|r| r.usage.clone()).unwrap_or_default()
+    }
+
+    /// Add the embedding usage of an index run with `model` to a project's
+    pub async fn add_usage(&self, project_root: &Path, model: &str, usage: &ModelUsage) {
+        let mut snapshot = self.snapshot.write().await;
+        if let Some(root) = snapshot.roots.get_mut(project_root) {
+            self.mark_root_changed(project_root).await;
+            root.usage.add(model, usage);
+        }
+    }
+
+    /// Vendored files of a project in the shared dependency collection
+    pub async fn get_deps(&self, project_root: &Path) -> Option<VendoredDeps> {
+        let snapshot = self.snapshot.read().await;
+        snapshot.roots.get(project_root).and_then(|r| r.deps.clone())
+    }
+
+    pub async fn set_deps(&self, project_root: &Path, deps: Option<VendoredDeps>) {
+        let mut snapshot = self.snapshot.write().await;
+        if let Some(root) = snapshot.roots.get_mut(project_root) {
+            self.mark_root_changed(project_root).await;
+            root.deps = deps;
+        }
+    }
+
+    /// Hashes of the files any project stored in a shared dependency
+    /// collection, which are already embedded there
+    pub async fn deps_hashes(&self, collection_name: &str) -> HashSet<String> {
+        let snapshot = self.snapshot.read().await;
+        snapshot
+            .roots
+            .values()
+            .filter_map(|r| r.deps.as_ref())
+            .filter(|deps| deps.collection_name == collection_name)
+            .flat_map(|deps| deps.files.values().cloned())
+            .collect()
+    }
+
+    /// Whether any project still uses a shared dependency collection
+    pub async fn uses_deps_collection(&self, collection_name: &str) -> bool {
+        let snapshot = self.snapshot.read().await;
+        snapshot
+            .roots
+            .values()
+            .any(|r| r.deps.as_ref().is_some_and(|deps| deps.collection_name == collection_name))
+    }
+
+    /// Record the monorepo root a sub-project belongs to
+    pub async fn set_workspace_root(&self, project_root: &Path, workspace_root: &Path) {
+        let mut snapshot = self.snapshot.write().await;
+        if let Some(root) = snapshot.roots.get_mut(project_root) {
+            self.mark_root_changed(project_root).await;
+            root.workspace_root = Some(workspace_root.to_path_buf());
+        }
+    }
+
+    /// Assign an alias to an indexed project, replacing any previous one.
+    /// Fails if the alias already belongs to another project.
+    pub async fn set_alias(&self, project_root: &Path, alias: &str) -> Result<()> {
+        let mut snapshot = self.snapshot.write().await;
+        if let Some((other, _)) = snapshot
+            .roots
+            .iter()
+            .find(|(path, r)| path.as_path() != project_root && r.alias.as_deref() == Some(alias))
+        {
+            anyhow::bail!("Alias '{}' is already used by {}", alias, other.display());
+        }
+        if snapshot.roots.values().any(|r| r.composites.contains(alias)) {
+            anyhow::bail!("Alias '{}' is already the name of a composite project", alias);
+        }
+
+        let root = snapshot
+            .roots
+            .get_mut(project_root)
+            .with_context(|| format!("Project not indexed: {}", project_root.display()))?;
+        root.alias = Some(alias.to_string());
+        self.mark_root_changed(project_root).await;
+        Ok(())
+    }
+
+    /// Record a relevance rating of a search result
+    pub async fn record_feedback(
+        &self,
+        project_root: &Path,
+        chunk_id: &str,
+        location: String,
+        relevant: bool,
+        query: Option<String>,
+    ) -> Result<()> {
+        let mut snapshot = self.snapshot.write().await;
+        let root = snapshot
+            .roots
+            .get_mut(project_root)
+            .with_context(|| format!("Project not indexed: {}", project_root.display()))?;
+        root.feedback.record(chunk_id, location, relevant, query);
+        self.mark_root_changed(project_root).await;
+        Ok(())
+    }
+
+    /// Relevance feedback collected for a project
+    pub async fn get_feedback(&self, project_root: &Path) -> Option<SearchFeedback> {
+        let snapshot = self.snapshot.read().await;
+        snapshot.roots.get(project_root).map(|r| r.feedback.clone())
+    }
+
+    /// Get the alias of a project, if any
+    pub async fn get_alias(&self, project_root: &Path) -> Option<String> {
+        let snapshot = self.snapshot.read().await;
+        snapshot.roots.get(project_root).and_then(|r| r.alias.clone())
+    }
+
+    /// Look up the project root registered under an alias
+    pub async fn resolve_alias(&self, alias: &str) -> Option<PathBuf> {
+        let snapshot = self.snapshot.read().await;
+        snapshot
+            .roots
+            .iter()
+            .find(|(_, r)| r.alias.as_deref() == Some(alias))
+            .map(|(path, _)| path.clone())
+    }
+
+    /// Add a project to a named composite project
+    pub async fn add_to_composite(&self, project_root: &Path, name: &str) -> Result<()> {
+        let mut snapshot = self.snapshot.write().await;
+        if let Some((other, _)) = snapshot.roots.iter().find(|(_, r)| r.alias.as_deref() == Some(name)) {
+            anyhow::bail!("'{}' is already the alias of {}", name, other.display());
+        }
+
+        let root = snapshot
+            .roots
+            .get_mut(project_root)
+            .with_context(|| format!("Project not indexed: {}", project_root.display()))?;
+        root.composites.insert(name.to_string());
+        self.mark_root_changed(project_root).await;
+        Ok(())
+    }
+
+    /// Get (project root, collection name) of the members of a composite project
+    pub async fn get_composite_members(&self, name: &str) -> Vec<(PathBuf, String)> {
+        let snapshot = self.snapshot.read().await;
+        let mut members: Vec<_> = snapshot
+            .roots
+            .iter()
+            .filter(|(_, root)| root.composites.contains(name))
+            .map(|(path, root)| (path.clone(), root.collection_name.clone()))
+            .collect();
+        members.sort();
+        members
+    }
+
+    /// Names of all composite projects
+    pub async fn get_composite_names(&self) -> BTreeSet<String> {
+        let snapshot = self.snapshot.read().await;
+        snapshot.roots.values().flat_map(|r| r.composites.iter().cloned()).collect()
+    }
+
+    /// Get (project root, collection name) of all sub-projects indexed from a workspace root
+    pub async fn get_workspace_members(&self, workspace_root: &Path) -> Vec<(PathBuf, String)> {
+        let snapshot = self.snapshot.read().await;
+        let mut members: Vec<_> = snapshot
+            .roots
+            .iter()
+            .filter(|(_, root)| root.workspace_root.as_deref() == Some(workspace_root))
+            .map(|(path, root)| (path.clone(), root.collection_name.clone()))
+            .collect();
+        members.sort();
+        members
+    }
+
     /// Get collection name for a project
     pub async fn get_collection_name(&self, project_root: &Path) -> Option<String> {
         let mut snapshot = self.snapshot.write().await;
         if let Some(root) = snapshot.roots.get_mut(project_root) {
+            self.mark_root_changed(project_root).await;
             root.touch(); // Update access time
             Some(root.collection_name.clone())
         } else {
@@ -173,6 +1235,13 @@ impl SnapshotManager {
         }
     }
 
+    /// Get collection name for a project without updating its access time,
+    /// for work that isn't a use of the project (such as a background refresh)
+    pub async fn peek_collection_name(&self, project_root: &Path) -> Option<String> {
+        let snapshot = self.snapshot.read().await;
+        snapshot.roots.get(project_root).map(|root| root.collection_name.clone())
+    }
+
     /// Get all collection names (for cross-project search)
     pub async fn get_all_collection_names(&self) -> Vec<(PathBuf, String)> {
         let snapshot = self.snapshot.read().await;
@@ -183,20 +1252,17 @@ impl SnapshotManager {
             .collect()
     }
 
-    /// Check if a path is within any indexed project
+    /// Indexed project containing a path; with nested indexed roots the
+    /// innermost one wins
     pub async fn find_project_root(&self, path: &Path) -> Option<PathBuf> {
         let snapshot = self.snapshot.read().await;
-        for root_path in snapshot.roots.keys() {
-            if path.starts_with(root_path) {
-                return Some(root_path.clone());
-            }
-        }
-        None
+        paths::deepest_root(path, snapshot.roots.keys()).cloned()
     }
 
     /// Remove a project root and return its collection name
     pub async fn remove_root(&self, project_root: &Path) -> Option<String> {
         let mut snapshot = self.snapshot.write().await;
+        self.mark_root_removed(project_root).await;
         snapshot.roots.remove(project_root).map(|r| r.collection_name)
     }
 
@@ -206,14 +1272,40 @@ impl SnapshotManager {
         snapshot.roots.keys().cloned().collect()
     }
 
-    /// Get project count
+    /// Get project count, the members of a monorepo counting as one
     pub async fn get_project_count(&self) -> usize {
         let snapshot = self.snapshot.read().await;
-        snapshot.roots.len()
+        project_count(&snapshot)
+    }
+
+    /// Chunk count and approximate stored bytes of a project
+    pub async fn get_index_size(&self, project_root: &Path) -> Option<(usize, u64)> {
+        let snapshot = self.snapshot.read().await;
+        let root = snapshot.roots.get(project_root)?;
+        Some((root.chunk_count(), root.stored_bytes()))
+    }
+
+    /// Approximate vector database storage of a project and of all projects
+    /// together, in bytes
+    pub async fn storage_usage(&self, project_root: &Path) -> (u64, u64) {
+        let snapshot = self.snapshot.read().await;
+        let project = snapshot.roots.get(project_root).map_or(0, |r| r.stored_bytes());
+        let total = snapshot.roots.values().map(|r| r.stored_bytes()).sum();
+        (project, total)
+    }
+
+    /// Re-read the persisted snapshot without replacing the in-memory one,
+    /// returning the store kind and its project count
+    pub async fn verify_store(&self) -> Result<(&'static str, usize)> {
+        let store = self.store.read().await;
+        let snapshot = store
+            .load()
+            .await
+            .with_context(|| format!("Failed to read snapshot at {}", self.snapshot_path.display()))?;
+        Ok((store.name(), snapshot.roots.len()))
     }
 
     /// Get max projects limit
-    #[allow(dead_code)] // Reserved for future use - could be exposed via status tool
     pub fn max_projects(&self) -> usize {
         self.max_projects
     }
@@ -222,24 +1314,152 @@ impl SnapshotManager {
     pub async fn clear(&self) {
         let mut snapshot = self.snapshot.write().await;
         snapshot.roots.clear();
+        self.changes.lock().await.full = true;
     }
 
     /// Clear a specific project
     pub async fn clear_project(&self, project_root: &Path) -> Option<String> {
         let mut snapshot = self.snapshot.write().await;
+        self.mark_root_removed(project_root).await;
         snapshot.roots.remove(project_root).map(|r| r.collection_name)
     }
 
-    /// Get all projects sorted by last accessed time (oldest first)
-    #[allow(dead_code)] // Reserved for future use - could be used for eviction reporting
-    pub async fn get_projects_by_age(&self) -> Vec<(PathBuf, u64)> {
+    /// When a project's files were last indexed (Unix seconds)
+    pub async fn last_indexed_at(&self, project_root: &Path) -> Option<u64> {
         let snapshot = self.snapshot.read().await;
-        let mut projects: Vec<_> = snapshot
-            .roots
-            .iter()
-            .map(|(path, root)| (path.clone(), root.last_accessed_at))
-            .collect();
-        projects.sort_by_key(|(_, timestamp)| *timestamp);
-        projects
+        let root = snapshot.roots.get(project_root)?;
+        Some(root.files.values().map(|f| f.indexed_at).max().unwrap_or(root.indexed_at))
+    }
+}
+
+/// Path a project counts under against `max_projects`: the members of a
+/// monorepo indexed with `sub_projects` count as one project
+fn project_unit<'a>(path: &'a Path, root: &'a RootInfo) -> &'a Path {
+    root.workspace_root.as_deref().unwrap_or(path)
+}
+
+/// Projects counted against `max_projects`
+fn project_count(snapshot: &Snapshot) -> usize {
+    snapshot.roots.iter().map(|(path, root)| project_unit(path, root)).collect::<HashSet<_>>().len()
+}
+
+/// Whether indexing `project_root` (a member of `workspace_root`, if any)
+/// adds a project: it isn't indexed, nor is another member of its monorepo
+fn adds_project(snapshot: &Snapshot, project_root: &Path, workspace_root: Option<&Path>) -> bool {
+    !snapshot.roots.contains_key(project_root)
+        && !workspace_root.is_some_and(|workspace| snapshot.roots.iter().any(|(path, root)| project_unit(path, root) == workspace))
+}
+
+/// Current time in seconds since the Unix epoch
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_eviction_forecast() {
+        let path = std::env::temp_dir().join(format!("snapshot-{}.json", uuid::Uuid::new_v4()));
+        let manager = SnapshotManager::new_with_max_projects(path, 3).unwrap();
+        for (i, name) in ["old", "pinned", "recent"].iter().enumerate() {
+            let root = PathBuf::from(format!("/repo/{}", name));
+            manager.get_or_create_root(&root, &format!("code_{}", name)).await;
+            let mut snapshot = manager.snapshot.write().await;
+            let info = snapshot.roots.get_mut(&root).unwrap();
+            info.last_accessed_at = 100 + i as u64;
+            info.settings.pinned = *name == "pinned";
+        }
+        manager.snapshot.write().await.roots.get_mut(Path::new("/repo/pinned")).unwrap().last_accessed_at = 1;
+
+        let summaries = manager.project_summaries().await;
+        assert_eq!(summaries[0].project_root, Path::new("/repo/pinned"));
+        assert!(summaries[0].pinned);
+
+        let forecast = manager.eviction_forecast().await;
+        assert_eq!(forecast.policy, "lru");
+        assert_eq!(forecast.next.as_deref(), Some(Path::new("/repo/old")));
+        assert_eq!(forecast.on_next_index, [PathBuf::from("/repo/old")]);
+
+        manager.clear_project(Path::new("/repo/recent")).await;
+        let forecast = manager.eviction_forecast().await;
+        assert_eq!(forecast.next.as_deref(), Some(Path::new("/repo/old")));
+        assert!(forecast.on_next_index.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_eviction_candidates() {
+        let path = std::env::temp_dir().join(format!("snapshot-{}.json", uuid::Uuid::new_v4()));
+        let manager = SnapshotManager::new_with_max_projects(path, 10)
+            .unwrap()
+            .with_eviction_policy(EvictionPolicy::Size { max_chunks: 80 });
+        for (i, (name, chunks, pinned)) in [("pinned", 60, true), ("old", 30, false), ("mid", 20, false), ("new", 10, false)].into_iter().enumerate() {
+            let root = PathBuf::from(format!("/repo/{}", name));
+            manager.get_or_create_root(&root, &format!("code_{}", name)).await;
+            manager.update_file(&root, PathBuf::from("lib.rs"), FileEntry::new("h".to_string(), chunks)).await;
+            let mut snapshot = manager.snapshot.write().await;
+            let info = snapshot.roots.get_mut(&root).unwrap();
+            info.last_accessed_at = i as u64;
+            info.settings.pinned = pinned;
+        }
+
+        // 120 chunks: the pinned project is the oldest but is skipped, and
+        // unpinned ones go oldest first until the total is under budget
+        let new_project = Path::new("/repo/other");
+        assert_eq!(manager.eviction_candidates(new_project, None).await, [PathBuf::from("/repo/old"), PathBuf::from("/repo/mid")]);
+        // Re-indexing a project that is already indexed evicts nothing
+        assert!(manager.eviction_candidates(Path::new("/repo/new"), None).await.is_empty());
+
+        manager.clear_project(Path::new("/repo/old")).await;
+        assert_eq!(manager.eviction_candidates(new_project, None).await, [PathBuf::from("/repo/mid")]);
+    }
+
+    #[tokio::test]
+    async fn test_workspace_counts_as_one_project() {
+        let path = std::env::temp_dir().join(format!("snapshot-{}.json", uuid::Uuid::new_v4()));
+        let manager = SnapshotManager::new_with_max_projects(path, 2).unwrap();
+        let mono = Path::new("/repo/mono");
+        manager.get_or_create_root(Path::new("/repo/solo"), "code_solo").await;
+        for (i, member) in ["/repo/mono/a", "/repo/mono/b"].into_iter().enumerate() {
+            let member = Path::new(member);
+            // The second member joins its monorepo instead of evicting
+            assert_eq!(manager.adds_project(member, Some(mono)).await, i == 0);
+            assert!(manager.eviction_candidates(member, Some(mono)).await.is_empty());
+            manager.get_or_create_root(member, &format!("code_{}", i)).await;
+            manager.set_workspace_root(member, mono).await;
+            manager.snapshot.write().await.roots.get_mut(member).unwrap().last_accessed_at = 0;
+        }
+        assert_eq!(manager.get_project_count().await, 2);
+
+        // The least recently accessed monorepo goes with all its members
+        assert_eq!(
+            manager.eviction_candidates(Path::new("/repo/other"), None).await,
+            [PathBuf::from("/repo/mono/a"), PathBuf::from("/repo/mono/b")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_saves_only_changed_roots() {
+        let path = std::env::temp_dir().join(format!("snapshot-{}.json", uuid::Uuid::new_v4()));
+        let manager = SnapshotManager::new(path.clone()).unwrap();
+        let (rated, other) = (Path::new("/repo/rated"), Path::new("/repo/other"));
+        manager.get_or_create_root(rated, "code_rated").await;
+        manager.get_or_create_root(other, "code_other").await;
+        manager.save().await.unwrap();
+        assert!(manager.changes.lock().await.roots.is_empty());
+
+        // Rating a result changes only its project's metadata
+        manager.record_feedback(rated, "abc", "src/lib.rs:1-5".to_string(), true, None).await.unwrap();
+        manager.update_file(other, PathBuf::from("lib.rs"), FileEntry::new("h".to_string(), 1)).await;
+        let changes = manager.changes.lock().await;
+        assert_eq!(changes.roots, HashSet::from([rated.to_path_buf()]));
+        assert_eq!(changes.files.len(), 1);
+        drop(changes);
+
+        let _ = std::fs::remove_file(path);
     }
 }
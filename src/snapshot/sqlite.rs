@@ -0,0 +1,252 @@
+use super::{FileEntry, RootInfo, Snapshot, SnapshotChanges, SnapshotStore};
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Snapshot store backed by SQLite.
+///
+/// Project roots are stored as one row each, and file entries live in their
+/// own table so a save only upserts the roots and files that changed since
+/// the last save instead of rewriting the whole snapshot.
+pub struct SqliteSnapshotStore {
+    path: PathBuf,
+    /// Shared with the blocking tasks running the queries
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteSnapshotStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open snapshot database: {}", path.display()))?;
+
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = NORMAL;
+             CREATE TABLE IF NOT EXISTS roots (
+                 path TEXT PRIMARY KEY,
+                 info TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS files (
+                 root TEXT NOT NULL,
+                 path TEXT NOT NULL,
+                 entry TEXT NOT NULL,
+                 PRIMARY KEY (root, path)
+             );",
+        )
+        .context("Failed to initialize snapshot database schema")?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    fn upsert_file(tx: &rusqlite::Transaction, root: &str, path: &str, entry: &str) -> Result<()> {
+        tx.execute(
+            "INSERT INTO files (root, path, entry) VALUES (?1, ?2, ?3)
+             ON CONFLICT(root, path) DO UPDATE SET entry = excluded.entry",
+            params![root, path, entry],
+        )?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl SnapshotStore for SqliteSnapshotStore {
+    async fn load(&self) -> Result<Snapshot> {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || -> Result<Snapshot> {
+            let conn = conn.lock().unwrap();
+            let mut snapshot = Snapshot::default();
+
+            let mut stmt = conn.prepare("SELECT path, info FROM roots")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (path, info) = row?;
+                let root: RootInfo = serde_json::from_str(&info)
+                    .with_context(|| format!("Corrupt root entry in snapshot database: {}", path))?;
+                snapshot.roots.insert(PathBuf::from(path), root);
+            }
+
+            let mut stmt = conn.prepare("SELECT root, path, entry FROM files")?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+            })?;
+            for row in rows {
+                let (root, path, entry) = row?;
+                if let Some(root_info) = snapshot.roots.get_mut(Path::new(&root)) {
+                    let entry: FileEntry = serde_json::from_str(&entry)
+                        .with_context(|| format!("Corrupt file entry in snapshot database: {}", path))?;
+                    root_info.files.insert(PathBuf::from(path), entry);
+                }
+            }
+
+            Ok(snapshot)
+        })
+        .await?
+    }
+
+    /// Rows are serialized here and written on the blocking pool, so a big
+    /// save doesn't stall the runtime
+    async fn save(&self, snapshot: &Snapshot, changes: &SnapshotChanges) -> Result<()> {
+        let roots: Vec<(String, String)> = snapshot
+            .roots
+            .iter()
+            .filter(|(path, _)| changes.full || changes.roots.contains(*path))
+            .map(|(path, root)| Ok((path.to_string_lossy().into_owned(), serde_json::to_string(&root.clone_without_files())?)))
+            .collect::<Result<_>>()?;
+        let removed_roots: Vec<String> = changes.removed_roots.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+        // (root, path, entry), `None` deleting the file's row
+        let mut files: Vec<(String, String, Option<String>)> = Vec::new();
+        if changes.full {
+            for (root_path, root) in &snapshot.roots {
+                for (file_path, entry) in &root.files {
+                    files.push((root_path.to_string_lossy().into_owned(), file_path.to_string_lossy().into_owned(), Some(serde_json::to_string(entry)?)));
+                }
+            }
+        } else {
+            for (root_path, file_path) in &changes.files {
+                let entry = match snapshot.roots.get(root_path).and_then(|r| r.files.get(file_path)) {
+                    Some(entry) => Some(serde_json::to_string(entry)?),
+                    None => None,
+                };
+                files.push((root_path.to_string_lossy().into_owned(), file_path.to_string_lossy().into_owned(), entry));
+            }
+        }
+        let full = changes.full;
+        let changed = files.len();
+        let changed_roots = roots.len();
+
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let mut conn = conn.lock().unwrap();
+            let tx = conn.transaction()?;
+
+            if full {
+                tx.execute("DELETE FROM roots", [])?;
+                tx.execute("DELETE FROM files", [])?;
+            }
+            for root in &removed_roots {
+                tx.execute("DELETE FROM roots WHERE path = ?1", params![root])?;
+                tx.execute("DELETE FROM files WHERE root = ?1", params![root])?;
+            }
+            for (path, info) in &roots {
+                tx.execute(
+                    "INSERT INTO roots (path, info) VALUES (?1, ?2)
+                     ON CONFLICT(path) DO UPDATE SET info = excluded.info",
+                    params![path, info],
+                )?;
+            }
+            for (root, path, entry) in &files {
+                match entry {
+                    Some(entry) => Self::upsert_file(&tx, root, path, entry)?,
+                    None => {
+                        tx.execute("DELETE FROM files WHERE root = ?1 AND path = ?2", params![root, path])?;
+                    }
+                }
+            }
+
+            tx.commit()?;
+            Ok(())
+        })
+        .await??;
+        tracing::debug!(
+            "Saved snapshot to {} ({} changed roots, {} changed files)",
+            self.path.display(),
+            if full { "all".to_string() } else { changed_roots.to_string() },
+            if full { "all".to_string() } else { changed.to_string() }
+        );
+        Ok(())
+    }
+
+    async fn remove(&self) -> Result<()> {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            conn.lock().unwrap().execute_batch("DELETE FROM files; DELETE FROM roots;")?;
+            Ok(())
+        })
+        .await?
+    }
+
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::ChunkSpan;
+
+    #[tokio::test]
+    async fn test_sqlite_store_round_trip() {
+        let path = std::env::temp_dir().join(format!("snapshot-{}.db", uuid::Uuid::new_v4()));
+        let store = SqliteSnapshotStore::open(&path).unwrap();
+
+        let root_path = PathBuf::from("/tmp/project");
+        let file_path = PathBuf::from("src/main.rs");
+        let mut snapshot = Snapshot::default();
+        let mut root = RootInfo::new("code_index_test".to_string());
+        root.files.insert(
+            file_path.clone(),
+            FileEntry {
+                hash: "abc".to_string(),
+                chunk_count: 3,
+                stored_bytes: 0,
+                indexed_at: 1,
+                symbols: Vec::new(),
+                fingerprint: None,
+                parse_fallback: None,
+                chunks: vec![
+                    ChunkSpan { id: "aaa".to_string(), start_line: 0, end_line: 9 },
+                    ChunkSpan { id: "bbb".to_string(), start_line: 10, end_line: 20 },
+                ],
+            },
+        );
+        root.composites.insert("checkout".to_string());
+        root.embedding_dimension = Some(768);
+        snapshot.roots.insert(root_path.clone(), root);
+
+        let mut changes = SnapshotChanges::default();
+        changes.roots.insert(root_path.clone());
+        changes.files.insert((root_path.clone(), file_path.clone()));
+        store.save(&snapshot, &changes).await.unwrap();
+
+        let loaded = store.load().await.unwrap();
+        let loaded_root = loaded.roots.get(&root_path).unwrap();
+        assert_eq!(loaded_root.collection_name, "code_index_test");
+        assert_eq!(loaded_root.files.get(&file_path).unwrap().chunk_count, 3);
+        // Chunk order survives, for get_chunk_context
+        assert_eq!(loaded_root.files.get(&file_path).unwrap().chunk_position("bbb"), Some(1));
+        assert!(loaded_root.composites.contains("checkout"));
+        assert_eq!(loaded_root.embedding_dimension, Some(768));
+
+        // Removing the file from the snapshot deletes its row on the next save
+        snapshot.roots.get_mut(&root_path).unwrap().files.clear();
+        store.save(&snapshot, &changes).await.unwrap();
+        assert!(store.load().await.unwrap().roots[&root_path].files.is_empty());
+
+        // A root's row is only rewritten when it is marked as changed
+        snapshot.roots.get_mut(&root_path).unwrap().alias = Some("checkout".to_string());
+        store.save(&snapshot, &SnapshotChanges::default()).await.unwrap();
+        assert_eq!(store.load().await.unwrap().roots[&root_path].alias, None);
+        let mut changes = SnapshotChanges::default();
+        changes.roots.insert(root_path.clone());
+        store.save(&snapshot, &changes).await.unwrap();
+        assert_eq!(store.load().await.unwrap().roots[&root_path].alias.as_deref(), Some("checkout"));
+
+        // Removed roots lose their row and their files
+        let mut changes = SnapshotChanges::default();
+        changes.removed_roots.insert(root_path.clone());
+        snapshot.roots.clear();
+        store.save(&snapshot, &changes).await.unwrap();
+        assert!(store.load().await.unwrap().roots.is_empty());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,114 @@
+use super::FileEntry;
+use crate::archive;
+use crate::parser::code_parser::CodeParser;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tokio::fs;
+
+/// Result of comparing a project's snapshot against the files on disk
+#[derive(Debug, Default)]
+pub struct StalenessReport {
+    /// Number of files recorded in the snapshot
+    pub total_files: usize,
+    /// Indexed files that no longer exist (relative paths)
+    pub missing: Vec<PathBuf>,
+    /// Indexed files whose content changed since indexing (relative paths)
+    pub changed: Vec<PathBuf>,
+}
+
+impl StalenessReport {
+    pub fn stale_count(&self) -> usize {
+        self.missing.len() + self.changed.len()
+    }
+
+    /// Percentage of indexed files that are missing or changed
+    pub fn stale_percentage(&self) -> f64 {
+        if self.total_files == 0 {
+            0.0
+        } else {
+            self.stale_count() as f64 * 100.0 / self.total_files as f64
+        }
+    }
+}
+
+/// Check indexed files against the disk. Files modified after they were
+/// indexed are re-hashed so touched-but-unchanged files are not reported.
+pub async fn check_staleness(project_root: &Path, files: &HashMap<PathBuf, FileEntry>) -> StalenessReport {
+    scan(project_root, files, true).await
+}
+
+/// Cheaper variant of [`check_staleness`] comparing modification times only:
+/// files touched after indexing count as changed even if their content is
+/// the same
+pub async fn check_modified(project_root: &Path, files: &HashMap<PathBuf, FileEntry>) -> StalenessReport {
+    scan(project_root, files, false).await
+}
+
+async fn scan(project_root: &Path, files: &HashMap<PathBuf, FileEntry>, rehash: bool) -> StalenessReport {
+    let mut report = StalenessReport {
+        total_files: files.len(),
+        ..Default::default()
+    };
+
+    // Text entries of each archive, read once; `None` if unreadable
+    let mut archives: HashMap<PathBuf, Option<HashMap<PathBuf, String>>> = HashMap::new();
+
+    for (relative_path, entry) in files {
+        // Entries inside an archive follow the archive's modification time
+        let virtual_entry = archive::split_virtual(relative_path);
+        let path = match &virtual_entry {
+            Some((archive_path, _)) => project_root.join(archive_path),
+            None => project_root.join(relative_path),
+        };
+        let metadata = match fs::metadata(&path).await {
+            Ok(m) => m,
+            Err(_) => {
+                report.missing.push(relative_path.clone());
+                continue;
+            }
+        };
+
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(u64::MAX);
+        if modified < entry.indexed_at {
+            continue;
+        }
+        if !rehash {
+            report.changed.push(relative_path.clone());
+            continue;
+        }
+
+        let hash = match virtual_entry {
+            Some((archive_path, archive_entry)) => {
+                if !archives.contains_key(&archive_path) {
+                    let entries = archive::read_text_entries(path, u64::MAX).await.ok();
+                    archives.insert(archive_path.clone(), entries.map(|entries| entries.into_iter().collect()));
+                }
+                match &archives[&archive_path] {
+                    Some(entries) => match entries.get(&archive_entry) {
+                        Some(content) => Some(CodeParser::hash_file(content)),
+                        // Gone from its archive
+                        None => {
+                            report.missing.push(relative_path.clone());
+                            continue;
+                        }
+                    },
+                    None => None,
+                }
+            }
+            None => fs::read_to_string(&path).await.ok().map(|content| CodeParser::hash_file(&content)),
+        };
+        if hash.as_ref() != Some(&entry.hash) {
+            report.changed.push(relative_path.clone());
+        }
+    }
+
+    report.missing.sort();
+    report.changed.sort();
+    report
+}
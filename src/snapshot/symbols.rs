@@ -0,0 +1,130 @@
+//! Export of a project's indexed symbols (`export_symbols`) as JSON or a
+//! ctags file
+
+use super::diff::IndexManifest;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// A symbol of an indexed file, with 1-based lines
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExportedSymbol {
+    pub name: String,
+    pub kind: String,
+    /// Slash-separated path relative to the project root
+    pub file: String,
+    pub start_line: usize,
+    /// Unknown for files indexed before end lines were recorded
+    pub end_line: Option<usize>,
+}
+
+/// All symbols of an index, by file and then line
+pub fn collect(manifest: &IndexManifest) -> Vec<ExportedSymbol> {
+    let mut symbols: Vec<ExportedSymbol> = manifest
+        .files
+        .iter()
+        .flat_map(|(file, entry)| {
+            entry.symbols.iter().map(move |s| ExportedSymbol {
+                name: s.name.clone(),
+                kind: s.kind.clone(),
+                file: file.clone(),
+                start_line: s.start_line + 1,
+                end_line: s.end_line.map(|line| line + 1),
+            })
+        })
+        .collect();
+    symbols.sort_by(|a, b| (&a.file, a.start_line).cmp(&(&b.file, b.start_line)));
+    symbols
+}
+
+/// Symbol count per kind
+pub fn count_by_kind(symbols: &[ExportedSymbol]) -> BTreeMap<&str, usize> {
+    let mut counts = BTreeMap::new();
+    for symbol in symbols {
+        *counts.entry(symbol.kind.as_str()).or_default() += 1;
+    }
+    counts
+}
+
+/// Symbols as a sorted extended-format tags file, as read by Vim, Emacs
+/// and other ctags consumers
+pub fn to_ctags(symbols: &[ExportedSymbol]) -> String {
+    let mut lines: Vec<String> = symbols
+        .iter()
+        .map(|s| {
+            let mut line = format!(
+                "{}\t{}\t{};\"\tkind:{}\tline:{}",
+                tag_field(&s.name),
+                tag_field(&s.file),
+                s.start_line,
+                s.kind,
+                s.start_line
+            );
+            if let Some(end) = s.end_line {
+                line.push_str(&format!("\tend:{}", end));
+            }
+            line
+        })
+        .collect();
+    lines.sort();
+
+    let mut tags = String::from(
+        "!_TAG_FILE_FORMAT\t2\t/extended format/\n\
+         !_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted, 2=foldcase/\n\
+         !_TAG_PROGRAM_NAME\tcode-context-mcp\t//\n",
+    );
+    for line in lines {
+        tags.push_str(&line);
+        tags.push('\n');
+    }
+    tags
+}
+
+/// Tabs and newlines would break the line-based format
+fn tag_field(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::diff::ManifestFile;
+    use crate::snapshot::SymbolEntry;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_export_ctags() {
+        let symbol = |name: &str, kind: &str, start_line, end_line| SymbolEntry {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            hash: String::new(),
+            start_line,
+            end_line,
+        };
+        let manifest = IndexManifest {
+            project_root: PathBuf::from("/project"),
+            exported_at: 0,
+            files: BTreeMap::from([(
+                "src/lib.rs".to_string(),
+                ManifestFile {
+                    hash: String::new(),
+                    symbols: vec![symbol("parse", "function", 9, Some(20)), symbol("Config", "struct", 0, None)],
+                },
+            )]),
+        };
+
+        let symbols = collect(&manifest);
+        assert_eq!(symbols[0].name, "Config");
+        assert_eq!((symbols[1].start_line, symbols[1].end_line), (10, Some(21)));
+        assert_eq!(count_by_kind(&symbols), BTreeMap::from([("function", 1), ("struct", 1)]));
+
+        let tags = to_ctags(&symbols);
+        let entries: Vec<&str> = tags.lines().filter(|l| !l.starts_with("!_TAG")).collect();
+        assert_eq!(
+            entries,
+            vec![
+                "Config\tsrc/lib.rs\t1;\"\tkind:struct\tline:1",
+                "parse\tsrc/lib.rs\t10;\"\tkind:function\tline:10\tend:21",
+            ]
+        );
+    }
+}
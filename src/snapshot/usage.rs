@@ -0,0 +1,97 @@
+//! Embedding usage of a project's index runs: requests and tokens per
+//! model, with the estimated cost of paid providers, so indexing of large
+//! monorepos can be budgeted
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Usage of one embedding model
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelUsage {
+    /// Embedding requests sent; texts served from the embedding cache or
+    /// reused within a run aren't requested
+    pub requests: u64,
+    /// Tokens the provider reported billing
+    pub tokens: u64,
+    /// Tokens of requests whose response reported none (local models and
+    /// providers without usage reporting), estimated from the text length
+    pub estimated_tokens: u64,
+    /// Cost in USD at the provider's list price, `0` for local models
+    pub cost_usd: f64,
+}
+
+impl ModelUsage {
+    pub fn add(&mut self, other: &ModelUsage) {
+        self.requests += other.requests;
+        self.tokens += other.tokens;
+        self.estimated_tokens += other.estimated_tokens;
+        self.cost_usd += other.cost_usd;
+    }
+
+    /// "1200 requests, 350000 tokens (~2000 estimated), $0.0070"
+    pub fn describe(&self) -> String {
+        let mut text = format!("{} requests, {} tokens", self.requests, self.tokens + self.estimated_tokens);
+        if self.estimated_tokens > 0 {
+            text.push_str(&format!(" (~{} estimated)", self.estimated_tokens));
+        }
+        if self.cost_usd > 0.0 {
+            text.push_str(&format!(", ${:.4}", self.cost_usd));
+        }
+        text
+    }
+}
+
+/// Cumulative embedding usage of a project, per model
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EmbeddingUsage {
+    pub models: BTreeMap<String, ModelUsage>,
+}
+
+impl EmbeddingUsage {
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    /// Add the usage of an index run with `model`
+    pub fn add(&mut self, model: &str, usage: &ModelUsage) {
+        if usage.requests > 0 {
+            self.models.entry(model.to_string()).or_default().add(usage);
+        }
+    }
+
+    /// Usage summed over all models
+    pub fn total(&self) -> ModelUsage {
+        let mut total = ModelUsage::default();
+        for usage in self.models.values() {
+            total.add(usage);
+        }
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_usage() {
+        let mut usage = EmbeddingUsage::default();
+        let run = ModelUsage { requests: 10, tokens: 4000, estimated_tokens: 0, cost_usd: 0.00008 };
+        usage.add("text-embedding-3-small", &run);
+        usage.add("text-embedding-3-small", &run);
+        usage.add("nomic-embed-text", &ModelUsage { requests: 5, estimated_tokens: 300, ..Default::default() });
+        usage.add("nomic-embed-text", &ModelUsage::default());
+
+        assert_eq!(usage.models.len(), 2);
+        assert_eq!(usage.models["text-embedding-3-small"].tokens, 8000);
+        assert_eq!(usage.models["text-embedding-3-small"].describe(), "20 requests, 8000 tokens, $0.0002");
+        assert_eq!(usage.models["nomic-embed-text"].describe(), "5 requests, 300 tokens (~300 estimated)");
+        assert_eq!(usage.total().requests, 25);
+
+        let json = serde_json::to_value(&usage).unwrap();
+        assert_eq!(json["nomic-embed-text"]["estimated_tokens"], 300);
+        assert_eq!(serde_json::from_value::<EmbeddingUsage>(json).unwrap(), usage);
+    }
+}
@@ -0,0 +1,188 @@
+//! Limit on concurrent vector database requests, so cross-project searches
+//! and indexing don't overload small (standalone) Milvus instances
+
+use super::{Metric, SearchResult, VectorDatabase};
+use anyhow::Result;
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Semaphore admitting at most a fixed number of requests at a time
+/// (unlimited when the limit is zero)
+#[derive(Clone, Default)]
+pub struct RequestLimiter {
+    permits: Option<Arc<Semaphore>>,
+}
+
+impl RequestLimiter {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            permits: (max_concurrent > 0).then(|| Arc::new(Semaphore::new(max_concurrent))),
+        }
+    }
+
+    /// Wait for a free slot, held until the permit is dropped
+    pub async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        let permits = self.permits.clone()?;
+        // The semaphore is never closed
+        permits.acquire_owned().await.ok()
+    }
+
+    /// Slots currently free, `None` when unlimited
+    pub fn available(&self) -> Option<usize> {
+        self.permits.as_ref().map(|p| p.available_permits())
+    }
+}
+
+/// Vector database wrapper sending at most `max_concurrent` requests at a
+/// time across all tool calls
+pub struct LimitedVectorDatabase {
+    inner: Arc<dyn VectorDatabase>,
+    limiter: RequestLimiter,
+}
+
+impl LimitedVectorDatabase {
+    pub fn new(inner: Arc<dyn VectorDatabase>, max_concurrent: usize) -> Self {
+        Self {
+            inner,
+            limiter: RequestLimiter::new(max_concurrent),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl VectorDatabase for LimitedVectorDatabase {
+    async fn create_collection(&self, name: &str, dimension: usize) -> Result<()> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.create_collection(name, dimension).await
+    }
+
+    async fn collection_dimension(&self, name: &str) -> Result<Option<usize>> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.collection_dimension(name).await
+    }
+
+    async fn upsert(&self, collection: &str, ids: &[i64], vectors: &[Vec<f32>], metadata: &[serde_json::Value]) -> Result<()> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.upsert(collection, ids, vectors, metadata).await
+    }
+
+    async fn search(&self, collection: &str, vector: &[f32], limit: usize) -> Result<Vec<SearchResult>> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.search(collection, vector, limit).await
+    }
+
+    async fn drop_collection(&self, name: &str) -> Result<()> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.drop_collection(name).await
+    }
+
+    async fn delete_by_file(&self, collection: &str, file_path: &str) -> Result<()> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.delete_by_file(collection, file_path).await
+    }
+
+    async fn delete_by_file_hashes(&self, collection: &str, file_hashes: &[String]) -> Result<()> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.delete_by_file_hashes(collection, file_hashes).await
+    }
+
+    /// Not limited: a health check must answer even when the limit is saturated
+    async fn health_check(&self) -> Result<String> {
+        self.inner.health_check().await
+    }
+
+    fn metric(&self) -> Metric {
+        self.inner.metric()
+    }
+
+    async fn create_partition(&self, collection: &str, partition: &str) -> Result<()> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.create_partition(collection, partition).await
+    }
+
+    async fn upsert_partition(
+        &self,
+        collection: &str,
+        partition: &str,
+        ids: &[i64],
+        vectors: &[Vec<f32>],
+        metadata: &[serde_json::Value],
+    ) -> Result<()> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.upsert_partition(collection, partition, ids, vectors, metadata).await
+    }
+
+    async fn search_partitions(
+        &self,
+        collection: &str,
+        partitions: &[String],
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.search_partitions(collection, partitions, vector, limit).await
+    }
+
+    async fn move_file(
+        &self,
+        collection: &str,
+        partition: Option<&str>,
+        old_path: &str,
+        new_path: &str,
+        updates: &serde_json::Value,
+    ) -> Result<bool> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.move_file(collection, partition, old_path, new_path, updates).await
+    }
+
+    async fn copy_file(
+        &self,
+        collection: &str,
+        file_path: &str,
+        target: &str,
+        updates: &serde_json::Value,
+        salt: i64,
+    ) -> Result<usize> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.copy_file(collection, file_path, target, updates, salt).await
+    }
+
+    async fn search_keywords(
+        &self,
+        collection: &str,
+        partitions: Option<&[String]>,
+        vector: &[f32],
+        limit: usize,
+        keywords: &[String],
+    ) -> Result<Vec<SearchResult>> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.search_keywords(collection, partitions, vector, limit, keywords).await
+    }
+
+    async fn count_rows(&self, collection: &str) -> Result<Option<usize>> {
+        let _permit = self.limiter.acquire().await;
+        self.inner.count_rows(collection).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_limiter_admits_up_to_limit() {
+        let limiter = RequestLimiter::new(2);
+        let first = limiter.acquire().await;
+        let _second = limiter.acquire().await;
+        assert_eq!(limiter.available(), Some(0));
+        assert!(tokio::time::timeout(Duration::from_millis(20), limiter.acquire()).await.is_err());
+
+        drop(first);
+        assert!(limiter.acquire().await.is_some());
+
+        let unlimited = RequestLimiter::new(0);
+        assert!(unlimited.acquire().await.is_none());
+        assert_eq!(unlimited.available(), None);
+    }
+}
@@ -1,9 +1,15 @@
-use super::{SearchResult, VectorDatabase};
+use super::{copied_id, matches_keywords, moved_id, moved_metadata, Metric, SearchResult, VectorDatabase, KEYWORD_FIELDS};
 use anyhow::{Context, Result};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
+/// Metric collections are created and searched with
+const METRIC: Metric = Metric::Cosine;
+
+/// Largest number of rows a query may return
+const MAX_QUERY_ROWS: usize = 16384;
+
 /// Milvus vector database client (using REST API)
 pub struct MilvusVectorDatabase {
     client: Client,
@@ -23,6 +29,8 @@ struct CreateCollectionRequest {
 struct InsertRequest {
     #[serde(rename = "collectionName")]
     collection_name: String,
+    #[serde(rename = "partitionName", skip_serializing_if = "Option::is_none")]
+    partition_name: Option<String>,
     data: Vec<InsertData>,
 }
 
@@ -46,6 +54,11 @@ struct SearchRequest {
     output_fields: Vec<String>,
     #[serde(rename = "metricType")]
     metric_type: String,
+    #[serde(rename = "partitionNames", skip_serializing_if = "Option::is_none")]
+    partition_names: Option<Vec<String>>,
+    /// Scalar filter expression applied before the vector search
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filter: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -91,12 +104,18 @@ impl MilvusVectorDatabase {
         }
     }
 
+    /// Send requests through a client with configured timeouts and pooling
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
     fn collection_url(&self) -> String {
         format!("{}/v2/vectordb/collections/create", self.address)
     }
 
-    fn insert_url(&self) -> String {
-        format!("{}/v2/vectordb/entities/insert", self.address)
+    fn upsert_url(&self) -> String {
+        format!("{}/v2/vectordb/entities/upsert", self.address)
     }
 
     fn search_url(&self) -> String {
@@ -106,55 +125,103 @@ impl MilvusVectorDatabase {
     fn drop_url(&self) -> String {
         format!("{}/v2/vectordb/collections/drop", self.address)
     }
-}
 
-#[async_trait::async_trait]
-impl VectorDatabase for MilvusVectorDatabase {
-    async fn create_collection(&self, name: &str, dimension: usize) -> Result<()> {
-        let request = CreateCollectionRequest {
-            collection_name: name.to_string(),
-            dimension,
-            metric_type: "COSINE".to_string(),
-        };
+    fn delete_url(&self) -> String {
+        format!("{}/v2/vectordb/entities/delete", self.address)
+    }
+
+    fn query_url(&self) -> String {
+        format!("{}/v2/vectordb/entities/query", self.address)
+    }
+
+    fn list_collections_url(&self) -> String {
+        format!("{}/v2/vectordb/collections/list", self.address)
+    }
 
+    fn has_collection_url(&self) -> String {
+        format!("{}/v2/vectordb/collections/has", self.address)
+    }
+
+    fn describe_collection_url(&self) -> String {
+        format!("{}/v2/vectordb/collections/describe", self.address)
+    }
+
+    /// POST a request and return the `data` of a successful response
+    async fn post_data(&self, url: String, request: serde_json::Value, action: &str) -> Result<serde_json::Value> {
         let response = self
             .client
-            .post(self.collection_url())
+            .post(url)
             .json(&request)
             .send()
             .await
-            .context("Failed to send create collection request")?;
+            .with_context(|| format!("Failed to send {} request", action))?;
 
-        let response_body: CreateCollectionResponse = response
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Milvus API error ({}): {}", status, body);
+        }
+
+        let mut body: serde_json::Value = response
             .json()
             .await
-            .context("Failed to parse create collection response")?;
-
-        if response_body.code != 0 {
-            anyhow::bail!("Milvus create collection error: {}", response_body.message.unwrap_or_default());
+            .with_context(|| format!("Failed to parse {} response", action))?;
+        if body.get("code").and_then(|c| c.as_i64()).unwrap_or(0) != 0 {
+            anyhow::bail!(
+                "Milvus {} error: {}",
+                action,
+                body.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error")
+            );
         }
+        Ok(body["data"].take())
+    }
 
-        // Log collection creation details
-        if let Some(collection_name) = &response_body.data.collection_name {
-            tracing::debug!("Created collection: {}", collection_name);
-        } else {
-            tracing::debug!("Created collection: {}", name);
+    /// Delete the rows of a collection matching a filter expression
+    async fn delete_where(&self, collection: &str, filter: String) -> Result<()> {
+        let request = json!({
+            "collectionName": collection,
+            "filter": filter,
+        });
+
+        let response = self
+            .client
+            .post(self.delete_url())
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to send delete request")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Milvus API error ({}): {}", status, body);
         }
 
         Ok(())
     }
 
-    async fn insert(&self, collection: &str, vectors: &[Vec<f32>], metadata: &[serde_json::Value]) -> Result<()> {
-        if vectors.len() != metadata.len() {
-            anyhow::bail!("Vectors and metadata length mismatch");
+    fn create_partition_url(&self) -> String {
+        format!("{}/v2/vectordb/partitions/create", self.address)
+    }
+
+    /// Upsert into a partition, or the default partition if `None`
+    async fn upsert_rows(
+        &self,
+        collection: &str,
+        partition: Option<&str>,
+        ids: &[i64],
+        vectors: &[Vec<f32>],
+        metadata: &[serde_json::Value],
+    ) -> Result<()> {
+        if ids.len() != vectors.len() || vectors.len() != metadata.len() {
+            anyhow::bail!("Ids, vectors and metadata length mismatch");
         }
 
-        let data: Vec<InsertData> = vectors
+        let data: Vec<InsertData> = ids
             .iter()
-            .zip(metadata.iter())
-            .enumerate()
-            .map(|(i, (vector, meta))| InsertData {
-                id: i as i64,
+            .zip(vectors.iter().zip(metadata.iter()))
+            .map(|(id, (vector, meta))| InsertData {
+                id: *id,
                 vector: vector.clone(),
                 metadata: meta.clone(),
             })
@@ -162,16 +229,17 @@ impl VectorDatabase for MilvusVectorDatabase {
 
         let request = InsertRequest {
             collection_name: collection.to_string(),
+            partition_name: partition.map(str::to_string),
             data,
         };
 
         let response = self
             .client
-            .post(self.insert_url())
+            .post(self.upsert_url())
             .json(&request)
             .send()
             .await
-            .context("Failed to send insert request")?;
+            .context("Failed to send upsert request")?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -182,13 +250,57 @@ impl VectorDatabase for MilvusVectorDatabase {
         Ok(())
     }
 
-    async fn search(&self, collection: &str, vector: &[f32], limit: usize) -> Result<Vec<SearchResult>> {
+    /// Ids, vectors and metadata of the rows stored for a file (in the given
+    /// partition, if any), at most `MAX_QUERY_ROWS` of them
+    async fn file_rows(
+        &self,
+        collection: &str,
+        partition: Option<&str>,
+        file_path: &str,
+    ) -> Result<(Vec<i64>, Vec<Vec<f32>>, Vec<serde_json::Value>)> {
+        let mut request = json!({
+            "collectionName": collection,
+            "filter": format!("metadata[\"file_path\"] == {}", serde_json::to_string(file_path)?),
+            "outputFields": ["id", "vector", "metadata"],
+            "limit": MAX_QUERY_ROWS,
+        });
+        if let Some(partition) = partition {
+            request["partitionNames"] = json!([partition]);
+        }
+        let data = self.post_data(self.query_url(), request, "query").await?;
+        let rows = data.as_array().map(Vec::as_slice).unwrap_or_default();
+
+        let mut ids = Vec::with_capacity(rows.len());
+        let mut vectors = Vec::with_capacity(rows.len());
+        let mut metadata = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id = row.get("id").and_then(|v| v.as_i64()).context("Milvus query row without id")?;
+            let vector: Vec<f32> = serde_json::from_value(row["vector"].clone()).context("Milvus query row without vector")?;
+            ids.push(id);
+            vectors.push(vector);
+            metadata.push(row.get("metadata").cloned().unwrap_or_default());
+        }
+        Ok((ids, vectors, metadata))
+    }
+
+    /// Search the given partitions, or the whole collection if `None`,
+    /// optionally only rows matching a filter expression
+    async fn search_in(
+        &self,
+        collection: &str,
+        partitions: Option<&[String]>,
+        vector: &[f32],
+        limit: usize,
+        filter: Option<String>,
+    ) -> Result<Vec<SearchResult>> {
         let request = SearchRequest {
             collection_name: collection.to_string(),
             data: vec![vector.to_vec()],
             limit,
             output_fields: vec!["metadata".to_string()],
-            metric_type: "COSINE".to_string(),
+            metric_type: METRIC.as_str().to_string(),
+            partition_names: partitions.map(<[String]>::to_vec),
+            filter,
         };
 
         let response = self
@@ -233,15 +345,57 @@ impl VectorDatabase for MilvusVectorDatabase {
                 // Metadata is directly in the result, or in extra fields
                 let metadata = r.metadata.unwrap_or_else(|| serde_json::Value::Object(r.extra));
                 
-                Some(SearchResult {
-                    score: r.score,
-                    metadata,
-                })
+                Some(SearchResult::new(r.score, METRIC, metadata))
             })
             .collect();
 
         Ok(results)
     }
+}
+
+#[async_trait::async_trait]
+impl VectorDatabase for MilvusVectorDatabase {
+    async fn create_collection(&self, name: &str, dimension: usize) -> Result<()> {
+        let request = CreateCollectionRequest {
+            collection_name: name.to_string(),
+            dimension,
+            metric_type: METRIC.as_str().to_string(),
+        };
+
+        let response = self
+            .client
+            .post(self.collection_url())
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to send create collection request")?;
+
+        let response_body: CreateCollectionResponse = response
+            .json()
+            .await
+            .context("Failed to parse create collection response")?;
+
+        if response_body.code != 0 {
+            anyhow::bail!("Milvus create collection error: {}", response_body.message.unwrap_or_default());
+        }
+
+        // Log collection creation details
+        if let Some(collection_name) = &response_body.data.collection_name {
+            tracing::debug!("Created collection: {}", collection_name);
+        } else {
+            tracing::debug!("Created collection: {}", name);
+        }
+
+        Ok(())
+    }
+
+    async fn upsert(&self, collection: &str, ids: &[i64], vectors: &[Vec<f32>], metadata: &[serde_json::Value]) -> Result<()> {
+        self.upsert_rows(collection, None, ids, vectors, metadata).await
+    }
+
+    async fn search(&self, collection: &str, vector: &[f32], limit: usize) -> Result<Vec<SearchResult>> {
+        self.search_in(collection, None, vector, limit, None).await
+    }
 
     async fn drop_collection(&self, name: &str) -> Result<()> {
         let request = json!({
@@ -264,4 +418,217 @@ impl VectorDatabase for MilvusVectorDatabase {
 
         Ok(())
     }
+
+    async fn collection_dimension(&self, name: &str) -> Result<Option<usize>> {
+        let request = json!({ "collectionName": name });
+        let has = self.post_data(self.has_collection_url(), request.clone(), "has collection").await?;
+        if !has.get("has").and_then(|h| h.as_bool()).unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let description = self
+            .post_data(self.describe_collection_url(), request, "describe collection")
+            .await?;
+        // The dimension is a type param of the vector field, as a string
+        let dimension = description
+            .get("fields")
+            .and_then(|f| f.as_array())
+            .into_iter()
+            .flatten()
+            .filter(|field| field.get("type").and_then(|t| t.as_str()) == Some("FloatVector"))
+            .flat_map(|field| field.get("params").and_then(|p| p.as_array()).cloned().unwrap_or_default())
+            .find(|param| param.get("key").and_then(|k| k.as_str()) == Some("dim"))
+            .and_then(|param| match &param["value"] {
+                serde_json::Value::String(s) => s.parse().ok(),
+                value => value.as_u64().map(|d| d as usize),
+            })
+            .with_context(|| format!("Milvus collection {} has no vector dimension", name))?;
+        Ok(Some(dimension))
+    }
+
+    async fn delete_by_file(&self, collection: &str, file_path: &str) -> Result<()> {
+        self.delete_where(collection, format!("metadata[\"file_path\"] == {}", serde_json::to_string(file_path)?))
+            .await
+    }
+
+    async fn delete_by_file_hashes(&self, collection: &str, file_hashes: &[String]) -> Result<()> {
+        if file_hashes.is_empty() {
+            return Ok(());
+        }
+        self.delete_where(collection, format!("metadata[\"file_hash\"] in {}", serde_json::to_string(file_hashes)?))
+            .await
+    }
+
+    async fn health_check(&self) -> Result<String> {
+        let response = self
+            .client
+            .post(self.list_collections_url())
+            .json(&json!({}))
+            .send()
+            .await
+            .with_context(|| format!("Milvus is not reachable at {}", self.address))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Milvus API error ({}): {}", status, body);
+        }
+
+        let body: serde_json::Value = response.json().await.context("Failed to parse Milvus response")?;
+        if body.get("code").and_then(|c| c.as_i64()).unwrap_or(0) != 0 {
+            anyhow::bail!(
+                "Milvus error: {}",
+                body.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error")
+            );
+        }
+
+        // The REST API does not report the server version; the v2 endpoints
+        // answering at all means Milvus 2.3.x or newer
+        let collections = body.get("data").and_then(|d| d.as_array()).map_or(0, |d| d.len());
+        Ok(format!("{} reachable (REST v2, Milvus >= 2.3), {} collections", self.address, collections))
+    }
+
+    fn metric(&self) -> Metric {
+        METRIC
+    }
+
+    async fn create_partition(&self, collection: &str, partition: &str) -> Result<()> {
+        let request = json!({
+            "collectionName": collection,
+            "partitionName": partition,
+        });
+
+        let response = self
+            .client
+            .post(self.create_partition_url())
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to send create partition request")?;
+
+        let body: serde_json::Value = response.json().await.context("Failed to parse create partition response")?;
+        if body.get("code").and_then(|c| c.as_i64()).unwrap_or(0) != 0 {
+            anyhow::bail!(
+                "Milvus create partition error: {}",
+                body.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error")
+            );
+        }
+        tracing::debug!("Created partition {} in {}", partition, collection);
+        Ok(())
+    }
+
+    async fn upsert_partition(
+        &self,
+        collection: &str,
+        partition: &str,
+        ids: &[i64],
+        vectors: &[Vec<f32>],
+        metadata: &[serde_json::Value],
+    ) -> Result<()> {
+        self.upsert_rows(collection, Some(partition), ids, vectors, metadata).await
+    }
+
+    async fn search_partitions(
+        &self,
+        collection: &str,
+        partitions: &[String],
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        self.search_in(collection, Some(partitions), vector, limit, None).await
+    }
+
+    async fn move_file(
+        &self,
+        collection: &str,
+        partition: Option<&str>,
+        old_path: &str,
+        new_path: &str,
+        updates: &serde_json::Value,
+    ) -> Result<bool> {
+        let (ids, vectors, mut metadata) = self.file_rows(collection, partition, old_path).await?;
+        // A file with more rows than one query returns is embedded again
+        if ids.is_empty() || ids.len() >= MAX_QUERY_ROWS {
+            return Ok(false);
+        }
+        for meta in &mut metadata {
+            moved_metadata(meta, old_path, new_path, updates);
+        }
+        // The moved rows are stored before the old ones are deleted
+        let ids: Vec<i64> = ids.into_iter().map(|id| moved_id(id, new_path)).collect();
+        self.upsert_rows(collection, partition, &ids, &vectors, &metadata).await?;
+        self.delete_by_file(collection, old_path).await?;
+        Ok(true)
+    }
+
+    async fn copy_file(
+        &self,
+        collection: &str,
+        file_path: &str,
+        target: &str,
+        updates: &serde_json::Value,
+        salt: i64,
+    ) -> Result<usize> {
+        let (ids, vectors, mut metadata) = self.file_rows(collection, None, file_path).await?;
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        if ids.len() >= MAX_QUERY_ROWS {
+            tracing::warn!("Copying only the first {} chunks of {}", MAX_QUERY_ROWS, file_path);
+        }
+        let ids: Vec<i64> = ids.into_iter().map(|id| copied_id(id, salt)).collect();
+        if let Some(updates) = updates.as_object() {
+            for meta in metadata.iter_mut().filter_map(|m| m.as_object_mut()) {
+                meta.extend(updates.clone());
+            }
+        }
+        self.upsert_rows(target, None, &ids, &vectors, &metadata).await?;
+        Ok(ids.len())
+    }
+
+    async fn search_keywords(
+        &self,
+        collection: &str,
+        partitions: Option<&[String]>,
+        vector: &[f32],
+        limit: usize,
+        keywords: &[String],
+    ) -> Result<Vec<SearchResult>> {
+        let mut results = self
+            .search_in(collection, partitions, vector, limit, Some(keyword_filter(keywords)?))
+            .await?;
+        // `_` in a keyword is a `like` wildcard: keep exact matches only
+        results.retain(|r| matches_keywords(&r.metadata, keywords));
+        Ok(results)
+    }
+
+    /// `count(*)` query, which unlike the collection statistics leaves out
+    /// deleted rows
+    async fn count_rows(&self, collection: &str) -> Result<Option<usize>> {
+        let request = json!({
+            "collectionName": collection,
+            "filter": "",
+            "outputFields": ["count(*)"],
+        });
+        let data = self.post_data(self.query_url(), request, "count").await?;
+        let count = data
+            .get(0)
+            .and_then(|row| row.get("count(*)"))
+            .and_then(|count| count.as_u64())
+            .context("Milvus count query returned no count")?;
+        Ok(Some(count as usize))
+    }
+}
+
+/// Filter expression matching rows whose symbol name or file path contains
+/// one of `keywords`
+fn keyword_filter(keywords: &[String]) -> Result<String> {
+    let mut clauses = Vec::with_capacity(keywords.len() * KEYWORD_FIELDS.len());
+    for keyword in keywords {
+        let pattern = format!("%{}%", keyword);
+        for field in KEYWORD_FIELDS {
+            clauses.push(format!("metadata[\"{}\"] like {}", field, serde_json::to_string(&pattern)?));
+        }
+    }
+    Ok(clauses.join(" or "))
 }
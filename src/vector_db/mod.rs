@@ -1,4 +1,7 @@
+pub mod limiter;
 pub mod milvus;
+pub mod qdrant;
+pub mod router;
 
 use anyhow::Result;
 
@@ -6,14 +9,300 @@ use anyhow::Result;
 #[async_trait::async_trait]
 pub trait VectorDatabase: Send + Sync {
     async fn create_collection(&self, name: &str, dimension: usize) -> Result<()>;
-    async fn insert(&self, collection: &str, vectors: &[Vec<f32>], metadata: &[serde_json::Value]) -> Result<()>;
+    /// Insert or replace rows with caller-chosen ids
+    async fn upsert(&self, collection: &str, ids: &[i64], vectors: &[Vec<f32>], metadata: &[serde_json::Value]) -> Result<()>;
     async fn search(&self, collection: &str, vector: &[f32], limit: usize) -> Result<Vec<SearchResult>>;
     async fn drop_collection(&self, name: &str) -> Result<()>;
+    /// Vector dimension of a collection, `None` if it doesn't exist
+    async fn collection_dimension(&self, name: &str) -> Result<Option<usize>>;
+    /// Delete all chunks stored for a file (by its `file_path` metadata)
+    async fn delete_by_file(&self, collection: &str, file_path: &str) -> Result<()>;
+    /// Delete all chunks stored with any of these `file_hash` metadata (file
+    /// versions in a shared dependency collection no project vendors anymore)
+    async fn delete_by_file_hashes(&self, collection: &str, file_hashes: &[String]) -> Result<()>;
+    /// Verify the database is reachable, returning a short description
+    async fn health_check(&self) -> Result<String>;
+    /// Similarity metric of the stored vectors (determines score normalization)
+    fn metric(&self) -> Metric;
+
+    /// Create a named partition of a collection; backends without
+    /// partitions keep everything in the collection
+    async fn create_partition(&self, _collection: &str, _partition: &str) -> Result<()> {
+        Ok(())
+    }
+    /// Insert or replace rows in one partition of a collection
+    async fn upsert_partition(
+        &self,
+        collection: &str,
+        _partition: &str,
+        ids: &[i64],
+        vectors: &[Vec<f32>],
+        metadata: &[serde_json::Value],
+    ) -> Result<()> {
+        self.upsert(collection, ids, vectors, metadata).await
+    }
+    /// Search only the given partitions of a collection
+    async fn search_partitions(
+        &self,
+        collection: &str,
+        _partitions: &[String],
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        self.search(collection, vector, limit).await
+    }
+    /// Re-point the stored chunks of a renamed file at its new path (in the
+    /// given partition, if the collection is partitioned), setting the
+    /// fields of `updates` on their metadata. Row ids are derived from the
+    /// file path, so the moved rows get new ids (see [`moved_id`]) and a file
+    /// later created at the old path doesn't replace them. Returns `false`
+    /// when nothing was moved, so the file is embedded again; backends that
+    /// can't rewrite rows always do.
+    async fn move_file(
+        &self,
+        _collection: &str,
+        _partition: Option<&str>,
+        _old_path: &str,
+        _new_path: &str,
+        _updates: &serde_json::Value,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+    /// Copy the stored chunks of a file into the collection `target`,
+    /// setting the fields of `updates` on their metadata. The copies get ids
+    /// derived from the originals and `salt` (see [`copied_id`]), so repeated
+    /// copies of a chunk don't replace each other. Returns how many chunks
+    /// were copied; backends that can't read rows back copy none.
+    async fn copy_file(
+        &self,
+        _collection: &str,
+        _file_path: &str,
+        _target: &str,
+        _updates: &serde_json::Value,
+        _salt: i64,
+    ) -> Result<usize> {
+        Ok(0)
+    }
+    /// Rows stored in a collection, `None` if the backend can't count them
+    async fn count_rows(&self, _collection: &str) -> Result<Option<usize>> {
+        Ok(None)
+    }
+    /// Search only chunks whose symbol name or file path contains one of
+    /// `keywords` (in the given partitions, if any). Backends with scalar
+    /// filters narrow the candidates before the vector search; others
+    /// over-fetch and filter the results.
+    async fn search_keywords(
+        &self,
+        collection: &str,
+        partitions: Option<&[String]>,
+        vector: &[f32],
+        limit: usize,
+        keywords: &[String],
+    ) -> Result<Vec<SearchResult>> {
+        let fetch = limit * KEYWORD_OVERFETCH;
+        let mut results = match partitions {
+            Some(partitions) => self.search_partitions(collection, partitions, vector, fetch).await?,
+            None => self.search(collection, vector, fetch).await?,
+        };
+        results.retain(|r| matches_keywords(&r.metadata, keywords));
+        results.truncate(limit);
+        Ok(results)
+    }
+}
+
+/// Over-fetch factor of `search_keywords` on backends filtering results
+/// client-side
+const KEYWORD_OVERFETCH: usize = 10;
+
+/// Metadata fields matched against `search_keywords` keywords
+pub const KEYWORD_FIELDS: &[&str] = &["symbol_name", "file_path"];
+
+/// Whether a chunk's symbol name or file path contains one of `keywords`
+pub fn matches_keywords(metadata: &serde_json::Value, keywords: &[String]) -> bool {
+    KEYWORD_FIELDS.iter().any(|field| {
+        metadata
+            .get(*field)
+            .and_then(|v| v.as_str())
+            .is_some_and(|value| keywords.iter().any(|k| value.contains(k.as_str())))
+    })
+}
+
+/// Metadata of a chunk stored for `old_path` after the file was renamed to
+/// `new_path`: its path and matching locations are rewritten and the fields
+/// of `updates` (such as the module derived from the path) set
+pub fn moved_metadata(metadata: &mut serde_json::Value, old_path: &str, new_path: &str, updates: &serde_json::Value) {
+    let renamed = |path: &mut serde_json::Value| {
+        if path.as_str() == Some(old_path) {
+            *path = serde_json::Value::from(new_path);
+        }
+    };
+    if let Some(path) = metadata.get_mut("file_path") {
+        renamed(path);
+    }
+    if let Some(locations) = metadata.get_mut("locations").and_then(|l| l.as_array_mut()) {
+        for path in locations.iter_mut().filter_map(|l| l.get_mut("file_path")) {
+            renamed(path);
+        }
+    }
+    if let (Some(fields), Some(updates)) = (metadata.as_object_mut(), updates.as_object()) {
+        fields.extend(updates.clone());
+    }
+}
+
+/// Id of a copy of row `id` made by [`VectorDatabase::copy_file`]
+pub fn copied_id(id: i64, salt: i64) -> i64 {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::new().chain_update(id.to_le_bytes()).chain_update(salt.to_le_bytes()).finalize();
+    let prefix = u64::from_le_bytes(digest[..8].try_into().unwrap());
+    (prefix & i64::MAX as u64) as i64
+}
+
+/// Id of row `id` after [`VectorDatabase::move_file`] moved it to `new_path`
+pub fn moved_id(id: i64, new_path: &str) -> i64 {
+    use sha2::{Digest, Sha256};
+    let digest = Sha256::digest(new_path.as_bytes());
+    copied_id(id, i64::from_le_bytes(digest[..8].try_into().unwrap()))
+}
+
+/// Partition holding a language's chunks in collection-per-language mode
+pub fn language_partition(language: Option<&str>) -> String {
+    let name: String = language
+        .unwrap_or("other")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    format!("lang_{}", name)
+}
+
+/// Lexical score at which a full-text match normalizes to 1 - 1/e
+const LEXICAL_SCALE: f32 = 8.0;
+
+/// Vector similarity metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// Cosine similarity in [-1, 1], higher is closer
+    Cosine,
+    /// Euclidean distance in [0, ∞), lower is closer
+    L2,
+    /// Inner product, unbounded, higher is closer
+    InnerProduct,
+    /// Full-text relevance (BM25, or the match count of a regex) of chunks
+    /// found by the full-text index, unbounded, higher is closer
+    Lexical,
+}
+
+impl Metric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Metric::Cosine => "COSINE",
+            Metric::L2 => "L2",
+            Metric::InnerProduct => "IP",
+            Metric::Lexical => "LEXICAL",
+        }
+    }
+
+    /// Map a raw backend score to a relevance in [0, 1] where higher is
+    /// better, so results from different metrics and backends compare
+    pub fn normalize(&self, raw: f32) -> f32 {
+        match self {
+            // Negative similarity is as unrelated as orthogonal
+            Metric::Cosine => raw.clamp(0.0, 1.0),
+            Metric::L2 => 1.0 / (1.0 + raw.max(0.0)),
+            Metric::InnerProduct => 1.0 / (1.0 + (-raw).exp()),
+            // Saturates for matches of several rare terms
+            Metric::Lexical => 1.0 - (-raw.max(0.0) / LEXICAL_SCALE).exp(),
+        }
+    }
+}
+
+/// Why `vector` can't be stored in a collection of `dimension`-dimensional
+/// vectors compared with `metric`, if it can't
+pub fn vector_problem(vector: &[f32], dimension: usize, metric: Metric) -> Option<String> {
+    if vector.len() != dimension {
+        return Some(format!("{} dimensions, the collection stores {}", vector.len(), dimension));
+    }
+    if vector.iter().any(|v| !v.is_finite()) {
+        return Some("NaN or infinite values".to_string());
+    }
+    // A zero vector has no direction to compare
+    if metric == Metric::Cosine && vector.iter().all(|v| *v == 0.0) {
+        return Some("only zeros, which have no cosine similarity".to_string());
+    }
+    None
 }
 
 /// Search result from vector database
 #[derive(Debug, Clone)]
 pub struct SearchResult {
+    /// Normalized relevance in [0, 1], higher is better
     pub score: f32,
+    /// Score as returned by the backend, in `metric` units
+    pub raw_score: f32,
+    pub metric: Metric,
     pub metadata: serde_json::Value,
 }
+
+impl SearchResult {
+    pub fn new(raw_score: f32, metric: Metric, metadata: serde_json::Value) -> Self {
+        Self {
+            score: metric.normalize(raw_score),
+            raw_score,
+            metric,
+            metadata,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moved_metadata() {
+        let mut metadata = serde_json::json!({
+            "file_path": "src/old.rs",
+            "module": "crate::old",
+            "locations": [
+                { "file_path": "src/old.rs", "start_line": 0 },
+                { "file_path": "src/copy.rs", "start_line": 4 },
+            ],
+        });
+        moved_metadata(&mut metadata, "src/old.rs", "src/new.rs", &serde_json::json!({ "module": "crate::new" }));
+        assert_eq!(metadata["file_path"], "src/new.rs");
+        assert_eq!(metadata["module"], "crate::new");
+        assert_eq!(metadata["locations"][0]["file_path"], "src/new.rs");
+        assert_eq!(metadata["locations"][1]["file_path"], "src/copy.rs");
+    }
+
+    #[test]
+    fn test_matches_keywords() {
+        let metadata = serde_json::json!({ "symbol_name": "parseConfig", "file_path": "src/loader.rs" });
+        let keywords = |words: &[&str]| words.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        assert!(matches_keywords(&metadata, &keywords(&["Config"])));
+        assert!(matches_keywords(&metadata, &keywords(&["render", "loader"])));
+        assert!(!matches_keywords(&metadata, &keywords(&["config"])));
+        assert!(!matches_keywords(&serde_json::json!({ "content": "Config" }), &keywords(&["Config"])));
+    }
+
+    #[test]
+    fn test_scores_normalize_to_relevance() {
+        assert_eq!(Metric::Cosine.normalize(0.8), 0.8);
+        assert_eq!(Metric::Cosine.normalize(-0.3), 0.0);
+        assert_eq!(Metric::L2.normalize(0.0), 1.0);
+        assert!(Metric::L2.normalize(0.5) > Metric::L2.normalize(2.0));
+        assert_eq!(Metric::InnerProduct.normalize(0.0), 0.5);
+        assert!(Metric::InnerProduct.normalize(3.0) > 0.9);
+    }
+
+    #[test]
+    fn test_vector_problem() {
+        assert_eq!(vector_problem(&[0.1, 0.2, 0.3], 3, Metric::Cosine), None);
+        assert_eq!(
+            vector_problem(&[0.1, 0.2], 3, Metric::Cosine).as_deref(),
+            Some("2 dimensions, the collection stores 3")
+        );
+        assert!(vector_problem(&[0.1, f32::NAN, 0.3], 3, Metric::L2).is_some());
+        assert!(vector_problem(&[0.0; 3], 3, Metric::Cosine).is_some());
+        assert_eq!(vector_problem(&[0.0; 3], 3, Metric::L2), None);
+    }
+}
@@ -0,0 +1,557 @@
+//! Qdrant vector database client (using the REST API), for users who
+//! already run Qdrant and don't want a Milvus deployment as well. Qdrant
+//! has no partitions: a point's partition is kept in a payload field, and
+//! partition searches filter on it.
+
+use super::{copied_id, matches_keywords, moved_id, moved_metadata, Metric, SearchResult, VectorDatabase, KEYWORD_FIELDS};
+use anyhow::{Context, Result};
+use reqwest::{Client, Method, StatusCode};
+use serde_json::json;
+
+/// Metric collections are created and searched with
+const METRIC: Metric = Metric::Cosine;
+
+/// Payload field holding the partition a point was stored in
+const PARTITION_FIELD: &str = "_partition";
+
+/// Points read per scroll request
+const SCROLL_PAGE: usize = 1024;
+
+/// Qdrant vector database client (using REST API)
+pub struct QdrantVectorDatabase {
+    client: Client,
+    address: String,
+    api_key: Option<String>,
+}
+
+impl QdrantVectorDatabase {
+    pub fn new(address: &str) -> Self {
+        Self {
+            client: Client::new(),
+            address: address.trim_end_matches('/').to_string(),
+            api_key: None,
+        }
+    }
+
+    /// Send requests through a client with configured timeouts and pooling
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Authenticate with the key of a Qdrant Cloud or secured instance
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    /// Send a request and return the `result` of a successful response,
+    /// `None` if the collection (or endpoint) doesn't exist
+    async fn call(&self, method: Method, path: &str, body: Option<serde_json::Value>, action: &str) -> Result<Option<serde_json::Value>> {
+        let mut request = self.client.request(method, format!("{}{}", self.address, path));
+        if let Some(key) = &self.api_key {
+            request = request.header("api-key", key);
+        }
+        if let Some(body) = &body {
+            request = request.json(body);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to send {} request", action))?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Qdrant {} error ({}): {}", action, status, error_message(&body));
+        }
+
+        let mut body: serde_json::Value = response
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse {} response", action))?;
+        Ok(Some(body["result"].take()))
+    }
+
+    /// [`call`](Self::call) on a collection that must exist
+    async fn call_collection(
+        &self,
+        method: Method,
+        collection: &str,
+        path: &str,
+        body: serde_json::Value,
+        action: &str,
+    ) -> Result<serde_json::Value> {
+        self.call(method, &format!("/collections/{}{}", collection, path), Some(body), action)
+            .await?
+            .with_context(|| format!("Qdrant collection {} doesn't exist", collection))
+    }
+
+    /// Upsert into a partition, or no partition if `None`
+    async fn upsert_points(
+        &self,
+        collection: &str,
+        partition: Option<&str>,
+        ids: &[i64],
+        vectors: &[Vec<f32>],
+        metadata: &[serde_json::Value],
+    ) -> Result<()> {
+        if ids.len() != vectors.len() || vectors.len() != metadata.len() {
+            anyhow::bail!("Ids, vectors and metadata length mismatch");
+        }
+
+        let points: Vec<serde_json::Value> = ids
+            .iter()
+            .zip(vectors.iter().zip(metadata.iter()))
+            .map(|(id, (vector, meta))| {
+                json!({
+                    "id": point_id(*id),
+                    "vector": vector,
+                    "payload": payload(meta, partition),
+                })
+            })
+            .collect();
+
+        self.call_collection(Method::PUT, collection, "/points?wait=true", json!({ "points": points }), "upsert")
+            .await?;
+        Ok(())
+    }
+
+    /// Search the whole collection, optionally only points matching a filter
+    async fn search_in(
+        &self,
+        collection: &str,
+        vector: &[f32],
+        limit: usize,
+        filter: Option<serde_json::Value>,
+    ) -> Result<Vec<SearchResult>> {
+        let mut request = json!({
+            "vector": vector,
+            "limit": limit,
+            "with_payload": true,
+        });
+        if let Some(filter) = filter {
+            request["filter"] = filter;
+        }
+        let result = self
+            .call_collection(Method::POST, collection, "/points/search", request, "search")
+            .await?;
+
+        let results = search_results(&result);
+        tracing::debug!("Search completed: collection={}, results={}", collection, results.len());
+        Ok(results)
+    }
+
+    /// Ids, vectors and metadata of the points stored for a file (in the
+    /// given partition, if any)
+    async fn file_rows(
+        &self,
+        collection: &str,
+        partition: Option<&str>,
+        file_path: &str,
+    ) -> Result<(Vec<i64>, Vec<Vec<f32>>, Vec<serde_json::Value>)> {
+        let mut ids = Vec::new();
+        let mut vectors = Vec::new();
+        let mut metadata_rows = Vec::new();
+        let mut offset = serde_json::Value::Null;
+        loop {
+            let mut request = json!({
+                "filter": file_filter(file_path, partition),
+                "limit": SCROLL_PAGE,
+                "with_payload": true,
+                "with_vector": true,
+            });
+            if !offset.is_null() {
+                request["offset"] = offset;
+            }
+            let result = self
+                .call_collection(Method::POST, collection, "/points/scroll", request, "scroll")
+                .await?;
+
+            let (rows, next) = scroll_page(result)?;
+            for (id, vector, metadata) in rows {
+                ids.push(id);
+                vectors.push(vector);
+                metadata_rows.push(metadata);
+            }
+            offset = next;
+            if offset.is_null() {
+                break;
+            }
+        }
+        Ok((ids, vectors, metadata_rows))
+    }
+}
+
+#[async_trait::async_trait]
+impl VectorDatabase for QdrantVectorDatabase {
+    async fn create_collection(&self, name: &str, dimension: usize) -> Result<()> {
+        let request = json!({
+            "vectors": { "size": dimension, "distance": "Cosine" },
+        });
+        self.call(Method::PUT, &format!("/collections/{}", name), Some(request), "create collection")
+            .await?
+            .context("Qdrant create collection endpoint not found")?;
+
+        // Deletes and moves filter by file, partition searches by partition
+        for field in ["file_path", PARTITION_FIELD] {
+            let request = json!({ "field_name": field, "field_schema": "keyword" });
+            self.call_collection(Method::PUT, name, "/index?wait=true", request, "create payload index")
+                .await?;
+        }
+        tracing::debug!("Created collection: {}", name);
+        Ok(())
+    }
+
+    async fn upsert(&self, collection: &str, ids: &[i64], vectors: &[Vec<f32>], metadata: &[serde_json::Value]) -> Result<()> {
+        self.upsert_points(collection, None, ids, vectors, metadata).await
+    }
+
+    async fn search(&self, collection: &str, vector: &[f32], limit: usize) -> Result<Vec<SearchResult>> {
+        self.search_in(collection, vector, limit, None).await
+    }
+
+    /// Dropping a collection that doesn't exist succeeds
+    async fn drop_collection(&self, name: &str) -> Result<()> {
+        self.call(Method::DELETE, &format!("/collections/{}", name), None, "drop collection")
+            .await?;
+        Ok(())
+    }
+
+    async fn collection_dimension(&self, name: &str) -> Result<Option<usize>> {
+        let Some(info) = self
+            .call(Method::GET, &format!("/collections/{}", name), None, "get collection")
+            .await?
+        else {
+            return Ok(None);
+        };
+        let dimension = info
+            .pointer("/config/params/vectors/size")
+            .and_then(|size| size.as_u64())
+            .with_context(|| format!("Qdrant collection {} has no vector dimension", name))?;
+        Ok(Some(dimension as usize))
+    }
+
+    /// Deleting from a collection that doesn't exist succeeds
+    async fn delete_by_file(&self, collection: &str, file_path: &str) -> Result<()> {
+        let request = json!({ "filter": file_filter(file_path, None) });
+        self.call(
+            Method::POST,
+            &format!("/collections/{}/points/delete?wait=true", collection),
+            Some(request),
+            "delete",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_by_file_hashes(&self, collection: &str, file_hashes: &[String]) -> Result<()> {
+        if file_hashes.is_empty() {
+            return Ok(());
+        }
+        let request = json!({ "filter": { "must": [{ "key": "file_hash", "match": { "any": file_hashes } }] } });
+        self.call(
+            Method::POST,
+            &format!("/collections/{}/points/delete?wait=true", collection),
+            Some(request),
+            "delete",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn health_check(&self) -> Result<String> {
+        let mut request = self.client.get(format!("{}/", self.address));
+        if let Some(key) = &self.api_key {
+            request = request.header("api-key", key);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Qdrant is not reachable at {}", self.address))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Qdrant API error ({}): {}", status, error_message(&body));
+        }
+        let info: serde_json::Value = response.json().await.context("Failed to parse Qdrant response")?;
+        let version = info.get("version").and_then(|v| v.as_str()).unwrap_or("unknown version");
+
+        let collections = self
+            .call(Method::GET, "/collections", None, "list collections")
+            .await?
+            .and_then(|result| result.get("collections").and_then(|c| c.as_array()).map(Vec::len))
+            .unwrap_or(0);
+        Ok(format!("{} reachable (Qdrant {}), {} collections", self.address, version, collections))
+    }
+
+    fn metric(&self) -> Metric {
+        METRIC
+    }
+
+    /// Partitions are a payload field, so there is nothing to create
+    async fn create_partition(&self, _collection: &str, _partition: &str) -> Result<()> {
+        Ok(())
+    }
+
+    async fn upsert_partition(
+        &self,
+        collection: &str,
+        partition: &str,
+        ids: &[i64],
+        vectors: &[Vec<f32>],
+        metadata: &[serde_json::Value],
+    ) -> Result<()> {
+        self.upsert_points(collection, Some(partition), ids, vectors, metadata).await
+    }
+
+    async fn search_partitions(
+        &self,
+        collection: &str,
+        partitions: &[String],
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let filter = json!({ "must": [partition_condition(partitions)] });
+        self.search_in(collection, vector, limit, Some(filter)).await
+    }
+
+    async fn move_file(
+        &self,
+        collection: &str,
+        partition: Option<&str>,
+        old_path: &str,
+        new_path: &str,
+        updates: &serde_json::Value,
+    ) -> Result<bool> {
+        let (ids, vectors, mut metadata) = self.file_rows(collection, partition, old_path).await?;
+        if ids.is_empty() {
+            return Ok(false);
+        }
+        for meta in &mut metadata {
+            moved_metadata(meta, old_path, new_path, updates);
+        }
+        // The moved points are stored before the old ones are deleted
+        let ids: Vec<i64> = ids.into_iter().map(|id| moved_id(id, new_path)).collect();
+        self.upsert_points(collection, partition, &ids, &vectors, &metadata).await?;
+        self.delete_by_file(collection, old_path).await?;
+        Ok(true)
+    }
+
+    async fn copy_file(
+        &self,
+        collection: &str,
+        file_path: &str,
+        target: &str,
+        updates: &serde_json::Value,
+        salt: i64,
+    ) -> Result<usize> {
+        let (ids, vectors, mut metadata) = self.file_rows(collection, None, file_path).await?;
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let ids: Vec<i64> = ids.into_iter().map(|id| copied_id(id, salt)).collect();
+        if let Some(updates) = updates.as_object() {
+            for meta in metadata.iter_mut().filter_map(|m| m.as_object_mut()) {
+                meta.extend(updates.clone());
+            }
+        }
+        self.upsert_points(target, None, &ids, &vectors, &metadata).await?;
+        Ok(ids.len())
+    }
+
+    /// A `text` match on a field without a full-text index is a
+    /// case-sensitive substring match, as `matches_keywords` expects
+    async fn search_keywords(
+        &self,
+        collection: &str,
+        partitions: Option<&[String]>,
+        vector: &[f32],
+        limit: usize,
+        keywords: &[String],
+    ) -> Result<Vec<SearchResult>> {
+        let filter = keyword_filter(keywords, partitions);
+        let mut results = self.search_in(collection, vector, limit, Some(filter)).await?;
+        results.retain(|r| matches_keywords(&r.metadata, keywords));
+        Ok(results)
+    }
+
+    async fn count_rows(&self, collection: &str) -> Result<Option<usize>> {
+        let result = self
+            .call_collection(Method::POST, collection, "/points/count", json!({ "exact": true }), "count")
+            .await?;
+        let count = result
+            .get("count")
+            .and_then(|count| count.as_u64())
+            .context("Qdrant count returned no count")?;
+        Ok(Some(count as usize))
+    }
+}
+
+/// Qdrant point ids are unsigned: chunk ids are stored with the same bits
+fn point_id(id: i64) -> u64 {
+    id as u64
+}
+
+/// Payload of a point: the chunk metadata, plus its partition if any
+fn payload(metadata: &serde_json::Value, partition: Option<&str>) -> serde_json::Value {
+    let mut payload = metadata.as_object().cloned().unwrap_or_default();
+    if let Some(partition) = partition {
+        payload.insert(PARTITION_FIELD.to_string(), json!(partition));
+    }
+    serde_json::Value::Object(payload)
+}
+
+/// Chunk id of a point id, the inverse of [`point_id`]
+fn chunk_id(point: &serde_json::Value) -> Result<i64> {
+    let id = point.get("id").and_then(|v| v.as_u64()).context("Qdrant point without a numeric id")?;
+    Ok(id as i64)
+}
+
+/// Results of a search response, scored by the collection's metric
+fn search_results(result: &serde_json::Value) -> Vec<SearchResult> {
+    result
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|point| {
+            let score = point.get("score").and_then(|s| s.as_f64()).unwrap_or_default() as f32;
+            SearchResult::new(score, METRIC, metadata(point.get("payload").cloned().unwrap_or_default()))
+        })
+        .collect()
+}
+
+/// A point read back: chunk id, vector and chunk metadata
+type Point = (i64, Vec<f32>, serde_json::Value);
+
+/// Points of a scroll response, and the offset of the next page (null
+/// after the last one)
+fn scroll_page(mut result: serde_json::Value) -> Result<(Vec<Point>, serde_json::Value)> {
+    let rows = result["points"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|point| {
+            let vector: Vec<f32> = serde_json::from_value(point["vector"].clone()).context("Qdrant point without vector")?;
+            Ok((chunk_id(point)?, vector, metadata(point.get("payload").cloned().unwrap_or_default())))
+        })
+        .collect::<Result<_>>()?;
+    Ok((rows, result["next_page_offset"].take()))
+}
+
+/// Filter of a keyword search: any keyword in any keyword field, within
+/// `partitions` if given
+fn keyword_filter(keywords: &[String], partitions: Option<&[String]>) -> serde_json::Value {
+    let should: Vec<serde_json::Value> = keywords
+        .iter()
+        .flat_map(|keyword| KEYWORD_FIELDS.iter().map(move |field| json!({ "key": field, "match": { "text": keyword } })))
+        .collect();
+    let mut filter = json!({ "should": should });
+    if let Some(partitions) = partitions {
+        filter["must"] = json!([partition_condition(partitions)]);
+    }
+    filter
+}
+
+/// Chunk metadata of a point's payload
+fn metadata(mut payload: serde_json::Value) -> serde_json::Value {
+    if let Some(fields) = payload.as_object_mut() {
+        fields.remove(PARTITION_FIELD);
+    }
+    payload
+}
+
+/// Condition matching points in one of `partitions`
+fn partition_condition(partitions: &[String]) -> serde_json::Value {
+    json!({ "key": PARTITION_FIELD, "match": { "any": partitions } })
+}
+
+/// Filter matching the points of a file (in the given partition, if any)
+fn file_filter(file_path: &str, partition: Option<&str>) -> serde_json::Value {
+    let mut must = vec![json!({ "key": "file_path", "match": { "value": file_path } })];
+    if let Some(partition) = partition {
+        must.push(json!({ "key": PARTITION_FIELD, "match": { "value": partition } }));
+    }
+    json!({ "must": must })
+}
+
+/// Error message of a Qdrant error response, or the body as it is
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|error| error.pointer("/status/error").and_then(|e| e.as_str()).map(str::to_string))
+        .unwrap_or_else(|| body.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qdrant_payload() {
+        let chunk = json!({ "file_path": "src/lib.rs", "start_line": 3 });
+        let stored = payload(&chunk, Some("lang_rust"));
+        assert_eq!(stored[PARTITION_FIELD], "lang_rust");
+        assert_eq!(metadata(stored), chunk);
+        assert_eq!(payload(&chunk, None), chunk);
+
+        assert_eq!(point_id(-2) as i64, -2);
+        assert_eq!(
+            file_filter("src/lib.rs", Some("lang_rust"))["must"][1],
+            json!({ "key": PARTITION_FIELD, "match": { "value": "lang_rust" } })
+        );
+        assert_eq!(
+            error_message(r#"{"status":{"error":"Wrong input: Vector dimension error"},"time":0.1}"#),
+            "Wrong input: Vector dimension error"
+        );
+    }
+
+    #[test]
+    fn test_qdrant_scroll_and_search_responses() {
+        // Negative chunk ids are stored as large point ids and read back
+        let id = moved_id(42, "src/lib.rs");
+        let page = json!({
+            "points": [
+                { "id": point_id(-7), "vector": [0.5, 0.5], "payload": { "file_path": "src/lib.rs", PARTITION_FIELD: "lang_rust" } },
+                { "id": point_id(id), "vector": [1.0, 0.0], "payload": { "file_path": "src/lib.rs" } },
+            ],
+            "next_page_offset": point_id(-3),
+        });
+        let (rows, next) = scroll_page(page).unwrap();
+        assert_eq!(rows.iter().map(|(id, _, _)| *id).collect::<Vec<_>>(), vec![-7, id]);
+        assert_eq!(rows[0].1, vec![0.5, 0.5]);
+        assert_eq!(rows[0].2, json!({ "file_path": "src/lib.rs" }));
+        assert_eq!(chunk_id(&json!({ "id": next })).unwrap(), -3);
+
+        let (rows, next) = scroll_page(json!({ "points": [], "next_page_offset": null })).unwrap();
+        assert!(rows.is_empty() && next.is_null());
+        // Points with UUID ids weren't written by this client
+        assert!(scroll_page(json!({ "points": [{ "id": "5c56c793-69f3-4fbf-87e6-c4bf54c28c26", "vector": [1.0] }] })).is_err());
+
+        let results = search_results(&json!([
+            { "id": 1, "score": 0.75, "payload": { "file_path": "a.rs", PARTITION_FIELD: "lang_rust" } },
+        ]));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].raw_score, 0.75);
+        assert_eq!(results[0].metric, METRIC);
+        assert_eq!(results[0].metadata, json!({ "file_path": "a.rs" }));
+    }
+
+    #[test]
+    fn test_qdrant_keyword_filter() {
+        let keywords = vec!["load_config".to_string()];
+        let filter = keyword_filter(&keywords, None);
+        assert_eq!(filter["should"].as_array().unwrap().len(), KEYWORD_FIELDS.len());
+        assert_eq!(filter["should"][0]["match"], json!({ "text": "load_config" }));
+        assert!(filter.get("must").is_none());
+
+        let partitions = vec!["lang_rust".to_string(), "lang_go".to_string()];
+        let filter = keyword_filter(&keywords, Some(&partitions));
+        assert_eq!(filter["must"][0], json!({ "key": PARTITION_FIELD, "match": { "any": ["lang_rust", "lang_go"] } }));
+    }
+}
@@ -0,0 +1,206 @@
+//! Several named vector database instances (`[vector_db.instances]`), with
+//! each project's collection kept on the instance it was indexed into, so
+//! a huge monorepo can live on a shared cluster while small repositories use
+//! a local standalone Milvus
+
+use super::{Metric, SearchResult, VectorDatabase};
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+
+/// Name of the instance at `milvus_address` (or `qdrant_address`), used by
+/// collections without a route
+pub const DEFAULT_INSTANCE: &str = "default";
+
+/// Instance of each project collection placed on a named instance.
+/// Routes outlive the project, so its collection can still be dropped
+/// after the project is removed from the snapshot.
+#[derive(Debug, Default)]
+pub struct CollectionRoutes {
+    routes: RwLock<HashMap<String, String>>,
+}
+
+impl CollectionRoutes {
+    /// Keep `collection` on `instance`, or on the default instance for `None`
+    pub fn set(&self, collection: &str, instance: Option<&str>) {
+        let mut routes = self.routes.write().unwrap();
+        match instance.filter(|name| *name != DEFAULT_INSTANCE) {
+            Some(instance) => routes.insert(collection.to_string(), instance.to_string()),
+            None => routes.remove(collection),
+        };
+    }
+
+    /// Instance of a collection; companion collections named after a
+    /// project's collection (`code_x_history`) follow it
+    pub fn instance_for(&self, collection: &str) -> Option<String> {
+        let routes = self.routes.read().unwrap();
+        if let Some(instance) = routes.get(collection) {
+            return Some(instance.clone());
+        }
+        routes
+            .iter()
+            .filter(|(name, _)| collection.strip_prefix(name.as_str()).is_some_and(|rest| rest.starts_with('_')))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, instance)| instance.clone())
+    }
+}
+
+/// Vector database sending each request to the instance its collection is
+/// routed to. Collections without a route (and the shared dependency
+/// collections) are on the default instance.
+pub struct RoutedVectorDatabase {
+    default: Arc<dyn VectorDatabase>,
+    instances: BTreeMap<String, Arc<dyn VectorDatabase>>,
+    routes: Arc<CollectionRoutes>,
+}
+
+impl RoutedVectorDatabase {
+    pub fn new(default: Arc<dyn VectorDatabase>, instances: BTreeMap<String, Arc<dyn VectorDatabase>>, routes: Arc<CollectionRoutes>) -> Self {
+        Self { default, instances, routes }
+    }
+
+    fn backend(&self, collection: &str) -> Result<&Arc<dyn VectorDatabase>> {
+        match self.routes.instance_for(collection) {
+            Some(instance) => self.instances.get(&instance).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Collection {} is on vector database instance '{}', which is no longer configured in [vector_db.instances]",
+                    collection,
+                    instance
+                )
+            }),
+            None => Ok(&self.default),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl VectorDatabase for RoutedVectorDatabase {
+    async fn create_collection(&self, name: &str, dimension: usize) -> Result<()> {
+        self.backend(name)?.create_collection(name, dimension).await
+    }
+
+    async fn collection_dimension(&self, name: &str) -> Result<Option<usize>> {
+        self.backend(name)?.collection_dimension(name).await
+    }
+
+    async fn upsert(&self, collection: &str, ids: &[i64], vectors: &[Vec<f32>], metadata: &[serde_json::Value]) -> Result<()> {
+        self.backend(collection)?.upsert(collection, ids, vectors, metadata).await
+    }
+
+    async fn search(&self, collection: &str, vector: &[f32], limit: usize) -> Result<Vec<SearchResult>> {
+        self.backend(collection)?.search(collection, vector, limit).await
+    }
+
+    async fn drop_collection(&self, name: &str) -> Result<()> {
+        self.backend(name)?.drop_collection(name).await
+    }
+
+    async fn delete_by_file(&self, collection: &str, file_path: &str) -> Result<()> {
+        self.backend(collection)?.delete_by_file(collection, file_path).await
+    }
+
+    async fn delete_by_file_hashes(&self, collection: &str, file_hashes: &[String]) -> Result<()> {
+        self.backend(collection)?.delete_by_file_hashes(collection, file_hashes).await
+    }
+
+    /// Every instance must be reachable
+    async fn health_check(&self) -> Result<String> {
+        let mut statuses = vec![format!("{}: {}", DEFAULT_INSTANCE, self.default.health_check().await?)];
+        for (name, instance) in &self.instances {
+            let status = instance
+                .health_check()
+                .await
+                .map_err(|e| e.context(format!("Vector database instance '{}'", name)))?;
+            statuses.push(format!("{}: {}", name, status));
+        }
+        Ok(statuses.join("; "))
+    }
+
+    /// All instances run the same backend, storing vectors with the same metric
+    fn metric(&self) -> Metric {
+        self.default.metric()
+    }
+
+    async fn create_partition(&self, collection: &str, partition: &str) -> Result<()> {
+        self.backend(collection)?.create_partition(collection, partition).await
+    }
+
+    async fn upsert_partition(
+        &self,
+        collection: &str,
+        partition: &str,
+        ids: &[i64],
+        vectors: &[Vec<f32>],
+        metadata: &[serde_json::Value],
+    ) -> Result<()> {
+        self.backend(collection)?.upsert_partition(collection, partition, ids, vectors, metadata).await
+    }
+
+    async fn search_partitions(
+        &self,
+        collection: &str,
+        partitions: &[String],
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        self.backend(collection)?.search_partitions(collection, partitions, vector, limit).await
+    }
+
+    async fn move_file(
+        &self,
+        collection: &str,
+        partition: Option<&str>,
+        old_path: &str,
+        new_path: &str,
+        updates: &serde_json::Value,
+    ) -> Result<bool> {
+        self.backend(collection)?.move_file(collection, partition, old_path, new_path, updates).await
+    }
+
+    /// The target is a companion of the collection, on the same instance
+    async fn copy_file(
+        &self,
+        collection: &str,
+        file_path: &str,
+        target: &str,
+        updates: &serde_json::Value,
+        salt: i64,
+    ) -> Result<usize> {
+        self.backend(collection)?.copy_file(collection, file_path, target, updates, salt).await
+    }
+
+    async fn search_keywords(
+        &self,
+        collection: &str,
+        partitions: Option<&[String]>,
+        vector: &[f32],
+        limit: usize,
+        keywords: &[String],
+    ) -> Result<Vec<SearchResult>> {
+        self.backend(collection)?.search_keywords(collection, partitions, vector, limit, keywords).await
+    }
+
+    async fn count_rows(&self, collection: &str) -> Result<Option<usize>> {
+        self.backend(collection)?.count_rows(collection).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collection_routes() {
+        let routes = CollectionRoutes::default();
+        routes.set("code_mono", Some("shared"));
+        routes.set("code_small", Some(DEFAULT_INSTANCE));
+        assert_eq!(routes.instance_for("code_mono").as_deref(), Some("shared"));
+        assert_eq!(routes.instance_for("code_mono_history").as_deref(), Some("shared"));
+        assert_eq!(routes.instance_for("code_monorepo"), None);
+        assert_eq!(routes.instance_for("code_small"), None);
+        assert_eq!(routes.instance_for("code_deps_1234"), None);
+
+        routes.set("code_mono", None);
+        assert_eq!(routes.instance_for("code_mono"), None);
+    }
+}
@@ -0,0 +1,357 @@
+//! Monorepo workspace detection (Cargo, pnpm, yarn/npm, go.work), and
+//! discovery of the projects in a directory of repositories
+
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSetBuilder};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Kind of workspace detected at a project root
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkspaceKind {
+    Cargo,
+    Pnpm,
+    Npm,
+    Go,
+}
+
+impl WorkspaceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkspaceKind::Cargo => "cargo",
+            WorkspaceKind::Pnpm => "pnpm",
+            WorkspaceKind::Npm => "npm/yarn",
+            WorkspaceKind::Go => "go",
+        }
+    }
+}
+
+/// A detected workspace and its member directories
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub kind: WorkspaceKind,
+    /// Absolute paths of member directories, sorted
+    pub members: Vec<PathBuf>,
+}
+
+/// Files and directories marking a project root
+const PROJECT_MARKERS: &[&str] = &[
+    ".git",
+    "Cargo.toml",
+    "package.json",
+    "go.mod",
+    "pyproject.toml",
+    "setup.py",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "composer.json",
+    "Gemfile",
+];
+
+/// Directories never searched for projects
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor", "dist", "build", "__pycache__"];
+
+/// A plausible project root found by [`discover_projects`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredProject {
+    pub path: PathBuf,
+    /// Markers present at the root (`.git`, `Cargo.toml`, ...)
+    pub markers: Vec<&'static str>,
+    /// Kind of workspace the root is, if it is one
+    pub workspace: Option<WorkspaceKind>,
+}
+
+/// Find project roots at or below `parent`, up to `max_depth` levels down.
+/// A project's subdirectories are not searched further: nested packages are
+/// part of it (or members of its workspace).
+pub fn discover_projects(parent: &Path, max_depth: usize) -> Vec<DiscoveredProject> {
+    let mut projects = Vec::new();
+    let mut walker = WalkDir::new(parent).max_depth(max_depth).sort_by_file_name().into_iter();
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy();
+        if entry.depth() > 0 && (name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref())) {
+            walker.skip_current_dir();
+            continue;
+        }
+
+        let markers: Vec<&'static str> = PROJECT_MARKERS
+            .iter()
+            .copied()
+            .filter(|marker| entry.path().join(marker).exists())
+            .collect();
+        if markers.is_empty() {
+            continue;
+        }
+        projects.push(DiscoveredProject {
+            path: entry.path().to_path_buf(),
+            markers,
+            workspace: detect_workspace(entry.path()).ok().flatten().map(|w| w.kind),
+        });
+        walker.skip_current_dir();
+    }
+    projects
+}
+
+/// Detect a workspace at `root`, returning `None` if the directory is not a
+/// workspace root or the workspace has no existing members.
+pub fn detect_workspace(root: &Path) -> Result<Option<Workspace>> {
+    let detected = if let Some(patterns) = cargo_members(root)? {
+        Some((WorkspaceKind::Cargo, patterns))
+    } else if let Some(patterns) = pnpm_members(root)? {
+        Some((WorkspaceKind::Pnpm, patterns))
+    } else if let Some(patterns) = npm_members(root)? {
+        Some((WorkspaceKind::Npm, patterns))
+    } else {
+        go_members(root)?.map(|patterns| (WorkspaceKind::Go, patterns))
+    };
+
+    let Some((kind, patterns)) = detected else {
+        return Ok(None);
+    };
+
+    let members = expand_members(root, &patterns)?;
+    if members.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(Workspace { kind, members }))
+}
+
+/// `[workspace] members = [...]` from Cargo.toml
+fn cargo_members(root: &Path) -> Result<Option<Vec<String>>> {
+    let path = root.join("Cargo.toml");
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let manifest: toml::Value = std::fs::read_to_string(&path)?
+        .parse()
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    Ok(manifest
+        .get("workspace")
+        .and_then(|w| w.get("members"))
+        .and_then(|m| m.as_array())
+        .map(|members| members.iter().filter_map(|m| m.as_str().map(String::from)).collect()))
+}
+
+/// `packages:` list from pnpm-workspace.yaml (only the simple list form is
+/// supported), `None` without packages so the other detectors get a turn
+fn pnpm_members(root: &Path) -> Result<Option<Vec<String>>> {
+    let path = root.join("pnpm-workspace.yaml");
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(&path)?;
+    let mut in_packages = false;
+    let mut patterns = Vec::new();
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(' ') && !line.starts_with('-') {
+            in_packages = trimmed == "packages:";
+            continue;
+        }
+        if in_packages {
+            if let Some(item) = trimmed.strip_prefix('-') {
+                patterns.push(item.trim().trim_matches(|c| c == '\'' || c == '"').to_string());
+            }
+        }
+    }
+
+    Ok((!patterns.is_empty()).then_some(patterns))
+}
+
+/// `workspaces` from package.json (array or `{ "packages": [...] }`)
+fn npm_members(root: &Path) -> Result<Option<Vec<String>>> {
+    let path = root.join("package.json");
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path)?)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    let workspaces = match manifest.get("workspaces") {
+        Some(serde_json::Value::Array(items)) => items,
+        Some(obj) => match obj.get("packages").and_then(|p| p.as_array()) {
+            Some(items) => items,
+            None => return Ok(None),
+        },
+        None => return Ok(None),
+    };
+
+    Ok(Some(workspaces.iter().filter_map(|w| w.as_str().map(String::from)).collect()))
+}
+
+/// `use` directives from go.work
+fn go_members(root: &Path) -> Result<Option<Vec<String>>> {
+    let path = root.join("go.work");
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(&path)?;
+    let mut in_block = false;
+    let mut patterns = Vec::new();
+    for line in content.lines() {
+        let line = line.split("//").next().unwrap_or("").trim();
+        if in_block {
+            if line == ")" {
+                in_block = false;
+            } else if !line.is_empty() {
+                patterns.push(line.to_string());
+            }
+        } else if let Some(rest) = line.strip_prefix("use") {
+            let rest = rest.trim();
+            if rest == "(" {
+                in_block = true;
+            } else if !rest.is_empty() {
+                patterns.push(rest.to_string());
+            }
+        }
+    }
+
+    Ok(Some(patterns))
+}
+
+/// Expand member patterns (which may contain globs or `!` exclusions) to
+/// existing directories under `root`
+fn expand_members(root: &Path, patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let mut include = GlobSetBuilder::new();
+    let mut exclude = GlobSetBuilder::new();
+    let mut max_depth = 1;
+
+    for pattern in patterns {
+        let (negated, pattern) = match pattern.strip_prefix('!') {
+            Some(p) => (true, p),
+            None => (false, pattern.as_str()),
+        };
+        let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+        if pattern.is_empty() || pattern == "." {
+            continue;
+        }
+
+        max_depth = max_depth.max(if pattern.contains("**") {
+            usize::MAX
+        } else {
+            pattern.split('/').count()
+        });
+
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("Invalid workspace member pattern: {}", pattern))?;
+        if negated {
+            exclude.add(glob);
+        } else {
+            include.add(glob);
+        }
+    }
+
+    let include = include.build()?;
+    let exclude = exclude.build()?;
+
+    let mut members: Vec<PathBuf> = WalkDir::new(root)
+        .min_depth(1)
+        .max_depth(max_depth)
+        .into_iter()
+        .filter_entry(|e| {
+            let name = e.file_name().to_string_lossy();
+            !name.starts_with('.') && name != "node_modules" && name != "target"
+        })
+        .flatten()
+        .filter(|e| e.file_type().is_dir())
+        .filter_map(|e| {
+            let relative = e.path().strip_prefix(root).ok()?.to_path_buf();
+            (include.is_match(&relative) && !exclude.is_match(&relative)).then(|| e.path().to_path_buf())
+        })
+        .collect();
+
+    members.sort();
+    Ok(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_cargo_workspace() {
+        let root = std::env::temp_dir().join(format!("workspace-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("crates/core")).unwrap();
+        std::fs::create_dir_all(root.join("crates/cli")).unwrap();
+        std::fs::create_dir_all(root.join("tools/gen")).unwrap();
+        std::fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\", \"tools/gen\"]\n",
+        )
+        .unwrap();
+
+        let workspace = detect_workspace(&root).unwrap().unwrap();
+        assert_eq!(workspace.kind, WorkspaceKind::Cargo);
+        assert_eq!(
+            workspace.members,
+            vec![root.join("crates/cli"), root.join("crates/core"), root.join("tools/gen")]
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_empty_pnpm_workspace_falls_through() {
+        let root = std::env::temp_dir().join(format!("workspace-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("packages/ui")).unwrap();
+        std::fs::write(root.join("pnpm-workspace.yaml"), "# no packages yet\n").unwrap();
+        std::fs::write(root.join("package.json"), r#"{ "workspaces": ["packages/*"] }"#).unwrap();
+
+        let workspace = detect_workspace(&root).unwrap().unwrap();
+        assert_eq!(workspace.kind, WorkspaceKind::Npm);
+        assert_eq!(workspace.members, vec![root.join("packages/ui")]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_discover_projects() {
+        let root = std::env::temp_dir().join(format!("discover-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("api/.git")).unwrap();
+        std::fs::create_dir_all(root.join("api/web")).unwrap();
+        std::fs::write(root.join("api/web/package.json"), "{}").unwrap();
+        std::fs::create_dir_all(root.join("libs/engine/crates/core")).unwrap();
+        std::fs::write(root.join("libs/engine/Cargo.toml"), "[workspace]\nmembers = [\"crates/*\"]\n").unwrap();
+        std::fs::create_dir_all(root.join("node_modules/left-pad")).unwrap();
+        std::fs::write(root.join("node_modules/left-pad/package.json"), "{}").unwrap();
+        std::fs::create_dir_all(root.join("notes")).unwrap();
+
+        let projects = discover_projects(&root, 3);
+        assert_eq!(
+            projects,
+            vec![
+                DiscoveredProject {
+                    path: root.join("api"),
+                    markers: vec![".git"],
+                    workspace: None,
+                },
+                DiscoveredProject {
+                    path: root.join("libs/engine"),
+                    markers: vec!["Cargo.toml"],
+                    workspace: Some(WorkspaceKind::Cargo),
+                },
+            ]
+        );
+        assert!(discover_projects(&root, 1).iter().all(|p| p.path != root.join("libs/engine")));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}